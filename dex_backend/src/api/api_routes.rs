@@ -1,11 +1,28 @@
+use crate::api_methods::address_labels::{
+    delete_contact_handler, list_contacts_handler, lookup_label_handler, set_contact_handler,
+};
 use crate::api_methods::handle_history::{
     handle_history, handle_parse_transactions, handle_signatures,
 };
-use crate::api_methods::jito::handle_jito_tip_floor;
+use crate::api_methods::history_export::handle_history_export;
+use crate::api_methods::bundles::{
+    bundle_status_handler, bundle_ws_handler, submit_bundle_handler, tip_account_handler,
+};
+use crate::api_methods::jito::{handle_jito_tip_floor, tip_history_handler, tip_recommendation_handler};
 use crate::api_methods::search_tokens::{
     get_capped_tokens_handler, search_token_by_mint_handler, search_tokens_handler,
 };
 use crate::api_methods::wallet_data::wallet_data_handler;
+use crate::api_methods::pools::pools_handler;
+use crate::api_methods::prices::prices_handler;
+use crate::api_methods::ohlcv::ohlcv_handler;
+use crate::api_methods::quote_ws::quote_ws_handler;
+use crate::api_methods::rewards::{reward_stats_handler, wallet_rewards_handler};
+use crate::api_methods::simulate::simulate_handler;
+use crate::api_methods::tx_status::{track_signatures_handler, tx_status_handler, tx_status_ws_handler};
+use crate::api_methods::priority_fee::priority_fee_handler;
+use crate::api_methods::wallet_pnl::wallet_pnl_handler;
+use crate::api_methods::wallet_stream::{wallet_sse_handler, wallet_ws_handler};
 use std::collections::HashMap;
 use warp::Filter;
 
@@ -22,18 +39,28 @@ pub fn api_routes() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
         .and(warp::query::<HashMap<String, String>>())
         .and_then(search_token_by_mint_handler);
 
-    let search_tokens_by_mint = warp::path("api")
+    let capped_tokens_route = warp::path("api")
         .and(warp::path("searchToken"))
         .and(warp::get())
         .and(warp::query::<HashMap<String, String>>())
         .and_then(get_capped_tokens_handler);
 
+    let history_export_route = warp::path!("api" / "history" / "export")
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(handle_history_export);
+
     let handle_history_route = warp::path("api")
         .and(warp::path("history"))
         .and(warp::post())
         .and(warp::body::json())
         .and_then(handle_history);
 
+    let wallet_pnl_route = warp::path!("api" / "wallet" / String / "pnl")
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(wallet_pnl_handler);
+
     let wallet_route = warp::path("api")
         .and(warp::path("wallet"))
         .and(warp::get())
@@ -54,12 +81,144 @@ pub fn api_routes() -> warp::filters::BoxedFilter<(impl warp::Reply,)> {
         .and(warp::get())
         .and_then(handle_jito_tip_floor);
 
+    let tip_recommendation_route = warp::path!("api" / "tips" / "recommend")
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(tip_recommendation_handler);
+
+    let tip_history_route = warp::path!("api" / "tips" / "history")
+        .and(warp::get())
+        .and_then(tip_history_handler);
+
+    let list_contacts_route = warp::path!("api" / "labels" / String)
+        .and(warp::get())
+        .and_then(list_contacts_handler);
+
+    let lookup_label_route = warp::path!("api" / "labels" / String / String)
+        .and(warp::get())
+        .and_then(lookup_label_handler);
+
+    let set_contact_route = warp::path!("api" / "labels" / String)
+        .and(warp::post())
+        .and(warp::header::optional::<String>("x-signature"))
+        .and(warp::header::optional::<String>("x-signature-timestamp"))
+        .and(warp::body::json())
+        .and_then(set_contact_handler);
+
+    let delete_contact_route = warp::path!("api" / "labels" / String / String)
+        .and(warp::delete())
+        .and(warp::header::optional::<String>("x-signature"))
+        .and(warp::header::optional::<String>("x-signature-timestamp"))
+        .and_then(delete_contact_handler);
+
+    let wallet_stream_ws_route = warp::path!("api" / "stream" / "wallet" / String)
+        .and(warp::ws())
+        .and_then(wallet_ws_handler);
+
+    let wallet_stream_sse_route = warp::path!("api" / "stream" / "wallet" / String)
+        .and(warp::get())
+        .and_then(wallet_sse_handler);
+
+    let pools_route = warp::path!("api" / "pools")
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(pools_handler);
+
+    let prices_route = warp::path!("api" / "prices")
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(prices_handler);
+
+    let reward_stats_route = warp::path!("api" / "rewards" / "stats")
+        .and(warp::get())
+        .and_then(reward_stats_handler);
+
+    let wallet_rewards_route = warp::path!("api" / "rewards" / String)
+        .and(warp::get())
+        .and_then(wallet_rewards_handler);
+
+    let ohlcv_route = warp::path!("api" / "ohlcv")
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(ohlcv_handler);
+
+    let simulate_route = warp::path!("api" / "simulate")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and_then(simulate_handler);
+
+    let priority_fee_route = warp::path!("api" / "priorityFee" / "estimate")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and_then(priority_fee_handler);
+
+    let submit_bundle_route = warp::path!("api" / "bundles")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and_then(submit_bundle_handler);
+
+    let tip_account_route = warp::path!("api" / "bundles" / "tipAccount")
+        .and(warp::get())
+        .and_then(tip_account_handler);
+
+    let bundle_status_route = warp::path!("api" / "bundles" / String)
+        .and(warp::get())
+        .and_then(bundle_status_handler);
+
+    let bundle_stream_ws_route = warp::path!("api" / "stream" / "bundle" / String)
+        .and(warp::ws())
+        .and_then(bundle_ws_handler);
+
+    let track_signatures_route = warp::path!("api" / "tx" / "track")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and_then(track_signatures_handler);
+
+    let tx_status_route = warp::path!("api" / "tx" / String / "status")
+        .and(warp::get())
+        .and_then(tx_status_handler);
+
+    let tx_stream_ws_route = warp::path!("api" / "stream" / "tx" / String)
+        .and(warp::ws())
+        .and_then(tx_status_ws_handler);
+
+    let quote_ws_route = warp::path!("quote_ws")
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::ws())
+        .and_then(quote_ws_handler);
+
     search_tokens_by_mint
         .or(search_tokens_by_name)
+        .or(capped_tokens_route)
+        .or(history_export_route)
         .or(handle_history_route)
+        .or(wallet_pnl_route)
         .or(wallet_route)
         .or(signatures_route)
         .or(parse_transactions_route)
         .or(jito_tip_floor_route)
+        .or(tip_recommendation_route)
+        .or(tip_history_route)
+        .or(list_contacts_route)
+        .or(lookup_label_route)
+        .or(set_contact_route)
+        .or(delete_contact_route)
+        .or(wallet_stream_ws_route)
+        .or(wallet_stream_sse_route)
+        .or(pools_route)
+        .or(prices_route)
+        .or(ohlcv_route)
+        .or(reward_stats_route)
+        .or(wallet_rewards_route)
+        .or(simulate_route)
+        .or(priority_fee_route)
+        .or(submit_bundle_route)
+        .or(tip_account_route)
+        .or(bundle_status_route)
+        .or(bundle_stream_ws_route)
+        .or(track_signatures_route)
+        .or(tx_status_route)
+        .or(tx_stream_ws_route)
+        .or(quote_ws_route)
         .boxed()
 }
@@ -0,0 +1,423 @@
+//! End-to-end tests of `api_routes` with every upstream replaced by the
+//! stand-ins from `dex_test_support`: a mock RPC node plus fake Solflare,
+//! Moralis, Jupiter and Jito servers, started once and shared by all tests.
+//! Nothing here leaves the machine, so the suite runs in CI. Tests share
+//! the fakes and the process globals, so each one uses its own addresses.
+
+use crate::api::api_routes::api_routes;
+use crate::api_methods::history_fixture_tests::offline_metadata;
+use crate::cache::token_cache::spawn_token_cache_loader;
+use crate::jito::tip_floor::{spawn_tip_floor_poller, tip_floors};
+use crate::jito::TIP_ACCOUNTS;
+use crate::pools::indexer::SOL_MINT;
+use crate::tx::system;
+use crate::REQWEST_CLIENT;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use dex_test_support::rpc::TOKEN_PROGRAM;
+use dex_test_support::solflare::chunk_entries;
+use dex_test_support::{FakeBlockEngine, FakeJupiter, FakeMoralis, FakeSolflare, MockAccount, MockRpc};
+use reqwest::Client;
+use serde_json::{json, Value};
+use solana_sdk::hash::Hash;
+use solana_sdk::message::{Message, VersionedMessage};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{write_keypair_file, Keypair};
+use solana_sdk::signer::Signer;
+use solana_sdk::transaction::{Transaction, VersionedTransaction};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+use warp::http::StatusCode;
+
+/// Owner of the recorded history fixtures.
+const WALLET: &str = "GfsJWjmGXMfct8JMR9Lm9ySUnniZbnGUTQDbT8ipWf9U";
+
+struct Upstreams {
+    rpc: MockRpc,
+    solflare: FakeSolflare,
+    moralis: FakeMoralis,
+    jupiter: FakeJupiter,
+    block_engine: FakeBlockEngine,
+    arb_signer: Keypair,
+}
+
+static UPSTREAMS: OnceLock<Upstreams> = OnceLock::new();
+
+/// Starts the fakes and points the backend at them before any test touches
+/// a global that reads its configuration.
+fn upstreams() -> &'static Upstreams {
+    UPSTREAMS.get_or_init(|| {
+        let upstreams = Upstreams {
+            rpc: MockRpc::start(),
+            solflare: FakeSolflare::start(),
+            moralis: FakeMoralis::start(),
+            jupiter: FakeJupiter::start(),
+            block_engine: FakeBlockEngine::start(),
+            arb_signer: Keypair::new(),
+        };
+        let dir = std::env::temp_dir().join(format!("dex-e2e-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let keypair_path = dir.join("arb-keypair.json");
+        write_keypair_file(&upstreams.arb_signer, &keypair_path).unwrap();
+
+        for (var, value) in [
+            ("RPC_URL", upstreams.rpc.url().to_string()),
+            ("SOLFLARE_WALLET_API_URL", upstreams.solflare.url().to_string()),
+            ("SOLFLARE_ACTIVITY_API_URL", upstreams.solflare.url().to_string()),
+            ("MORALIS_API_URL", upstreams.moralis.url().to_string()),
+            ("JUPITER_TOKEN_LIST_URL", upstreams.jupiter.token_list_url()),
+            ("JITO_BLOCK_ENGINES", upstreams.block_engine.url().to_string()),
+            ("JITO_TIP_FLOOR_URL", upstreams.block_engine.tip_floor_url()),
+            ("ARB_KEYPAIR", keypair_path.display().to_string()),
+            ("REWARD_LEDGER_PATH", dir.join("rewards.bin").display().to_string()),
+        ] {
+            std::env::set_var(var, value);
+        }
+        // Shares the fixture tests' metadata API, which owns `TOKEN_METADATA_API`.
+        offline_metadata();
+        REQWEST_CLIENT.get_or_init(Client::new);
+        upstreams
+    })
+}
+
+fn read_fixture(relative: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(relative);
+    std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("missing fixture {}: {}", path.display(), e))
+}
+
+async fn get(path: &str) -> (StatusCode, Value) {
+    let response = warp::test::request().path(path).reply(&api_routes()).await;
+    (response.status(), serde_json::from_slice(response.body()).unwrap())
+}
+
+async fn post(path: &str, body: Value) -> (StatusCode, Value) {
+    let response = warp::test::request().method("POST").path(path).json(&body).reply(&api_routes()).await;
+    (response.status(), serde_json::from_slice(response.body()).unwrap())
+}
+
+/// Polls `path` until `done` accepts the body.
+async fn wait_for(path: &str, done: impl Fn(&Value) -> bool) -> Value {
+    for _ in 0..100 {
+        let (_, body) = get(path).await;
+        if done(&body) {
+            return body;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("{} never reached the expected state", path);
+}
+
+#[tokio::test]
+async fn wallet_adds_exact_amounts_to_the_portfolio() {
+    let upstreams = upstreams();
+    let address = Pubkey::new_unique().to_string();
+    upstreams.solflare.set_portfolio(
+        &address,
+        json!({ "tokens": [{
+            "mint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+            "decimals": 6,
+            "totalUiAmount": 1.3,
+            "accounts": [{ "amount": "1000001" }, { "amount": "300000" }],
+        }] }),
+    );
+
+    let (status, body) = get(&format!("/api/wallet?address={}", address)).await;
+    assert_eq!(status, StatusCode::OK);
+    let token = &body["result"]["tokens"][0];
+    assert_eq!(token["totalAmount"], "1300001");
+    assert_eq!(token["totalUiAmountString"], "1.300001");
+    assert!(upstreams.solflare.requests().iter().any(|request| request.contains(&address)));
+}
+
+#[tokio::test]
+async fn history_signatures_and_transactions_come_from_solflare() {
+    let upstreams = upstreams();
+    upstreams.solflare.set_signatures(WALLET, chunk_entries(&read_fixture("solflare/signatures.txt")));
+    for transaction in chunk_entries(&read_fixture("solflare/transactions.txt")) {
+        upstreams.solflare.add_transaction(transaction);
+    }
+    let golden_transactions: Value = serde_json::from_str(&read_fixture("golden/solflare_transactions.json")).unwrap();
+    let golden_signatures: Value = serde_json::from_str(&read_fixture("golden/solflare_signatures.json")).unwrap();
+    let hashes: Vec<&Value> = golden_signatures.as_array().unwrap().iter().map(|entry| &entry["hash"]).collect();
+
+    let (status, body) = post("/api/signatures", json!({ "address": WALLET, "limit": 10 })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["signatures"].as_array().unwrap().iter().collect::<Vec<_>>(), hashes);
+
+    let request = json!({ "accounts": [{ "chainId": "solana:101", "address": WALLET }], "limit": 10 });
+    let (status, body) = post("/api/history", request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["results"], golden_transactions);
+
+    let (status, body) = post("/api/fetchTransactions", json!({ "address": WALLET, "signatures": hashes[..2] })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["results"].as_array().unwrap()[..], golden_transactions.as_array().unwrap()[..2]);
+
+    let (status, _) = post("/api/signatures", json!({ "address": "not-a-key" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn history_export_pages_the_rpc_newest_first() {
+    let upstreams = upstreams();
+    let mut signatures = HashMap::new();
+    for name in ["sol_transfer", "usdc_received", "swap_sol_usdc", "failed_swap", "token2022_transfer", "closed_account"] {
+        let response: Value = serde_json::from_str(&read_fixture(&format!("rpc/{}.json", name))).unwrap();
+        signatures.insert(name, upstreams.rpc.add_transaction(response["result"].clone()));
+    }
+
+    let response = warp::test::request()
+        .path(&format!("/api/history/export?address={}&format=json", WALLET))
+        .reply(&api_routes())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let rows: Vec<Value> = serde_json::from_slice(response.body()).unwrap();
+    let mut exported: Vec<&str> = rows.iter().map(|row| row["signature"].as_str().unwrap()).collect();
+    exported.dedup();
+    // By slot, newest first. The received USDC only loaded the wallet's
+    // token account, so the node doesn't list it under the wallet.
+    let expected: Vec<&str> = ["closed_account", "token2022_transfer", "failed_swap", "swap_sol_usdc", "sol_transfer"]
+        .iter()
+        .map(|name| signatures[name].as_str())
+        .collect();
+    assert_eq!(exported, expected);
+    assert!(!upstreams.rpc.calls("getSignaturesForAddress").is_empty());
+}
+
+#[tokio::test]
+async fn wallet_pnl_flags_truncated_history() {
+    let upstreams = upstreams();
+    for name in ["sol_transfer", "usdc_received", "swap_sol_usdc", "failed_swap", "token2022_transfer", "closed_account"] {
+        let response: Value = serde_json::from_str(&read_fixture(&format!("rpc/{}.json", name))).unwrap();
+        upstreams.rpc.add_transaction(response["result"].clone());
+    }
+
+    let (status, body) = get(&format!("/api/wallet/{}/pnl?limit=2", WALLET)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["result"]["transactionsAnalysed"], 2);
+    assert_eq!(body["result"]["truncated"], true);
+
+    let (_, body) = get(&format!("/api/wallet/{}/pnl", WALLET)).await;
+    assert_eq!(body["result"]["transactionsAnalysed"], 5);
+    assert_eq!(body["result"]["truncated"], false);
+}
+
+#[tokio::test]
+async fn token_lookups_use_the_jupiter_list_then_moralis() {
+    let upstreams = upstreams();
+    let (listed, unlisted, unknown) =
+        (Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string());
+    upstreams.jupiter.set_tokens(vec![json!({
+        "address": listed,
+        "symbol": "LST",
+        "name": "Listed",
+        "logoURI": "https://example.invalid/lst.png",
+    })]);
+    upstreams.moralis.set_token(&unlisted, json!({ "name": "Unlisted", "symbol": "UNL", "logo": null }));
+    spawn_token_cache_loader().await;
+    assert!(upstreams.jupiter.fetches() >= 1);
+
+    let (_, body) = get(&format!("/api/searchTokensByMint?query={}", listed)).await;
+    assert_eq!(body["result"]["symbol"], "LST");
+    let (_, body) = get("/api/searchTokensByName?query=lst").await;
+    assert_eq!(body["results"][0]["address"], listed);
+
+    for _ in 0..2 {
+        let (_, body) = get(&format!("/api/searchTokensByMint?query={}", unlisted)).await;
+        assert_eq!((&body["result"]["name"], &body["result"]["logoURI"]), (&json!("Unlisted"), &json!("")));
+    }
+    let (_, body) = get(&format!("/api/searchTokensByMint?query={}", unknown)).await;
+    assert_eq!(body, json!({ "success": false, "error": "Token not found" }));
+
+    // The second lookup of `unlisted` was served from the cache.
+    let requests = upstreams.moralis.requests();
+    assert_eq!(requests.iter().filter(|mint| **mint == unlisted).count(), 1);
+    assert!(!requests.contains(&listed));
+
+    upstreams.solflare.set_capped_tokens("unl", json!({ "tokens": [{ "address": unlisted }] }));
+    let (_, body) = get("/api/searchToken?query=unl").await;
+    assert_eq!(body["tokens"][0]["address"], unlisted);
+}
+
+#[tokio::test]
+async fn simulate_previews_the_signers_balance_changes() {
+    let upstreams = upstreams();
+    let (signer, recipient, mint, token_account) =
+        (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let rpc = &upstreams.rpc;
+    rpc.set_account(&signer.to_string(), MockAccount::system(1_000_000_000));
+    rpc.set_account(&mint.to_string(), MockAccount::mint(TOKEN_PROGRAM, 6));
+    rpc.set_account(
+        &token_account.to_string(),
+        MockAccount::token(TOKEN_PROGRAM, &mint.to_string(), &signer.to_string(), 5_000_000),
+    );
+    // Sends 0.01 SOL, and 2 of the token as far as the mock is concerned.
+    rpc.set_simulated_account(&signer.to_string(), Some(MockAccount::system(989_995_000)));
+    rpc.set_simulated_account(
+        &token_account.to_string(),
+        Some(MockAccount::token(TOKEN_PROGRAM, &mint.to_string(), &signer.to_string(), 3_000_000)),
+    );
+    rpc.set_simulation(Value::Null, &["Program 11111111111111111111111111111111 success"], 150);
+
+    let message = VersionedMessage::Legacy(Message::new_with_blockhash(
+        &[system::transfer(&signer, &recipient, 10_000_000)],
+        Some(&signer),
+        &Hash::new_unique(),
+    ));
+    let request = json!({
+        "transaction": BASE64.encode(bincode::serialize(&message).unwrap()),
+        "signer": signer.to_string(),
+    });
+    let (status, body) = post("/api/simulate", request.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let result = &body["result"];
+    assert_eq!((&result["status"], &result["unitsConsumed"], &result["networkFee"]), (&json!("success"), &json!(150), &json!("5000")));
+    let changes: Vec<(&Value, &Value, &Value)> = result["balanceChanges"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|change| (&change["token"]["id"], &change["rawAmount"], &change["to"]))
+        .collect();
+    let (sol, token) = (json!("solana:101/nativeToken:501"), json!(format!("solana:101/address:{}", mint)));
+    let unknown = json!("solana:101/address:unknown");
+    assert_eq!(
+        changes,
+        [
+            (&sol, &json!("10000000"), &unknown),
+            (&token, &json!("2000000"), &unknown),
+            (&sol, &json!("5000"), &json!("solana:101/address:solana:101/fee")),
+        ]
+    );
+    assert_eq!(result["balanceChanges"][1]["token"]["decimals"], 6);
+
+    // The simulation queried the signer's token accounts under both programs.
+    let queried: Vec<Value> = rpc.calls("getTokenAccountsByOwner");
+    assert!(queried.iter().any(|params| params[0] == signer.to_string()));
+    assert!(rpc.calls("getAccountInfo").iter().any(|params| params[0] == mint.to_string()));
+
+    // Failed simulations return no state; only the fee is shown.
+    rpc.set_simulation(json!({ "InstructionError": [0, { "Custom": 1 }] }), &["Program failed"], 90);
+    let (_, body) = post("/api/simulate", request).await;
+    rpc.set_simulation(Value::Null, &[], 0);
+    assert_eq!(body["result"]["status"], "failed");
+    assert_eq!(body["result"]["balanceChanges"].as_array().unwrap().len(), 1);
+}
+
+/// A confirmed `jsonParsed` transaction with the fields the reward ledger
+/// reads.
+fn confirmed(transaction: &Transaction, keys: &[Pubkey], balances: [&[u64]; 2], token_balances: [Value; 2]) -> Value {
+    json!({
+        "slot": 300,
+        "blockTime": 1_700_000_000,
+        "meta": {
+            "err": null,
+            "fee": 5_000,
+            "preBalances": balances[0],
+            "postBalances": balances[1],
+            "preTokenBalances": token_balances[0],
+            "postTokenBalances": token_balances[1],
+        },
+        "transaction": {
+            "signatures": [transaction.signatures[0].to_string()],
+            "message": {
+                "accountKeys": keys
+                    .iter()
+                    .map(|key| json!({ "pubkey": key.to_string(), "signer": false, "writable": true }))
+                    .collect::<Vec<_>>(),
+            },
+        },
+    })
+}
+
+fn encoded(transaction: &Transaction) -> String {
+    BASE64.encode(bincode::serialize(&VersionedTransaction::from(transaction.clone())).unwrap())
+}
+
+#[tokio::test]
+async fn landed_back_runs_are_rewarded() {
+    let upstreams = upstreams();
+    let (user, signer) = (Keypair::new(), upstreams.arb_signer.pubkey());
+    let (pool, wsol) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let swap = Transaction::new(
+        &[&user],
+        Message::new(&[system::transfer(&user.pubkey(), &pool, 5_000)], Some(&user.pubkey())),
+        Hash::new_unique(),
+    );
+    let arb = Transaction::new(
+        &[&upstreams.arb_signer],
+        Message::new(&[system::transfer(&signer, &TIP_ACCOUNTS[0], 100_000)], Some(&signer)),
+        Hash::new_unique(),
+    );
+
+    // As confirmed: the back-run takes 0.02 SOL gross into the signer's
+    // wSOL account and pays the tip, the fee and the user's 0.01.
+    let wsol_balance = |amount: u64| {
+        json!([{
+            "accountIndex": 1,
+            "mint": SOL_MINT.to_string(),
+            "owner": signer.to_string(),
+            "uiTokenAmount": { "amount": amount.to_string(), "decimals": 9 },
+        }])
+    };
+    upstreams.rpc.add_transaction(confirmed(
+        &swap,
+        &[user.pubkey(), pool],
+        [&[50_000_000, 0], &[49_995_000, 0]],
+        [json!([]), json!([])],
+    ));
+    upstreams.rpc.add_transaction(confirmed(
+        &arb,
+        &[signer, wsol, TIP_ACCOUNTS[0], user.pubkey(), pool],
+        [
+            &[10_000_000_000, 5_002_039_280, 0, 49_995_000, 0],
+            &[9_989_895_000, 5_022_039_280, 100_000, 59_995_000, 0],
+        ],
+        [wsol_balance(5_000_000_000), wsol_balance(5_020_000_000)],
+    ));
+
+    let (status, body) = post("/api/bundles", json!({ "transactions": [encoded(&swap), encoded(&arb)] })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let bundle_id = body["result"]["bundleId"].as_str().unwrap().to_string();
+    assert!(upstreams.block_engine.bundles().iter().any(|bundle| bundle.bundle_id == bundle_id));
+
+    let landed = wait_for(&format!("/api/bundles/{}", bundle_id), |body| body["result"]["state"] != "pending").await;
+    assert_eq!(landed["result"]["state"], "landed");
+
+    let path = format!("/api/rewards/{}", user.pubkey());
+    let rewards = wait_for(&path, |body| body["result"]["rewards"].as_array().is_some_and(|r| !r.is_empty())).await;
+    let reward = &rewards["result"]["rewards"][0];
+    assert_eq!(reward["arbSignature"], arb.signatures[0].to_string());
+    assert_eq!(reward["swapSignature"], swap.signatures[0].to_string());
+    assert_eq!(reward["mint"], SOL_MINT.to_string());
+    assert_eq!(reward["userReward"], 10_000_000);
+
+    let (_, stats) = get("/api/rewards/stats").await;
+    assert!(stats["result"]["backruns"].as_u64().unwrap() >= 1);
+}
+
+#[tokio::test]
+async fn tip_floor_is_sampled_from_the_block_engine() {
+    let upstreams = upstreams();
+    upstreams.block_engine.set_tip_floor(json!([{
+        "time": "2025-06-01T12:00:00Z",
+        "landed_tips_25th_percentile": 0.000002,
+        "landed_tips_50th_percentile": 0.00002,
+        "landed_tips_75th_percentile": 0.0002,
+        "landed_tips_95th_percentile": 0.002,
+        "landed_tips_99th_percentile": 0.02,
+        "ema_landed_tips_50th_percentile": 0.00002,
+    }]));
+    spawn_tip_floor_poller();
+    for _ in 0..50 {
+        if tip_floors().latest().is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let (status, body) = get("/api/fetchJitoTipFloor").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["result"]["data"][0]["landed_tips_50th_percentile"], 0.00002);
+}
@@ -1 +1,3 @@
-pub mod api_routes;
\ No newline at end of file
+pub mod api_routes;
+#[cfg(test)]
+mod e2e_tests;
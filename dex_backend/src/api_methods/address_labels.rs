@@ -0,0 +1,175 @@
+use crate::labels::registry::{labels, ContactError};
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::time::{SystemTime, UNIX_EPOCH};
+use warp::http::StatusCode;
+use warp::{reply, Rejection, Reply};
+
+/// How far the signed timestamp may be from the server clock, either way.
+const SIGNATURE_MAX_AGE_SECS: u64 = 300;
+
+#[derive(Debug, Deserialize)]
+pub struct ContactRequest {
+    pub address: String,
+    pub name: String,
+}
+
+/// Contacts saved by `owner`.
+pub async fn list_contacts_handler(owner: String) -> Result<Box<dyn Reply>, Rejection> {
+    if owner.parse::<Pubkey>().is_err() {
+        return Ok(error_reply("Invalid owner address", StatusCode::BAD_REQUEST));
+    }
+
+    Ok(Box::new(reply::json(&serde_json::json!({
+        "success": true,
+        "result": labels().contacts(&owner),
+    }))))
+}
+
+/// Label of a single address as `owner` sees it: their contact first, then
+/// the shared registry.
+pub async fn lookup_label_handler(
+    owner: String,
+    address: String,
+) -> Result<Box<dyn Reply>, Rejection> {
+    Ok(Box::new(reply::json(&serde_json::json!({
+        "success": true,
+        "result": labels().lookup(Some(&owner), &address),
+    }))))
+}
+
+/// What `owner` signs, base58 in `x-signature`, to change a contact. The
+/// unix timestamp goes in `x-signature-timestamp` and bounds replays.
+pub fn contact_auth_message(owner: &str, action: &str, address: &str, timestamp: u64, name: &str) -> String {
+    format!("equiswap:labels:{}:{}:{}:{}:{}", owner, action, address, timestamp, name)
+}
+
+pub async fn set_contact_handler(
+    owner: String,
+    signature: Option<String>,
+    timestamp: Option<String>,
+    req: ContactRequest,
+) -> Result<Box<dyn Reply>, Rejection> {
+    if owner.parse::<Pubkey>().is_err() || req.address.parse::<Pubkey>().is_err() {
+        return Ok(error_reply("Invalid address", StatusCode::BAD_REQUEST));
+    }
+    let name = req.name.trim();
+    if let Err(message) = verify_owner(&owner, signature, timestamp, |timestamp| {
+        contact_auth_message(&owner, "set", &req.address, timestamp, name)
+    }) {
+        return Ok(error_reply(message, StatusCode::UNAUTHORIZED));
+    }
+
+    match labels().set_contact(&owner, &req.address, name) {
+        Ok(()) => {}
+        Err(ContactError::Io(e)) => {
+            eprintln!("[labels] Failed to save contacts: {}", e);
+            return Ok(error_reply(
+                "Failed to save contact",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ));
+        }
+        Err(e) => return Ok(error_reply(&e.to_string(), StatusCode::BAD_REQUEST)),
+    }
+
+    Ok(Box::new(reply::json(&serde_json::json!({
+        "success": true,
+        "result": labels().lookup(Some(&owner), &req.address),
+    }))))
+}
+
+pub async fn delete_contact_handler(
+    owner: String,
+    address: String,
+    signature: Option<String>,
+    timestamp: Option<String>,
+) -> Result<Box<dyn Reply>, Rejection> {
+    if let Err(message) = verify_owner(&owner, signature, timestamp, |timestamp| {
+        contact_auth_message(&owner, "delete", &address, timestamp, "")
+    }) {
+        return Ok(error_reply(message, StatusCode::UNAUTHORIZED));
+    }
+
+    match labels().remove_contact(&owner, &address) {
+        Ok(true) => Ok(Box::new(reply::json(&serde_json::json!({ "success": true })))),
+        Ok(false) => Ok(error_reply("Contact not found", StatusCode::NOT_FOUND)),
+        Err(e) => {
+            eprintln!("[labels] Failed to save contacts: {}", e);
+            Ok(error_reply(
+                "Failed to delete contact",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ))
+        }
+    }
+}
+
+/// Checks that `owner` signed `message(timestamp)` recently.
+fn verify_owner(
+    owner: &str,
+    signature: Option<String>,
+    timestamp: Option<String>,
+    message: impl FnOnce(u64) -> String,
+) -> Result<(), &'static str> {
+    let owner = owner.parse::<Pubkey>().map_err(|_| "Invalid owner address")?;
+    let signature = signature
+        .and_then(|signature| signature.parse::<Signature>().ok())
+        .ok_or("Missing or invalid 'x-signature' header")?;
+    let timestamp = timestamp
+        .and_then(|timestamp| timestamp.trim().parse::<u64>().ok())
+        .ok_or("Missing or invalid 'x-signature-timestamp' header")?;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    if now.abs_diff(timestamp) > SIGNATURE_MAX_AGE_SECS {
+        return Err("Signature timestamp expired");
+    }
+    if !signature.verify(owner.as_ref(), message(timestamp).as_bytes()) {
+        return Err("Signature does not match the owner");
+    }
+    Ok(())
+}
+
+fn error_reply(message: &str, status: StatusCode) -> Box<dyn Reply> {
+    Box::new(reply::with_status(
+        reply::json(&serde_json::json!({
+            "success": false,
+            "error": message
+        })),
+        status,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Keypair;
+    use solana_sdk::signer::Signer;
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    #[test]
+    fn only_a_fresh_signature_by_the_owner_passes() {
+        let owner = Keypair::new();
+        let owner_address = owner.pubkey().to_string();
+        let address = Pubkey::new_unique().to_string();
+        let signed = |by: &Keypair, timestamp: u64| {
+            let message = contact_auth_message(&owner_address, "set", &address, timestamp, "Alice");
+            Some(by.sign_message(message.as_bytes()).to_string())
+        };
+        let verify = |signature: Option<String>, timestamp: u64| {
+            verify_owner(&owner_address, signature, Some(timestamp.to_string()), |timestamp| {
+                contact_auth_message(&owner_address, "set", &address, timestamp, "Alice")
+            })
+        };
+
+        let timestamp = now();
+        assert_eq!(verify(signed(&owner, timestamp), timestamp), Ok(()));
+        assert!(verify(signed(&Keypair::new(), timestamp), timestamp).is_err());
+        assert!(verify(None, timestamp).is_err());
+
+        let stale = timestamp - SIGNATURE_MAX_AGE_SECS - 1;
+        assert_eq!(verify(signed(&owner, stale), stale), Err("Signature timestamp expired"));
+    }
+}
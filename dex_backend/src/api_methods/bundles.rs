@@ -0,0 +1,103 @@
+use crate::arb::rewards::spawn_reward_recorder;
+use crate::jito::bundles::{bundle_tracker, check_bundle, BundleStatus};
+use crate::jito::random_tip_account;
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::sync::watch;
+use warp::filters::ws::{Message, WebSocket, Ws};
+use warp::http::StatusCode;
+use warp::{reply, Rejection, Reply};
+
+#[derive(Debug, Deserialize)]
+pub struct BundleRequest {
+    /// Signed transactions, base64, in bundle order.
+    pub transactions: Vec<String>,
+}
+
+/// `POST /api/bundles`
+///
+/// Submits the bundle to every configured block engine and returns its
+/// status; follow it on `GET /api/bundles/{id}` or the WebSocket at
+/// `/api/stream/bundle/{id}`. Back-runs in it are recorded in the reward
+/// ledger once it lands.
+pub async fn submit_bundle_handler(req: BundleRequest) -> Result<Box<dyn Reply>, Rejection> {
+    let signatures = match check_bundle(&req.transactions) {
+        Ok(signatures) => signatures,
+        Err(e) => return Ok(error_reply(&e.to_string(), StatusCode::BAD_REQUEST)),
+    };
+    match bundle_tracker().submit(req.transactions, signatures).await {
+        Ok(status) => {
+            if let Some(updates) = bundle_tracker().watch(&status.bundle_id) {
+                spawn_reward_recorder(updates);
+            }
+            Ok(Box::new(reply::json(&serde_json::json!({
+                "success": true,
+                "result": status,
+            }))))
+        }
+        Err(e) => {
+            eprintln!("[jito] Bundle submission failed: {}", e);
+            Ok(error_reply(&e.to_string(), StatusCode::BAD_GATEWAY))
+        }
+    }
+}
+
+/// `GET /api/bundles/{id}`
+pub async fn bundle_status_handler(bundle_id: String) -> Result<Box<dyn Reply>, Rejection> {
+    match bundle_tracker().status(&bundle_id) {
+        Some(status) => Ok(Box::new(reply::json(&serde_json::json!({
+            "success": true,
+            "result": status,
+        })))),
+        None => Ok(error_reply("Unknown bundle", StatusCode::NOT_FOUND)),
+    }
+}
+
+/// `GET /api/bundles/tipAccount`: the tip account a client building its own
+/// bundle should pay, picked at random to spread write locks.
+pub async fn tip_account_handler() -> Result<Box<dyn Reply>, Rejection> {
+    Ok(Box::new(reply::json(&serde_json::json!({
+        "success": true,
+        "result": random_tip_account().to_string(),
+    }))))
+}
+
+/// `GET /api/stream/bundle/{id}` with a WebSocket upgrade. Pushes the
+/// status on every change and closes once the bundle is final.
+pub async fn bundle_ws_handler(bundle_id: String, ws: Ws) -> Result<Box<dyn Reply>, Rejection> {
+    match bundle_tracker().watch(&bundle_id) {
+        Some(updates) => Ok(Box::new(ws.on_upgrade(move |socket| bundle_ws_session(socket, updates)))),
+        None => Ok(error_reply("Unknown bundle", StatusCode::NOT_FOUND)),
+    }
+}
+
+async fn bundle_ws_session(socket: WebSocket, mut updates: watch::Receiver<BundleStatus>) {
+    let (mut outgoing, mut incoming) = socket.split();
+    loop {
+        tokio::select! {
+            changed = updates.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                let status = updates.borrow_and_update().clone();
+                let Ok(payload) = serde_json::to_string(&status) else { break };
+                if outgoing.send(Message::text(payload)).await.is_err() || status.state.is_final() {
+                    break;
+                }
+            }
+            message = incoming.next() => match message {
+                Some(Ok(message)) if message.is_close() => break,
+                Some(Ok(_)) => {}
+                _ => break,
+            },
+        }
+    }
+    let _ = outgoing.send(Message::close()).await;
+}
+
+fn error_reply(message: &str, status: StatusCode) -> Box<dyn Reply> {
+    Box::new(reply::with_status(
+        reply::json(&serde_json::json!({ "success": false, "error": message })),
+        status,
+    ))
+}
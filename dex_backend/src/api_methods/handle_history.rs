@@ -1,8 +1,13 @@
+use crate::labels::registry::{labels, AddressLabel};
+use crate::rpc::rpc_url::get_rpc_url;
+use crate::tx_errors::decode::{decode_transaction_error, TxFailure};
+use crate::utils::token_amount::{format_token_amount, parse_token_amount, serialize_as_string};
+use crate::utils::upstreams::solflare_activity_api_url;
 use crate::{REQWEST_CLIENT, SEEN_SIGNATURES};
 use anyhow::Result;
 use dashmap::DashSet;
 use dotenv::dotenv;
-use futures::stream::{iter, FuturesUnordered, StreamExt};
+use futures::stream::{self, iter, FuturesUnordered, Stream, StreamExt};
 use mpl_token_metadata::accounts::Metadata;
 use mpl_token_metadata::ID as TOKEN_METADATA_PROGRAM_ID;
 use reqwest::Client;
@@ -13,7 +18,6 @@ use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::pubkey::Pubkey;
 use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
-use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
 use warp::http::StatusCode;
@@ -70,10 +74,17 @@ pub struct InteractionData {
 
 #[derive(Debug, Serialize)]
 pub struct BalanceChange {
+    /// Exact UI amount, formatted from `rawAmount` and `token.decimals`.
     pub amount: String,
+    #[serde(serialize_with = "serialize_as_string")]
+    pub rawAmount: u128,
     pub from: String,
     pub to: String,
     pub token: TokenInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fromLabel: Option<AddressLabel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toLabel: Option<AddressLabel>,
 }
 
 #[derive(Debug, Serialize)]
@@ -99,10 +110,15 @@ pub struct ChainMeta {
     pub transactionId: String,
     pub status: String,
     pub networkFee: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feePayer: Option<String>,
+    /// Decoded `meta.err`; only the RPC path has enough data to fill it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<TxFailure>,
 }
 
 #[derive(Debug, Clone)]
-struct TokenMetadata {
+pub(crate) struct TokenMetadata {
     pub(crate) name: String,
     pub(crate) symbol: String,
     logoURI: String,
@@ -111,18 +127,26 @@ struct TokenMetadata {
 
 const MAX_RETRIES: usize = 3;
 const RETRY_DELAYS: [u64; 3] = [1, 1, 1];
+/// Seconds between `getTransaction` polls for a transaction the node does
+/// not return yet, doubling like `arb::rewards`.
+const NOT_FOUND_RETRY_DELAYS: [u64; MAX_RETRIES] = [1, 2, 4];
 const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+const FEE_ADDRESS: &str = "solana:101/address:solana:101/fee";
+const HISTORY_PAGE_SIZE: usize = 1000;
+const CONCURRENT_TX_FETCHES: usize = 8;
 
 pub async fn fetch_token_metadata(mint: &str) -> Option<TokenMetadata> {
     println!("Fetching token metadata for mint: {}", mint);
 
     let fetch_token_metadata = Instant::now();
     // First: try local metadata API
+    let metadata_api =
+        std::env::var("TOKEN_METADATA_API").unwrap_or_else(|_| "http://localhost:7777".into());
     let client = Client::new();
     if let Ok(response) = client
         .get(&format!(
-            "http://localhost:7777/api/searchTokensByMint?query={}",
-            mint
+            "{}/api/searchTokensByMint?query={}",
+            metadata_api, mint
         ))
         .send()
         .await
@@ -187,7 +211,7 @@ pub async fn fetch_token_metadata(mint: &str) -> Option<TokenMetadata> {
     None
 }
 
-async fn async_normalize_transaction(tx_data: Value, wallet: &Pubkey) -> Option<NormalizedTx> {
+pub(crate) async fn async_normalize_transaction(tx_data: Value, wallet: &Pubkey) -> Option<NormalizedTx> {
     println!("Normalizing transaction data for wallet: {}", wallet);
     let async_normalize_transaction = Instant::now();
 
@@ -200,10 +224,14 @@ async fn async_normalize_transaction(tx_data: Value, wallet: &Pubkey) -> Option<
     let wallet_address = wallet.to_string();
     let mut balance_changes = Vec::new();
 
+    // jsonParsed returns account keys as objects, raw encodings as strings
+    let account_key = |k: &Value| k.as_str().or_else(|| k["pubkey"].as_str()).map(String::from);
+    let fee_payer = account_keys.first().and_then(account_key);
+
     // Process SOL balance changes
     if let Some(wallet_index) = account_keys
         .iter()
-        .position(|k| k.as_str() == Some(&wallet_address))
+        .position(|k| account_key(k).as_deref() == Some(wallet_address.as_str()))
     {
         let pre_balances = meta.get("preBalances")?.as_array()?;
         let post_balances = meta.get("postBalances")?.as_array()?;
@@ -219,26 +247,19 @@ async fn async_normalize_transaction(tx_data: Value, wallet: &Pubkey) -> Option<
             .get(wallet_index)
             .and_then(|v| v.as_u64())
             .unwrap_or(0);
-        let delta_sol = post_sol as i64 - pre_sol as i64;
+        // Only the fee payer (index 0) is charged the fee; it is reported
+        // separately below, so keep it out of the transfer delta.
+        let fee = meta.get("fee").and_then(|f| f.as_u64()).unwrap_or(0);
+        let paid_fee = if wallet_index == 0 { fee } else { 0 };
+        let delta_sol = post_sol as i128 - pre_sol as i128 + paid_fee as i128;
 
         if delta_sol != 0 {
-            let amount = delta_sol.abs() as f64 / 1e9;
-            let (from, to) = if delta_sol > 0 {
-                ("unknown", &wallet_address)
-            } else {
-                (wallet_address.as_str(), &"unknown".to_string())
-            };
-            balance_changes.push(create_sol_change(&from.to_string(), to, amount));
+            balance_changes.push(sol_delta_change(&wallet_address, delta_sol));
         }
 
         // Process fee as separate balance change
-        let fee = meta.get("fee").and_then(|f| f.as_u64()).unwrap_or(0);
-        if fee > 0 {
-            balance_changes.push(create_sol_change(
-                &wallet_address,
-                &"solana:101/fee".to_string(),
-                fee as f64 / 1e9,
-            ));
+        if paid_fee > 0 {
+            balance_changes.push(fee_change(&wallet_address, paid_fee));
         }
     }
 
@@ -263,46 +284,25 @@ async fn async_normalize_transaction(tx_data: Value, wallet: &Pubkey) -> Option<
 
     // Calculate balance changes per mint
     for mint in mints {
-        let pre_amount = sum_token_balances(&pre_token_balances, &wallet_address, &mint);
-        let post_amount = sum_token_balances(&post_token_balances, &wallet_address, &mint);
-        let delta = post_amount - pre_amount;
+        let pre_amount = sum_token_balances(pre_token_balances, &wallet_address, &mint);
+        let post_amount = sum_token_balances(post_token_balances, &wallet_address, &mint);
+        let delta = post_amount as i128 - pre_amount as i128;
 
-        if delta == 0.0 {
+        if delta == 0 {
             continue;
         }
 
-        let metadata = metadata_map
-            .get(&mint)
-            .cloned()
-            .unwrap_or_else(|| TokenMetadata {
-                name: "Unknown Token".into(),
-                symbol: "UNKNOWN".into(),
-                logoURI: "".into(),
-                decimals: get_decimals_from_balance(
-                    &pre_token_balances,
-                    &post_token_balances,
-                    &mint,
-                ),
-            });
-
-        let (from, to) = if delta > 0.0 {
-            ("unknown", &wallet_address)
-        } else {
-            (wallet_address.as_str(), &"unknown".to_string())
-        };
-
-        balance_changes.push(BalanceChange {
-            amount: delta.abs().to_string(),
-            from: format_address(&from.to_string()),
-            to: format_address(to),
-            token: TokenInfo {
-                id: format!("solana:101/address:{}", mint),
-                displayName: metadata.name,
-                symbol: metadata.symbol,
-                decimals: metadata.decimals,
-                logoURI: metadata.logoURI,
-            },
-        });
+        // Decimals on the balance entries are authoritative for the raw amounts
+        let decimals = get_decimals_from_balance(pre_token_balances, post_token_balances, &mint)
+            .or_else(|| metadata_map.get(&mint).map(|metadata| metadata.decimals))
+            .unwrap_or(0);
+        balance_changes.push(token_delta_change(
+            &wallet_address,
+            &mint,
+            delta,
+            decimals,
+            metadata_map.get(&mint),
+        ));
     }
 
     if balance_changes.is_empty() {
@@ -314,52 +314,57 @@ async fn async_normalize_transaction(tx_data: Value, wallet: &Pubkey) -> Option<
         async_normalize_transaction.elapsed()
     );
 
-    Some(NormalizedTx {
-        id: format!("solana:101/tx:{}", signature),
-        timestamp: block_time,
-        interactionData: InteractionData {
-            transactionType: "TRANSFER".into(),
-            balanceChanges: balance_changes,
-        },
-        chainMeta: ChainMeta {
-            transactionId: signature.to_string(),
-            status: if meta.get("err")?.is_null() {
-                "success"
-            } else {
-                "failed"
-            }
-            .into(),
-            networkFee: meta
-                .get("fee")
-                .and_then(|f| f.as_u64())
-                .unwrap_or(0)
-                .to_string(),
+    Some(with_labels(
+        NormalizedTx {
+            id: format!("solana:101/tx:{}", signature),
+            timestamp: block_time,
+            interactionData: InteractionData {
+                transactionType: "TRANSFER".into(),
+                balanceChanges: balance_changes,
+            },
+            chainMeta: ChainMeta {
+                transactionId: signature.to_string(),
+                status: if meta.get("err")?.is_null() {
+                    "success"
+                } else {
+                    "failed"
+                }
+                .into(),
+                networkFee: meta
+                    .get("fee")
+                    .and_then(|f| f.as_u64())
+                    .unwrap_or(0)
+                    .to_string(),
+                feePayer: fee_payer,
+                failure: decode_transaction_error(&tx_data),
+            },
         },
-    })
+        &wallet_address,
+    ))
 }
 
-fn sum_token_balances(balances: &[Value], owner: &str, mint: &str) -> f64 {
+/// Sum of raw base units (`uiTokenAmount.amount`) held by `owner` in `mint`.
+fn sum_token_balances(balances: &[Value], owner: &str, mint: &str) -> u128 {
     balances
         .iter()
         .filter(|b| b["owner"].as_str() == Some(owner) && b["mint"].as_str() == Some(mint))
-        .filter_map(|b| b["uiTokenAmount"]["uiAmount"].as_f64())
+        .filter_map(|b| b["uiTokenAmount"]["amount"].as_str()?.parse::<u128>().ok())
         .sum()
 }
 
-fn get_decimals_from_balance(pre: &[Value], post: &[Value], mint: &str) -> u8 {
+fn get_decimals_from_balance(pre: &[Value], post: &[Value], mint: &str) -> Option<u8> {
     // Check pre balances first
     pre.iter()
         .chain(post.iter())
         .find(|b| b["mint"].as_str() == Some(mint))
         .and_then(|b| b["uiTokenAmount"]["decimals"].as_u64())
         .map(|d| d as u8)
-        .unwrap_or(0)
 }
 
-async fn get_parsed_transaction(signature: &str) -> Result<Value> {
+pub(crate) async fn get_parsed_transaction(signature: &str) -> Result<Value> {
     println!("Fetching transaction data for signature: {}", signature);
     let mut attempts = 0;
-    let mut rpc_url = "http://frankfurt.o7node.com:7799";
+    let rpc_url = get_rpc_url();
     let get_parsed_transaction = Instant::now();
     loop {
         let request_body = json!({
@@ -379,7 +384,7 @@ async fn get_parsed_transaction(signature: &str) -> Result<Value> {
         match REQWEST_CLIENT
             .get()
             .unwrap()
-            .post(rpc_url)
+            .post(&rpc_url)
             .json(&request_body)
             .send()
             .await
@@ -393,6 +398,11 @@ async fn get_parsed_transaction(signature: &str) -> Result<Value> {
                 if let Some(result) = json["result"].as_object() {
                     return Ok(json!(result));
                 }
+                if attempts >= MAX_RETRIES {
+                    return Err(anyhow::anyhow!("Transaction {} not found", signature));
+                }
+                tokio::time::sleep(Duration::from_secs(NOT_FOUND_RETRY_DELAYS[attempts])).await;
+                attempts += 1;
             }
             Err(e) => {
                 if attempts >= MAX_RETRIES {
@@ -462,13 +472,126 @@ async fn get_parsed_transaction(signature: &str) -> Result<Value> {
 //     }
 // }
 
+/// One page of `getSignaturesForAddress`, newest first. Pass the last
+/// signature of the previous page as `before` to walk further back.
+pub(crate) async fn fetch_signatures_page(
+    pubkey: &Pubkey,
+    before: Option<String>,
+    limit: usize,
+) -> Result<Vec<Value>> {
+    let request_body = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "getSignaturesForAddress",
+        "params": [
+            pubkey.to_string(),
+            {
+                "limit": limit,
+                "before": before,
+                "commitment": "confirmed"
+            }
+        ]
+    });
+
+    let json: Value = REQWEST_CLIENT
+        .get()
+        .unwrap()
+        .post(get_rpc_url())
+        .json(&request_body)
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    match json["result"].as_array() {
+        Some(result) => Ok(result.clone()),
+        None => Err(anyhow::anyhow!(
+            "getSignaturesForAddress failed: {}",
+            json["error"]
+        )),
+    }
+}
+
+/// Normalized transactions for `pubkey` with block time inside `[from, to]`,
+/// newest first. Signature pages are fetched lazily as the stream is polled;
+/// an `Err` item means paging failed and nothing further will be yielded.
+pub(crate) fn normalized_history_stream(
+    pubkey: Pubkey,
+    from: u64,
+    to: u64,
+) -> impl Stream<Item = Result<NormalizedTx>> {
+    normalize_signatures(history_signature_stream(pubkey, from, to), pubkey)
+}
+
+/// Signatures of `pubkey` with block time inside `[from, to]`, newest first,
+/// without fetching the transactions. An `Err` item ends the stream.
+pub(crate) fn history_signature_stream(pubkey: Pubkey, from: u64, to: u64) -> impl Stream<Item = Result<String>> {
+    stream::unfold(Some(None::<String>), move |cursor| async move {
+        let before = cursor?;
+        let page = match fetch_signatures_page(&pubkey, before, HISTORY_PAGE_SIZE).await {
+            Ok(page) => page,
+            Err(e) => return Some((vec![Err(e)], None)),
+        };
+
+        let mut reached_start = false;
+        let signatures: Vec<Result<String>> = page
+            .iter()
+            .filter_map(|entry| {
+                let block_time = entry["blockTime"].as_u64()?;
+                if block_time < from {
+                    reached_start = true;
+                    return None;
+                }
+                if block_time > to {
+                    return None;
+                }
+                entry["signature"].as_str().map(|s| Ok(s.to_string()))
+            })
+            .collect();
+
+        let next_cursor = page
+            .last()
+            .and_then(|entry| entry["signature"].as_str())
+            .filter(|_| !reached_start && page.len() == HISTORY_PAGE_SIZE)
+            .map(|s| Some(s.to_string()));
+
+        Some((signatures, next_cursor))
+    })
+    .flat_map(stream::iter)
+}
+
+/// Fetches and normalizes each signature in order, skipping transactions
+/// that cannot be fetched. `Err` items are passed through.
+pub(crate) fn normalize_signatures(
+    signatures: impl Stream<Item = Result<String>>,
+    pubkey: Pubkey,
+) -> impl Stream<Item = Result<NormalizedTx>> {
+    signatures
+        .map(move |signature| async move {
+            let signature = match signature {
+                Ok(signature) => signature,
+                Err(e) => return Some(Err(e)),
+            };
+            match get_parsed_transaction(&signature).await {
+                Ok(tx_data) => async_normalize_transaction(tx_data, &pubkey).await.map(Ok),
+                Err(e) => {
+                    eprintln!("[history] Skipping {}: {}", signature, e);
+                    None
+                }
+            }
+        })
+        .buffered(CONCURRENT_TX_FETCHES)
+        .filter_map(|item| async move { item })
+}
+
 pub async fn fetch_solflare_signatures(
     pubkey: &Pubkey,
     limit: usize,
 ) -> Result<Vec<SolflareSignature>> {
     let client = Client::new();
     let url = format!(
-        "https://activity-api.solflare.com/v1/signatures?address={}&network=mainnet&ignoreFailed=0&limit={}",
+        "{}/v1/signatures?address={}&network=mainnet&ignoreFailed=0&limit={}",
+        solflare_activity_api_url(),
         pubkey.to_string(),
         limit
     );
@@ -493,56 +616,51 @@ pub async fn fetch_solflare_signatures(
         .text()
         .await?;
 
+    Ok(parse_solflare_signature_chunks(&response))
+}
+
+/// Parses a `/v1/signatures` response body. Entries without a hash are
+/// dropped; chunks that fail to decode are skipped.
+pub(crate) fn parse_solflare_signature_chunks(raw_text: &str) -> Vec<SolflareSignature> {
     let mut signatures = Vec::new();
-    for chunk in response.split("<|EOF|>") {
-        if chunk.trim().is_empty() {
+    for json in solflare_chunks(raw_text) {
+        let Some(data) = json["data"].as_array() else {
             continue;
-        }
+        };
 
-        match serde_json::from_str::<Value>(chunk.trim()) {
-            Ok(json) => {
-                if let Some(data) = json["data"].as_array() {
-                    for entry in data {
-                        let hash = entry
-                            .get("hash")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("")
-                            .to_string();
-                        let block_time =
-                            entry.get("blockTime").and_then(|v| v.as_u64()).unwrap_or(0);
-                        let slot = entry.get("slot").and_then(|v| v.as_u64()).unwrap_or(0);
-                        let public_key = entry
-                            .get("publicKey")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("")
-                            .to_string();
-                        let err = entry
-                            .get("err")
-                            .and_then(|v| v.as_str())
-                            .map(|s| s.to_string());
-
-                        if hash.is_empty() {
-                            continue; // Skip malformed entries
-                        }
-
-                        signatures.push(SolflareSignature {
-                            hash,
-                            block_time,
-                            slot,
-                            err,
-                            public_key,
-                        });
-                    }
-                }
-            }
-            Err(e) => {
-                eprintln!("[warn] Skipped malformed chunk: {}", e);
-                continue;
+        for entry in data {
+            let hash = entry
+                .get("hash")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+            let block_time = entry.get("blockTime").and_then(|v| v.as_u64()).unwrap_or(0);
+            let slot = entry.get("slot").and_then(|v| v.as_u64()).unwrap_or(0);
+            let public_key = entry
+                .get("publicKey")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+            let err = entry
+                .get("err")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
+
+            if hash.is_empty() {
+                continue; // Skip malformed entries
             }
+
+            signatures.push(SolflareSignature {
+                hash,
+                block_time,
+                slot,
+                err,
+                public_key,
+            });
         }
     }
 
-    Ok(signatures)
+    signatures
 }
 async fn get_parsed_transaction_solflare(
     signatures: Vec<String>,
@@ -559,7 +677,7 @@ async fn get_parsed_transaction_solflare(
     });
 
     let response = client
-        .post("https://activity-api.solflare.com/v1/transactions?network=mainnet")
+        .post(format!("{}/v1/transactions?network=mainnet", solflare_activity_api_url()))
         .header("accept", "*/*")
         .header("accept-encoding", "gzip, deflate, br, zstd")
         .header("accept-language", "ru,en-US;q=0.9,en;q=0.8,de;q=0.7")
@@ -581,47 +699,34 @@ async fn get_parsed_transaction_solflare(
     println!("[timing] Solflare API call took {:?}", start.elapsed());
 
     let raw_text = response.text().await?;
-    let chunks = raw_text.split("<|EOF|>").filter(|c| !c.trim().is_empty());
-
-    let mut parsed_txs = Vec::new();
-
-    let semaphore = Arc::new(Semaphore::new(10));
-    let mut futures = FuturesUnordered::new();
+    Ok(parse_solflare_transaction_chunks(&raw_text, wallet))
+}
 
-    for chunk in chunks {
-        let json: Value = match serde_json::from_str(chunk.trim()) {
-            Ok(v) => v,
+/// Splits a Solflare `<|EOF|>`-delimited stream into its JSON chunks,
+/// skipping empty and malformed ones.
+fn solflare_chunks(raw_text: &str) -> impl Iterator<Item = Value> + '_ {
+    raw_text
+        .split("<|EOF|>")
+        .filter(|chunk| !chunk.trim().is_empty())
+        .filter_map(|chunk| match serde_json::from_str::<Value>(chunk.trim()) {
+            Ok(json) => Some(json),
             Err(e) => {
-                eprintln!("Error decoding JSON chunk: {e}");
-                continue;
-            }
-        };
-
-        if let Some(arr) = json["data"].as_array() {
-            for tx in arr {
-                if let Some(tx) = tx.as_object() {
-                    let tx = tx.clone();
-                    let wallet = *wallet;
-                    let permit = semaphore.clone().acquire_owned().await.unwrap();
-
-                    futures.push(tokio::spawn(async move {
-                        let _permit = permit;
-                        parse_solflare_tx(tx, &wallet)
-                    }));
-                }
+                eprintln!("[warn] Skipped malformed chunk: {}", e);
+                None
             }
-        }
-    }
-
-    while let Some(result) = futures.next().await {
-        match result {
-            Ok(Some(tx)) => parsed_txs.push(tx),
-            Ok(None) => {} // нормально
-            Err(e) => eprintln!("tokio join error: {e}"),
-        }
-    }
+        })
+}
 
-    Ok(parsed_txs)
+/// Parses a `/v1/transactions` response body into normalized transactions,
+/// in the order Solflare returned them.
+pub(crate) fn parse_solflare_transaction_chunks(raw_text: &str, wallet: &Pubkey) -> Vec<NormalizedTx> {
+    solflare_chunks(raw_text)
+        .flat_map(|json| json["data"].as_array().cloned().unwrap_or_default())
+        .filter_map(|tx| match tx {
+            Value::Object(tx) => parse_solflare_tx(tx, wallet),
+            _ => None,
+        })
+        .collect()
 }
 pub async fn handle_parse_transactions(
     req: TransactionParseRequest,
@@ -637,10 +742,12 @@ pub async fn handle_parse_transactions(
     };
 
     match get_parsed_transaction_solflare(req.signatures, &pubkey).await {
-        Ok(results) => Ok(warp::reply::with_status(
-            warp::reply::json(&PhantomHistoryResponse { results }),
-            StatusCode::OK,
-        )),
+        Ok(results) => {
+            Ok(warp::reply::with_status(
+                warp::reply::json(&PhantomHistoryResponse { results }),
+                StatusCode::OK,
+            ))
+        }
         Err(err) => {
             eprintln!("Failed to fetch Solflare transactions: {}", err);
             Ok(warp::reply::with_status(
@@ -727,12 +834,24 @@ pub fn parse_solflare_tx(
     let mut changes = Vec::new();
 
     // Helper to parse token info
-    let parse_token = |t: &Value| -> Option<(String, String, String, u8)> {
+    // Solflare reports UI amounts; convert to base units exactly. A leg
+    // whose amount does not parse is left out, so say which.
+    let parse_token = |t: &Value| -> Option<(u128, String, String, u8)> {
+        let decimals = t["decimals"].as_u64().unwrap_or(0) as u8;
+        let ui_amount = match &t["amount"] {
+            Value::String(amount) => amount.clone(),
+            Value::Number(amount) => amount.to_string(),
+            _ => "0".to_string(),
+        };
+        let Some(amount) = parse_token_amount(&ui_amount, decimals) else {
+            eprintln!("[history] Dropping leg of {} with unparseable amount {:?}: {}", hash, ui_amount, t);
+            return None;
+        };
         Some((
-            t["amount"].as_str().unwrap_or("0").to_string(),
+            amount,
             t["symbol"].as_str().unwrap_or("UNKNOWN").to_string(),
             t["image"].as_str().unwrap_or("").to_string(),
-            t["decimals"].as_u64().unwrap_or(0) as u8,
+            decimals,
         ))
     };
 
@@ -745,7 +864,8 @@ pub fn parse_solflare_tx(
         for t in positives {
             if let Some((amount, symbol, image, decimals)) = parse_token(t) {
                 changes.push(BalanceChange {
-                    amount,
+                    amount: format_token_amount(amount, decimals),
+                    rawAmount: amount,
                     from: sender.clone(),
                     to: wallet_str.clone(),
                     token: TokenInfo {
@@ -755,6 +875,8 @@ pub fn parse_solflare_tx(
                         decimals,
                         logoURI: image,
                     },
+                    fromLabel: None,
+                    toLabel: None,
                 });
             }
         }
@@ -769,7 +891,8 @@ pub fn parse_solflare_tx(
         for t in negatives {
             if let Some((amount, symbol, image, decimals)) = parse_token(t) {
                 changes.push(BalanceChange {
-                    amount,
+                    amount: format_token_amount(amount, decimals),
+                    rawAmount: amount,
                     from: wallet_str.clone(),
                     to: recipient.clone(),
                     token: TokenInfo {
@@ -779,6 +902,8 @@ pub fn parse_solflare_tx(
                         decimals,
                         logoURI: image,
                     },
+                    fromLabel: None,
+                    toLabel: None,
                 });
             }
         }
@@ -794,22 +919,27 @@ pub fn parse_solflare_tx(
         })
         .unwrap_or_else(|| "0".into());
 
-    Some(NormalizedTx {
-        id: format!("solana:101/tx:{}", hash),
-        timestamp,
-        interactionData: InteractionData {
-            transactionType: tx_type.to_string(),
-            balanceChanges: changes,
-        },
-        chainMeta: ChainMeta {
-            transactionId: hash.into(),
-            status: if status { "failed" } else { "success" }.into(),
-            networkFee: fee,
+    Some(with_labels(
+        NormalizedTx {
+            id: format!("solana:101/tx:{}", hash),
+            timestamp,
+            interactionData: InteractionData {
+                transactionType: tx_type.to_string(),
+                balanceChanges: changes,
+            },
+            chainMeta: ChainMeta {
+                transactionId: hash.into(),
+                status: if status { "failed" } else { "success" }.into(),
+                networkFee: fee,
+                feePayer: None,
+                failure: None,
+            },
         },
-    })
+        &wallet_str,
+    ))
 }
 
-async fn fetch_metadata_concurrently(mints: HashSet<String>) -> HashMap<String, TokenMetadata> {
+pub(crate) async fn fetch_metadata_concurrently(mints: HashSet<String>) -> HashMap<String, TokenMetadata> {
     println!("Fetching metadata for mints: {:?}", mints);
     let mut metadata_futures = FuturesUnordered::new();
     for mint in mints {
@@ -919,9 +1049,10 @@ pub async fn handle_signatures(req: SignatureRequest) -> Result<impl Reply, warp
     }
 }
 
-fn create_sol_change(from: &String, to: &String, amount: f64) -> BalanceChange {
+fn create_sol_change(from: &String, to: &String, lamports: u64) -> BalanceChange {
     BalanceChange {
-        amount: amount.to_string(),
+        amount: format_token_amount(lamports as u128, 9),
+        rawAmount: lamports as u128,
         from: format_address(from),
         to: format_address(to),
         token: TokenInfo {
@@ -931,18 +1062,63 @@ fn create_sol_change(from: &String, to: &String, amount: f64) -> BalanceChange {
             decimals: 9,
             logoURI: SOL_LOGO.into(),
         },
+        fromLabel: None,
+        toLabel: None,
+    }
+}
+
+/// The network fee `wallet` paid, as a SOL change to `solana:101/fee`.
+pub(crate) fn fee_change(wallet: &str, lamports: u64) -> BalanceChange {
+    create_sol_change(&wallet.to_string(), &"solana:101/fee".to_string(), lamports)
+}
+
+/// SOL moved into (positive `delta`) or out of `wallet`, counterparty
+/// unknown.
+pub(crate) fn sol_delta_change(wallet: &str, delta: i128) -> BalanceChange {
+    let wallet = wallet.to_string();
+    let unknown = "unknown".to_string();
+    let (from, to) = if delta > 0 { (&unknown, &wallet) } else { (&wallet, &unknown) };
+    create_sol_change(from, to, delta.unsigned_abs() as u64)
+}
+
+/// `mint` moved into (positive `delta`) or out of `wallet`, counterparty
+/// unknown. Without metadata the token is shown as unknown.
+pub(crate) fn token_delta_change(
+    wallet: &str,
+    mint: &str,
+    delta: i128,
+    decimals: u8,
+    metadata: Option<&TokenMetadata>,
+) -> BalanceChange {
+    let (from, to) = if delta > 0 { ("unknown", wallet) } else { (wallet, "unknown") };
+    let raw_amount = delta.unsigned_abs();
+    BalanceChange {
+        amount: format_token_amount(raw_amount, decimals),
+        rawAmount: raw_amount,
+        from: format_address(&from.to_string()),
+        to: format_address(&to.to_string()),
+        token: TokenInfo {
+            id: format!("solana:101/address:{}", mint),
+            displayName: metadata.map_or("Unknown Token".into(), |m| m.name.clone()),
+            symbol: metadata.map_or("UNKNOWN".into(), |m| m.symbol.clone()),
+            decimals,
+            logoURI: metadata.map_or(String::new(), |m| m.logoURI.clone()),
+        },
+        fromLabel: None,
+        toLabel: None,
     }
 }
 
 fn create_token_change(
     from: &String,
     to: &String,
-    amount: f64,
+    raw_amount: u128,
     mint: &String,
     decimals: u8,
 ) -> BalanceChange {
     BalanceChange {
-        amount: amount.to_string(),
+        amount: format_token_amount(raw_amount, decimals),
+        rawAmount: raw_amount,
         from: format_address(from),
         to: format_address(to),
         token: TokenInfo {
@@ -952,7 +1128,38 @@ fn create_token_change(
             decimals,
             logoURI: "".into(),
         },
+        fromLabel: None,
+        toLabel: None,
+    }
+}
+
+pub(crate) const USD_STABLE_MINTS: [&str; 2] = [
+    "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", // USDC
+    "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB", // USDT
+];
+
+/// Network fee rows are emitted as a SOL change paid to `solana:101/fee`.
+pub(crate) fn is_fee_change(change: &BalanceChange) -> bool {
+    change.to == FEE_ADDRESS
+}
+
+/// Mint behind a normalized `token.id`; native SOL maps to the wSOL mint.
+pub(crate) fn mint_from_token_id(token_id: &str) -> String {
+    match token_id.strip_prefix("solana:101/address:") {
+        Some(mint) => mint.to_string(),
+        None => SOL_MINT.to_string(),
+    }
+}
+
+/// Attaches address labels to counterparties, as seen by `owner`. Both
+/// normalizers finish here, so every history path comes out labeled.
+fn with_labels(mut tx: NormalizedTx, owner: &str) -> NormalizedTx {
+    let registry = labels();
+    for change in tx.interactionData.balanceChanges.iter_mut() {
+        change.fromLabel = registry.lookup(Some(owner), &change.from);
+        change.toLabel = registry.lookup(Some(owner), &change.to);
     }
+    tx
 }
 
 fn format_address(address: &String) -> String {
@@ -962,9 +1169,9 @@ fn format_address(address: &String) -> String {
 const SOL_LOGO: &str = "https://raw.githubusercontent.com/solana-labs/token-list/main/assets/mainnet/So11111111111111111111111111111111111111112/logo.png";
 
 use tokio;
-use tokio::sync::Semaphore;
 
 #[tokio::test]
+#[ignore = "hits mainnet metadata sources and localhost:7777"]
 async fn test_token_metadata_resolution() {
     dotenv().ok();
     REQWEST_CLIENT.set(Client::new()).unwrap();
@@ -0,0 +1,277 @@
+use crate::api_methods::handle_history::{
+    is_fee_change, mint_from_token_id, normalized_history_stream, NormalizedTx, USD_STABLE_MINTS,
+};
+use crate::ohlcv::store::candle_store;
+use crate::utils::token_amount::to_ui_f64;
+use futures::channel::mpsc::{channel, Sender};
+use futures::{SinkExt, StreamExt};
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use warp::http::{header, Response, StatusCode};
+use warp::hyper::body::{Body, Bytes};
+use warp::{reply, Rejection, Reply};
+
+const CSV_HEADER: &str =
+    "signature,timestamp,kind,direction,mint,symbol,raw_amount,ui_amount,usd_value,from,to,from_label,to_label,status\n";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// One line of the accounting export: a single swap leg, transfer or fee.
+#[derive(Debug, Serialize)]
+pub struct HistoryExportRow {
+    pub signature: String,
+    pub timestamp: u64,
+    pub kind: String,
+    pub direction: String,
+    pub mint: String,
+    pub symbol: String,
+    pub raw_amount: String,
+    pub ui_amount: String,
+    pub usd_value: Option<f64>,
+    pub from: String,
+    pub to: String,
+    /// Registry or contact names of the counterparties, as the wallet sees them.
+    pub from_label: Option<String>,
+    pub to_label: Option<String>,
+    pub status: String,
+}
+
+pub async fn handle_history_export(
+    query: HashMap<String, String>,
+) -> Result<Box<dyn Reply>, Rejection> {
+    let pubkey = match query.get("address").map(|a| a.parse::<Pubkey>()) {
+        Some(Ok(pk)) => pk,
+        _ => return Ok(bad_request("Invalid or missing 'address' parameter")),
+    };
+
+    let from = match parse_timestamp(query.get("from"), 0) {
+        Some(ts) => ts,
+        None => return Ok(bad_request("Invalid 'from' timestamp")),
+    };
+    let to = match parse_timestamp(query.get("to"), unix_now()) {
+        Some(ts) => ts,
+        None => return Ok(bad_request("Invalid 'to' timestamp")),
+    };
+    if from > to {
+        return Ok(bad_request("'from' must not be after 'to'"));
+    }
+
+    let format = match query.get("format").map(|f| f.as_str()) {
+        None | Some("csv") => ExportFormat::Csv,
+        Some("json") => ExportFormat::Json,
+        Some(_) => return Ok(bad_request("'format' must be csv or json")),
+    };
+
+    let (tx, rx) = channel::<Result<Bytes, std::io::Error>>(16);
+    tokio::spawn(stream_history_rows(pubkey, from, to, format, tx));
+
+    let (content_type, extension) = match format {
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+        ExportFormat::Json => ("application/json", "json"),
+    };
+
+    let response = Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"equiswap-history-{}-{}-{}.{}\"",
+                pubkey, from, to, extension
+            ),
+        )
+        .body(Body::wrap_stream(rx))
+        .unwrap();
+
+    Ok(Box::new(response))
+}
+
+/// Normalizes every transaction inside `[from, to]` and pushes rows into the
+/// response body as they are produced, so memory stays flat regardless of the
+/// range size.
+async fn stream_history_rows(
+    pubkey: Pubkey,
+    from: u64,
+    to: u64,
+    format: ExportFormat,
+    mut tx: Sender<Result<Bytes, std::io::Error>>,
+) {
+    let start = Instant::now();
+    let mut rows_written = 0usize;
+
+    let opening = match format {
+        ExportFormat::Csv => CSV_HEADER,
+        ExportFormat::Json => "[",
+    };
+    if tx.send(Ok(Bytes::from(opening))).await.is_err() {
+        return;
+    }
+
+    let history = normalized_history_stream(pubkey, from, to);
+    futures::pin_mut!(history);
+
+    while let Some(normalized_tx) = history.next().await {
+        let normalized_tx = match normalized_tx {
+            Ok(normalized_tx) => normalized_tx,
+            Err(e) => {
+                eprintln!("[export] Failed to fetch history for {}: {}", pubkey, e);
+                let _ = tx.send(Err(std::io::Error::other(e.to_string()))).await;
+                return;
+            }
+        };
+
+        for row in export_rows(&normalized_tx, &pubkey) {
+            let line = match format {
+                ExportFormat::Csv => csv_line(&row),
+                ExportFormat::Json => {
+                    let separator = if rows_written == 0 { "" } else { "," };
+                    format!("{}{}", separator, serde_json::to_string(&row).unwrap())
+                }
+            };
+            rows_written += 1;
+            if tx.send(Ok(Bytes::from(line))).await.is_err() {
+                // Client went away, stop fetching.
+                return;
+            }
+        }
+    }
+
+    if format == ExportFormat::Json {
+        let _ = tx.send(Ok(Bytes::from("]"))).await;
+    }
+
+    println!(
+        "[export] Exported {} rows for {} in {:?}",
+        rows_written,
+        pubkey,
+        start.elapsed()
+    );
+}
+
+/// Splits a normalized transaction into export rows. A transaction whose
+/// non-fee changes move tokens both in and out of the wallet is a swap.
+fn export_rows(normalized_tx: &NormalizedTx, wallet: &Pubkey) -> Vec<HistoryExportRow> {
+    let wallet_address = format!("solana:101/address:{}", wallet);
+    let changes = &normalized_tx.interactionData.balanceChanges;
+
+    let has_in = changes
+        .iter()
+        .any(|c| !is_fee_change(c) && c.to == wallet_address);
+    let has_out = changes
+        .iter()
+        .any(|c| !is_fee_change(c) && c.from == wallet_address);
+    let trade_kind = if has_in && has_out { "swap" } else { "transfer" };
+
+    changes
+        .iter()
+        .map(|change| {
+            let mint = mint_from_token_id(&change.token.id);
+            let usd_value = usd_value_at(
+                &mint,
+                change.rawAmount,
+                change.token.decimals,
+                normalized_tx.timestamp,
+            );
+
+            HistoryExportRow {
+                signature: normalized_tx.chainMeta.transactionId.clone(),
+                timestamp: normalized_tx.timestamp,
+                kind: if is_fee_change(change) { "fee" } else { trade_kind }.to_string(),
+                direction: if change.to == wallet_address { "in" } else { "out" }.to_string(),
+                raw_amount: change.rawAmount.to_string(),
+                ui_amount: change.amount.clone(),
+                symbol: change.token.symbol.clone(),
+                mint,
+                usd_value,
+                from: strip_address_prefix(&change.from),
+                to: strip_address_prefix(&change.to),
+                from_label: change.fromLabel.as_ref().map(|label| label.name.clone()),
+                to_label: change.toLabel.as_ref().map(|label| label.name.clone()),
+                status: normalized_tx.chainMeta.status.clone(),
+            }
+        })
+        .collect()
+}
+
+/// USD value of an amount at block time: stablecoins at face value, other
+/// mints at the close of their candle against a stablecoin holding the
+/// time. Mints without one are left empty rather than valued at today's
+/// price.
+fn usd_value_at(mint: &str, raw_amount: u128, decimals: u8, timestamp: u64) -> Option<f64> {
+    let amount = to_ui_f64(raw_amount, decimals);
+    if USD_STABLE_MINTS.contains(&mint) {
+        return Some(amount);
+    }
+    let store = candle_store();
+    USD_STABLE_MINTS
+        .iter()
+        .find_map(|stable| store.price_at(mint, stable, timestamp as i64))
+        .map(|price| amount * price)
+}
+
+fn strip_address_prefix(address: &str) -> String {
+    address
+        .strip_prefix("solana:101/address:")
+        .unwrap_or(address)
+        .to_string()
+}
+
+fn csv_line(row: &HistoryExportRow) -> String {
+    let fields = [
+        row.signature.clone(),
+        row.timestamp.to_string(),
+        row.kind.clone(),
+        row.direction.clone(),
+        row.mint.clone(),
+        row.symbol.clone(),
+        row.raw_amount.clone(),
+        row.ui_amount.clone(),
+        row.usd_value.map(|v| v.to_string()).unwrap_or_default(),
+        row.from.clone(),
+        row.to.clone(),
+        row.from_label.clone().unwrap_or_default(),
+        row.to_label.clone().unwrap_or_default(),
+        row.status.clone(),
+    ];
+
+    let escaped: Vec<String> = fields.iter().map(|f| csv_escape(f)).collect();
+    format!("{}\n", escaped.join(","))
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn parse_timestamp(value: Option<&String>, default: u64) -> Option<u64> {
+    match value {
+        None => Some(default),
+        Some(v) if v.trim().is_empty() => Some(default),
+        Some(v) => v.trim().parse::<u64>().ok(),
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn bad_request(message: &str) -> Box<dyn Reply> {
+    Box::new(reply::with_status(
+        reply::json(&serde_json::json!({
+            "success": false,
+            "error": message
+        })),
+        StatusCode::BAD_REQUEST,
+    ))
+}
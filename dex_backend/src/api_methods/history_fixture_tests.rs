@@ -0,0 +1,188 @@
+//! Golden-file tests for history normalization. Payloads recorded from
+//! mainnet with `cargo run --bin record_fixture` sit under
+//! `tests/fixtures/recorded/<wallet>/` and are all checked; the hand-written
+//! ones under `tests/fixtures/rpc` and `tests/fixtures/solflare` cover edge
+//! cases recordings rarely hit. Run with `UPDATE_GOLDEN=1` to rewrite the
+//! goldens after an intentional output change, then review the diff.
+
+use crate::api_methods::handle_history::{
+    async_normalize_transaction, parse_solflare_signature_chunks,
+    parse_solflare_transaction_chunks,
+};
+use crate::api_methods::search_tokens::TokenSearchResult;
+use crate::TOKEN_METADATA_CACHE;
+use dashmap::DashMap;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use warp::{Filter, Reply};
+
+/// Owner of the hand-written fixtures; the other parties are alice and bob.
+const WALLET: &str = "GfsJWjmGXMfct8JMR9Lm9ySUnniZbnGUTQDbT8ipWf9U";
+
+fn fixture_path(relative: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(relative)
+}
+
+fn read_fixture(relative: &str) -> String {
+    std::fs::read_to_string(fixture_path(relative))
+        .unwrap_or_else(|e| panic!("missing fixture {}: {}", relative, e))
+}
+
+fn wallet() -> Pubkey {
+    Pubkey::from_str(WALLET).unwrap()
+}
+
+/// Keeps metadata lookups off the network: `TOKEN_METADATA_API` points at a
+/// local stand-in that answers from the shared cache, seeded with the known
+/// fixture mints. Anything else falls back to "Unknown Token".
+pub(crate) fn offline_metadata() {
+    static METADATA_API: OnceLock<String> = OnceLock::new();
+    METADATA_API.get_or_init(|| {
+        let cache = TOKEN_METADATA_CACHE.get_or_init(DashMap::new);
+        for (mint, name, symbol) in [
+            ("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "USD Coin", "USDC"),
+            ("DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263", "Bonk", "Bonk"),
+        ] {
+            cache.insert(
+                mint.to_string(),
+                TokenSearchResult {
+                    name: name.to_string(),
+                    symbol: symbol.to_string(),
+                    address: mint.to_string(),
+                    logoURI: format!("https://example.invalid/{}.png", symbol),
+                },
+            );
+        }
+
+        let search_by_mint = warp::path!("api" / "searchTokensByMint")
+            .and(warp::query::<HashMap<String, String>>())
+            .map(|query: HashMap<String, String>| {
+                let cache = TOKEN_METADATA_CACHE.get_or_init(DashMap::new);
+                let body = match query.get("query").and_then(|mint| cache.get(mint)) {
+                    Some(token) => json!({ "success": true, "result": token.value().clone() }),
+                    None => json!({ "success": false, "error": "Token not found" }),
+                };
+                warp::reply::json(&body).into_response()
+            })
+            .boxed();
+        let url = dex_test_support::serve(search_by_mint);
+        std::env::set_var("TOKEN_METADATA_API", &url);
+        url
+    });
+}
+
+fn assert_golden(name: &str, actual: &Value) {
+    let path = fixture_path(&format!("golden/{}.json", name));
+    let rendered = format!("{}\n", serde_json::to_string_pretty(actual).unwrap());
+
+    if std::env::var("UPDATE_GOLDEN").is_ok() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, rendered).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!("missing golden {}; run with UPDATE_GOLDEN=1", path.display())
+    });
+    let expected: Value = serde_json::from_str(&expected).unwrap();
+    assert_eq!(
+        &expected,
+        actual,
+        "golden mismatch for {}; run with UPDATE_GOLDEN=1 and review the diff",
+        name
+    );
+}
+
+async fn normalize_rpc_fixture(name: &str) -> Value {
+    offline_metadata();
+    let response: Value = serde_json::from_str(&read_fixture(&format!("rpc/{}.json", name))).unwrap();
+    let normalized = async_normalize_transaction(response["result"].clone(), &wallet()).await;
+    serde_json::to_value(normalized).unwrap()
+}
+
+#[tokio::test]
+async fn rpc_sol_transfer() {
+    let actual = normalize_rpc_fixture("sol_transfer").await;
+    assert_golden("rpc_sol_transfer", &actual);
+}
+
+#[tokio::test]
+async fn rpc_usdc_received() {
+    let actual = normalize_rpc_fixture("usdc_received").await;
+    assert_golden("rpc_usdc_received", &actual);
+}
+
+#[tokio::test]
+async fn rpc_swap_sol_usdc() {
+    let actual = normalize_rpc_fixture("swap_sol_usdc").await;
+    assert_golden("rpc_swap_sol_usdc", &actual);
+}
+
+#[tokio::test]
+async fn rpc_failed_swap() {
+    let actual = normalize_rpc_fixture("failed_swap").await;
+    assert_golden("rpc_failed_swap", &actual);
+}
+
+#[tokio::test]
+async fn rpc_token2022_transfer() {
+    let actual = normalize_rpc_fixture("token2022_transfer").await;
+    assert_golden("rpc_token2022_transfer", &actual);
+}
+
+#[tokio::test]
+async fn rpc_closed_account() {
+    let actual = normalize_rpc_fixture("closed_account").await;
+    assert_golden("rpc_closed_account", &actual);
+}
+
+#[test]
+fn solflare_transactions() {
+    let parsed = parse_solflare_transaction_chunks(&read_fixture("solflare/transactions.txt"), &wallet());
+    assert_golden("solflare_transactions", &serde_json::to_value(parsed).unwrap());
+}
+
+#[test]
+fn solflare_signatures() {
+    let parsed = parse_solflare_signature_chunks(&read_fixture("solflare/signatures.txt"));
+    assert_golden("solflare_signatures", &serde_json::to_value(parsed).unwrap());
+}
+
+/// Every recorded payload against its golden under
+/// `golden/recorded/<wallet>/`.
+#[tokio::test]
+async fn recorded_fixtures() {
+    offline_metadata();
+    let Ok(wallets) = std::fs::read_dir(fixture_path("recorded")) else {
+        return;
+    };
+    for wallet_dir in wallets.flatten() {
+        let address = wallet_dir.file_name().to_string_lossy().to_string();
+        let wallet = Pubkey::from_str(&address).unwrap_or_else(|_| panic!("{} is not a wallet address", address));
+        for kind in ["rpc", "solflare_signatures", "solflare_transactions"] {
+            let Ok(files) = std::fs::read_dir(wallet_dir.path().join(kind)) else {
+                continue;
+            };
+            for file in files.flatten() {
+                let path = file.path();
+                let name = path.file_stem().unwrap().to_string_lossy().to_string();
+                let body = std::fs::read_to_string(&path).unwrap();
+                let actual = match kind {
+                    "rpc" => {
+                        let response: Value = serde_json::from_str(&body).unwrap();
+                        serde_json::to_value(async_normalize_transaction(response["result"].clone(), &wallet).await)
+                    }
+                    "solflare_signatures" => serde_json::to_value(parse_solflare_signature_chunks(&body)),
+                    _ => serde_json::to_value(parse_solflare_transaction_chunks(&body, &wallet)),
+                };
+                assert_golden(&format!("recorded/{}/{}_{}", address, kind, name), &actual.unwrap());
+            }
+        }
+    }
+}
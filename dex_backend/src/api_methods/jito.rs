@@ -1,85 +1,58 @@
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use chrono::{DateTime, Utc};
-use solana_client::client_error::reqwest::StatusCode;
-use warp::{Rejection, Reply};
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct JitoBundleTipFloor {
-    /// Timestamp when the tip floor data was recorded
-    pub time: DateTime<Utc>,
-
-    /// 25th percentile of landed tips (in SOL)
-    pub landed_tips_25th_percentile: f64,
-
-    /// 50th percentile (median) of landed tips (in SOL)
-    pub landed_tips_50th_percentile: f64,
-
-    /// 75th percentile of landed tips (in SOL)
-    pub landed_tips_75th_percentile: f64,
-
-    /// 95th percentile of landed tips (in SOL)
-    pub landed_tips_95th_percentile: f64,
-
-    /// 99th percentile of landed tips (in SOL)
-    pub landed_tips_99th_percentile: f64,
-
-    /// Exponential Moving Average of the 50th percentile of landed tips (in SOL)
-    pub ema_landed_tips_50th_percentile: f64,
+use crate::jito::tip_floor::{recommend_tip, tip_floors, Urgency};
+use chrono::Utc;
+use std::collections::HashMap;
+use warp::http::StatusCode;
+use warp::{reply, Rejection, Reply};
+
+/// `GET /api/fetchJitoTipFloor`: the latest sample from the poller.
+pub async fn handle_jito_tip_floor() -> Result<Box<dyn Reply>, Rejection> {
+    match tip_floors().latest() {
+        Some(tip_floor) => Ok(Box::new(reply::json(&serde_json::json!({
+            "success": true,
+            "result": {
+                "data": [tip_floor],
+                "timestamp": Utc::now()
+            }
+        })))),
+        None => Ok(no_samples()),
+    }
 }
 
-/// Request type for the API endpoint
-#[derive(Debug, Deserialize)]
-pub struct JitoApiRequest {
-    /// Optional parameter to filter or customize the request
-    format: Option<String>,
+/// `GET /api/tips/recommend?urgency=low|normal|high|turbo`
+///
+/// The tip to outbid recent bundles at `urgency` (normal by default),
+/// capped per urgency by `JITO_TIP_CAP_<URGENCY>` in SOL.
+pub async fn tip_recommendation_handler(query: HashMap<String, String>) -> Result<Box<dyn Reply>, Rejection> {
+    let urgency = match query.get("urgency").map(|u| u.parse::<Urgency>()) {
+        None => Urgency::Normal,
+        Some(Ok(urgency)) => urgency,
+        Some(Err(e)) => return Ok(error_reply(&e, StatusCode::BAD_REQUEST)),
+    };
+    let Some(tip_floor) = tip_floors().latest() else {
+        return Ok(no_samples());
+    };
+
+    Ok(Box::new(reply::json(&serde_json::json!({
+        "success": true,
+        "result": recommend_tip(&tip_floor, urgency, urgency.cap_sol()),
+    }))))
 }
 
-/// Response type for the API endpoint
-#[derive(Debug, Serialize)]
-pub struct JitoApiResponse {
-    /// Status of the response
-    status: String,
-
-    /// The tip floor data
-    data: Vec<JitoBundleTipFloor>,
-
-    /// Timestamp of when the response was generated
-    timestamp: DateTime<Utc>,
+/// `GET /api/tips/history`: the poller's samples, oldest first.
+pub async fn tip_history_handler() -> Result<Box<dyn Reply>, Rejection> {
+    Ok(Box::new(reply::json(&serde_json::json!({
+        "success": true,
+        "result": tip_floors().history(),
+    }))))
 }
 
-
-async fn fetch_jito_tip_floor(client: &Client) -> Result<Vec<JitoBundleTipFloor>, reqwest::Error> {
-    let response = client
-        .get("https://bundles.jito.wtf/api/v1/bundles/tip_floor")
-        .send()
-        .await?;
-
-    let tip_floors: Vec<JitoBundleTipFloor> = response.json().await?;
-    Ok(tip_floors)
+fn no_samples() -> Box<dyn Reply> {
+    error_reply("Tip floor not sampled yet", StatusCode::SERVICE_UNAVAILABLE)
 }
 
-// Handler function for the Warp route
-pub async fn handle_jito_tip_floor() -> Result<impl Reply, Rejection> {
-    let client = Client::new();
-
-    match fetch_jito_tip_floor(&client).await {
-        Ok(tip_floors) => Ok(warp::reply::json(&serde_json::json!({
-            "success": true,
-            "result": {
-                "data": tip_floors,
-                "timestamp": Utc::now()
-            }
-        }))),
-        Err(e) => {
-            eprintln!("Error fetching Jito tip floor: {}", e);
-            Ok(warp::reply::json(&serde_json::json!({
-                "success": false,
-                "error": e.to_string()
-            })))
-        }
-    }
+fn error_reply(message: &str, status: StatusCode) -> Box<dyn Reply> {
+    Box::new(reply::with_status(
+        reply::json(&serde_json::json!({ "success": false, "error": message })),
+        status,
+    ))
 }
-
-
@@ -1,5 +1,21 @@
 pub mod search_tokens;
 pub mod handle_history;
+pub mod history_export;
 
 pub mod wallet_data;
+pub mod wallet_pnl;
+pub mod wallet_stream;
+pub mod quote_ws;
 pub mod jito;
+pub mod bundles;
+pub mod tx_status;
+pub mod address_labels;
+pub mod pools;
+pub mod simulate;
+pub mod priority_fee;
+pub mod prices;
+pub mod ohlcv;
+pub mod rewards;
+
+#[cfg(test)]
+pub(crate) mod history_fixture_tests;
@@ -0,0 +1,83 @@
+use crate::ohlcv::backfill::spawn_backfill;
+use crate::ohlcv::store::candle_store;
+use crate::ohlcv::Interval;
+use crate::pools::indexer::ensure_discovered;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+use warp::http::StatusCode;
+use warp::{reply, Rejection, Reply};
+
+/// Most candles per response; `from` is moved up to fit.
+const MAX_CANDLES: i64 = 1_000;
+/// Candles returned when `from` is left out.
+const DEFAULT_CANDLES: i64 = 300;
+
+/// `GET /api/ohlcv?base&quote&interval&from&to`
+///
+/// Gap-filled candles of `quote` per `base`; `from` and `to` are unix
+/// seconds. The first request for a pair starts tracking it and backfills
+/// it from recent swaps, so it may come back empty.
+pub async fn ohlcv_handler(params: HashMap<String, String>) -> Result<Box<dyn Reply>, Rejection> {
+    let mint = |key: &str| params.get(key).and_then(|m| Pubkey::from_str(m.trim()).ok());
+    let (Some(base), Some(quote)) = (mint("base"), mint("quote")) else {
+        return Ok(error_reply("Missing or invalid 'base' or 'quote'", StatusCode::BAD_REQUEST));
+    };
+    let interval = match params.get("interval").map(|i| i.parse::<Interval>()) {
+        None => Interval::M15,
+        Some(Ok(interval)) => interval,
+        Some(Err(e)) => return Ok(error_reply(&e, StatusCode::BAD_REQUEST)),
+    };
+    let time = |key: &str| params.get(key).and_then(|t| t.parse::<i64>().ok());
+    let to = time("to").unwrap_or_else(|| chrono::Utc::now().timestamp());
+    let from = time("from")
+        .unwrap_or(to - DEFAULT_CANDLES * interval.seconds())
+        .max(to - (MAX_CANDLES - 1) * interval.seconds());
+    if from > to {
+        return Ok(error_reply("'from' must not be after 'to'", StatusCode::BAD_REQUEST));
+    }
+
+    let (base, quote) = (base.to_string(), quote.to_string());
+    let store = candle_store();
+    let backfilling = store.track(&base, &quote);
+    if backfilling {
+        for mint in [&base, &quote] {
+            if let Err(e) = ensure_discovered(&Pubkey::from_str(mint).unwrap()).await {
+                eprintln!("[ohlcv] Discovery of {} failed: {}", mint, e);
+            }
+        }
+        spawn_backfill(base.clone(), quote.clone());
+    }
+
+    let candles = store.candles(&base, &quote, interval, from, to).unwrap_or_default();
+    let candles: Vec<serde_json::Value> = candles
+        .iter()
+        .map(|c| {
+            serde_json::json!({
+                "time": c.time,
+                "open": c.open,
+                "high": c.high,
+                "low": c.low,
+                "close": c.close,
+                "volume": c.volume,
+            })
+        })
+        .collect();
+    Ok(Box::new(reply::json(&serde_json::json!({
+        "success": true,
+        "result": {
+            "base": base,
+            "quote": quote,
+            "interval": interval.label(),
+            "backfilling": backfilling,
+            "candles": candles,
+        },
+    }))))
+}
+
+fn error_reply(message: &str, status: StatusCode) -> Box<dyn Reply> {
+    Box::new(reply::with_status(
+        reply::json(&serde_json::json!({ "success": false, "error": message })),
+        status,
+    ))
+}
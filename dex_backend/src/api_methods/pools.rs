@@ -0,0 +1,53 @@
+use crate::pools::indexer::ensure_discovered;
+use crate::pools::registry::pool_registry;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+use warp::http::StatusCode;
+use warp::{reply, Rejection, Reply};
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+/// Indexed pools trading `mint`, deepest first, optionally only against
+/// `other`. The first request for a mint discovers its pools and keeps it
+/// tracked from then on.
+pub async fn pools_handler(params: HashMap<String, String>) -> Result<Box<dyn Reply>, Rejection> {
+    let Some(mint) = params.get("mint").and_then(|m| Pubkey::from_str(m.trim()).ok()) else {
+        return Ok(error_reply("Missing or invalid 'mint'", StatusCode::BAD_REQUEST));
+    };
+    let other = match params.get("other").map(|m| Pubkey::from_str(m.trim())) {
+        None => None,
+        Some(Ok(other)) => Some(other),
+        Some(Err(_)) => return Ok(error_reply("Invalid 'other' mint", StatusCode::BAD_REQUEST)),
+    };
+    let limit = params
+        .get("limit")
+        .and_then(|l| l.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_LIMIT)
+        .clamp(1, MAX_LIMIT);
+
+    if let Err(e) = ensure_discovered(&mint).await {
+        eprintln!("[pools] Discovery of {} failed: {}", mint, e);
+        return Ok(error_reply("Failed to discover pools", StatusCode::BAD_GATEWAY));
+    }
+
+    let registry = pool_registry();
+    let mut pools = match other {
+        Some(other) => registry.pools_for_pair(&mint.to_string(), &other.to_string()),
+        None => registry.pools_for_mint(&mint.to_string()),
+    };
+    pools.truncate(limit);
+
+    Ok(Box::new(reply::json(&serde_json::json!({
+        "success": true,
+        "result": pools,
+    }))))
+}
+
+fn error_reply(message: &str, status: StatusCode) -> Box<dyn Reply> {
+    Box::new(reply::with_status(
+        reply::json(&serde_json::json!({ "success": false, "error": message })),
+        status,
+    ))
+}
@@ -0,0 +1,42 @@
+use crate::pools::oracle::price_oracle;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+use warp::http::StatusCode;
+use warp::{reply, Rejection, Reply};
+
+const MAX_MINTS: usize = 100;
+
+/// `GET /api/prices?mints=a,b,...`
+///
+/// USD prices derived from the indexed pools, keyed by mint; mints without
+/// a priced pool map to `null`.
+pub async fn prices_handler(params: HashMap<String, String>) -> Result<Box<dyn Reply>, Rejection> {
+    let mints: Vec<&str> = params
+        .get("mints")
+        .map(|mints| mints.split(',').map(str::trim).filter(|m| !m.is_empty()).collect())
+        .unwrap_or_default();
+    if mints.is_empty() {
+        return Ok(error_reply("Missing 'mints'", StatusCode::BAD_REQUEST));
+    }
+    if mints.len() > MAX_MINTS {
+        return Ok(error_reply(&format!("At most {} mints per request", MAX_MINTS), StatusCode::BAD_REQUEST));
+    }
+    if let Some(invalid) = mints.iter().find(|mint| Pubkey::from_str(mint).is_err()) {
+        return Ok(error_reply(&format!("Invalid mint {}", invalid), StatusCode::BAD_REQUEST));
+    }
+
+    let oracle = price_oracle();
+    let prices: HashMap<&str, _> = mints.into_iter().map(|mint| (mint, oracle.price(mint))).collect();
+    Ok(Box::new(reply::json(&serde_json::json!({
+        "success": true,
+        "result": prices,
+    }))))
+}
+
+fn error_reply(message: &str, status: StatusCode) -> Box<dyn Reply> {
+    Box::new(reply::with_status(
+        reply::json(&serde_json::json!({ "success": false, "error": message })),
+        status,
+    ))
+}
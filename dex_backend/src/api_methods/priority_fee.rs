@@ -0,0 +1,99 @@
+use crate::api_methods::simulate::decode_transaction;
+use crate::pools::registry::pool_registry;
+use crate::rpc::prioritization_fees::get_recent_prioritization_fees;
+use crate::rpc::simulate::simulate_transaction;
+use crate::tx::builder::compute_unit_limit;
+use crate::tx::fees::{estimate_priority_fee, writable_accounts};
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use warp::http::StatusCode;
+use warp::{reply, Rejection, Reply};
+
+/// Units assumed for a swap when there is no transaction to simulate.
+const DEFAULT_SWAP_COMPUTE_UNITS: u64 = 300_000;
+/// Pools of a pair whose fee markets are sampled, deepest first.
+const POOLS_PER_PAIR: usize = 3;
+
+/// The accounts to price, from the first of: a transaction, an explicit
+/// account list, or the mint pair whose pools a swap would lock.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriorityFeeRequest {
+    /// Base64 transaction or message; simulated for its compute units.
+    pub transaction: Option<String>,
+    pub accounts: Option<Vec<String>>,
+    pub input_mint: Option<String>,
+    pub output_mint: Option<String>,
+    /// Overrides the simulated or assumed compute units.
+    pub compute_units: Option<u64>,
+}
+
+/// `POST /api/priorityFee/estimate`
+///
+/// Percentiles of the recent prioritization fees on the accounts, as
+/// micro-lamport prices and as totals at the transaction's unit limit.
+pub async fn priority_fee_handler(req: PriorityFeeRequest) -> Result<Box<dyn Reply>, Rejection> {
+    let (accounts, signatures, simulated_units) = if let Some(encoded) = &req.transaction {
+        let transaction = match decode_transaction(encoded) {
+            Ok(transaction) => transaction,
+            Err(e) => return Ok(error_reply(&e, StatusCode::BAD_REQUEST)),
+        };
+        let units = match req.compute_units {
+            Some(_) => None,
+            None => match simulate_transaction(&transaction, &[]).await {
+                Ok(simulation) => simulation.units_consumed,
+                Err(e) => {
+                    eprintln!("[fees] Simulation failed: {}", e);
+                    return Ok(error_reply("Simulation failed", StatusCode::BAD_GATEWAY));
+                }
+            },
+        };
+        let signatures = transaction.message.header().num_required_signatures as u64;
+        (writable_accounts(&transaction.message), signatures, units)
+    } else if let Some(accounts) = &req.accounts {
+        match accounts.iter().map(|a| Pubkey::from_str(a)).collect::<Result<Vec<_>, _>>() {
+            Ok(accounts) => (accounts, 1, None),
+            Err(_) => return Ok(error_reply("Invalid account address", StatusCode::BAD_REQUEST)),
+        }
+    } else if let (Some(input), Some(output)) = (&req.input_mint, &req.output_mint) {
+        let pools = pool_registry().pools_for_pair(input, output);
+        let accounts = pools.iter().take(POOLS_PER_PAIR).filter_map(|p| Pubkey::from_str(&p.address).ok()).collect();
+        (accounts, 1, None)
+    } else {
+        return Ok(error_reply(
+            "Pass 'transaction', 'accounts', or 'inputMint' and 'outputMint'",
+            StatusCode::BAD_REQUEST,
+        ));
+    };
+    if accounts.is_empty() {
+        return Ok(error_reply("No writable accounts to price", StatusCode::BAD_REQUEST));
+    }
+
+    let recent_fees = match get_recent_prioritization_fees(&accounts).await {
+        Ok(fees) => fees,
+        Err(e) => {
+            eprintln!("[fees] getRecentPrioritizationFees failed: {}", e);
+            return Ok(error_reply("Failed to fetch prioritization fees", StatusCode::BAD_GATEWAY));
+        }
+    };
+    let compute_units = req.compute_units.or(simulated_units).unwrap_or(DEFAULT_SWAP_COMPUTE_UNITS);
+    let estimate = estimate_priority_fee(&recent_fees, compute_unit_limit(compute_units), signatures);
+
+    Ok(Box::new(reply::json(&serde_json::json!({
+        "success": true,
+        "result": {
+            "computeUnits": compute_units,
+            "simulated": simulated_units.is_some(),
+            "slots": recent_fees.len(),
+            "estimate": estimate,
+        },
+    }))))
+}
+
+fn error_reply(message: &str, status: StatusCode) -> Box<dyn Reply> {
+    Box::new(reply::with_status(
+        reply::json(&serde_json::json!({ "success": false, "error": message })),
+        status,
+    ))
+}
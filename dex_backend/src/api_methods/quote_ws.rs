@@ -0,0 +1,208 @@
+use crate::arb::{quote_backrun, ArbQuote};
+use crate::quote::breakdown::{max_price_impact_pct, quote_breakdown, QuoteBreakdown};
+use crate::quote::params::QuoteParams;
+use crate::quote::pool_watch::PoolWatch;
+use crate::quote::{fetch_quote, SwapQuote};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Instant, MissedTickBehavior};
+use warp::filters::ws::{Message, WebSocket, Ws};
+use warp::http::StatusCode;
+use warp::{reply, Rejection, Reply};
+
+/// Requotes at most this often, however busy the pools are.
+const REQUOTE_THROTTLE: Duration = Duration::from_millis(500);
+/// Requote even without pool changes so the message keeps a fresh blockhash.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+const PING_INTERVAL: Duration = Duration::from_secs(20);
+/// Close sessions whose client stopped answering pings or sending frames.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// `GET /quote_ws?signer&x_mint&y_mint&amount&slippage&priority_fee`: streams
+/// swap quote frames; a JSON object with any of those fields re-subscribes.
+pub async fn quote_ws_handler(
+    query: HashMap<String, String>,
+    ws: Ws,
+) -> Result<Box<dyn Reply>, Rejection> {
+    match QuoteParams::from_query(&query) {
+        Ok(params) => Ok(Box::new(ws.on_upgrade(move |socket| quote_session(socket, params)))),
+        Err(e) => Ok(Box::new(reply::with_status(
+            reply::json(&json!({ "success": false, "error": e })),
+            StatusCode::BAD_REQUEST,
+        ))),
+    }
+}
+
+/// A finished quote: the frame to send and, if it routed, its pools. Tagged
+/// with the params generation it was computed for.
+struct QuoteResult {
+    generation: u64,
+    frame: Value,
+    pools: Option<Vec<String>>,
+}
+
+/// Quotes run in their own task so pings, client frames and the idle check
+/// keep flowing; a re-subscription cancels the one in flight.
+async fn quote_session(socket: WebSocket, mut params: QuoteParams) {
+    println!("[quote] Session opened for {}", params.signer);
+    let (mut outgoing, mut incoming) = socket.split();
+
+    let (changed_tx, mut changed_rx) = mpsc::channel::<()>(1);
+    let mut pool_watch: Option<PoolWatch> = None;
+    let mut dirty = true;
+    let mut last_quote: Option<Instant> = None;
+    let mut last_client_activity = Instant::now();
+    let (result_tx, mut result_rx) = mpsc::channel::<QuoteResult>(1);
+    let mut quoting: Option<JoinHandle<()>> = None;
+    let mut generation: u64 = 0;
+
+    let mut throttle = interval(REQUOTE_THROTTLE);
+    throttle.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut ping = interval(PING_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = throttle.tick() => {
+                if last_client_activity.elapsed() > IDLE_TIMEOUT {
+                    println!("[quote] Closing idle session for {}", params.signer);
+                    let _ = outgoing.send(Message::close_with(1000u16, "idle timeout")).await;
+                    break;
+                }
+                if quoting.is_some() {
+                    continue;
+                }
+                let stale = last_quote.is_none_or(|at| at.elapsed() >= REFRESH_INTERVAL);
+                if !dirty && !stale {
+                    continue;
+                }
+                dirty = false;
+                last_quote = Some(Instant::now());
+
+                let (results, params) = (result_tx.clone(), params.clone());
+                quoting = Some(tokio::spawn(async move {
+                    let (frame, pools) = compute_quote(&params).await;
+                    let _ = results.send(QuoteResult { generation, frame, pools }).await;
+                }));
+            }
+            Some(result) = result_rx.recv() => {
+                if result.generation != generation {
+                    continue; // finished just before a re-subscription cancelled it
+                }
+                quoting = None;
+                if let Some(pools) = result.pools {
+                    if pool_watch.as_ref().map(|w| w.pools()) != Some(pools.as_slice()) {
+                        pool_watch = Some(PoolWatch::start(pools, changed_tx.clone()));
+                    }
+                }
+                if outgoing.send(Message::text(result.frame.to_string())).await.is_err() {
+                    break;
+                }
+            }
+            Some(()) = changed_rx.recv() => dirty = true,
+            _ = ping.tick() => {
+                if outgoing.send(Message::ping(Vec::new())).await.is_err() {
+                    break;
+                }
+            }
+            message = incoming.next() => {
+                let message = match message {
+                    Some(Ok(message)) => message,
+                    _ => break,
+                };
+                last_client_activity = Instant::now();
+                if message.is_close() {
+                    break;
+                }
+                let Ok(text) = message.to_str() else {
+                    continue; // pongs and binary frames only count as activity
+                };
+                let update = serde_json::from_str::<Value>(text)
+                    .map_err(|_| "re-subscription must be JSON".to_string())
+                    .and_then(|frame| params.updated_with(&frame));
+                match update {
+                    Ok(updated) => {
+                        if updated != params {
+                            params = updated;
+                            if let Some(task) = quoting.take() {
+                                task.abort();
+                            }
+                            generation += 1;
+                            pool_watch = None;
+                            dirty = true;
+                        }
+                    }
+                    Err(e) => {
+                        let frame = json!({ "error": e });
+                        if outgoing.send(Message::text(frame.to_string())).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(task) = quoting {
+        task.abort();
+    }
+    println!("[quote] Session closed for {}", params.signer);
+}
+
+/// Quotes `params` and searches for a back-run on the route.
+async fn compute_quote(params: &QuoteParams) -> (Value, Option<Vec<String>>) {
+    match fetch_quote(params).await {
+        Ok((quote, mints)) => {
+            let max_impact = max_price_impact_pct();
+            let unchecked = quote_breakdown(&quote, &mints, None, params.slippage_bps, max_impact);
+            let arb = if unchecked.blocked() {
+                None
+            } else {
+                match quote_backrun(&params.signer, &quote.hops).await {
+                    Ok(arb) => arb,
+                    Err(e) => {
+                        eprintln!("[arb] Back-run search failed for {}: {}", params.signer, e);
+                        None
+                    }
+                }
+            };
+            let breakdown = match &arb {
+                Some(arb) => quote_breakdown(&quote, &mints, Some(arb), params.slippage_bps, max_impact),
+                None => unchecked,
+            };
+            (quote_frame(&quote, &breakdown, arb.as_ref()), Some(quote.pools.clone()))
+        }
+        Err(e) => {
+            eprintln!("[quote] Quote failed for {}: {}", params.signer, e);
+            (json!({ "error": e.to_string() }), None)
+        }
+    }
+}
+
+fn quote_frame(quote: &SwapQuote, breakdown: &QuoteBreakdown, arb: Option<&ArbQuote>) -> Value {
+    let blocked = breakdown.blocked();
+    json!({
+        "expected_out": breakdown.expected_out,
+        "expected_out_raw": breakdown.expected_out_raw,
+        "min_out": breakdown.min_out,
+        "min_out_raw": breakdown.min_out_raw,
+        "in_amount_raw": quote.in_amount.to_string(),
+        "mid_price": breakdown.mid_price,
+        "execution_price": breakdown.execution_price,
+        "price_impact_pct": breakdown.price_impact_pct,
+        "route": quote.route_labels(),
+        "hops": breakdown.hops,
+        "fees": breakdown.fees,
+        "token_extensions": breakdown.token_extensions,
+        "blocked": blocked,
+        "block_reason": breakdown.block_reason,
+        "message": (!blocked).then_some(&quote.message),
+        "arb_transaction": arb.map(|arb| arb.transaction.clone()),
+        "arb_reward_raw": arb.map(|arb| arb.backrun.user_reward.to_string()),
+        "arb_reward_mint": arb.map(|arb| arb.backrun.mint.to_string()),
+        "arb_reward": arb.map(|arb| arb.reward_ui()),
+    })
+}
@@ -0,0 +1,34 @@
+use crate::arb::rewards::reward_ledger;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use warp::http::StatusCode;
+use warp::{reply, Rejection, Reply};
+
+/// `GET /api/rewards/{address}`
+///
+/// Back-run rewards paid to the wallet, totalled per mint and itemized
+/// newest first. Amounts are raw units of each reward's mint.
+pub async fn wallet_rewards_handler(address: String) -> Result<Box<dyn Reply>, Rejection> {
+    if Pubkey::from_str(&address).is_err() {
+        return Ok(error_reply("Invalid address", StatusCode::BAD_REQUEST));
+    }
+    Ok(Box::new(reply::json(&serde_json::json!({
+        "success": true,
+        "result": reward_ledger().wallet(&address),
+    }))))
+}
+
+/// `GET /api/rewards/stats`: every landed back-run, totalled per mint.
+pub async fn reward_stats_handler() -> Result<Box<dyn Reply>, Rejection> {
+    Ok(Box::new(reply::json(&serde_json::json!({
+        "success": true,
+        "result": reward_ledger().stats(),
+    }))))
+}
+
+fn error_reply(message: &str, status: StatusCode) -> Box<dyn Reply> {
+    Box::new(reply::with_status(
+        reply::json(&serde_json::json!({ "success": false, "error": message })),
+        status,
+    ))
+}
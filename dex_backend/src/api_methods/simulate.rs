@@ -0,0 +1,288 @@
+use crate::api_methods::handle_history::{
+    fee_change, fetch_metadata_concurrently, sol_delta_change, token_delta_change, BalanceChange,
+};
+use crate::pools::indexer::SOL_MINT;
+use crate::pools::layout::Layout;
+use crate::pools::{TOKEN_2022_PROGRAM, TOKEN_PROGRAM};
+use crate::rpc::accounts::{get_multiple_accounts, get_token_accounts_by_owner, RawAccount};
+use crate::rpc::mint_decimals::get_mint_decimals;
+use crate::rpc::simulate::simulate_transaction;
+use crate::tx::fees::{network_fee, writable_accounts};
+use crate::tx_errors::decode::decode_error;
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use futures::future::join_all;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use warp::http::StatusCode;
+use warp::{reply, Rejection, Reply};
+
+/// Base layout of an SPL Token account; Token-2022 accounts with
+/// extensions are longer and carry an account type byte right after it.
+const TOKEN_ACCOUNT_LEN: usize = 165;
+const TOKEN_ACCOUNT_TYPE: u8 = 2;
+
+#[derive(Debug, Deserialize)]
+pub struct SimulateRequest {
+    /// Base64 of a serialized transaction, or of a bare message.
+    pub transaction: String,
+    pub signer: String,
+}
+
+/// `POST /api/simulate`
+///
+/// Runs the transaction against current state and previews it for `signer`:
+/// compute units, logs, the decoded failure if any, and the signer's
+/// balance changes in history's format, network fee included.
+pub async fn simulate_handler(req: SimulateRequest) -> Result<Box<dyn Reply>, Rejection> {
+    let Ok(signer) = req.signer.parse::<Pubkey>() else {
+        return Ok(error_reply("Invalid signer address", StatusCode::BAD_REQUEST));
+    };
+    let transaction = match decode_transaction(&req.transaction) {
+        Ok(transaction) => transaction,
+        Err(e) => return Ok(error_reply(&e, StatusCode::BAD_REQUEST)),
+    };
+
+    match preview(&transaction, &signer).await {
+        Ok(result) => Ok(Box::new(reply::json(&json!({ "success": true, "result": result })))),
+        Err(e) => {
+            eprintln!("[simulate] Simulation for {} failed: {}", signer, e);
+            Ok(error_reply("Simulation failed", StatusCode::BAD_GATEWAY))
+        }
+    }
+}
+
+/// Accepts signed or unsigned transactions as well as bare messages, which
+/// get empty signatures since the simulation doesn't verify them.
+pub(crate) fn decode_transaction(encoded: &str) -> Result<VersionedTransaction, String> {
+    let bytes = BASE64.decode(encoded.trim()).map_err(|_| "'transaction' must be base64".to_string())?;
+    // A message can happen to parse as a transaction; only a parse that
+    // consumes every byte and has a signature per signer is one.
+    let transaction = match bincode::deserialize::<VersionedTransaction>(&bytes) {
+        Ok(transaction)
+            if transaction.signatures.len() == transaction.message.header().num_required_signatures as usize
+                && bincode::serialized_size(&transaction).ok() == Some(bytes.len() as u64) =>
+        {
+            transaction
+        }
+        _ => {
+            let message = bincode::deserialize::<VersionedMessage>(&bytes)
+                .map_err(|_| "'transaction' is not a transaction or message".to_string())?;
+            let signatures = vec![Signature::default(); message.header().num_required_signatures as usize];
+            VersionedTransaction { signatures, message }
+        }
+    };
+    // Account indexes come from the client; nothing may index by them
+    // before this.
+    transaction.sanitize().map_err(|e| format!("'transaction' is malformed: {}", e))?;
+    Ok(transaction)
+}
+
+async fn preview(transaction: &VersionedTransaction, signer: &Pubkey) -> Result<Value> {
+    let message = &transaction.message;
+    let (legacy_accounts, token_2022_accounts) = tokio::try_join!(
+        get_token_accounts_by_owner(signer, &TOKEN_PROGRAM),
+        get_token_accounts_by_owner(signer, &TOKEN_2022_PROGRAM),
+    )?;
+
+    // The signer, its token accounts, and anything else the transaction
+    // may write, which covers token accounts it creates.
+    let keys = message.static_account_keys();
+    let mut watched = vec![*signer];
+    watched.extend(legacy_accounts.into_iter().chain(token_2022_accounts));
+    watched.extend(writable_accounts(message));
+    let mut seen = HashSet::new();
+    watched.retain(|key| seen.insert(*key));
+
+    let (before, simulation) =
+        tokio::try_join!(get_multiple_accounts(&watched), simulate_transaction(transaction, &watched))?;
+
+    // The fee is taken from the payer's lamports in the simulated state;
+    // like history, it is shown as its own row rather than in the SOL delta.
+    let fee = network_fee(message);
+    let paid_fee = if keys.first() == Some(signer) { fee } else { 0 };
+
+    let mut balance_changes = Vec::new();
+    // Failed simulations return no account state; only the fee is spent.
+    if simulation.accounts.len() == watched.len() {
+        let after: HashMap<Pubkey, RawAccount> = watched
+            .iter()
+            .zip(&simulation.accounts)
+            .filter_map(|(key, account)| Some((*key, account.clone()?)))
+            .collect();
+        let deltas = balance_deltas(signer, &before.accounts, &after);
+        balance_changes = delta_changes(signer, deltas, paid_fee).await;
+    }
+    if paid_fee > 0 {
+        balance_changes.push(fee_change(&signer.to_string(), paid_fee));
+    }
+
+    let logs: Vec<&str> = simulation.logs.iter().map(String::as_str).collect();
+    let instruction_programs: Vec<String> =
+        message.instructions().iter().map(|ix| keys[ix.program_id_index as usize].to_string()).collect();
+    let failure = simulation.err.as_ref().map(|err| decode_error(err, &instruction_programs, &logs));
+
+    Ok(json!({
+        "status": if failure.is_none() { "success" } else { "failed" },
+        "slot": simulation.slot,
+        "unitsConsumed": simulation.units_consumed,
+        "logs": simulation.logs,
+        "failure": failure,
+        "networkFee": fee.to_string(),
+        "balanceChanges": balance_changes,
+    }))
+}
+
+/// How the signer's holdings move between two account states.
+#[derive(Debug, Default, PartialEq)]
+struct BalanceDeltas {
+    lamports: i128,
+    /// Raw amount per mint across the signer's token accounts. Wrapped SOL
+    /// is left out, as in history: it is unwrapped into lamports.
+    tokens: BTreeMap<Pubkey, i128>,
+}
+
+fn balance_deltas(
+    signer: &Pubkey,
+    before: &HashMap<Pubkey, RawAccount>,
+    after: &HashMap<Pubkey, RawAccount>,
+) -> BalanceDeltas {
+    let lamports = |state: &HashMap<Pubkey, RawAccount>| state.get(signer).map_or(0, |a| a.lamports as i128);
+    let mut deltas = BalanceDeltas { lamports: lamports(after) - lamports(before), ..Default::default() };
+
+    for (state, sign) in [(before, -1), (after, 1)] {
+        for account in state.values() {
+            let Some((mint, owner, amount)) = token_account(account) else {
+                continue;
+            };
+            if owner == *signer && mint != SOL_MINT {
+                *deltas.tokens.entry(mint).or_default() += sign * amount as i128;
+            }
+        }
+    }
+    deltas.tokens.retain(|_, delta| *delta != 0);
+    deltas
+}
+
+/// `(mint, owner, amount)` of an SPL Token or Token-2022 account.
+fn token_account(account: &RawAccount) -> Option<(Pubkey, Pubkey, u64)> {
+    if account.owner != TOKEN_PROGRAM && account.owner != TOKEN_2022_PROGRAM {
+        return None;
+    }
+    let data = &account.data;
+    let is_account =
+        data.len() == TOKEN_ACCOUNT_LEN || data.get(TOKEN_ACCOUNT_LEN) == Some(&TOKEN_ACCOUNT_TYPE);
+    if !is_account {
+        return None; // mints
+    }
+    let layout = Layout::new(data, "token", TOKEN_ACCOUNT_LEN).ok()?;
+    Some((layout.pubkey(0).ok()?, layout.pubkey(32).ok()?, layout.u64(64).ok()?))
+}
+
+async fn delta_changes(signer: &Pubkey, deltas: BalanceDeltas, paid_fee: u64) -> Vec<BalanceChange> {
+    let wallet = signer.to_string();
+    let mut changes = Vec::new();
+    let transferred = deltas.lamports + paid_fee as i128;
+    if transferred != 0 {
+        changes.push(sol_delta_change(&wallet, transferred));
+    }
+
+    let mints: Vec<String> = deltas.tokens.keys().map(|mint| mint.to_string()).collect();
+    let (metadata, decimals) = tokio::join!(
+        fetch_metadata_concurrently(mints.iter().cloned().collect()),
+        join_all(mints.iter().map(|mint| get_mint_decimals(mint))),
+    );
+    for ((mint, delta), decimals) in mints.iter().zip(deltas.tokens.values()).zip(decimals) {
+        let decimals = decimals.unwrap_or_else(|e| {
+            eprintln!("[simulate] No decimals for {}: {}", mint, e);
+            0
+        });
+        changes.push(token_delta_change(&wallet, mint, *delta, decimals, metadata.get(mint)));
+    }
+    changes
+}
+
+fn error_reply(message: &str, status: StatusCode) -> Box<dyn Reply> {
+    Box::new(reply::with_status(
+        reply::json(&json!({ "success": false, "error": message })),
+        status,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::Message;
+
+    fn token_account_data(mint: &Pubkey, owner: &Pubkey, amount: u64) -> RawAccount {
+        let mut data = vec![0u8; TOKEN_ACCOUNT_LEN];
+        data[..32].copy_from_slice(mint.as_ref());
+        data[32..64].copy_from_slice(owner.as_ref());
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        RawAccount { owner: TOKEN_PROGRAM, lamports: 2_039_280, data }
+    }
+
+    #[test]
+    fn balance_deltas_net_the_signers_accounts_per_mint() {
+        let signer = Pubkey::new_unique();
+        let (usdc, bonk) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (usdc_account, bonk_account, other) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let wallet = |lamports| RawAccount { owner: Pubkey::default(), lamports, data: Vec::new() };
+
+        let before = HashMap::from([
+            (signer, wallet(1_000_000_000)),
+            (usdc_account, token_account_data(&usdc, &signer, 5_000_000)),
+            (other, token_account_data(&usdc, &Pubkey::new_unique(), 7)),
+        ]);
+        // Pays 2 USDC into a newly created BONK account; the other
+        // owner's account doesn't count.
+        let after = HashMap::from([
+            (signer, wallet(997_955_720)),
+            (usdc_account, token_account_data(&usdc, &signer, 3_000_000)),
+            (bonk_account, token_account_data(&bonk, &signer, 42)),
+            (other, token_account_data(&usdc, &Pubkey::new_unique(), 9)),
+        ]);
+
+        let deltas = balance_deltas(&signer, &before, &after);
+        assert_eq!(deltas.lamports, -2_044_280);
+        assert_eq!(deltas.tokens, BTreeMap::from([(usdc, -2_000_000), (bonk, 42)]));
+    }
+
+    #[test]
+    fn decode_transaction_accepts_bare_messages() {
+        let payer = Pubkey::new_unique();
+        let message = VersionedMessage::Legacy(Message::new_with_blockhash(
+            &[crate::tx::system::transfer(&payer, &Pubkey::new_unique(), 1)],
+            Some(&payer),
+            &Hash::new_unique(),
+        ));
+        let unsigned = BASE64.encode(bincode::serialize(&message).unwrap());
+        let transaction = decode_transaction(&unsigned).unwrap();
+        assert_eq!(transaction.signatures, vec![Signature::default()]);
+        assert_eq!(transaction.message, message);
+
+        let signed = BASE64.encode(bincode::serialize(&transaction).unwrap());
+        assert_eq!(decode_transaction(&signed).unwrap().message, message);
+        assert!(decode_transaction("not base64!").is_err());
+    }
+
+    #[test]
+    fn decode_transaction_rejects_out_of_range_indexes() {
+        let payer = Pubkey::new_unique();
+        let mut message = Message::new_with_blockhash(
+            &[crate::tx::system::transfer(&payer, &Pubkey::new_unique(), 1)],
+            Some(&payer),
+            &Hash::new_unique(),
+        );
+        message.instructions[0].program_id_index = 200;
+        let encoded = BASE64.encode(bincode::serialize(&VersionedMessage::Legacy(message)).unwrap());
+        assert!(decode_transaction(&encoded).unwrap_err().contains("malformed"));
+    }
+}
@@ -0,0 +1,108 @@
+use crate::stream::signature_tracker::{lookup_signature, signature_tracker, SignatureStatus};
+use crate::tx_errors::decode::decode_error;
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::sync::watch;
+use warp::filters::ws::{Message, WebSocket, Ws};
+use warp::http::StatusCode;
+use warp::{reply, Rejection, Reply};
+
+/// Signatures belonging to one logical swap, e.g. a swap and its resends.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackRequest {
+    pub signatures: Vec<String>,
+    /// From the blockhash the transactions were signed with; assumed fresh
+    /// when left out.
+    pub last_valid_block_height: Option<u64>,
+}
+
+/// `POST /api/tx/track`
+pub async fn track_signatures_handler(req: TrackRequest) -> Result<Box<dyn Reply>, Rejection> {
+    if req.signatures.is_empty() {
+        return Ok(error_reply("'signatures' must not be empty", StatusCode::BAD_REQUEST));
+    }
+    match signature_tracker().track(req.signatures, req.last_valid_block_height).await {
+        Ok(statuses) => Ok(Box::new(reply::json(&serde_json::json!({
+            "success": true,
+            "result": statuses,
+        })))),
+        Err(e) => Ok(error_reply(&e.to_string(), StatusCode::BAD_REQUEST)),
+    }
+}
+
+/// `GET /api/tx/{signature}/status`
+///
+/// A tracked signature's stage, with the rest of its group. Signatures
+/// nobody tracks are looked up once, without expiry detection.
+pub async fn tx_status_handler(signature: String) -> Result<Box<dyn Reply>, Rejection> {
+    let tracker = signature_tracker();
+    if let Some(status) = tracker.status(&signature) {
+        let group: Vec<SignatureStatus> =
+            status.group.iter().filter(|s| **s != signature).filter_map(|s| tracker.status(s)).collect();
+        return Ok(Box::new(reply::json(&serde_json::json!({
+            "success": true,
+            "result": { "status": status, "group": group },
+        }))));
+    }
+
+    match lookup_signature(&signature).await {
+        Ok(Some((stage, lookup))) => Ok(Box::new(reply::json(&serde_json::json!({
+            "success": true,
+            "result": {
+                "status": {
+                    "signature": signature,
+                    "stage": stage,
+                    "slot": lookup.slot,
+                    "failure": lookup.err.map(|err| decode_error(&err, &[], &[])),
+                },
+                "group": [],
+            },
+        })))),
+        Ok(None) => Ok(error_reply("Unknown signature", StatusCode::NOT_FOUND)),
+        Err(e) => {
+            eprintln!("[tracker] Lookup of {} failed: {}", signature, e);
+            Ok(error_reply("Signature lookup failed", StatusCode::BAD_GATEWAY))
+        }
+    }
+}
+
+/// `GET /api/stream/tx/{signature}` with a WebSocket upgrade. Pushes the
+/// status on every change and closes once it is finalized or expired.
+pub async fn tx_status_ws_handler(signature: String, ws: Ws) -> Result<Box<dyn Reply>, Rejection> {
+    match signature_tracker().watch(&signature) {
+        Some(updates) => Ok(Box::new(ws.on_upgrade(move |socket| tx_status_session(socket, updates)))),
+        None => Ok(error_reply("Signature is not tracked", StatusCode::NOT_FOUND)),
+    }
+}
+
+async fn tx_status_session(socket: WebSocket, mut updates: watch::Receiver<SignatureStatus>) {
+    let (mut outgoing, mut incoming) = socket.split();
+    loop {
+        tokio::select! {
+            changed = updates.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                let status = updates.borrow_and_update().clone();
+                let Ok(payload) = serde_json::to_string(&status) else { break };
+                if outgoing.send(Message::text(payload)).await.is_err() || status.stage.is_final() {
+                    break;
+                }
+            }
+            message = incoming.next() => match message {
+                Some(Ok(message)) if message.is_close() => break,
+                Some(Ok(_)) => {}
+                _ => break,
+            },
+        }
+    }
+    let _ = outgoing.send(Message::close()).await;
+}
+
+fn error_reply(message: &str, status: StatusCode) -> Box<dyn Reply> {
+    Box::new(reply::with_status(
+        reply::json(&serde_json::json!({ "success": false, "error": message })),
+        status,
+    ))
+}
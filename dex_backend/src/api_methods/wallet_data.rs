@@ -1,32 +1,14 @@
+use crate::utils::token_amount::{format_token_amount, parse_token_amount};
+use crate::utils::upstreams::solflare_wallet_api_url;
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use warp::{Rejection, Reply};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct TokenMetadata {
-    pub name: String,
-    pub symbol: String,
-    pub decimals: u8,
-    pub mint: String,
-    #[serde(rename = "imageUri")]
-    pub logoURI: String,
-    pub accounts: Vec<TokenAccountInfo>,
-    #[serde(rename = "totalUiAmount")]
-    pub total_ui_amount: f64,
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct TokenAccountInfo {
-    pub pubkey: String,
-    #[serde(rename = "uiAmount")]
-    pub ui_amount: f64,
-}
-
 pub async fn get_wallet_data(address: &str) -> anyhow::Result<Value> {
     let url = format!(
-        "https://wallet-api.solflare.com/v3/portfolio/tokens/{}?network=mainnet&currency=USD&enablePartialErrors=true",
+        "{}/v3/portfolio/tokens/{}?network=mainnet&currency=USD&enablePartialErrors=true",
+        solflare_wallet_api_url(),
         address
     );
 
@@ -49,10 +31,47 @@ pub async fn get_wallet_data(address: &str) -> anyhow::Result<Value> {
         .send()
         .await?;
 
-    let body: Value = res.json().await?;
+    let mut body: Value = res.json().await?;
+    add_exact_amounts(&mut body);
     Ok(body)
 }
 
+/// Solflare reports `totalUiAmount` as a float. Adds the base-unit total
+/// (`totalAmount`) and its exact UI string next to it, preferring the raw
+/// per-account amounts when Solflare includes them.
+fn add_exact_amounts(body: &mut Value) {
+    let Some(tokens) = body["tokens"].as_array_mut() else {
+        return;
+    };
+
+    for token in tokens {
+        let decimals = token["decimals"].as_u64().unwrap_or(0) as u8;
+
+        let from_accounts: Option<u128> = token["accounts"]
+            .as_array()
+            .filter(|accounts| !accounts.is_empty())
+            .and_then(|accounts| {
+                accounts
+                    .iter()
+                    .map(|a| a["amount"].as_str()?.parse::<u128>().ok())
+                    .sum()
+            });
+        let raw = from_accounts.or_else(|| {
+            // Fixed-point formatting, rounded at the mint's precision.
+            let ui = match &token["totalUiAmount"] {
+                Value::String(ui) => ui.clone(),
+                ui => format!("{:.*}", decimals as usize, ui.as_f64()?),
+            };
+            parse_token_amount(&ui, decimals)
+        });
+
+        if let Some(raw) = raw {
+            token["totalAmount"] = Value::String(raw.to_string());
+            token["totalUiAmountString"] = Value::String(format_token_amount(raw, decimals));
+        }
+    }
+}
+
 pub async fn wallet_data_handler(query: HashMap<String, String>) -> Result<impl Reply, Rejection> {
     let address = query.get("address").cloned().unwrap_or_default();
 
@@ -67,3 +86,22 @@ pub async fn wallet_data_handler(query: HashMap<String, String>) -> Result<impl
         }))),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn float_totals_never_parse_in_exponent_form() {
+        let mut body = json!({ "tokens": [
+            { "decimals": 9, "totalUiAmount": 1e-7 },
+            { "decimals": 6, "totalUiAmount": 12345678.5 },
+        ] });
+        add_exact_amounts(&mut body);
+
+        assert_eq!(body["tokens"][0]["totalAmount"], "100");
+        assert_eq!(body["tokens"][0]["totalUiAmountString"], "0.0000001");
+        assert_eq!(body["tokens"][1]["totalAmount"], "12345678500000");
+    }
+}
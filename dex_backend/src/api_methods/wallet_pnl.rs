@@ -0,0 +1,164 @@
+use crate::api_methods::handle_history::{history_signature_stream, normalize_signatures, NormalizedTx};
+use crate::api_methods::wallet_data::get_wallet_data;
+use crate::arb::arb_signer;
+use crate::pnl::cost_basis::{CostBasisMethod, PnlEngine};
+use crate::pnl::history_pnl::{apply_transaction, PriceBook};
+use futures::StreamExt;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+use std::collections::{HashMap, HashSet};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use warp::http::StatusCode;
+use warp::{reply, Rejection, Reply};
+
+const DEFAULT_TX_LIMIT: usize = 1000;
+const MAX_TX_LIMIT: usize = 10_000;
+
+pub async fn wallet_pnl_handler(
+    address: String,
+    query: HashMap<String, String>,
+) -> Result<Box<dyn Reply>, Rejection> {
+    let total_start = Instant::now();
+
+    let pubkey = match address.parse::<Pubkey>() {
+        Ok(pk) => pk,
+        Err(_) => return Ok(error_reply("Invalid wallet address", StatusCode::BAD_REQUEST)),
+    };
+
+    let method = match query.get("method") {
+        None => CostBasisMethod::Fifo,
+        Some(m) => match CostBasisMethod::parse(m) {
+            Some(method) => method,
+            None => {
+                return Ok(error_reply(
+                    "'method' must be fifo or average",
+                    StatusCode::BAD_REQUEST,
+                ))
+            }
+        },
+    };
+
+    let from = query.get("from").and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+    let to = query
+        .get("to")
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or_else(unix_now);
+    let limit = query
+        .get("limit")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_TX_LIMIT)
+        .min(MAX_TX_LIMIT);
+
+    // Signatures page newest first, cost basis needs oldest first: page back
+    // to the start of the range and analyse its oldest `limit` transactions,
+    // so lots are always tracked from where they were acquired.
+    let mut signatures: Vec<String> = Vec::new();
+    let signature_stream = history_signature_stream(pubkey, from, to);
+    futures::pin_mut!(signature_stream);
+    while let Some(item) = signature_stream.next().await {
+        match item {
+            Ok(signature) => signatures.push(signature),
+            Err(e) => return Ok(history_failed(&pubkey, e)),
+        }
+    }
+    let truncated = signatures.len() > limit;
+    let oldest: Vec<Result<String, anyhow::Error>> = signatures.into_iter().rev().take(limit).map(Ok).collect();
+
+    let mut history: Vec<NormalizedTx> = Vec::new();
+    let stream = normalize_signatures(futures::stream::iter(oldest), pubkey);
+    futures::pin_mut!(stream);
+    while let Some(item) = stream.next().await {
+        match item {
+            Ok(tx) => history.push(tx),
+            Err(e) => return Ok(history_failed(&pubkey, e)),
+        }
+    }
+
+    let current_prices = fetch_current_prices(&address).await;
+    let mut prices = PriceBook::new(current_prices.clone());
+    let reward_payers = arb_reward_payers();
+    let mut engine = PnlEngine::new(method);
+
+    for tx in &history {
+        apply_transaction(&mut engine, &mut prices, tx, &address, &reward_payers);
+    }
+
+    let report = engine.report(&current_prices);
+    let mut estimated: Vec<&String> = prices.estimated_mints.iter().collect();
+    estimated.sort();
+
+    println!(
+        "[timing] Total wallet_pnl_handler duration: {:?} ({} txs)",
+        total_start.elapsed(),
+        history.len()
+    );
+
+    Ok(Box::new(reply::json(&serde_json::json!({
+        "success": true,
+        "result": {
+            "address": address,
+            "transactionsAnalysed": history.len(),
+            "truncated": truncated,
+            "estimatedPriceMints": estimated,
+            "pnl": report,
+        }
+    }))))
+}
+
+/// Current USD prices from the Solflare portfolio, keyed by mint.
+async fn fetch_current_prices(address: &str) -> HashMap<String, f64> {
+    let mut prices = HashMap::new();
+    match get_wallet_data(address).await {
+        Ok(data) => {
+            if let Some(tokens) = data["tokens"].as_array() {
+                for token in tokens {
+                    if let (Some(mint), Some(price)) =
+                        (token["mint"].as_str(), token["price"].as_f64())
+                    {
+                        prices.insert(mint.to_string(), price);
+                    }
+                }
+            }
+        }
+        Err(e) => eprintln!("[pnl] Failed to fetch current prices: {}", e),
+    }
+    prices
+}
+
+/// Fee payers of the arbitrage back-runs; incoming transfers they pay for
+/// are rewards. Our own arb signer, plus any comma separated in
+/// `ARB_REWARD_PAYERS`.
+fn arb_reward_payers() -> HashSet<String> {
+    let mut payers: HashSet<String> = std::env::var("ARB_REWARD_PAYERS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if let Some(signer) = arb_signer() {
+        payers.insert(signer.pubkey().to_string());
+    }
+    payers
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn history_failed(pubkey: &Pubkey, e: anyhow::Error) -> Box<dyn Reply> {
+    eprintln!("[pnl] Failed to load history for {}: {}", pubkey, e);
+    error_reply("Failed to load transaction history", StatusCode::BAD_GATEWAY)
+}
+
+fn error_reply(message: &str, status: StatusCode) -> Box<dyn Reply> {
+    Box::new(reply::with_status(
+        reply::json(&serde_json::json!({
+            "success": false,
+            "error": message
+        })),
+        status,
+    ))
+}
@@ -0,0 +1,85 @@
+use crate::stream::wallet_feed::subscribe;
+use futures::{stream, SinkExt, Stream, StreamExt};
+use solana_sdk::pubkey::Pubkey;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use warp::filters::sse::Event;
+use warp::filters::ws::{Message, WebSocket, Ws};
+use warp::http::StatusCode;
+use warp::{reply, Rejection, Reply};
+
+/// `GET /api/stream/wallet/{address}` with a WebSocket upgrade.
+pub async fn wallet_ws_handler(address: String, ws: Ws) -> Result<Box<dyn Reply>, Rejection> {
+    let wallet = match address.parse::<Pubkey>() {
+        Ok(pk) => pk,
+        Err(_) => return Ok(invalid_address()),
+    };
+
+    Ok(Box::new(ws.on_upgrade(move |socket| wallet_ws_session(socket, wallet))))
+}
+
+/// `GET /api/stream/wallet/{address}` as Server-Sent Events, for clients
+/// that cannot hold a WebSocket.
+pub async fn wallet_sse_handler(address: String) -> Result<Box<dyn Reply>, Rejection> {
+    let wallet = match address.parse::<Pubkey>() {
+        Ok(pk) => pk,
+        Err(_) => return Ok(invalid_address()),
+    };
+
+    let events = feed_events(subscribe(wallet))
+        .map(|payload| Ok::<_, Infallible>(Event::default().event("transaction").data(payload.as_str())));
+
+    Ok(Box::new(warp::sse::reply(warp::sse::keep_alive().stream(events))))
+}
+
+async fn wallet_ws_session(socket: WebSocket, wallet: Pubkey) {
+    println!("[stream] WebSocket client connected for {}", wallet);
+    let (mut outgoing, mut incoming) = socket.split();
+    let mut events = Box::pin(feed_events(subscribe(wallet)));
+
+    loop {
+        tokio::select! {
+            event = events.next() => {
+                let Some(payload) = event else { break };
+                if outgoing.send(Message::text(payload.as_str())).await.is_err() {
+                    break;
+                }
+            }
+            message = incoming.next() => match message {
+                Some(Ok(message)) if message.is_close() => break,
+                Some(Ok(_)) => {}
+                _ => break,
+            },
+        }
+    }
+
+    // Dropping the receiver lets the feed shut down once it is the last one.
+    println!("[stream] WebSocket client disconnected for {}", wallet);
+}
+
+/// Feed payloads for one client. A client too slow to keep up skips what it
+/// missed and is told how many events were dropped.
+fn feed_events(receiver: Receiver<Arc<String>>) -> impl Stream<Item = Arc<String>> {
+    stream::unfold(receiver, |mut receiver| async move {
+        match receiver.recv().await {
+            Ok(payload) => Some((payload, receiver)),
+            Err(RecvError::Lagged(skipped)) => {
+                let notice = serde_json::json!({ "status": "lagged", "skipped": skipped });
+                Some((Arc::new(notice.to_string()), receiver))
+            }
+            Err(RecvError::Closed) => None,
+        }
+    })
+}
+
+fn invalid_address() -> Box<dyn Reply> {
+    Box::new(reply::with_status(
+        reply::json(&serde_json::json!({
+            "success": false,
+            "error": "Invalid wallet address"
+        })),
+        StatusCode::BAD_REQUEST,
+    ))
+}
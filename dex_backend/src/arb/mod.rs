@@ -0,0 +1,182 @@
+//! Back-runs of user swaps whose profit is shared with the swapper.
+//!
+//! After a quote, the user's hops are replayed against the indexed pools to
+//! get the state their swap leaves behind. A profitable cycle through the
+//! moved pools is sized against the signer's inventory and built into a
+//! signed transaction the frontend bundles right after the user's swap.
+
+pub mod rewards;
+pub mod search;
+pub mod transaction;
+
+use crate::arb::search::{apply_user_swap, search_backrun, Backrun, SearchParams, UserHop, BASE_MINTS};
+use crate::arb::transaction::{build_arb_transaction, ArbAccounts};
+use crate::jito::random_tip_account;
+use crate::pools::indexer::{SOL_DECIMALS, STABLE_MINTS};
+use crate::pools::layout::token_account_amount;
+use crate::pools::registry::pool_registry;
+use crate::pools::state_cache::pool_state_cache;
+use crate::pools::TOKEN_PROGRAM;
+use crate::rpc::accounts::get_multiple_accounts;
+use crate::rpc::blockhash::get_latest_blockhash;
+use crate::tx::ata::associated_token_address;
+use crate::tx::fees::SIGNATURE_FEE_LAMPORTS;
+use crate::tx::lookup_tables::{configured_lookup_tables, fetch_lookup_tables};
+use crate::tx::token_2022::TokenMints;
+use crate::utils::token_amount::format_token_amount;
+use crate::ARB_SIGNER;
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair};
+use solana_sdk::signer::Signer;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// Share of the net profit paid to the swapper unless `ARB_USER_SHARE_BPS`
+/// says otherwise.
+const DEFAULT_USER_SHARE_BPS: u64 = 9_000;
+/// Jito tip per back-run unless `ARB_TIP_LAMPORTS` says otherwise.
+const DEFAULT_TIP_LAMPORTS: u64 = 100_000;
+/// Pools loaded per pair a cycle can trade through, deepest first.
+const POOLS_PER_PAIR: usize = 3;
+
+/// A back-run ready to bundle after the user's swap.
+#[derive(Debug, Clone)]
+pub struct ArbQuote {
+    pub backrun: Backrun,
+    /// Jito tip the back-run pays, out of its profit.
+    pub tip_lamports: u64,
+    /// Base64 of the signed, serialized transaction.
+    pub transaction: String,
+}
+
+impl ArbQuote {
+    /// The user's reward in UI units of the reward mint.
+    pub fn reward_ui(&self) -> f64 {
+        let decimals = STABLE_MINTS
+            .iter()
+            .find(|(stable, _)| *stable == self.backrun.mint)
+            .map_or(SOL_DECIMALS, |(_, decimals)| *decimals);
+        format_token_amount(self.backrun.user_reward as u128, decimals).parse().unwrap_or(0.0)
+    }
+}
+
+/// The keypair back-runs are signed with, read once from the path in
+/// `ARB_KEYPAIR`. Without it back-runs are disabled.
+pub fn arb_signer() -> Option<&'static Keypair> {
+    ARB_SIGNER
+        .get_or_init(|| {
+            let path = std::env::var("ARB_KEYPAIR").ok()?;
+            match read_keypair_file(&path) {
+                Ok(keypair) => {
+                    println!("[arb] Signing back-runs as {}", keypair.pubkey());
+                    Some(keypair)
+                }
+                Err(e) => {
+                    eprintln!("[arb] Failed to read ARB_KEYPAIR {}: {}", path, e);
+                    None
+                }
+            }
+        })
+        .as_ref()
+}
+
+fn env_u64(key: &str, default: u64) -> u64 {
+    std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+/// Finds and signs the best back-run of `user`'s swap, if any pays for
+/// itself.
+pub async fn quote_backrun(user: &Pubkey, hops: &[UserHop]) -> Result<Option<ArbQuote>> {
+    let Some(signer) = arb_signer() else {
+        return Ok(None);
+    };
+    let registry = pool_registry();
+
+    let mut addresses: Vec<Pubkey> = hops.iter().map(|hop| hop.pool).collect();
+    for hop in hops {
+        let mut pairs = vec![(hop.input_mint, hop.output_mint)];
+        for base in BASE_MINTS {
+            pairs.push((base, hop.output_mint));
+            pairs.push((hop.input_mint, base));
+        }
+        for (a, b) in pairs {
+            let entries = registry.pools_for_pair(&a.to_string(), &b.to_string());
+            addresses.extend(
+                entries.iter().take(POOLS_PER_PAIR).filter_map(|entry| Pubkey::from_str(&entry.address).ok()),
+            );
+        }
+    }
+    let mut seen = HashSet::new();
+    addresses.retain(|address| seen.insert(*address));
+
+    let mut loaded = pool_state_cache().load(&addresses).await?;
+    let simulated = apply_user_swap(&mut loaded.pools, hops);
+    if simulated.is_empty() {
+        return Ok(None);
+    }
+
+    // Base mint inventory sits in the signer's token accounts; the mints'
+    // extensions tell which token program and hook each traded mint uses.
+    let mints: Vec<Pubkey> = loaded
+        .pools
+        .iter()
+        .flat_map(|pool| pool.mints())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let inventory_accounts: Vec<Pubkey> = BASE_MINTS
+        .iter()
+        .map(|mint| associated_token_address(&signer.pubkey(), mint, &TOKEN_PROGRAM))
+        .collect();
+    let (fetched, mut token_mints) =
+        tokio::try_join!(get_multiple_accounts(&inventory_accounts), TokenMints::fetch(&mints))?;
+    token_mints.fetch_hook_lists().await?;
+    loaded.pools.retain(|pool| token_mints.tradable(pool.as_ref()));
+
+    let inventory = BASE_MINTS
+        .iter()
+        .zip(&inventory_accounts)
+        .filter_map(|(mint, account)| {
+            let amount = token_account_amount(&fetched.accounts.get(account)?.data).ok()?;
+            Some((*mint, amount))
+        })
+        .collect();
+
+    let tip_lamports = env_u64("ARB_TIP_LAMPORTS", DEFAULT_TIP_LAMPORTS);
+    let params = SearchParams {
+        inventory,
+        sol_usd: registry.sol_usd(),
+        cost_lamports: tip_lamports + SIGNATURE_FEE_LAMPORTS,
+        user_share_bps: env_u64("ARB_USER_SHARE_BPS", DEFAULT_USER_SHARE_BPS).min(10_000),
+    };
+    let Some(backrun) = search_backrun(&loaded.pools, &simulated, &params) else {
+        return Ok(None);
+    };
+
+    let table_keys = configured_lookup_tables();
+    let (lookup_tables, blockhash) = tokio::try_join!(fetch_lookup_tables(&table_keys), get_latest_blockhash())?;
+    let accounts = ArbAccounts {
+        signer,
+        user: *user,
+        mints: &token_mints,
+        tip_account: random_tip_account(),
+        tip_lamports,
+        lookup_tables: &lookup_tables,
+    };
+    let transaction = build_arb_transaction(&loaded.pools, &backrun, &accounts, blockhash)?;
+    println!(
+        "[arb] Back-run of {} nets {} of {}, {} to the user",
+        user,
+        backrun.net_profit(),
+        backrun.mint,
+        backrun.user_reward
+    );
+    Ok(Some(ArbQuote {
+        backrun,
+        tip_lamports,
+        transaction: BASE64.encode(bincode::serialize(&transaction)?),
+    }))
+}
@@ -0,0 +1,515 @@
+//! Ledger of landed back-runs and what they paid the swapper, read from the
+//! confirmed transactions rather than from the quotes that priced them.
+//!
+//! Bundles submitted through `/api/bundles` are followed until they land.
+//! Every back-run in a landed bundle, a transaction paid for by the arb
+//! signer, is recorded against the user's swap right before it.
+
+use crate::api_methods::handle_history::get_parsed_transaction;
+use crate::arb::arb_signer;
+use crate::jito::bundles::{BundleState, BundleStatus};
+use crate::jito::TIP_ACCOUNTS;
+use crate::pools::indexer::{SOL_DECIMALS, SOL_MINT};
+use crate::REWARD_LEDGER;
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// `getTransaction` can lag a bundle's landed status by a few slots.
+const FETCH_RETRY_DELAYS: [u64; 4] = [1, 2, 4, 8];
+
+/// One landed back-run, in raw units of `mint`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reward {
+    /// The user's swap the back-run followed.
+    pub swap_signature: String,
+    pub arb_signature: String,
+    pub user: String,
+    /// Mint the cycle started and ended in, and the reward was paid in.
+    pub mint: String,
+    pub decimals: u8,
+    /// What the cycle netted before the user's share. SOL cycles have the
+    /// tip and fee taken off already; token cycles paid them in SOL, see
+    /// `cost_lamports`.
+    pub profit: i64,
+    pub user_reward: u64,
+    /// Transaction fee plus Jito tip.
+    pub cost_lamports: u64,
+    pub slot: u64,
+    /// Unix seconds, when the node knows it.
+    pub block_time: Option<i64>,
+}
+
+/// Back-runs summed per mint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MintTotals {
+    pub mint: String,
+    pub decimals: u8,
+    pub backruns: u64,
+    pub profit: i64,
+    pub user_reward: u64,
+    pub cost_lamports: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletRewards {
+    pub address: String,
+    pub totals: Vec<MintTotals>,
+    /// Newest first.
+    pub rewards: Vec<Reward>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RewardStats {
+    pub backruns: usize,
+    pub users: usize,
+    pub last_slot: Option<u64>,
+    pub totals: Vec<MintTotals>,
+}
+
+/// Every recorded back-run, by its signature, saved to disk as bincode.
+pub struct RewardLedger {
+    rewards: DashMap<String, Reward>,
+    path: PathBuf,
+    save_lock: Mutex<()>,
+}
+
+pub fn reward_ledger() -> &'static RewardLedger {
+    REWARD_LEDGER.get_or_init(RewardLedger::load)
+}
+
+impl RewardLedger {
+    pub fn load() -> Self {
+        Self::load_from(PathBuf::from(
+            std::env::var("REWARD_LEDGER_PATH").unwrap_or_else(|_| "rewards.bin".into()),
+        ))
+    }
+
+    fn load_from(path: PathBuf) -> Self {
+        let ledger = RewardLedger { rewards: DashMap::new(), path, save_lock: Mutex::new(()) };
+        match std::fs::read(&ledger.path) {
+            Ok(raw) => match bincode::deserialize::<Vec<Reward>>(&raw) {
+                Ok(saved) => saved.into_iter().for_each(|reward| {
+                    ledger.rewards.insert(reward.arb_signature.clone(), reward);
+                }),
+                Err(e) => eprintln!("[arb] Ignoring malformed {}: {}", ledger.path.display(), e),
+            },
+            Err(_) => println!("[arb] No saved rewards at {}", ledger.path.display()),
+        }
+        ledger
+    }
+
+    /// Returns whether the back-run is new to the ledger.
+    pub fn record(&self, reward: Reward) -> bool {
+        if self.rewards.contains_key(&reward.arb_signature) {
+            return false;
+        }
+        self.rewards.insert(reward.arb_signature.clone(), reward);
+        true
+    }
+
+    pub fn wallet(&self, address: &str) -> WalletRewards {
+        let mut rewards: Vec<Reward> =
+            self.rewards.iter().filter(|reward| reward.user == address).map(|reward| reward.clone()).collect();
+        rewards.sort_by(|a, b| b.slot.cmp(&a.slot).then_with(|| a.arb_signature.cmp(&b.arb_signature)));
+        WalletRewards { address: address.to_string(), totals: totals(&rewards), rewards }
+    }
+
+    pub fn stats(&self) -> RewardStats {
+        let rewards: Vec<Reward> = self.rewards.iter().map(|reward| reward.clone()).collect();
+        RewardStats {
+            backruns: rewards.len(),
+            users: rewards.iter().map(|reward| reward.user.as_str()).collect::<HashSet<_>>().len(),
+            last_slot: rewards.iter().map(|reward| reward.slot).max(),
+            totals: totals(&rewards),
+        }
+    }
+
+    /// Reads the landed bundle's transactions and records each back-run in
+    /// it. Returns the newly recorded ones.
+    pub async fn record_bundle(&self, signatures: &[String], signer: &Pubkey) -> Result<Vec<Reward>> {
+        let mut transactions = Vec::with_capacity(signatures.len());
+        for signature in signatures {
+            transactions.push(fetch_confirmed(signature).await?);
+        }
+        Ok(self.record_transactions(&transactions, signer)?)
+    }
+
+    /// Records every back-run of `signer` in the bundle's transactions and
+    /// saves them. A back-run the reward can't be read from is logged and
+    /// skipped rather than losing the rest.
+    fn record_transactions(&self, transactions: &[Value], signer: &Pubkey) -> std::io::Result<Vec<Reward>> {
+        let signer_key = signer.to_string();
+        let mut recorded = Vec::new();
+        for pair in transactions.windows(2) {
+            let (swap, arb) = (&pair[0], &pair[1]);
+            if fee_payer(arb).as_ref() != Some(&signer_key) || fee_payer(swap).as_ref() == Some(&signer_key) {
+                continue;
+            }
+            let reward = match reward_from_transactions(swap, arb, signer) {
+                Ok(reward) => reward,
+                Err(e) => {
+                    let signature = first_signature(arb).unwrap_or_default();
+                    eprintln!("[rewards] Skipping back-run {}: {}", signature, e);
+                    continue;
+                }
+            };
+            if self.record(reward.clone()) {
+                recorded.push(reward);
+            }
+        }
+        if !recorded.is_empty() {
+            self.save()?;
+        }
+        Ok(recorded)
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let _guard = self.save_lock.lock().unwrap();
+        let saved: Vec<Reward> = self.rewards.iter().map(|reward| reward.clone()).collect();
+        let bytes = bincode::serialize(&saved).map_err(std::io::Error::other)?;
+        let tmp = self.path.with_extension("bin.tmp");
+        std::fs::write(&tmp, bytes)?;
+        std::fs::rename(tmp, &self.path)
+    }
+}
+
+fn totals(rewards: &[Reward]) -> Vec<MintTotals> {
+    let mut by_mint: BTreeMap<&str, MintTotals> = BTreeMap::new();
+    for reward in rewards {
+        let totals = by_mint.entry(&reward.mint).or_insert_with(|| MintTotals {
+            mint: reward.mint.clone(),
+            decimals: reward.decimals,
+            backruns: 0,
+            profit: 0,
+            user_reward: 0,
+            cost_lamports: 0,
+        });
+        totals.backruns += 1;
+        totals.profit = totals.profit.saturating_add(reward.profit);
+        totals.user_reward = totals.user_reward.saturating_add(reward.user_reward);
+        totals.cost_lamports = totals.cost_lamports.saturating_add(reward.cost_lamports);
+    }
+    by_mint.into_values().collect()
+}
+
+/// Records the bundle's back-runs once it lands. Does nothing without an
+/// arb signer, since no bundle can then hold one.
+pub fn spawn_reward_recorder(mut updates: watch::Receiver<BundleStatus>) {
+    let Some(signer) = arb_signer().map(|keypair| keypair.pubkey()) else {
+        return;
+    };
+    tokio::spawn(async move {
+        let status = match updates.wait_for(|status| status.state.is_final()).await {
+            Ok(status) => status.clone(),
+            Err(_) => return,
+        };
+        if status.state != BundleState::Landed {
+            return;
+        }
+        match reward_ledger().record_bundle(&status.signatures, &signer).await {
+            Ok(rewards) => {
+                for reward in rewards {
+                    println!(
+                        "[arb] Back-run {} landed at slot {}: {} of {} to {}",
+                        reward.arb_signature, reward.slot, reward.user_reward, reward.mint, reward.user
+                    );
+                }
+            }
+            Err(e) => eprintln!("[arb] Failed to record rewards of bundle {}: {}", status.bundle_id, e),
+        }
+    });
+}
+
+async fn fetch_confirmed(signature: &str) -> Result<Value> {
+    for delay in FETCH_RETRY_DELAYS {
+        match get_parsed_transaction(signature).await {
+            Ok(transaction) => return Ok(transaction),
+            Err(_) => tokio::time::sleep(Duration::from_secs(delay)).await,
+        }
+    }
+    Err(anyhow!("transaction {} is not confirmed", signature))
+}
+
+/// The reward `arb`, a confirmed back-run signed by `signer`, paid to the
+/// payer of `swap`. Both are `getTransaction` results in `jsonParsed`.
+pub fn reward_from_transactions(swap: &Value, arb: &Value, signer: &Pubkey) -> Result<Reward> {
+    let meta = &arb["meta"];
+    if !meta["err"].is_null() {
+        return Err(anyhow!("back-run failed on chain"));
+    }
+    let keys = account_keys(arb);
+    let signer_key = signer.to_string();
+    if keys.first() != Some(&signer_key) {
+        return Err(anyhow!("transaction is not a back-run"));
+    }
+    let user = fee_payer(swap).ok_or_else(|| anyhow!("swap has no fee payer"))?;
+
+    let fee = meta["fee"].as_u64().unwrap_or(0);
+    let tip: i128 = keys
+        .iter()
+        .enumerate()
+        .filter(|(_, key)| TIP_ACCOUNTS.iter().any(|tip| tip.to_string() == **key))
+        .map(|(index, _)| lamport_delta(meta, index))
+        .sum();
+    let user_lamports = keys.iter().position(|key| *key == user).map_or(0, |index| lamport_delta(meta, index));
+
+    // SOL rewards are paid in lamports out of the signer's balance, while
+    // the cycle itself runs through its wSOL account.
+    let sol_mint = SOL_MINT.to_string();
+    let (mint, decimals, user_reward, signer_gain) = if user_lamports > 0 {
+        let gain = lamport_delta(meta, 0) + token_delta(meta, &signer_key, &sol_mint);
+        (sol_mint, SOL_DECIMALS, user_lamports, gain)
+    } else {
+        let (mint, decimals) = token_mints(meta, &user)
+            .into_iter()
+            .find(|(mint, _)| token_delta(meta, &user, mint) > 0)
+            .ok_or_else(|| anyhow!("back-run paid the user nothing"))?;
+        let reward = token_delta(meta, &user, &mint);
+        let gain = token_delta(meta, &signer_key, &mint);
+        (mint, decimals, reward, gain)
+    };
+
+    Ok(Reward {
+        swap_signature: first_signature(swap).ok_or_else(|| anyhow!("swap has no signature"))?,
+        arb_signature: first_signature(arb).ok_or_else(|| anyhow!("back-run has no signature"))?,
+        user,
+        mint,
+        decimals,
+        profit: i64::try_from(signer_gain + user_reward).map_err(|_| anyhow!("back-run profit overflows"))?,
+        user_reward: u64::try_from(user_reward)?,
+        cost_lamports: fee + u64::try_from(tip.max(0))?,
+        slot: arb["slot"].as_u64().ok_or_else(|| anyhow!("back-run has no slot"))?,
+        block_time: arb["blockTime"].as_i64(),
+    })
+}
+
+/// Static and loaded keys in index order; `jsonParsed` lists both.
+fn account_keys(transaction: &Value) -> Vec<String> {
+    transaction["transaction"]["message"]["accountKeys"]
+        .as_array()
+        .map(|keys| {
+            keys.iter()
+                .filter_map(|key| key.as_str().or_else(|| key["pubkey"].as_str()).map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn fee_payer(transaction: &Value) -> Option<String> {
+    account_keys(transaction).into_iter().next()
+}
+
+fn first_signature(transaction: &Value) -> Option<String> {
+    transaction["transaction"]["signatures"][0].as_str().map(str::to_string)
+}
+
+fn lamport_delta(meta: &Value, index: usize) -> i128 {
+    let balance = |field: &str| meta[field][index].as_u64().unwrap_or(0) as i128;
+    balance("postBalances") - balance("preBalances")
+}
+
+/// Change of what `owner` holds of `mint` across all its token accounts.
+fn token_delta(meta: &Value, owner: &str, mint: &str) -> i128 {
+    let held = |field: &str| -> i128 {
+        meta[field]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|balance| balance["owner"].as_str() == Some(owner) && balance["mint"].as_str() == Some(mint))
+            .filter_map(|balance| balance["uiTokenAmount"]["amount"].as_str()?.parse::<i128>().ok())
+            .sum()
+    };
+    held("postTokenBalances") - held("preTokenBalances")
+}
+
+/// Mints `owner` holds in the transaction's token balances, with decimals.
+fn token_mints(meta: &Value, owner: &str) -> Vec<(String, u8)> {
+    let mut mints: Vec<(String, u8)> = Vec::new();
+    let balances = ["preTokenBalances", "postTokenBalances"].into_iter().filter_map(|field| meta[field].as_array());
+    for balance in balances.flatten() {
+        if balance["owner"].as_str() != Some(owner) {
+            continue;
+        }
+        let (Some(mint), Some(decimals)) = (balance["mint"].as_str(), balance["uiTokenAmount"]["decimals"].as_u64())
+        else {
+            continue;
+        };
+        if !mints.iter().any(|(known, _)| known == mint) {
+            mints.push((mint.to_string(), decimals as u8));
+        }
+    }
+    mints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::indexer::USDC_MINT;
+    use serde_json::json;
+
+    fn token_balance(index: usize, owner: &Pubkey, mint: &Pubkey, decimals: u8, amount: u64) -> Value {
+        json!({
+            "accountIndex": index,
+            "mint": mint.to_string(),
+            "owner": owner.to_string(),
+            "uiTokenAmount": { "amount": amount.to_string(), "decimals": decimals },
+        })
+    }
+
+    /// A confirmed `jsonParsed` transaction with only the fields the ledger
+    /// reads.
+    fn transaction(signature: &str, keys: &[Pubkey], balances: [&[u64]; 2], tokens: [Vec<Value>; 2]) -> Value {
+        let [pre_tokens, post_tokens] = tokens;
+        json!({
+            "slot": 300,
+            "blockTime": 1_700_000_000,
+            "meta": {
+                "err": null,
+                "fee": 5_000,
+                "preBalances": balances[0],
+                "postBalances": balances[1],
+                "preTokenBalances": pre_tokens,
+                "postTokenBalances": post_tokens,
+            },
+            "transaction": {
+                "signatures": [signature],
+                "message": {
+                    "accountKeys": keys
+                        .iter()
+                        .map(|key| json!({ "pubkey": key.to_string(), "signer": false, "writable": true }))
+                        .collect::<Vec<_>>(),
+                },
+            },
+        })
+    }
+
+    fn reward(arb_signature: &str, user: &str, mint: &Pubkey, slot: u64, user_reward: u64) -> Reward {
+        Reward {
+            swap_signature: format!("swap-{}", arb_signature),
+            arb_signature: arb_signature.to_string(),
+            user: user.to_string(),
+            mint: mint.to_string(),
+            decimals: 6,
+            profit: user_reward as i64 * 10 / 9,
+            user_reward,
+            cost_lamports: 105_000,
+            slot,
+            block_time: None,
+        }
+    }
+
+    #[test]
+    fn rewards_come_from_the_confirmed_balances() {
+        let (signer, user, wsol, pool) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let swap = transaction("user-swap", &[user, pool], [&[50_000_000, 0], &[49_995_000, 0]], [vec![], vec![]]);
+
+        // SOL cycle: 0.02 gross into the wSOL account, tip, fee and the
+        // user's 0.01 paid from the signer's lamports.
+        let sol_arb = transaction(
+            "sol-arb",
+            &[signer, wsol, TIP_ACCOUNTS[0], user, pool],
+            [
+                &[10_000_000_000, 5_002_039_280, 0, 49_995_000, 0],
+                &[9_989_895_000, 5_022_039_280, 100_000, 59_995_000, 0],
+            ],
+            [
+                vec![token_balance(1, &signer, &SOL_MINT, 9, 5_000_000_000)],
+                vec![token_balance(1, &signer, &SOL_MINT, 9, 5_020_000_000)],
+            ],
+        );
+        let reward = reward_from_transactions(&swap, &sol_arb, &signer).unwrap();
+        assert_eq!(reward.swap_signature, "user-swap");
+        assert_eq!(reward.arb_signature, "sol-arb");
+        assert_eq!((reward.user, reward.mint), (user.to_string(), SOL_MINT.to_string()));
+        assert_eq!((reward.user_reward, reward.profit, reward.cost_lamports), (10_000_000, 19_895_000, 105_000));
+        assert_eq!((reward.slot, reward.block_time, reward.decimals), (300, Some(1_700_000_000), 9));
+
+        // USDC cycle: the reward moves between token accounts, the user's
+        // created in the same transaction.
+        let (signer_usdc, user_usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let usdc_arb = transaction(
+            "usdc-arb",
+            &[signer, signer_usdc, user_usdc, TIP_ACCOUNTS[1], user],
+            [
+                &[10_000_000_000, 2_039_280, 0, 0, 49_995_000],
+                &[9_997_855_720, 2_039_280, 2_039_280, 100_000, 49_995_000],
+            ],
+            [
+                vec![token_balance(1, &signer, &USDC_MINT, 6, 90_000_000)],
+                vec![
+                    token_balance(1, &signer, &USDC_MINT, 6, 90_200_000),
+                    token_balance(2, &user, &USDC_MINT, 6, 1_800_000),
+                ],
+            ],
+        );
+        let reward = reward_from_transactions(&swap, &usdc_arb, &signer).unwrap();
+        assert_eq!(reward.mint, USDC_MINT.to_string());
+        assert_eq!((reward.user_reward, reward.profit, reward.decimals), (1_800_000, 2_000_000, 6));
+
+        let mut failed = usdc_arb.clone();
+        failed["meta"]["err"] = json!({ "InstructionError": [2, { "Custom": 6022 }] });
+        assert!(reward_from_transactions(&swap, &failed, &signer).is_err());
+        assert!(reward_from_transactions(&swap, &usdc_arb, &Pubkey::new_unique()).is_err());
+        let unpaid = transaction("unpaid", &[signer, user], [&[1_000, 0], &[1_000, 0]], [vec![], vec![]]);
+        assert!(reward_from_transactions(&swap, &unpaid, &signer).is_err());
+    }
+
+    #[test]
+    fn unreadable_back_runs_are_skipped() {
+        let path = std::env::temp_dir().join(format!("rewards-skip-{}.bin", std::process::id()));
+        let ledger = RewardLedger::load_from(path.clone());
+        let (signer, user, pool) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let swap = |signature| transaction(signature, &[user, pool], [&[1_000, 0], &[1_000, 0]], [vec![], vec![]]);
+        let mut failed =
+            transaction("failed-arb", &[signer, user], [&[1_000_000, 0], &[990_000, 10_000]], [vec![], vec![]]);
+        failed["meta"]["err"] = json!({ "InstructionError": [0, { "Custom": 1 }] });
+        let paid = transaction("paid-arb", &[signer, user], [&[1_000_000, 0], &[990_000, 10_000]], [vec![], vec![]]);
+
+        let recorded = ledger.record_transactions(&[swap("s1"), failed, swap("s2"), paid], &signer).unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].arb_signature, "paid-arb");
+        let reloaded = RewardLedger::load_from(path.clone());
+        assert_eq!(reloaded.wallet(&user.to_string()).rewards, recorded);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn ledger_totals_wallets_and_persists() {
+        let path = std::env::temp_dir().join(format!("rewards-ledger-{}.bin", std::process::id()));
+        let ledger = RewardLedger::load_from(path.clone());
+        let (alice, bob) = (Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string());
+        assert!(ledger.record(reward("a1", &alice, &USDC_MINT, 10, 900)));
+        assert!(ledger.record(reward("a2", &alice, &USDC_MINT, 12, 1_800)));
+        assert!(ledger.record(reward("a3", &alice, &SOL_MINT, 11, 90)));
+        assert!(ledger.record(reward("b1", &bob, &USDC_MINT, 13, 9)));
+        assert!(!ledger.record(reward("a1", &alice, &USDC_MINT, 10, 1)));
+
+        let wallet = ledger.wallet(&alice);
+        assert_eq!(wallet.rewards.iter().map(|r| r.arb_signature.as_str()).collect::<Vec<_>>(), ["a2", "a3", "a1"]);
+        let usdc = wallet.totals.iter().find(|totals| totals.mint == USDC_MINT.to_string()).unwrap();
+        assert_eq!((usdc.backruns, usdc.user_reward, usdc.profit), (2, 2_700, 3_000));
+        assert_eq!(wallet.totals.len(), 2);
+
+        let stats = ledger.stats();
+        assert_eq!((stats.backruns, stats.users, stats.last_slot), (4, 2, Some(13)));
+
+        ledger.save().unwrap();
+        let reloaded = RewardLedger::load_from(path.clone());
+        assert_eq!(reloaded.wallet(&bob).rewards, ledger.wallet(&bob).rewards);
+        let _ = std::fs::remove_file(path);
+    }
+}
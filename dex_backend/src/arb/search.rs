@@ -0,0 +1,333 @@
+use crate::pools::indexer::{SOL_MINT, STABLE_MINTS, USDC_MINT, USDT_MINT};
+use crate::pools::{Pool, PoolQuote};
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+/// Mints a back-run can start and end in: the signer keeps inventory in
+/// them and they have a SOL value to net the costs against.
+pub const BASE_MINTS: [Pubkey; 3] = [SOL_MINT, USDC_MINT, USDT_MINT];
+/// Pools tried per leg of a cycle, deepest first.
+const MAX_POOLS_PER_LEG: usize = 3;
+/// The coarse scan tries inventory / 2^k for k up to this.
+const SCAN_STEPS: u32 = 24;
+const REFINE_ITERATIONS: usize = 100;
+
+/// One hop of the user's swap, as routed by the aggregator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserHop {
+    pub pool: Pubkey,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub amount_in: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArbLeg {
+    #[serde(with = "crate::pools::pubkey_string")]
+    pub pool: Pubkey,
+    #[serde(with = "crate::pools::pubkey_string")]
+    pub input_mint: Pubkey,
+    #[serde(with = "crate::pools::pubkey_string")]
+    pub output_mint: Pubkey,
+    pub amount_in: u64,
+    pub amount_out: u64,
+}
+
+/// A profitable cycle from `mint` back to itself, sized and priced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Backrun {
+    #[serde(with = "crate::pools::pubkey_string")]
+    pub mint: Pubkey,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    /// Tip and fees, in `mint` units.
+    pub cost: u64,
+    /// Paid to the swapper, in `mint` units.
+    pub user_reward: u64,
+    pub legs: Vec<ArbLeg>,
+}
+
+impl Backrun {
+    /// What the cycle earns after costs, before the user's share.
+    pub fn net_profit(&self) -> u64 {
+        self.amount_out - self.amount_in - self.cost
+    }
+}
+
+/// What bounds and prices the search.
+#[derive(Debug, Clone)]
+pub struct SearchParams {
+    /// Signer balances of the base mints; a cycle never spends more.
+    pub inventory: HashMap<Pubkey, u64>,
+    pub sol_usd: Option<f64>,
+    /// Tip plus transaction fee.
+    pub cost_lamports: u64,
+    /// Share of the net profit paid to the swapper.
+    pub user_share_bps: u64,
+}
+
+/// Runs the user's hops against the pools in place, in route order.
+/// Returns the hops that could be simulated; the rest trade through pools
+/// we don't load or ran out of the loaded liquidity, and are skipped.
+pub fn apply_user_swap(pools: &mut [Box<dyn Pool>], hops: &[UserHop]) -> Vec<UserHop> {
+    hops.iter()
+        .filter(|hop| {
+            pools
+                .iter_mut()
+                .find(|pool| pool.address() == hop.pool)
+                .is_some_and(|pool| pool.apply_swap(&hop.input_mint, hop.amount_in).is_ok())
+        })
+        .copied()
+        .collect()
+}
+
+/// Best back-run over `pools` once the user's hops have been applied.
+///
+/// A user hop selling X for Y leaves X cheap in its pool, so every cycle
+/// buys X back there: base mint S to Y through another pool, Y to X
+/// through the user's pool, then X to S through another pool. The first or
+/// last leg drops out when S is Y or X.
+pub fn search_backrun(pools: &[Box<dyn Pool>], hops: &[UserHop], params: &SearchParams) -> Option<Backrun> {
+    let mut best: Option<(u64, Backrun)> = None;
+    for hop in hops {
+        let Some(user_pool) = pools.iter().position(|pool| pool.address() == hop.pool) else {
+            continue;
+        };
+        for base in BASE_MINTS {
+            let inventory = params.inventory.get(&base).copied().unwrap_or(0);
+            let Some(cost) = lamports_in(&base, params.cost_lamports, params.sol_usd) else {
+                continue;
+            };
+            if inventory == 0 || (base == hop.input_mint && base == hop.output_mint) {
+                continue;
+            }
+            for cycle in cycles(pools, base, hop, user_pool) {
+                let Some(backrun) = size_cycle(pools, &cycle, inventory, cost, params.user_share_bps) else {
+                    continue;
+                };
+                let Some(value) = value_in_lamports(&base, backrun.net_profit(), params.sol_usd) else {
+                    continue;
+                };
+                if best.as_ref().is_none_or(|(best_value, _)| value > *best_value) {
+                    best = Some((value, backrun));
+                }
+            }
+        }
+    }
+    best.map(|(_, backrun)| backrun)
+}
+
+/// A cycle as (pool index, input mint) per leg.
+type Cycle = Vec<(usize, Pubkey)>;
+
+fn cycles(pools: &[Box<dyn Pool>], base: Pubkey, hop: &UserHop, user_pool: usize) -> Vec<Cycle> {
+    let entries = if base == hop.output_mint {
+        vec![None]
+    } else {
+        pools_between(pools, base, hop.output_mint, user_pool).into_iter().map(Some).collect()
+    };
+    let exits = if base == hop.input_mint {
+        vec![None]
+    } else {
+        pools_between(pools, hop.input_mint, base, user_pool).into_iter().map(Some).collect()
+    };
+
+    let mut cycles = Vec::new();
+    for entry in &entries {
+        for exit in &exits {
+            let mut cycle = Vec::with_capacity(3);
+            if let Some(entry) = entry {
+                cycle.push((*entry, base));
+            }
+            cycle.push((user_pool, hop.output_mint));
+            if let Some(exit) = exit {
+                cycle.push((*exit, hop.input_mint));
+            }
+            if cycle.len() >= 2 {
+                cycles.push(cycle);
+            }
+        }
+    }
+    cycles
+}
+
+/// Pools trading `a` against `b` other than `exclude`, in the order given.
+fn pools_between(pools: &[Box<dyn Pool>], a: Pubkey, b: Pubkey, exclude: usize) -> Vec<usize> {
+    pools
+        .iter()
+        .enumerate()
+        .filter(|(i, pool)| {
+            let mints = pool.mints();
+            *i != exclude && (mints == [a, b] || mints == [b, a])
+        })
+        .map(|(i, _)| i)
+        .take(MAX_POOLS_PER_LEG)
+        .collect()
+}
+
+fn quote_cycle(pools: &[Box<dyn Pool>], cycle: &Cycle, amount_in: u64) -> Option<Vec<PoolQuote>> {
+    let mut amount = amount_in;
+    let mut quotes = Vec::with_capacity(cycle.len());
+    for (pool, input_mint) in cycle {
+        let quote = pools[*pool].quote_exact_in(input_mint, amount).ok()?;
+        amount = quote.amount_out;
+        quotes.push(quote);
+    }
+    Some(quotes)
+}
+
+/// Sizes the cycle for the most profit within `inventory`. Cycle profit is
+/// concave in the input, so a scan over powers of two finds the right
+/// neighbourhood and a ternary search refines it.
+fn size_cycle(pools: &[Box<dyn Pool>], cycle: &Cycle, inventory: u64, cost: u64, share_bps: u64) -> Option<Backrun> {
+    let gain = |amount: u64| -> i128 {
+        match quote_cycle(pools, cycle, amount) {
+            Some(quotes) => quotes.last().unwrap().amount_out as i128 - amount as i128,
+            None => i128::MIN,
+        }
+    };
+
+    let (mut best_amount, mut best_gain) = (0, 0);
+    for k in 0..=SCAN_STEPS {
+        let amount = inventory >> k;
+        if amount == 0 {
+            break;
+        }
+        let g = gain(amount);
+        if g > best_gain {
+            (best_amount, best_gain) = (amount, g);
+        }
+    }
+    if best_gain <= 0 {
+        return None;
+    }
+
+    let (mut low, mut high) = (best_amount / 2, best_amount.saturating_mul(2).min(inventory));
+    for _ in 0..REFINE_ITERATIONS {
+        if high - low < 3 {
+            break;
+        }
+        let third = (high - low) / 3;
+        if gain(low + third) < gain(high - third) {
+            low += third;
+        } else {
+            high -= third;
+        }
+    }
+    for amount in low..=high {
+        let g = gain(amount);
+        if g > best_gain {
+            (best_amount, best_gain) = (amount, g);
+        }
+    }
+
+    if best_gain <= cost as i128 {
+        return None;
+    }
+    let quotes = quote_cycle(pools, cycle, best_amount)?;
+    let amount_out = quotes.last()?.amount_out;
+    let net = amount_out - best_amount - cost;
+    let user_reward = (net as u128 * share_bps as u128 / 10_000) as u64;
+    if user_reward == 0 {
+        return None;
+    }
+    let legs = cycle
+        .iter()
+        .zip(&quotes)
+        .map(|((pool, input_mint), quote)| {
+            let mints = pools[*pool].mints();
+            ArbLeg {
+                pool: pools[*pool].address(),
+                input_mint: *input_mint,
+                output_mint: if mints[0] == *input_mint { mints[1] } else { mints[0] },
+                amount_in: quote.amount_in,
+                amount_out: quote.amount_out,
+            }
+        })
+        .collect();
+    Some(Backrun { mint: cycle[0].1, amount_in: best_amount, amount_out, cost, user_reward, legs })
+}
+
+/// `lamports` worth of a base mint, rounded up.
+fn lamports_in(mint: &Pubkey, lamports: u64, sol_usd: Option<f64>) -> Option<u64> {
+    if *mint == SOL_MINT {
+        return Some(lamports);
+    }
+    let (_, decimals) = STABLE_MINTS.iter().find(|(stable, _)| stable == mint)?;
+    let usd = lamports as f64 / 1e9 * sol_usd?;
+    Some((usd * 10f64.powi(*decimals as i32)).ceil() as u64)
+}
+
+fn value_in_lamports(mint: &Pubkey, amount: u64, sol_usd: Option<f64>) -> Option<u64> {
+    if *mint == SOL_MINT {
+        return Some(amount);
+    }
+    let (_, decimals) = STABLE_MINTS.iter().find(|(stable, _)| stable == mint)?;
+    let usd = amount as f64 / 10f64.powi(*decimals as i32);
+    Some((usd / sol_usd? * 1e9) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::pool_from_snapshot;
+
+    fn snapshot_pools() -> Vec<Box<dyn Pool>> {
+        vec![
+            pool_from_snapshot("raydium_amm_v4_sol_usdc", "58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2"),
+            pool_from_snapshot("raydium_clmm_sol_usdc", "3ucNos4NbumPLZNWztqGHNFFgkHeRMBQAVemeeomsUxv"),
+            pool_from_snapshot("whirlpool_sol_usdc", "Czfq3xZZDmsdGdUyrNLtRhGc47cXcZtLG4crryfu44zE"),
+        ]
+    }
+
+    fn params() -> SearchParams {
+        SearchParams {
+            inventory: HashMap::from([(SOL_MINT, 5_000_000_000_000), (USDC_MINT, 500_000_000_000)]),
+            sol_usd: Some(150.0),
+            cost_lamports: 1_005_000,
+            user_share_bps: 9_000,
+        }
+    }
+
+    #[test]
+    fn applied_swaps_chain_like_a_single_quote() {
+        let pool = pool_from_snapshot("raydium_clmm_sol_usdc", "3ucNos4NbumPLZNWztqGHNFFgkHeRMBQAVemeeomsUxv");
+        let whole = pool.quote_exact_in(&SOL_MINT, 2_000_000_000_000).unwrap();
+
+        let mut stepped = pool.boxed_clone();
+        let first = stepped.apply_swap(&SOL_MINT, 1_000_000_000_000).unwrap();
+        let second = stepped.apply_swap(&SOL_MINT, 1_000_000_000_000).unwrap();
+        let stepped_out = first.amount_out + second.amount_out;
+        // Each step rounds in the pool's favour on its own.
+        assert!(whole.amount_out.abs_diff(stepped_out) <= 2, "{} vs {}", whole.amount_out, stepped_out);
+        assert_eq!(pool.quote_exact_in(&SOL_MINT, 1_000_000_000_000).unwrap(), first);
+    }
+
+    #[test]
+    fn large_sell_leaves_a_backrun_for_the_user() {
+        let mut pools = snapshot_pools();
+        let hop = UserHop {
+            pool: pools[0].address(),
+            input_mint: SOL_MINT,
+            output_mint: USDC_MINT,
+            amount_in: 20_000_000_000_000,
+        };
+        assert!(search_backrun(&pools, &[hop], &params()).is_none());
+
+        assert_eq!(apply_user_swap(&mut pools, &[hop]), [hop]);
+        let backrun = search_backrun(&pools, &[hop], &params()).unwrap();
+
+        // The sell made SOL cheap in the v4 pool, so the cycle buys it there.
+        assert!(backrun.legs.iter().any(|leg| leg.pool == hop.pool && leg.output_mint == SOL_MINT));
+        assert_eq!(backrun.legs.first().unwrap().input_mint, backrun.mint);
+        assert_eq!(backrun.legs.last().unwrap().output_mint, backrun.mint);
+        for pair in backrun.legs.windows(2) {
+            assert_eq!(pair[0].amount_out, pair[1].amount_in);
+        }
+        assert!(backrun.amount_out > backrun.amount_in + backrun.cost);
+        assert_eq!(backrun.user_reward, backrun.net_profit() * 9 / 10);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::arb::search::Backrun;
+use crate::pools::indexer::SOL_MINT;
+use crate::pools::{Pool, SwapAccounts};
+use crate::tx::ata::{associated_token_address, create_ata_idempotent};
+use crate::tx::builder::{compile_message, swap_transfers};
+use crate::tx::token_2022::TokenMints;
+use crate::tx::{system, token};
+use anyhow::{anyhow, Result};
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use solana_sdk::signer::Signer;
+use solana_sdk::message::AddressLookupTableAccount;
+use solana_sdk::transaction::VersionedTransaction;
+
+/// Three concentrated-liquidity swaps crossing a few ticks each fit well
+/// within this.
+const ARB_COMPUTE_UNIT_LIMIT: u32 = 400_000;
+
+/// Who signs, who gets paid, and what the arb costs to land.
+pub struct ArbAccounts<'a> {
+    pub signer: &'a Keypair,
+    pub user: Pubkey,
+    /// Extensions of the pools' mints, hook lists included.
+    pub mints: &'a TokenMints,
+    pub tip_account: Pubkey,
+    pub tip_lamports: u64,
+    pub lookup_tables: &'a [AddressLookupTableAccount],
+}
+
+impl ArbAccounts<'_> {
+    fn token_program(&self, mint: &Pubkey) -> Pubkey {
+        self.mints.token_program(mint)
+    }
+
+    fn signer_ata(&self, mint: &Pubkey) -> Pubkey {
+        associated_token_address(&self.signer.pubkey(), mint, &self.token_program(mint))
+    }
+}
+
+/// The signed back-run: the cycle's swaps out of and back into the signer's
+/// token accounts, the Jito tip, and the user's share of the profit. The
+/// last swap's minimum covers the input, costs and reward, so the signer
+/// never pays out more than the cycle made.
+pub fn build_arb_transaction(
+    pools: &[Box<dyn Pool>],
+    backrun: &Backrun,
+    accounts: &ArbAccounts,
+    blockhash: Hash,
+) -> Result<VersionedTransaction> {
+    let signer = accounts.signer.pubkey();
+    let mut instructions: Vec<Instruction> =
+        vec![ComputeBudgetInstruction::set_compute_unit_limit(ARB_COMPUTE_UNIT_LIMIT)];
+
+    for leg in &backrun.legs {
+        if leg.output_mint != backrun.mint {
+            let program = accounts.token_program(&leg.output_mint);
+            instructions.push(create_ata_idempotent(&signer, &signer, &leg.output_mint, &program));
+        }
+    }
+
+    let break_even = backrun.amount_in + backrun.cost + backrun.user_reward;
+    for (i, leg) in backrun.legs.iter().enumerate() {
+        let pool = pools
+            .iter()
+            .find(|pool| pool.address() == leg.pool)
+            .ok_or_else(|| anyhow!("pool {} is not loaded", leg.pool))?;
+        let swap = SwapAccounts {
+            user: signer,
+            input_mint: leg.input_mint,
+            user_source: accounts.signer_ata(&leg.input_mint),
+            user_destination: accounts.signer_ata(&leg.output_mint),
+        };
+        let minimum_out = if i + 1 == backrun.legs.len() { break_even } else { leg.amount_out };
+        let transfers =
+            swap_transfers(pool.as_ref(), accounts.mints, &swap, &leg.output_mint, leg.amount_in, leg.amount_out)?;
+        instructions.push(pool.swap_instruction_for(&swap, &transfers, leg.amount_in, minimum_out)?);
+    }
+
+    instructions.push(system::transfer(&signer, &accounts.tip_account, accounts.tip_lamports));
+    if backrun.mint == SOL_MINT {
+        // Paid from the signer's lamports rather than its wSOL so the user
+        // gets native SOL.
+        instructions.push(system::transfer(&signer, &accounts.user, backrun.user_reward));
+    } else {
+        let program = accounts.token_program(&backrun.mint);
+        instructions.push(create_ata_idempotent(&signer, &accounts.user, &backrun.mint, &program));
+        instructions.push(token::transfer(
+            &accounts.signer_ata(&backrun.mint),
+            &associated_token_address(&accounts.user, &backrun.mint, &program),
+            &signer,
+            &program,
+            backrun.user_reward,
+        ));
+    }
+
+    let message = compile_message(&signer, &instructions, accounts.lookup_tables, blockhash)?;
+    Ok(VersionedTransaction::try_new(message, &[accounts.signer])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arb::search::{apply_user_swap, search_backrun, SearchParams, UserHop};
+    use crate::pools::indexer::USDC_MINT;
+    use crate::pools::pool_from_snapshot;
+    use crate::tx::system::SYSTEM_PROGRAM;
+    use crate::tx::token_2022::MintExtensions;
+    use std::collections::HashMap;
+
+    /// A back-run of a 2M USDC buy of SOL on the Whirlpool.
+    fn sol_backrun() -> (Vec<Box<dyn Pool>>, Backrun) {
+        let mut pools = vec![
+            pool_from_snapshot("raydium_clmm_sol_usdc", "3ucNos4NbumPLZNWztqGHNFFgkHeRMBQAVemeeomsUxv"),
+            pool_from_snapshot("whirlpool_sol_usdc", "Czfq3xZZDmsdGdUyrNLtRhGc47cXcZtLG4crryfu44zE"),
+        ];
+        let hop = UserHop {
+            pool: pools[1].address(),
+            input_mint: USDC_MINT,
+            output_mint: SOL_MINT,
+            amount_in: 2_000_000_000_000,
+        };
+        apply_user_swap(&mut pools, &[hop]);
+        let params = SearchParams {
+            inventory: HashMap::from([(SOL_MINT, 5_000_000_000_000)]),
+            sol_usd: Some(150.0),
+            cost_lamports: 105_000,
+            user_share_bps: 9_000,
+        };
+        let backrun = search_backrun(&pools, &[hop], &params).unwrap();
+        (pools, backrun)
+    }
+
+    #[test]
+    fn arb_transaction_swaps_tips_and_pays_the_user() {
+        let (pools, backrun) = sol_backrun();
+        assert_eq!(backrun.mint, SOL_MINT);
+
+        let signer = Keypair::new();
+        let user = Pubkey::new_unique();
+        let tip_account = Pubkey::new_unique();
+        let mints = TokenMints::default();
+        let accounts = ArbAccounts {
+            signer: &signer,
+            user,
+            mints: &mints,
+            tip_account,
+            tip_lamports: 100_000,
+            lookup_tables: &[],
+        };
+        let transaction = build_arb_transaction(&pools, &backrun, &accounts, Hash::new_unique()).unwrap();
+        assert!(transaction.verify_with_results().into_iter().all(|ok| ok));
+
+        let message = &transaction.message;
+        let keys = message.static_account_keys();
+        assert_eq!(keys[0], signer.pubkey());
+        let programs: Vec<Pubkey> =
+            message.instructions().iter().map(|ix| keys[ix.program_id_index as usize]).collect();
+        let venues: Vec<Pubkey> = backrun
+            .legs
+            .iter()
+            .map(|leg| pools.iter().find(|pool| pool.address() == leg.pool).unwrap().venue().program_id())
+            .collect();
+        assert_eq!(&programs[2..4], venues.as_slice());
+        assert_eq!(&programs[4..], [SYSTEM_PROGRAM, SYSTEM_PROGRAM]);
+
+        let payout = message.instructions().last().unwrap();
+        assert_eq!(keys[payout.accounts[1] as usize], user);
+        assert_eq!(payout.data[4..12], backrun.user_reward.to_le_bytes());
+    }
+
+    #[test]
+    fn hooked_mints_need_their_hook_accounts() {
+        let (pools, backrun) = sol_backrun();
+        let usdc = MintExtensions { transfer_hook: Some(Pubkey::new_unique()), ..MintExtensions::default() };
+        let mints = TokenMints::new(HashMap::from([(USDC_MINT, usdc)]), 0, 0);
+        let signer = Keypair::new();
+        let accounts = ArbAccounts {
+            signer: &signer,
+            user: Pubkey::new_unique(),
+            mints: &mints,
+            tip_account: Pubkey::new_unique(),
+            tip_lamports: 100_000,
+            lookup_tables: &[],
+        };
+        let err = build_arb_transaction(&pools, &backrun, &accounts, Hash::new_unique()).unwrap_err();
+        assert!(err.to_string().contains("transfer hook"), "{}", err);
+    }
+}
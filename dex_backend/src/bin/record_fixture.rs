@@ -0,0 +1,182 @@
+//! Records payloads for the history fixture tests.
+//!
+//!   cargo run --bin record_fixture -- rpc <address> <signature> <name>
+//!   cargo run --bin record_fixture -- solflare-signatures <address> <name>
+//!   cargo run --bin record_fixture -- solflare-transactions <address> <name> <signature>...
+//!   cargo run --bin record_fixture -- accounts <name> <address>...
+//!
+//! History payloads land under `tests/fixtures/recorded/<address>/`, the
+//! wallet they are normalized for: RPC ones as the full `getTransaction`
+//! jsonParsed response in `rpc/<name>.json`, Solflare ones as the raw
+//! `<|EOF|>`-chunked body in `solflare_signatures/<name>.txt` or
+//! `solflare_transactions/<name>.txt`. Solflare needs the API token in
+//! `SOLFLARE_TOKEN`. The fixture tests pick up everything recorded there;
+//! write their goldens with `UPDATE_GOLDEN=1 cargo test history_fixture`
+//! and review them.
+//!
+//! Account snapshots for the pool math tests land in
+//! `tests/fixtures/pools/<name>.json`; pass the pool together with everything
+//! its `Pool::accounts_to_update` lists so the snapshot is from one slot.
+
+use reqwest::blocking::Client;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const SOLFLARE_API: &str = "https://activity-api.solflare.com/v1";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let client = Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .expect("failed to build http client");
+
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["rpc", address, signature, name] => record_rpc(&client, address, signature, name),
+        ["solflare-signatures", address, name] => record_solflare_signatures(&client, address, name),
+        ["solflare-transactions", address, name, signatures @ ..] if !signatures.is_empty() => {
+            record_solflare_transactions(&client, address, name, signatures)
+        }
+        ["accounts", name, addresses @ ..] if !addresses.is_empty() => record_accounts(&client, name, addresses),
+        _ => {
+            eprintln!(
+                "usage:\n  record_fixture rpc <address> <signature> <name>\n  \
+                 record_fixture solflare-signatures <address> <name>\n  \
+                 record_fixture solflare-transactions <address> <name> <signature>...\n  \
+                 record_fixture accounts <name> <address>..."
+            );
+            std::process::exit(2);
+        }
+    };
+
+    match result {
+        Ok(path) => println!("[fixture] Wrote {}", path.display()),
+        Err(e) => {
+            eprintln!("[fixture] Recording failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn solflare_token() -> anyhow::Result<String> {
+    std::env::var("SOLFLARE_TOKEN").map_err(|_| anyhow::anyhow!("SOLFLARE_TOKEN is not set"))
+}
+
+fn rpc_url() -> String {
+    std::env::var("RPC_URL").unwrap_or_else(|_| "http://frankfurt.o7node.com:7799".into())
+}
+
+fn record_rpc(client: &Client, address: &str, signature: &str, name: &str) -> anyhow::Result<PathBuf> {
+    let response: Value = client
+        .post(rpc_url())
+        .json(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "getTransaction",
+            "params": [
+                signature,
+                {
+                    "encoding": "jsonParsed",
+                    "commitment": "confirmed",
+                    "maxSupportedTransactionVersion": 0
+                }
+            ]
+        }))
+        .send()?
+        .json()?;
+
+    if response["result"].is_null() {
+        anyhow::bail!("transaction {} not found: {}", signature, response);
+    }
+
+    write_fixture(
+        &format!("recorded/{}/rpc/{}.json", address, name),
+        format!("{}\n", serde_json::to_string_pretty(&response)?),
+    )
+}
+
+fn record_solflare_signatures(client: &Client, address: &str, name: &str) -> anyhow::Result<PathBuf> {
+    let body = client
+        .get(format!(
+            "{}/signatures?address={}&network=mainnet&ignoreFailed=0&limit=20",
+            SOLFLARE_API, address
+        ))
+        .header("authorization", format!("Bearer {}", solflare_token()?))
+        .send()?
+        .text()?;
+
+    write_fixture(&format!("recorded/{}/solflare_signatures/{}.txt", address, name), body)
+}
+
+fn record_solflare_transactions(
+    client: &Client,
+    address: &str,
+    name: &str,
+    signatures: &[&str],
+) -> anyhow::Result<PathBuf> {
+    let body = client
+        .post(format!("{}/transactions?network=mainnet", SOLFLARE_API))
+        .header("authorization", format!("Bearer {}", solflare_token()?))
+        .json(&json!({
+            "language": "en",
+            "layout": "web",
+            "address": address,
+            "signatures": signatures,
+        }))
+        .send()?
+        .text()?;
+
+    write_fixture(&format!("recorded/{}/solflare_transactions/{}.txt", address, name), body)
+}
+
+fn record_accounts(client: &Client, name: &str, addresses: &[&str]) -> anyhow::Result<PathBuf> {
+    let response: Value = client
+        .post(rpc_url())
+        .json(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "getMultipleAccounts",
+            "params": [addresses, { "encoding": "base64", "commitment": "confirmed" }]
+        }))
+        .send()?
+        .json()?;
+
+    let values = response["result"]["value"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("unexpected response: {}", response))?;
+    let mut accounts = serde_json::Map::new();
+    for (address, account) in addresses.iter().zip(values) {
+        // Missing accounts (e.g. uninitialized tick arrays) are left out.
+        if account.is_null() {
+            eprintln!("[fixture] {} does not exist, skipping", address);
+            continue;
+        }
+        accounts.insert(
+            address.to_string(),
+            json!({
+                "owner": account["owner"],
+                "lamports": account["lamports"],
+                "data": account["data"],
+            }),
+        );
+    }
+
+    let snapshot = json!({
+        "slot": response["result"]["context"]["slot"],
+        "accounts": accounts,
+    });
+    write_fixture(
+        &format!("pools/{}.json", name),
+        format!("{}\n", serde_json::to_string_pretty(&snapshot)?),
+    )
+}
+
+fn write_fixture(relative: &str, contents: String) -> anyhow::Result<PathBuf> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(relative);
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    std::fs::write(&path, contents)?;
+    Ok(path)
+}
@@ -1,4 +1,5 @@
 use crate::api_methods::search_tokens::TokenSearchResult;
+use crate::utils::upstreams::jupiter_token_list_url;
 use crate::TOKEN_METADATA_CACHE;
 use dashmap::DashMap;
 use reqwest::Client;
@@ -15,7 +16,7 @@ pub async fn spawn_token_cache_loader() {
 
     println!("[cache] Fetching full token list from Jupiter...");
 
-    match client.get("https://cache.jup.ag/tokens").send().await {
+    match client.get(jupiter_token_list_url()).send().await {
         Ok(resp) => match resp.json::<Value>().await {
             Ok(json) => {
                 if let Some(tokens) = json.as_array() {
@@ -70,7 +71,7 @@ pub fn spawn_token_cache_updater() {
             ticker.tick().await;
             println!("[cache] Refreshing Jupiter token list...");
 
-            match client.get("https://cache.jup.ag/tokens").send().await {
+            match client.get(jupiter_token_list_url()).send().await {
                 Ok(resp) => match resp.json::<Value>().await {
                     Ok(json) => {
                         if let Some(tokens) = json["tokens"].as_array() {
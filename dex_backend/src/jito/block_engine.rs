@@ -0,0 +1,111 @@
+use crate::REQWEST_CLIENT;
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Public mainnet block engines, used unless `JITO_BLOCK_ENGINES` lists
+/// others (e.g. a local mock).
+const DEFAULT_BLOCK_ENGINES: [&str; 7] = [
+    "https://mainnet.block-engine.jito.wtf",
+    "https://amsterdam.mainnet.block-engine.jito.wtf",
+    "https://frankfurt.mainnet.block-engine.jito.wtf",
+    "https://london.mainnet.block-engine.jito.wtf",
+    "https://ny.mainnet.block-engine.jito.wtf",
+    "https://tokyo.mainnet.block-engine.jito.wtf",
+    "https://slc.mainnet.block-engine.jito.wtf",
+];
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where a bundle is in the block engine, per `getInflightBundleStatuses`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InflightStatus {
+    /// Not known to this engine, or too old to be.
+    Unknown,
+    Pending,
+    Failed,
+    Landed { slot: u64 },
+}
+
+/// Block engines to submit to, from `JITO_BLOCK_ENGINES` (comma separated)
+/// or the public ones, with the engines in `JITO_REGION` first.
+pub fn configured_block_engines() -> Vec<String> {
+    let configured: Vec<String> = std::env::var("JITO_BLOCK_ENGINES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|url| url.trim().trim_end_matches('/').to_string())
+        .filter(|url| !url.is_empty())
+        .collect();
+    let engines = if configured.is_empty() {
+        DEFAULT_BLOCK_ENGINES.iter().map(|url| url.to_string()).collect()
+    } else {
+        configured
+    };
+    order_by_region(engines, std::env::var("JITO_REGION").ok().as_deref())
+}
+
+/// Engines whose host names `region` first, keeping the order otherwise.
+pub fn order_by_region(mut engines: Vec<String>, region: Option<&str>) -> Vec<String> {
+    if let Some(region) = region.map(str::to_lowercase).filter(|region| !region.is_empty()) {
+        let in_region = |url: &String| {
+            let host = url.split("://").nth(1).unwrap_or(url).split('/').next().unwrap_or_default();
+            host.split('.').any(|label| label.eq_ignore_ascii_case(&region))
+        };
+        engines.sort_by_key(|url| !in_region(url));
+    }
+    engines
+}
+
+async fn engine_call(engine: &str, path: &str, method: &str, params: Value) -> Result<Value> {
+    let client = REQWEST_CLIENT.get_or_init(Client::new);
+    let mut response: Value = client
+        .post(format!("{}/api/v1/{}", engine, path))
+        .timeout(REQUEST_TIMEOUT)
+        .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    if let Some(error) = response.get("error") {
+        return Err(anyhow!("{} failed: {}", method, error));
+    }
+    Ok(response["result"].take())
+}
+
+/// Sends base64 transactions as one bundle; returns the bundle id.
+pub async fn send_bundle(engine: &str, transactions: &[String]) -> Result<String> {
+    let result = engine_call(engine, "bundles", "sendBundle", json!([transactions, { "encoding": "base64" }])).await?;
+    result.as_str().map(String::from).ok_or_else(|| anyhow!("sendBundle returned no bundle id"))
+}
+
+pub async fn inflight_bundle_status(engine: &str, bundle_id: &str) -> Result<InflightStatus> {
+    let result =
+        engine_call(engine, "getInflightBundleStatuses", "getInflightBundleStatuses", json!([[bundle_id]])).await?;
+    let Some(entry) = result["value"].as_array().and_then(|value| value.first()) else {
+        return Ok(InflightStatus::Unknown);
+    };
+    Ok(match entry["status"].as_str() {
+        Some("Pending") => InflightStatus::Pending,
+        Some("Failed") => InflightStatus::Failed,
+        Some("Landed") => InflightStatus::Landed { slot: entry["landed_slot"].as_u64().unwrap_or_default() },
+        _ => InflightStatus::Unknown,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engines_in_the_region_come_first() {
+        let engines: Vec<String> = DEFAULT_BLOCK_ENGINES.iter().map(|url| url.to_string()).collect();
+        let ordered = order_by_region(engines.clone(), Some("Frankfurt"));
+        assert_eq!(ordered[0], "https://frankfurt.mainnet.block-engine.jito.wtf");
+        assert_eq!(ordered[1], "https://mainnet.block-engine.jito.wtf");
+        assert_eq!(ordered.len(), engines.len());
+
+        assert_eq!(order_by_region(engines.clone(), None), engines);
+        assert_eq!(order_by_region(engines.clone(), Some("mars")), engines);
+    }
+}
@@ -0,0 +1,302 @@
+use crate::jito::block_engine::{configured_block_engines, inflight_bundle_status, send_bundle, InflightStatus};
+use crate::jito::TIP_ACCOUNTS;
+use crate::tx::system::SYSTEM_PROGRAM;
+use crate::BUNDLE_TRACKER;
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use dashmap::DashMap;
+use futures::future::join_all;
+use serde::Serialize;
+use solana_sdk::transaction::VersionedTransaction;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+/// Block engines take at most this many transactions per bundle.
+const MAX_BUNDLE_TRANSACTIONS: usize = 5;
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// A bundle's blockhash is good for about 150 slots; anything that hasn't
+/// landed by then never will.
+const BUNDLE_EXPIRY: Duration = Duration::from_secs(90);
+/// Finished bundles stay queryable this long.
+const RETENTION: Duration = Duration::from_secs(600);
+/// `SystemInstruction::Transfer`.
+const SYSTEM_TRANSFER_TAG: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BundleState {
+    Pending,
+    Landed,
+    Failed,
+    Expired,
+}
+
+impl BundleState {
+    pub fn is_final(self) -> bool {
+        self != BundleState::Pending
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleStatus {
+    pub bundle_id: String,
+    pub state: BundleState,
+    /// Signature of each transaction, in bundle order.
+    pub signatures: Vec<String>,
+    /// Engines that accepted the bundle; the first one is tracked.
+    pub engines: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub landed_slot: Option<u64>,
+    /// Unix seconds.
+    pub submitted_at: u64,
+}
+
+/// Bundles submitted through the backend and where they stand. Each one is
+/// polled on its first accepting engine until it lands, fails or expires.
+pub struct BundleTracker {
+    engines: Vec<String>,
+    poll_interval: Duration,
+    expiry: Duration,
+    bundles: Arc<DashMap<String, watch::Receiver<BundleStatus>>>,
+}
+
+pub fn bundle_tracker() -> &'static BundleTracker {
+    BUNDLE_TRACKER.get_or_init(|| BundleTracker::new(configured_block_engines(), POLL_INTERVAL, BUNDLE_EXPIRY))
+}
+
+impl BundleTracker {
+    pub fn new(engines: Vec<String>, poll_interval: Duration, expiry: Duration) -> Self {
+        BundleTracker { engines, poll_interval, expiry, bundles: Arc::new(DashMap::new()) }
+    }
+
+    /// Sends a bundle that passed [`check_bundle`] to every engine at once
+    /// and starts tracking it. An error means no engine took it.
+    pub async fn submit(&self, transactions: Vec<String>, signatures: Vec<String>) -> Result<BundleStatus> {
+        let results = join_all(self.engines.iter().map(|engine| send_bundle(engine, &transactions))).await;
+        let mut bundle_id = None;
+        let mut engines = Vec::new();
+        let mut errors = Vec::new();
+        for (engine, result) in self.engines.iter().zip(results) {
+            match result {
+                Ok(id) => {
+                    bundle_id.get_or_insert(id);
+                    engines.push(engine.clone());
+                }
+                Err(e) => errors.push(format!("{}: {}", engine, e)),
+            }
+        }
+        let Some(bundle_id) = bundle_id else {
+            return Err(anyhow!("no block engine accepted the bundle ({})", errors.join("; ")));
+        };
+        for error in &errors {
+            eprintln!("[jito] Bundle {} rejected by {}", bundle_id, error);
+        }
+
+        let status = BundleStatus {
+            bundle_id: bundle_id.clone(),
+            state: BundleState::Pending,
+            signatures,
+            engines,
+            landed_slot: None,
+            submitted_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+        };
+        let (sender, receiver) = watch::channel(status.clone());
+        self.bundles.insert(bundle_id.clone(), receiver);
+        tokio::spawn(track(
+            self.bundles.clone(),
+            status.engines[0].clone(),
+            bundle_id,
+            sender,
+            self.poll_interval,
+            self.expiry,
+        ));
+        println!("[jito] Bundle {} accepted by {} engines", status.bundle_id, status.engines.len());
+        Ok(status)
+    }
+
+    pub fn status(&self, bundle_id: &str) -> Option<BundleStatus> {
+        self.bundles.get(bundle_id).map(|receiver| receiver.borrow().clone())
+    }
+
+    /// Follows a bundle's status changes; the current status is marked
+    /// unseen so it is the first one received.
+    pub fn watch(&self, bundle_id: &str) -> Option<watch::Receiver<BundleStatus>> {
+        self.bundles.get(bundle_id).map(|receiver| {
+            let mut receiver = receiver.clone();
+            receiver.mark_changed();
+            receiver
+        })
+    }
+}
+
+/// Decodes the bundle and checks it can land: fully signed, within the
+/// size limit, and tipping one of the tip accounts. Returns each
+/// transaction's signature.
+pub fn check_bundle(transactions: &[String]) -> Result<Vec<String>> {
+    if transactions.is_empty() || transactions.len() > MAX_BUNDLE_TRANSACTIONS {
+        return Err(anyhow!("a bundle holds 1 to {} transactions", MAX_BUNDLE_TRANSACTIONS));
+    }
+    let decoded = transactions
+        .iter()
+        .enumerate()
+        .map(|(i, encoded)| {
+            let bytes = BASE64.decode(encoded).map_err(|_| anyhow!("transaction {} is not base64", i))?;
+            let transaction: VersionedTransaction =
+                bincode::deserialize(&bytes).map_err(|_| anyhow!("transaction {} does not decode", i))?;
+            if !transaction.verify_with_results().into_iter().all(|ok| ok) {
+                return Err(anyhow!("transaction {} is not fully signed", i));
+            }
+            Ok(transaction)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if !decoded.iter().any(pays_tip) {
+        return Err(anyhow!("no transaction in the bundle tips a Jito tip account"));
+    }
+    Ok(decoded.iter().map(|transaction| transaction.signatures[0].to_string()).collect())
+}
+
+/// Whether the transaction transfers SOL to a tip account. Tip accounts
+/// can't come from lookup tables, so only static keys are checked.
+fn pays_tip(transaction: &VersionedTransaction) -> bool {
+    let keys = transaction.message.static_account_keys();
+    transaction.message.instructions().iter().any(|ix| {
+        keys.get(ix.program_id_index as usize) == Some(&SYSTEM_PROGRAM)
+            && ix.data.get(..4) == Some(&SYSTEM_TRANSFER_TAG.to_le_bytes())
+            && ix
+                .accounts
+                .get(1)
+                .and_then(|&index| keys.get(index as usize))
+                .is_some_and(|to| TIP_ACCOUNTS.contains(to))
+    })
+}
+
+async fn track(
+    bundles: Arc<DashMap<String, watch::Receiver<BundleStatus>>>,
+    engine: String,
+    bundle_id: String,
+    sender: watch::Sender<BundleStatus>,
+    poll_interval: Duration,
+    expiry: Duration,
+) {
+    let deadline = Instant::now() + expiry;
+    loop {
+        tokio::time::sleep(poll_interval).await;
+        let (state, landed_slot) = match inflight_bundle_status(&engine, &bundle_id).await {
+            Ok(InflightStatus::Landed { slot }) => (BundleState::Landed, Some(slot)),
+            Ok(InflightStatus::Failed) => (BundleState::Failed, None),
+            Ok(InflightStatus::Pending | InflightStatus::Unknown) => (BundleState::Pending, None),
+            Err(e) => {
+                eprintln!("[jito] Status check for bundle {} failed: {}", bundle_id, e);
+                (BundleState::Pending, None)
+            }
+        };
+        let state = if state == BundleState::Pending && Instant::now() >= deadline {
+            BundleState::Expired
+        } else {
+            state
+        };
+        if state.is_final() {
+            sender.send_modify(|status| {
+                status.state = state;
+                status.landed_slot = landed_slot;
+            });
+            println!("[jito] Bundle {} {:?}", bundle_id, state);
+            break;
+        }
+    }
+
+    // Receivers keep the final status after the sender is gone.
+    drop(sender);
+    tokio::time::sleep(RETENTION).await;
+    bundles.remove(&bundle_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tx::system;
+    use serde_json::{json, Value};
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::Message;
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::signature::Keypair;
+    use solana_sdk::signer::Signer;
+    use solana_sdk::transaction::Transaction;
+    use warp::Filter;
+
+    fn encoded(transaction: Transaction) -> String {
+        BASE64.encode(bincode::serialize(&VersionedTransaction::from(transaction)).unwrap())
+    }
+
+    fn signed_transfer(to: &Pubkey) -> String {
+        let payer = Keypair::new();
+        let message = Message::new(&[system::transfer(&payer.pubkey(), to, 1_000)], Some(&payer.pubkey()));
+        encoded(Transaction::new(&[&payer], message, Hash::new_unique()))
+    }
+
+    /// A block engine that accepts every bundle and reports it landed at
+    /// slot 42 once asked twice.
+    fn mock_block_engine() -> String {
+        let polls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let routes = warp::path!("api" / "v1" / String).and(warp::body::json()).map(move |path: String, body: Value| {
+            let result = match (path.as_str(), body["method"].as_str()) {
+                ("bundles", Some("sendBundle")) => json!("mock-bundle"),
+                ("getInflightBundleStatuses", Some("getInflightBundleStatuses")) => {
+                    let status = if polls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) < 1 {
+                        json!({ "bundle_id": "mock-bundle", "status": "Pending", "landed_slot": null })
+                    } else {
+                        json!({ "bundle_id": "mock-bundle", "status": "Landed", "landed_slot": 42 })
+                    };
+                    json!({ "context": { "slot": 41 }, "value": [status] })
+                }
+                _ => return warp::reply::json(&json!({ "jsonrpc": "2.0", "id": 1, "error": "unknown method" })),
+            };
+            warp::reply::json(&json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+        });
+        let (address, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        format!("http://{}", address)
+    }
+
+    #[test]
+    fn bundles_must_be_signed_and_tip() {
+        let tipped = signed_transfer(&TIP_ACCOUNTS[3]);
+        let untipped = signed_transfer(&Pubkey::new_unique());
+        assert_eq!(check_bundle(&[untipped.clone(), tipped.clone()]).unwrap().len(), 2);
+        assert!(check_bundle(&[untipped]).is_err());
+        assert!(check_bundle(&vec![tipped.clone(); 6]).is_err());
+
+        let payer = Pubkey::new_unique();
+        let message = Message::new(&[system::transfer(&payer, &TIP_ACCOUNTS[0], 1_000)], Some(&payer));
+        assert!(check_bundle(&[encoded(Transaction::new_unsigned(message))]).is_err());
+    }
+
+    #[tokio::test]
+    async fn submitted_bundles_are_tracked_until_they_land() {
+        let engine = mock_block_engine();
+        // The second engine refuses connections; the bundle still goes out.
+        let tracker = BundleTracker::new(
+            vec![engine.clone(), "http://127.0.0.1:9".into()],
+            Duration::from_millis(10),
+            Duration::from_secs(5),
+        );
+        let bundle = vec![signed_transfer(&TIP_ACCOUNTS[0])];
+        let signatures = check_bundle(&bundle).unwrap();
+        let status = tracker.submit(bundle, signatures.clone()).await.unwrap();
+        assert_eq!(status.bundle_id, "mock-bundle");
+        assert_eq!(status.engines, vec![engine]);
+        assert_eq!(status.signatures, signatures);
+        assert_eq!(status.state, BundleState::Pending);
+
+        let mut updates = tracker.watch("mock-bundle").unwrap();
+        let landed = updates.wait_for(|status| status.state.is_final()).await.unwrap().clone();
+        assert_eq!(landed.state, BundleState::Landed);
+        assert_eq!(landed.landed_slot, Some(42));
+        assert_eq!(tracker.status("mock-bundle").unwrap().state, BundleState::Landed);
+        assert!(tracker.status("other").is_none());
+    }
+}
@@ -0,0 +1,26 @@
+//! Jito block engine integration.
+
+pub mod block_engine;
+pub mod bundles;
+pub mod tip_floor;
+
+use rand::seq::SliceRandom;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+/// Accounts the tip payment program accepts tips into; bundles spread
+/// their tips across them to avoid write-lock contention.
+pub const TIP_ACCOUNTS: [Pubkey; 8] = [
+    pubkey!("96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5"),
+    pubkey!("HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe"),
+    pubkey!("Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY"),
+    pubkey!("ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49"),
+    pubkey!("DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh"),
+    pubkey!("ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt"),
+    pubkey!("DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL"),
+    pubkey!("3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT"),
+];
+
+pub fn random_tip_account() -> Pubkey {
+    *TIP_ACCOUNTS.choose(&mut rand::thread_rng()).unwrap()
+}
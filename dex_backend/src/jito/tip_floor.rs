@@ -0,0 +1,220 @@
+use crate::utils::upstreams::jito_tip_floor_url;
+use crate::{REQWEST_CLIENT, TIP_FLOORS};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::time::interval;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// An hour of samples at the poll interval.
+const HISTORY_LEN: usize = 360;
+/// Block engines drop bundles tipping less than this.
+pub const MIN_TIP_LAMPORTS: u64 = 1_000;
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JitoBundleTipFloor {
+    /// Timestamp when the tip floor data was recorded
+    pub time: DateTime<Utc>,
+
+    /// 25th percentile of landed tips (in SOL)
+    pub landed_tips_25th_percentile: f64,
+
+    /// 50th percentile (median) of landed tips (in SOL)
+    pub landed_tips_50th_percentile: f64,
+
+    /// 75th percentile of landed tips (in SOL)
+    pub landed_tips_75th_percentile: f64,
+
+    /// 95th percentile of landed tips (in SOL)
+    pub landed_tips_95th_percentile: f64,
+
+    /// 99th percentile of landed tips (in SOL)
+    pub landed_tips_99th_percentile: f64,
+
+    /// Exponential Moving Average of the 50th percentile of landed tips (in SOL)
+    pub ema_landed_tips_50th_percentile: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+    Low,
+    Normal,
+    High,
+    Turbo,
+}
+
+impl FromStr for Urgency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "low" => Ok(Urgency::Low),
+            "normal" => Ok(Urgency::Normal),
+            "high" => Ok(Urgency::High),
+            "turbo" => Ok(Urgency::Turbo),
+            _ => Err("'urgency' must be low, normal, high or turbo".to_string()),
+        }
+    }
+}
+
+impl Urgency {
+    /// Most a recommendation at this urgency tips, in SOL, unless
+    /// `JITO_TIP_CAP_<URGENCY>` says otherwise.
+    fn default_cap_sol(self) -> f64 {
+        match self {
+            Urgency::Low => 0.0001,
+            Urgency::Normal => 0.0005,
+            Urgency::High => 0.002,
+            Urgency::Turbo => 0.01,
+        }
+    }
+
+    pub fn cap_sol(self) -> f64 {
+        let key = format!("JITO_TIP_CAP_{:?}", self).to_uppercase();
+        std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(self.default_cap_sol())
+    }
+
+    /// The share of recent bundles this urgency aims to outbid. Normal
+    /// follows the median or its EMA, whichever is higher, so a sudden dip
+    /// doesn't undercut a busy period.
+    fn floor_sol(self, floor: &JitoBundleTipFloor) -> f64 {
+        match self {
+            Urgency::Low => floor.landed_tips_25th_percentile,
+            Urgency::Normal => floor.landed_tips_50th_percentile.max(floor.ema_landed_tips_50th_percentile),
+            Urgency::High => floor.landed_tips_75th_percentile,
+            Urgency::Turbo => floor.landed_tips_95th_percentile,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TipRecommendation {
+    pub urgency: Urgency,
+    pub tip_lamports: u64,
+    pub tip_sol: f64,
+    /// The cap cut the recommendation short of the target percentile.
+    pub capped: bool,
+    /// Sample the recommendation is based on.
+    pub sampled_at: DateTime<Utc>,
+}
+
+pub fn recommend_tip(floor: &JitoBundleTipFloor, urgency: Urgency, cap_sol: f64) -> TipRecommendation {
+    let target = (urgency.floor_sol(floor) * LAMPORTS_PER_SOL).round() as u64;
+    let cap = (cap_sol * LAMPORTS_PER_SOL).round() as u64;
+    let tip_lamports = target.min(cap).max(MIN_TIP_LAMPORTS);
+    TipRecommendation {
+        urgency,
+        tip_lamports,
+        tip_sol: tip_lamports as f64 / LAMPORTS_PER_SOL,
+        capped: target > cap,
+        sampled_at: floor.time,
+    }
+}
+
+/// The latest tip floor samples, oldest first.
+#[derive(Default)]
+pub struct TipFloorHistory {
+    samples: RwLock<VecDeque<JitoBundleTipFloor>>,
+}
+
+pub fn tip_floors() -> &'static TipFloorHistory {
+    TIP_FLOORS.get_or_init(TipFloorHistory::default)
+}
+
+impl TipFloorHistory {
+    /// Adds a sample unless it repeats the newest one's time.
+    pub fn record(&self, sample: JitoBundleTipFloor) {
+        let mut samples = self.samples.write().unwrap();
+        if samples.back().is_some_and(|last| last.time == sample.time) {
+            return;
+        }
+        if samples.len() == HISTORY_LEN {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    pub fn latest(&self) -> Option<JitoBundleTipFloor> {
+        self.samples.read().unwrap().back().cloned()
+    }
+
+    pub fn history(&self) -> Vec<JitoBundleTipFloor> {
+        self.samples.read().unwrap().iter().cloned().collect()
+    }
+}
+
+async fn fetch_tip_floor() -> Result<Vec<JitoBundleTipFloor>, reqwest::Error> {
+    let client = REQWEST_CLIENT.get_or_init(Client::new);
+    client.get(jito_tip_floor_url()).send().await?.error_for_status()?.json().await
+}
+
+/// Samples the tip floor every [`POLL_INTERVAL`] into [`tip_floors`].
+pub fn spawn_tip_floor_poller() {
+    tokio::spawn(async move {
+        let history = tip_floors();
+        let mut ticker = interval(POLL_INTERVAL);
+        loop {
+            ticker.tick().await;
+            match fetch_tip_floor().await {
+                Ok(samples) => samples.into_iter().for_each(|sample| history.record(sample)),
+                Err(e) => eprintln!("[jito] Tip floor poll failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(minute: u32, p50: f64) -> JitoBundleTipFloor {
+        JitoBundleTipFloor {
+            time: format!("2025-06-01T12:{:02}:00Z", minute).parse().unwrap(),
+            landed_tips_25th_percentile: 0.000005,
+            landed_tips_50th_percentile: p50,
+            landed_tips_75th_percentile: 0.0001,
+            landed_tips_95th_percentile: 0.004,
+            landed_tips_99th_percentile: 0.02,
+            ema_landed_tips_50th_percentile: 0.00002,
+        }
+    }
+
+    #[test]
+    fn recommendations_follow_the_percentiles_within_the_cap() {
+        let floor = sample(0, 0.00001);
+        let low = recommend_tip(&floor, Urgency::Low, 0.0001);
+        assert_eq!((low.tip_lamports, low.capped), (5_000, false));
+        // The EMA is above the median, so normal follows it.
+        assert_eq!(recommend_tip(&floor, Urgency::Normal, 0.0005).tip_lamports, 20_000);
+        assert_eq!(recommend_tip(&floor, Urgency::High, 0.002).tip_lamports, 100_000);
+        let turbo = recommend_tip(&floor, Urgency::Turbo, 0.001);
+        assert_eq!((turbo.tip_lamports, turbo.capped), (1_000_000, true));
+
+        let quiet = JitoBundleTipFloor { landed_tips_25th_percentile: 0.0, ..floor };
+        assert_eq!(recommend_tip(&quiet, Urgency::Low, 0.0001).tip_lamports, MIN_TIP_LAMPORTS);
+        assert_eq!("Turbo".parse::<Urgency>(), Ok(Urgency::Turbo));
+        assert!("max".parse::<Urgency>().is_err());
+    }
+
+    #[test]
+    fn history_keeps_the_newest_distinct_samples() {
+        let history = TipFloorHistory::default();
+        history.record(sample(0, 0.1));
+        history.record(sample(0, 0.2));
+        assert_eq!(history.history().len(), 1);
+        for minute in 1..=HISTORY_LEN as u32 {
+            history.record(sample(minute % 60, minute as f64));
+        }
+        let samples = history.history();
+        assert_eq!(samples.len(), HISTORY_LEN);
+        assert_eq!(history.latest().unwrap().landed_tips_50th_percentile, HISTORY_LEN as f64);
+        assert_eq!(samples[0].landed_tips_50th_percentile, 1.0);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::ADDRESS_LABELS;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Longest contact name, in characters.
+pub const MAX_LABEL_LENGTH: usize = 64;
+/// Most contacts a single owner may save.
+pub const MAX_CONTACTS_PER_OWNER: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelCategory {
+    Program,
+    AmmPool,
+    JitoTip,
+    FeeVault,
+    ArbProgram,
+    Exchange,
+    Contact,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddressLabel {
+    pub name: String,
+    pub category: LabelCategory,
+}
+
+#[derive(Debug, Deserialize)]
+struct LabelEntry {
+    address: String,
+    name: String,
+    category: LabelCategory,
+}
+
+/// Why a contact couldn't be saved.
+#[derive(Debug)]
+pub enum ContactError {
+    /// Empty, or longer than [`MAX_LABEL_LENGTH`].
+    NameLength,
+    /// The owner already has [`MAX_CONTACTS_PER_OWNER`] contacts.
+    TooManyContacts,
+    Io(std::io::Error),
+}
+
+impl fmt::Display for ContactError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContactError::NameLength => write!(f, "'name' must be 1-{} characters", MAX_LABEL_LENGTH),
+            ContactError::TooManyContacts => {
+                write!(f, "contact book is full ({} entries)", MAX_CONTACTS_PER_OWNER)
+            }
+            ContactError::Io(e) => write!(f, "failed to save contacts: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ContactError {}
+
+/// Known addresses from the bundled dataset and the deployment's own
+/// accounts, plus contacts each wallet saved for itself. A wallet's own
+/// contacts win over the shared labels.
+pub struct LabelRegistry {
+    known: HashMap<String, AddressLabel>,
+    /// owner wallet -> address -> label
+    contacts: DashMap<String, HashMap<String, AddressLabel>>,
+    contacts_path: PathBuf,
+    save_lock: Mutex<()>,
+}
+
+impl LabelRegistry {
+    pub fn load() -> Self {
+        let mut known: HashMap<String, AddressLabel> =
+            serde_json::from_str::<Vec<LabelEntry>>(include_str!("known_labels.json"))
+                .expect("known_labels.json is malformed")
+                .into_iter()
+                .map(|e| (e.address, AddressLabel { name: e.name, category: e.category }))
+                .collect();
+
+        // EquiSwap's own accounts differ per deployment.
+        for (var, name, category) in [
+            ("SWAP_PROGRAM_ID", "EquiSwap", LabelCategory::Program),
+            ("ARB_PROGRAM_ID", "EquiSwap Arb", LabelCategory::ArbProgram),
+            ("FEE_VAULT_ADDRESS", "EquiSwap Fee Vault", LabelCategory::FeeVault),
+        ] {
+            if let Ok(address) = std::env::var(var) {
+                if !address.trim().is_empty() {
+                    known.insert(
+                        address.trim().to_string(),
+                        AddressLabel { name: name.to_string(), category },
+                    );
+                }
+            }
+        }
+
+        let contacts_path = PathBuf::from(
+            std::env::var("USER_LABELS_PATH").unwrap_or_else(|_| "user_labels.json".into()),
+        );
+        let mut contacts = DashMap::new();
+        match std::fs::read_to_string(&contacts_path) {
+            Ok(raw) => match serde_json::from_str::<HashMap<String, HashMap<String, AddressLabel>>>(&raw) {
+                Ok(saved) => contacts.extend(saved),
+                Err(e) => eprintln!("[labels] Ignoring malformed {}: {}", contacts_path.display(), e),
+            },
+            Err(_) => println!("[labels] No saved contacts at {}", contacts_path.display()),
+        }
+
+        println!("[labels] Loaded {} known addresses, {} contact books", known.len(), contacts.len());
+
+        Self {
+            known,
+            contacts,
+            contacts_path,
+            save_lock: Mutex::new(()),
+        }
+    }
+
+    /// Label for `address` as seen by `owner`; accepts bare or
+    /// `solana:101/address:`-prefixed addresses.
+    pub fn lookup(&self, owner: Option<&str>, address: &str) -> Option<AddressLabel> {
+        let address = address.strip_prefix("solana:101/address:").unwrap_or(address);
+        if let Some(owner) = owner {
+            if let Some(label) = self.contacts.get(owner).and_then(|book| book.get(address).cloned()) {
+                return Some(label);
+            }
+        }
+        self.known.get(address).cloned()
+    }
+
+    pub fn contacts(&self, owner: &str) -> HashMap<String, AddressLabel> {
+        self.contacts.get(owner).map(|book| book.clone()).unwrap_or_default()
+    }
+
+    /// Saves or renames a contact. Renaming doesn't count against the
+    /// per-owner cap.
+    pub fn set_contact(&self, owner: &str, address: &str, name: &str) -> Result<(), ContactError> {
+        if name.is_empty() || name.chars().count() > MAX_LABEL_LENGTH {
+            return Err(ContactError::NameLength);
+        }
+        {
+            let mut book = self.contacts.entry(owner.to_string()).or_default();
+            if !book.contains_key(address) && book.len() >= MAX_CONTACTS_PER_OWNER {
+                return Err(ContactError::TooManyContacts);
+            }
+            book.insert(
+                address.to_string(),
+                AddressLabel {
+                    name: name.to_string(),
+                    category: LabelCategory::Contact,
+                },
+            );
+        }
+        self.save().map_err(ContactError::Io)
+    }
+
+    /// Returns whether the contact existed.
+    pub fn remove_contact(&self, owner: &str, address: &str) -> std::io::Result<bool> {
+        let removed = self
+            .contacts
+            .get_mut(owner)
+            .is_some_and(|mut book| book.remove(address).is_some());
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let _guard = self.save_lock.lock().unwrap();
+        let snapshot: HashMap<String, HashMap<String, AddressLabel>> = self
+            .contacts
+            .iter()
+            .filter(|book| !book.is_empty())
+            .map(|book| (book.key().clone(), book.value().clone()))
+            .collect();
+        let tmp = self.contacts_path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&snapshot)?)?;
+        std::fs::rename(tmp, &self.contacts_path)
+    }
+}
+
+pub fn labels() -> &'static LabelRegistry {
+    ADDRESS_LABELS.get_or_init(LabelRegistry::load)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OWNER: &str = "GfsJWjmGXMfct8JMR9Lm9ySUnniZbnGUTQDbT8ipWf9U";
+    const JITO_TIP: &str = "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5";
+
+    fn registry(name: &str) -> LabelRegistry {
+        let mut registry = LabelRegistry::load();
+        registry.contacts.clear();
+        registry.contacts_path = std::env::temp_dir().join(format!("{}-{}.json", name, std::process::id()));
+        registry
+    }
+
+    #[test]
+    fn resolves_bundled_labels_with_or_without_prefix() {
+        let registry = registry("labels-bundled");
+        let expected = Some(AddressLabel {
+            name: "Jito Tip Account 1".into(),
+            category: LabelCategory::JitoTip,
+        });
+        assert_eq!(registry.lookup(None, JITO_TIP), expected);
+        assert_eq!(registry.lookup(Some(OWNER), &format!("solana:101/address:{}", JITO_TIP)), expected);
+        assert_eq!(registry.lookup(None, "unknown"), None);
+    }
+
+    #[test]
+    fn contacts_are_per_owner_and_persisted() {
+        let registry = registry("labels-contacts");
+        registry.set_contact(OWNER, JITO_TIP, "My tips").unwrap();
+
+        assert_eq!(registry.lookup(Some(OWNER), JITO_TIP).unwrap().name, "My tips");
+        assert_eq!(registry.lookup(None, JITO_TIP).unwrap().name, "Jito Tip Account 1");
+
+        let saved: HashMap<String, HashMap<String, AddressLabel>> =
+            serde_json::from_slice(&std::fs::read(&registry.contacts_path).unwrap()).unwrap();
+        assert_eq!(saved[OWNER][JITO_TIP].category, LabelCategory::Contact);
+
+        assert!(registry.remove_contact(OWNER, JITO_TIP).unwrap());
+        assert!(!registry.remove_contact(OWNER, JITO_TIP).unwrap());
+        std::fs::remove_file(&registry.contacts_path).unwrap();
+    }
+
+    #[test]
+    fn contacts_are_capped_per_owner_and_by_length() {
+        let registry = registry("labels-caps");
+        let too_long = "x".repeat(MAX_LABEL_LENGTH + 1);
+        assert!(matches!(registry.set_contact(OWNER, JITO_TIP, &too_long), Err(ContactError::NameLength)));
+        assert!(matches!(registry.set_contact(OWNER, JITO_TIP, ""), Err(ContactError::NameLength)));
+
+        let full: HashMap<String, AddressLabel> = (0..MAX_CONTACTS_PER_OWNER)
+            .map(|i| {
+                let label = AddressLabel { name: format!("contact {}", i), category: LabelCategory::Contact };
+                (format!("address-{}", i), label)
+            })
+            .collect();
+        registry.contacts.insert(OWNER.to_string(), full);
+
+        assert!(matches!(registry.set_contact(OWNER, JITO_TIP, "One more"), Err(ContactError::TooManyContacts)));
+        registry.set_contact(OWNER, "address-0", "Renamed").unwrap();
+        assert_eq!(registry.lookup(Some(OWNER), "address-0").unwrap().name, "Renamed");
+        std::fs::remove_file(&registry.contacts_path).unwrap();
+    }
+}
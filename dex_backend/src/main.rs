@@ -5,26 +5,68 @@ use reqwest::Client;
 use crate::server::start_server::start_server;
 use dashmap::{DashMap, DashSet};
 use crate::api_methods::search_tokens::TokenSearchResult;
+use crate::labels::registry::LabelRegistry;
+use crate::pools::indexer::spawn_pool_indexer;
+use crate::pools::oracle::{spawn_price_oracle, PriceOracle};
+use crate::ohlcv::spawn_candle_sampler;
+use crate::ohlcv::store::CandleStore;
+use crate::pools::registry::PoolRegistry;
+use crate::pools::state_cache::{spawn_pool_state_cache, PoolStateCache};
+use solana_sdk::signature::Keypair;
+use crate::stream::wallet_feed::FeedSender;
+use crate::stream::signature_tracker::SignatureTracker;
+use crate::jito::bundles::BundleTracker;
+use crate::arb::rewards::RewardLedger;
+use crate::jito::tip_floor::{spawn_tip_floor_poller, TipFloorHistory};
 use crate::cache::token_cache::{spawn_token_cache_loader, spawn_token_cache_updater};
 use crate::rpc::spawn_rpc_health_checker::spawn_rpc_health_checker;
+use crate::tx_errors::program_errors::DeployedProgram;
 
 mod server;
 mod api;
 mod api_methods;
 mod cache;
 mod rpc;
+mod pnl;
+mod utils;
+mod tx_errors;
+mod labels;
+mod stream;
+mod quote;
+mod pools;
+mod arb;
+mod jito;
+mod tx;
+mod ohlcv;
 pub static REQWEST_CLIENT: OnceLock<Client> = OnceLock::new();
 pub static SEEN_SIGNATURES: OnceLock<DashSet<String>> = OnceLock::new();
 
 pub static TOKEN_METADATA_CACHE: OnceLock<DashMap<String, TokenSearchResult>> = OnceLock::new();
+pub static ADDRESS_LABELS: OnceLock<LabelRegistry> = OnceLock::new();
+pub static WALLET_FEEDS: OnceLock<DashMap<String, FeedSender>> = OnceLock::new();
+pub static MINT_DECIMALS_CACHE: OnceLock<DashMap<String, u8>> = OnceLock::new();
+pub static POOL_REGISTRY: OnceLock<PoolRegistry> = OnceLock::new();
+pub static PRICE_ORACLE: OnceLock<PriceOracle> = OnceLock::new();
+pub static CANDLE_STORE: OnceLock<CandleStore> = OnceLock::new();
+pub static ARB_SIGNER: OnceLock<Option<Keypair>> = OnceLock::new();
+pub static BUNDLE_TRACKER: OnceLock<BundleTracker> = OnceLock::new();
+pub static TIP_FLOORS: OnceLock<TipFloorHistory> = OnceLock::new();
+pub static SIGNATURE_TRACKER: OnceLock<SignatureTracker> = OnceLock::new();
+pub static POOL_STATE_CACHE: OnceLock<PoolStateCache> = OnceLock::new();
+pub static REWARD_LEDGER: OnceLock<RewardLedger> = OnceLock::new();
+pub static SWAP_PROGRAM_ERRORS: OnceLock<Option<DeployedProgram>> = OnceLock::new();
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     dotenv().ok();
     REQWEST_CLIENT.set(Client::new()).unwrap();
     // spawn_rpc_health_checker();
-    // spawn_token_cache_loader().await;
-    // println!("[cache] Token cache loaded");
+    spawn_token_cache_loader().await;
     // spawn_token_cache_updater();
+    spawn_pool_indexer();
+    spawn_price_oracle();
+    spawn_pool_state_cache();
+    spawn_candle_sampler();
+    spawn_tip_floor_poller();
 
     tokio::spawn(start_server());
     loop {
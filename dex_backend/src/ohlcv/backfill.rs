@@ -0,0 +1,131 @@
+use crate::api_methods::handle_history::{fetch_signatures_page, get_parsed_transaction};
+use crate::ohlcv::store::candle_store;
+use crate::ohlcv::Tick;
+use crate::pools::loader::load_pools;
+use crate::pools::registry::pool_registry;
+use anyhow::{anyhow, Result};
+use futures::stream::{self, StreamExt};
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+/// Recent signatures of the pool replayed per backfill.
+const BACKFILL_SIGNATURES: usize = 1_000;
+const BACKFILL_CONCURRENCY: usize = 8;
+
+/// Replays the recent swaps of the pair's deepest pool into its candles.
+pub fn spawn_backfill(base: String, quote: String) {
+    tokio::spawn(async move {
+        match backfill(&base, &quote).await {
+            Ok(swaps) => println!("[ohlcv] Backfilled {}/{} from {} swaps", base, quote, swaps),
+            Err(e) => eprintln!("[ohlcv] Backfill of {}/{} failed: {}", base, quote, e),
+        }
+    });
+}
+
+async fn backfill(base: &str, quote: &str) -> Result<usize> {
+    let entry = pool_registry()
+        .pools_for_pair(base, quote)
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("no indexed pool"))?;
+    let address = Pubkey::from_str(&entry.address)?;
+    let loaded = load_pools(&[address]).await?;
+    let pool = loaded.pools.first().ok_or_else(|| anyhow!("pool {} failed to load", address))?;
+    let vaults = pool.reserve_vaults();
+    let base_side = if pool.mints()[0].to_string() == base { 0 } else { 1 };
+
+    let signatures: Vec<String> = fetch_signatures_page(&address, None, BACKFILL_SIGNATURES)
+        .await?
+        .into_iter()
+        .filter(|signature| signature["err"].is_null())
+        .filter_map(|signature| signature["signature"].as_str().map(String::from))
+        .collect();
+    let ticks: Vec<Tick> = stream::iter(signatures)
+        .map(|signature| async move { get_parsed_transaction(&signature).await })
+        .buffer_unordered(BACKFILL_CONCURRENCY)
+        .filter_map(|transaction| async move { swap_tick(&transaction.ok()?, vaults, base_side) })
+        .collect()
+        .await;
+
+    let store = candle_store();
+    for tick in &ticks {
+        store.record(base, quote, tick);
+    }
+    Ok(ticks.len())
+}
+
+/// The price and base volume of a swap through the pool whose reserves sit
+/// in `vaults`, from their balance changes. Deposits and withdrawals move
+/// both vaults the same way and are skipped.
+pub fn swap_tick(transaction: &Value, vaults: [Pubkey; 2], base_side: usize) -> Option<Tick> {
+    let meta = &transaction["meta"];
+    if !meta["err"].is_null() {
+        return None;
+    }
+    let time = transaction["blockTime"].as_i64()?;
+    let keys = transaction["transaction"]["message"]["accountKeys"].as_array()?;
+
+    let change = |vault: Pubkey| -> Option<f64> {
+        let vault = vault.to_string();
+        let index = keys
+            .iter()
+            .position(|key| key["pubkey"].as_str().or(key.as_str()) == Some(vault.as_str()))?;
+        let balance = |key: &str| -> Option<(i128, i32)> {
+            let entry = meta[key].as_array()?.iter().find(|b| b["accountIndex"].as_u64() == Some(index as u64))?;
+            let amount = entry["uiTokenAmount"]["amount"].as_str()?.parse().ok()?;
+            Some((amount, entry["uiTokenAmount"]["decimals"].as_u64()? as i32))
+        };
+        let (pre, decimals) = balance("preTokenBalances")?;
+        let (post, _) = balance("postTokenBalances")?;
+        Some((post - pre) as f64 / 10f64.powi(decimals))
+    };
+    let changes = [change(vaults[0])?, change(vaults[1])?];
+    if changes[0] * changes[1] >= 0.0 {
+        return None;
+    }
+    let base = changes[base_side].abs();
+    let quote = changes[1 - base_side].abs();
+    Some(Tick { time, price: quote / base, volume: base })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn balance(index: u64, amount: &str, decimals: u64) -> Value {
+        json!({ "accountIndex": index, "uiTokenAmount": { "amount": amount, "decimals": decimals } })
+    }
+
+    #[test]
+    fn swaps_are_read_from_vault_balance_changes() {
+        let (sol_vault, usdc_vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let transaction = |pre: [&str; 2], post: [&str; 2]| {
+            json!({
+                "blockTime": 1_700_000_000,
+                "meta": {
+                    "err": null,
+                    "preTokenBalances": [balance(1, pre[0], 9), balance(3, pre[1], 6)],
+                    "postTokenBalances": [balance(1, post[0], 9), balance(3, post[1], 6)],
+                },
+                "transaction": { "message": { "accountKeys": [
+                    { "pubkey": Pubkey::new_unique().to_string() },
+                    { "pubkey": sol_vault.to_string() },
+                    { "pubkey": Pubkey::new_unique().to_string() },
+                    { "pubkey": usdc_vault.to_string() },
+                ] } },
+            })
+        };
+
+        // 2 SOL sold into the pool for 300 USDC.
+        let sell = transaction(["10000000000", "5000000000"], ["12000000000", "4700000000"]);
+        let tick = swap_tick(&sell, [sol_vault, usdc_vault], 0).unwrap();
+        assert_eq!(tick, Tick { time: 1_700_000_000, price: 150.0, volume: 2.0 });
+        let inverted = swap_tick(&sell, [sol_vault, usdc_vault], 1).unwrap();
+        assert_eq!((inverted.price, inverted.volume), (2.0 / 300.0, 300.0));
+
+        let deposit = transaction(["10000000000", "5000000000"], ["11000000000", "5150000000"]);
+        assert!(swap_tick(&deposit, [sol_vault, usdc_vault], 0).is_none());
+    }
+}
@@ -0,0 +1,252 @@
+//! OHLCV candles per mint pair, built from our own observations.
+//!
+//! Pairs are tracked from the first time they are charted. Every
+//! [`SAMPLE_INTERVAL`] the sampler records a snapshot of each tracked pair's
+//! price from the pool-derived oracle; a backfill job replays the recent
+//! swaps of the pair's deepest pool for history and volume. Ticks are
+//! folded into every interval at once and kept for each interval's
+//! retention.
+
+pub mod backfill;
+pub mod store;
+
+use crate::ohlcv::store::candle_store;
+use crate::pools::oracle::price_oracle;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use tokio::time::{interval, Duration};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(15);
+/// Samples between saves of the store to disk.
+const SAVE_EVERY: u64 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Interval {
+    M1,
+    M5,
+    M15,
+    H1,
+    H4,
+    D1,
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "1m" => Ok(Interval::M1),
+            "5m" => Ok(Interval::M5),
+            "15m" => Ok(Interval::M15),
+            "1h" => Ok(Interval::H1),
+            "4h" => Ok(Interval::H4),
+            "1d" => Ok(Interval::D1),
+            _ => Err("'interval' must be 1m, 5m, 15m, 1h, 4h or 1d".to_string()),
+        }
+    }
+}
+
+impl Interval {
+    pub const ALL: [Interval; 6] =
+        [Interval::M1, Interval::M5, Interval::M15, Interval::H1, Interval::H4, Interval::D1];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Interval::M1 => "1m",
+            Interval::M5 => "5m",
+            Interval::M15 => "15m",
+            Interval::H1 => "1h",
+            Interval::H4 => "4h",
+            Interval::D1 => "1d",
+        }
+    }
+
+    pub fn seconds(self) -> i64 {
+        match self {
+            Interval::M1 => 60,
+            Interval::M5 => 5 * 60,
+            Interval::M15 => 15 * 60,
+            Interval::H1 => 60 * 60,
+            Interval::H4 => 4 * 60 * 60,
+            Interval::D1 => 24 * 60 * 60,
+        }
+    }
+
+    /// Candles kept: a day of 1m up to five years of 1d.
+    pub fn retention(self) -> usize {
+        match self {
+            Interval::M1 => 1_440,
+            Interval::M5 => 7 * 288,
+            Interval::M15 => 14 * 96,
+            Interval::H1 => 90 * 24,
+            Interval::H4 => 365 * 6,
+            Interval::D1 => 5 * 365,
+        }
+    }
+
+    /// Start of the candle `time` falls in.
+    pub fn bucket(self, time: i64) -> i64 {
+        time - time.rem_euclid(self.seconds())
+    }
+}
+
+/// One price observation: a pool snapshot, or a swap with its volume.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tick {
+    /// Unix seconds.
+    pub time: i64,
+    /// Quote per base, in UI units.
+    pub price: f64,
+    /// Base traded, in UI units; zero for snapshots.
+    pub volume: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    /// Start of the candle, unix seconds.
+    pub time: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    /// Times of the ticks that set `open` and `close`, so ticks arriving
+    /// out of order still land in the right place.
+    opened_at: i64,
+    closed_at: i64,
+}
+
+impl Candle {
+    fn new(interval: Interval, tick: &Tick) -> Self {
+        Candle {
+            time: interval.bucket(tick.time),
+            open: tick.price,
+            high: tick.price,
+            low: tick.price,
+            close: tick.price,
+            volume: tick.volume,
+            opened_at: tick.time,
+            closed_at: tick.time,
+        }
+    }
+
+    fn add(&mut self, tick: &Tick) {
+        self.high = self.high.max(tick.price);
+        self.low = self.low.min(tick.price);
+        self.volume += tick.volume;
+        if tick.time < self.opened_at {
+            self.open = tick.price;
+            self.opened_at = tick.time;
+        }
+        if tick.time >= self.closed_at {
+            self.close = tick.price;
+            self.closed_at = tick.time;
+        }
+    }
+
+    /// A candle where nothing was observed: flat at the previous close.
+    fn flat(time: i64, price: f64) -> Self {
+        Candle {
+            time,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: 0.0,
+            opened_at: time,
+            closed_at: time,
+        }
+    }
+}
+
+/// Every candle from the one holding `from` to the one holding `to`.
+/// Buckets without observations repeat the previous close; `previous` is
+/// the close before `from`, if any.
+pub fn fill_gaps(candles: &[Candle], interval: Interval, from: i64, to: i64, previous: Option<f64>) -> Vec<Candle> {
+    let mut filled = Vec::new();
+    let mut observed = candles.iter().peekable();
+    let mut close = previous;
+    let mut time = interval.bucket(from);
+    while time <= to {
+        while observed.next_if(|candle| candle.time < time).is_some() {}
+        match observed.next_if(|candle| candle.time == time) {
+            Some(candle) => {
+                close = Some(candle.close);
+                filled.push(*candle);
+            }
+            None => {
+                if let Some(price) = close {
+                    filled.push(Candle::flat(time, price));
+                }
+            }
+        }
+        time += interval.seconds();
+    }
+    filled
+}
+
+/// Records each tracked pair's oracle price every [`SAMPLE_INTERVAL`].
+pub fn spawn_candle_sampler() {
+    tokio::spawn(async move {
+        let store = candle_store();
+        let oracle = price_oracle();
+        let mut ticker = interval(SAMPLE_INTERVAL);
+        let mut samples: u64 = 0;
+        loop {
+            ticker.tick().await;
+            let time = chrono::Utc::now().timestamp();
+            for (base, quote) in store.pairs() {
+                let (Some(base_price), Some(quote_price)) = (oracle.price(&base), oracle.price(&quote)) else {
+                    continue;
+                };
+                if quote_price.price_usd > 0.0 {
+                    let price = base_price.price_usd / quote_price.price_usd;
+                    store.record(&base, &quote, &Tick { time, price, volume: 0.0 });
+                }
+            }
+            samples += 1;
+            if samples.is_multiple_of(SAVE_EVERY) {
+                if let Err(e) = store.save() {
+                    eprintln!("[ohlcv] Failed to save candles: {}", e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(time: i64, price: f64, volume: f64) -> Tick {
+        Tick { time, price, volume }
+    }
+
+    #[test]
+    fn candles_take_ticks_in_any_order() {
+        let mut candle = Candle::new(Interval::M1, &tick(130, 10.0, 1.0));
+        candle.add(&tick(170, 12.0, 2.0));
+        candle.add(&tick(125, 9.0, 0.0));
+        candle.add(&tick(150, 13.0, 0.5));
+        assert_eq!(candle.time, 120);
+        assert_eq!((candle.open, candle.high, candle.low, candle.close), (9.0, 13.0, 9.0, 12.0));
+        assert_eq!(candle.volume, 3.5);
+        assert_eq!(Interval::H4.bucket(5 * 3_600 + 7), 4 * 3_600);
+        assert_eq!("1H".parse::<Interval>(), Ok(Interval::H1));
+    }
+
+    #[test]
+    fn gaps_repeat_the_previous_close() {
+        let candles = [
+            Candle::new(Interval::M1, &tick(60, 5.0, 1.0)),
+            Candle::new(Interval::M1, &tick(240, 7.0, 1.0)),
+        ];
+        let filled = fill_gaps(&candles, Interval::M1, 0, 300, None);
+        let closes: Vec<(i64, f64)> = filled.iter().map(|c| (c.time, c.close)).collect();
+        assert_eq!(closes, [(60, 5.0), (120, 5.0), (180, 5.0), (240, 7.0), (300, 7.0)]);
+        assert_eq!(filled[1].volume, 0.0);
+
+        let seeded = fill_gaps(&candles[1..], Interval::M1, 120, 240, Some(4.0));
+        assert_eq!(seeded.iter().map(|c| c.close).collect::<Vec<_>>(), [4.0, 4.0, 7.0]);
+    }
+}
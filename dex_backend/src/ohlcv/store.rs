@@ -0,0 +1,196 @@
+use crate::ohlcv::{fill_gaps, Candle, Interval, Tick};
+use crate::CANDLE_STORE;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Base and quote mint; the price is quote per base.
+pub type Pair = (String, String);
+
+/// A pair's candles at every interval, oldest first.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PairSeries {
+    series: HashMap<Interval, VecDeque<Candle>>,
+}
+
+impl PairSeries {
+    fn record(&mut self, tick: &Tick) {
+        for interval in Interval::ALL {
+            let candles = self.series.entry(interval).or_default();
+            let span = (interval.retention() as i64 - 1) * interval.seconds();
+            let time = interval.bucket(tick.time);
+            if candles.back().is_some_and(|newest| time < newest.time - span) {
+                continue;
+            }
+            match candles.binary_search_by_key(&time, |candle| candle.time) {
+                Ok(index) => candles[index].add(tick),
+                Err(index) => candles.insert(index, Candle::new(interval, tick)),
+            }
+            let newest = candles.back().map_or(time, |candle| candle.time);
+            while candles.front().is_some_and(|oldest| oldest.time < newest - span) {
+                candles.pop_front();
+            }
+        }
+    }
+}
+
+/// Candles of every tracked pair, saved to disk as bincode.
+pub struct CandleStore {
+    pairs: DashMap<Pair, PairSeries>,
+    path: PathBuf,
+    save_lock: Mutex<()>,
+}
+
+pub fn candle_store() -> &'static CandleStore {
+    CANDLE_STORE.get_or_init(CandleStore::load)
+}
+
+impl CandleStore {
+    pub fn load() -> Self {
+        Self::load_from(PathBuf::from(std::env::var("OHLCV_STORE_PATH").unwrap_or_else(|_| "ohlcv.bin".into())))
+    }
+
+    fn load_from(path: PathBuf) -> Self {
+        let store = CandleStore { pairs: DashMap::new(), path, save_lock: Mutex::new(()) };
+        match std::fs::read(&store.path) {
+            Ok(raw) => match bincode::deserialize::<Vec<(Pair, PairSeries)>>(&raw) {
+                Ok(saved) => saved.into_iter().for_each(|(pair, series)| {
+                    store.pairs.insert(pair, series);
+                }),
+                Err(e) => eprintln!("[ohlcv] Ignoring malformed {}: {}", store.path.display(), e),
+            },
+            Err(_) => println!("[ohlcv] No saved candles at {}", store.path.display()),
+        }
+        store
+    }
+
+    /// Returns whether the pair was newly tracked.
+    pub fn track(&self, base: &str, quote: &str) -> bool {
+        let pair = (base.to_string(), quote.to_string());
+        if self.pairs.contains_key(&pair) {
+            return false;
+        }
+        self.pairs.insert(pair, PairSeries::default());
+        true
+    }
+
+    pub fn pairs(&self) -> Vec<Pair> {
+        self.pairs.iter().map(|entry| entry.key().clone()).collect()
+    }
+
+    /// Folds the tick into the pair's candles; untracked pairs are ignored.
+    /// Ticks older than an interval's retention are dropped for it.
+    pub fn record(&self, base: &str, quote: &str, tick: &Tick) {
+        if let Some(mut series) = self.pairs.get_mut(&(base.to_string(), quote.to_string())) {
+            series.record(tick);
+        }
+    }
+
+    /// Gap-filled candles from the one holding `from` to the one holding
+    /// `to`, or `None` for an untracked pair.
+    pub fn candles(&self, base: &str, quote: &str, interval: Interval, from: i64, to: i64) -> Option<Vec<Candle>> {
+        let series = self.pairs.get(&(base.to_string(), quote.to_string()))?;
+        let Some(candles) = series.series.get(&interval) else {
+            return Some(Vec::new());
+        };
+        let start = candles.partition_point(|candle| candle.time < interval.bucket(from));
+        let end = candles.partition_point(|candle| candle.time <= to);
+        let previous = start.checked_sub(1).map(|index| candles[index].close);
+        let observed: Vec<Candle> = candles.range(start..end.max(start)).copied().collect();
+        Some(fill_gaps(&observed, interval, from, to, previous))
+    }
+
+    /// Close of the finest candle holding `time`, or `None` when no
+    /// retained candle of the pair does.
+    pub fn price_at(&self, base: &str, quote: &str, time: i64) -> Option<f64> {
+        let series = self.pairs.get(&(base.to_string(), quote.to_string()))?;
+        Interval::ALL.iter().find_map(|interval| {
+            let candles = series.series.get(interval)?;
+            let index = candles.binary_search_by_key(&interval.bucket(time), |candle| candle.time).ok()?;
+            Some(candles[index].close)
+        })
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let _guard = self.save_lock.lock().unwrap();
+        let entries: Vec<_> = self.pairs.iter().collect();
+        let saved: Vec<(&Pair, &PairSeries)> = entries.iter().map(|entry| (entry.key(), entry.value())).collect();
+        let bytes = bincode::serialize(&saved).map_err(std::io::Error::other)?;
+        drop(entries);
+        let tmp = self.path.with_extension("bin.tmp");
+        std::fs::write(&tmp, bytes)?;
+        std::fs::rename(tmp, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "So11111111111111111111111111111111111111112";
+    const QUOTE: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn store(name: &str) -> CandleStore {
+        CandleStore::load_from(std::env::temp_dir().join(format!("{}-{}.bin", name, std::process::id())))
+    }
+
+    fn tick(time: i64, price: f64) -> Tick {
+        Tick { time, price, volume: 1.0 }
+    }
+
+    #[test]
+    fn ticks_fold_into_every_interval_within_retention() {
+        let store = store("ohlcv-fold");
+        store.record(BASE, QUOTE, &tick(0, 1.0));
+        assert!(store.candles(BASE, QUOTE, Interval::M1, 0, 60).is_none());
+
+        assert!(store.track(BASE, QUOTE));
+        assert!(!store.track(BASE, QUOTE));
+        for minute in 0..10 {
+            store.record(BASE, QUOTE, &tick(minute * 60 + 5, 100.0 + minute as f64));
+        }
+        let five = store.candles(BASE, QUOTE, Interval::M5, 0, 599).unwrap();
+        assert_eq!(five.len(), 2);
+        assert_eq!((five[0].open, five[0].high, five[0].close, five[0].volume), (100.0, 104.0, 104.0, 5.0));
+        let one = store.candles(BASE, QUOTE, Interval::M1, 180, 240).unwrap();
+        assert_eq!(one.iter().map(|c| c.close).collect::<Vec<_>>(), [103.0, 104.0]);
+
+        // A day later the first minutes fall out of the 1m retention.
+        store.record(BASE, QUOTE, &tick(86_400 + 5, 200.0));
+        let day_old = store.candles(BASE, QUOTE, Interval::M1, 0, 60).unwrap();
+        assert_eq!(day_old.iter().map(|c| c.time).collect::<Vec<_>>(), [60]);
+        store.record(BASE, QUOTE, &tick(65, 1.0));
+        assert_eq!(store.candles(BASE, QUOTE, Interval::M5, 0, 0).unwrap()[0].low, 1.0);
+    }
+
+    #[test]
+    fn prices_come_from_the_finest_candle_holding_the_time() {
+        let store = store("ohlcv-price-at");
+        assert_eq!(store.price_at(BASE, QUOTE, 30), None);
+        store.track(BASE, QUOTE);
+        store.record(BASE, QUOTE, &tick(30, 2.0));
+        store.record(BASE, QUOTE, &tick(90, 3.0));
+        assert_eq!(store.price_at(BASE, QUOTE, 45), Some(2.0));
+        // No 1m candle at 150; the 5m one closed at 3.
+        assert_eq!(store.price_at(BASE, QUOTE, 150), Some(3.0));
+        assert_eq!(store.price_at(BASE, QUOTE, 2 * 86_400), None);
+    }
+
+    #[test]
+    fn saved_candles_round_trip() {
+        let store = store("ohlcv-save");
+        store.track(BASE, QUOTE);
+        store.record(BASE, QUOTE, &tick(30, 2.5));
+        store.save().unwrap();
+
+        let reloaded = CandleStore::load_from(store.path.clone());
+        assert_eq!(reloaded.pairs(), vec![(BASE.to_string(), QUOTE.to_string())]);
+        assert_eq!(
+            reloaded.candles(BASE, QUOTE, Interval::D1, 0, 0),
+            store.candles(BASE, QUOTE, Interval::D1, 0, 0)
+        );
+        std::fs::remove_file(&store.path).unwrap();
+    }
+}
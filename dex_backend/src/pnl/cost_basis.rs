@@ -0,0 +1,386 @@
+use crate::utils::token_amount::{format_token_amount, serialize_as_string, to_ui_f64};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CostBasisMethod {
+    Fifo,
+    Average,
+}
+
+impl CostBasisMethod {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "fifo" => Some(Self::Fifo),
+            "average" | "avg" => Some(Self::Average),
+            _ => None,
+        }
+    }
+}
+
+/// Token identity for engine events; quantities are always base units.
+#[derive(Debug, Clone, Copy)]
+pub struct Asset<'a> {
+    pub mint: &'a str,
+    pub symbol: &'a str,
+    pub decimals: u8,
+}
+
+#[derive(Debug, Clone)]
+struct Lot {
+    quantity: u128,
+    cost_usd: f64,
+    acquired_at: u64,
+}
+
+/// A disposal matched against one or more open lots.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClosedPosition {
+    pub signature: String,
+    pub quantity: String,
+    #[serde(serialize_with = "serialize_as_string")]
+    pub raw_quantity: u128,
+    pub proceeds_usd: f64,
+    pub cost_basis_usd: f64,
+    pub realized_pnl_usd: f64,
+    pub opened_at: u64,
+    pub closed_at: u64,
+}
+
+#[derive(Debug, Default)]
+struct TokenBook {
+    symbol: String,
+    decimals: u8,
+    lots: VecDeque<Lot>,
+    realized_pnl_usd: f64,
+    fees_usd: f64,
+    rewards_usd: f64,
+    /// Quantity disposed of with no open lot to match, e.g. tokens acquired
+    /// before the analysed window. Excluded from realized PnL.
+    untracked_quantity: u128,
+    closed_positions: Vec<ClosedPosition>,
+}
+
+impl TokenBook {
+    fn quantity(&self) -> u128 {
+        self.lots.iter().map(|l| l.quantity).sum()
+    }
+
+    fn cost_basis_usd(&self) -> f64 {
+        self.lots.iter().map(|l| l.cost_usd).sum()
+    }
+
+    /// Takes `quantity` out of the open lots and returns the matched
+    /// quantity, its cost and the earliest acquisition time it touched.
+    fn take(&mut self, quantity: u128) -> (u128, f64, u64) {
+        let mut remaining = quantity;
+        let mut matched = 0;
+        let mut cost = 0.0;
+        let mut opened_at = u64::MAX;
+
+        while remaining > 0 {
+            let Some(lot) = self.lots.front_mut() else {
+                break;
+            };
+            let used = remaining.min(lot.quantity);
+            let used_cost = lot.cost_usd * (used as f64 / lot.quantity as f64);
+            matched += used;
+            cost += used_cost;
+            opened_at = opened_at.min(lot.acquired_at);
+            lot.quantity -= used;
+            lot.cost_usd -= used_cost;
+            remaining -= used;
+            if lot.quantity == 0 {
+                self.lots.pop_front();
+            }
+        }
+
+        (matched, cost, if matched > 0 { opened_at } else { 0 })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenPnl {
+    pub mint: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub quantity: String,
+    #[serde(serialize_with = "serialize_as_string")]
+    pub raw_quantity: u128,
+    pub cost_basis_usd: f64,
+    /// Per whole token, not per base unit.
+    pub average_cost_usd: Option<f64>,
+    pub current_price_usd: Option<f64>,
+    pub market_value_usd: Option<f64>,
+    pub realized_pnl_usd: f64,
+    pub unrealized_pnl_usd: Option<f64>,
+    pub fees_usd: f64,
+    pub rewards_usd: f64,
+    pub untracked_quantity: String,
+    pub closed_positions: Vec<ClosedPosition>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PnlPoint {
+    pub timestamp: u64,
+    pub signature: String,
+    pub realized_pnl_usd: f64,
+    pub cost_basis_usd: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PnlTotals {
+    pub realized_pnl_usd: f64,
+    pub unrealized_pnl_usd: f64,
+    pub fees_usd: f64,
+    pub rewards_usd: f64,
+    pub cost_basis_usd: f64,
+    pub market_value_usd: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PnlReport {
+    pub method: CostBasisMethod,
+    pub tokens: Vec<TokenPnl>,
+    pub totals: PnlTotals,
+    pub time_series: Vec<PnlPoint>,
+}
+
+/// Per-mint cost basis ledger. Events must be applied oldest first.
+///
+/// With `Average`, every acquisition is merged into a single pooled lot so a
+/// disposal always matches at the running average unit cost.
+pub struct PnlEngine {
+    method: CostBasisMethod,
+    books: HashMap<String, TokenBook>,
+    time_series: Vec<PnlPoint>,
+}
+
+impl PnlEngine {
+    pub fn new(method: CostBasisMethod) -> Self {
+        Self {
+            method,
+            books: HashMap::new(),
+            time_series: Vec::new(),
+        }
+    }
+
+    fn book(&mut self, asset: Asset) -> &mut TokenBook {
+        let book = self.books.entry(asset.mint.to_string()).or_default();
+        if book.symbol.is_empty() {
+            book.symbol = asset.symbol.to_string();
+            book.decimals = asset.decimals;
+        }
+        book
+    }
+
+    pub fn acquire(&mut self, asset: Asset, quantity: u128, cost_usd: f64, timestamp: u64) {
+        if quantity == 0 {
+            return;
+        }
+        let method = self.method;
+        let book = self.book(asset);
+
+        match (method, book.lots.front_mut()) {
+            (CostBasisMethod::Average, Some(pooled)) => {
+                pooled.quantity += quantity;
+                pooled.cost_usd += cost_usd;
+            }
+            _ => book.lots.push_back(Lot {
+                quantity,
+                cost_usd,
+                acquired_at: timestamp,
+            }),
+        }
+    }
+
+    /// Sells `quantity` for `proceeds_usd`, realizing PnL on the matched part.
+    pub fn dispose(
+        &mut self,
+        asset: Asset,
+        quantity: u128,
+        proceeds_usd: f64,
+        timestamp: u64,
+        signature: &str,
+    ) {
+        if quantity == 0 {
+            return;
+        }
+        let book = self.book(asset);
+        let (matched, cost, opened_at) = book.take(quantity);
+        book.untracked_quantity += quantity - matched;
+
+        if matched == 0 {
+            return;
+        }
+
+        let matched_proceeds = proceeds_usd * (matched as f64 / quantity as f64);
+        let pnl = matched_proceeds - cost;
+        book.realized_pnl_usd += pnl;
+        book.closed_positions.push(ClosedPosition {
+            signature: signature.to_string(),
+            quantity: format_token_amount(matched, asset.decimals),
+            raw_quantity: matched,
+            proceeds_usd: matched_proceeds,
+            cost_basis_usd: cost,
+            realized_pnl_usd: pnl,
+            opened_at,
+            closed_at: timestamp,
+        });
+    }
+
+    /// Moves tokens out of the wallet without a sale (e.g. a transfer to
+    /// another address). Cost basis leaves with them, nothing is realized.
+    pub fn remove(&mut self, asset: Asset, quantity: u128) {
+        let book = self.book(asset);
+        let (matched, _, _) = book.take(quantity);
+        book.untracked_quantity += quantity - matched;
+    }
+
+    /// Network fees not capitalised into a lot are a realized expense.
+    pub fn record_fee(&mut self, asset: Asset, fee_usd: f64) {
+        let book = self.book(asset);
+        book.fees_usd += fee_usd;
+        book.realized_pnl_usd -= fee_usd;
+    }
+
+    /// Fees paid for a swap are added to the acquired lot instead; they are
+    /// still reported under `feesUsd` for the acquired token.
+    pub fn track_capitalised_fee(&mut self, asset: Asset, fee_usd: f64) {
+        self.book(asset).fees_usd += fee_usd;
+    }
+
+    /// Arbitrage rewards are income at receipt and open a lot at that value.
+    pub fn reward(&mut self, asset: Asset, quantity: u128, value_usd: f64, timestamp: u64) {
+        self.acquire(asset, quantity, value_usd, timestamp);
+        let book = self.book(asset);
+        book.rewards_usd += value_usd;
+        book.realized_pnl_usd += value_usd;
+    }
+
+    /// Records a point of the time series after a transaction was applied.
+    pub fn checkpoint(&mut self, timestamp: u64, signature: &str) {
+        let (realized, cost) = self.books.values().fold((0.0, 0.0), |(r, c), book| {
+            (r + book.realized_pnl_usd, c + book.cost_basis_usd())
+        });
+        self.time_series.push(PnlPoint {
+            timestamp,
+            signature: signature.to_string(),
+            realized_pnl_usd: realized,
+            cost_basis_usd: cost,
+        });
+    }
+
+    pub fn report(&self, current_prices: &HashMap<String, f64>) -> PnlReport {
+        let mut tokens: Vec<TokenPnl> = self
+            .books
+            .iter()
+            .map(|(mint, book)| {
+                let quantity = book.quantity();
+                let ui_quantity = to_ui_f64(quantity, book.decimals);
+                let cost_basis_usd = book.cost_basis_usd();
+                let current_price_usd = current_prices.get(mint).copied();
+                let market_value_usd = current_price_usd.map(|p| p * ui_quantity);
+
+                TokenPnl {
+                    mint: mint.clone(),
+                    symbol: book.symbol.clone(),
+                    decimals: book.decimals,
+                    quantity: format_token_amount(quantity, book.decimals),
+                    raw_quantity: quantity,
+                    cost_basis_usd,
+                    average_cost_usd: (quantity > 0).then(|| cost_basis_usd / ui_quantity),
+                    current_price_usd,
+                    market_value_usd,
+                    realized_pnl_usd: book.realized_pnl_usd,
+                    unrealized_pnl_usd: market_value_usd.map(|v| v - cost_basis_usd),
+                    fees_usd: book.fees_usd,
+                    rewards_usd: book.rewards_usd,
+                    untracked_quantity: format_token_amount(book.untracked_quantity, book.decimals),
+                    closed_positions: book.closed_positions.clone(),
+                }
+            })
+            .collect();
+
+        tokens.sort_by(|a, b| {
+            b.realized_pnl_usd
+                .abs()
+                .partial_cmp(&a.realized_pnl_usd.abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let totals = PnlTotals {
+            realized_pnl_usd: tokens.iter().map(|t| t.realized_pnl_usd).sum(),
+            unrealized_pnl_usd: tokens.iter().filter_map(|t| t.unrealized_pnl_usd).sum(),
+            fees_usd: tokens.iter().map(|t| t.fees_usd).sum(),
+            rewards_usd: tokens.iter().map(|t| t.rewards_usd).sum(),
+            cost_basis_usd: tokens.iter().map(|t| t.cost_basis_usd).sum(),
+            market_value_usd: tokens.iter().filter_map(|t| t.market_value_usd).sum(),
+        };
+
+        PnlReport {
+            method: self.method,
+            tokens,
+            totals,
+            time_series: self.time_series.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TKN: Asset = Asset {
+        mint: "mint",
+        symbol: "TKN",
+        decimals: 6,
+    };
+    const ONE: u128 = 1_000_000;
+
+    fn realized(engine: &PnlEngine) -> f64 {
+        engine.report(&HashMap::new()).totals.realized_pnl_usd
+    }
+
+    #[test]
+    fn fifo_matches_oldest_lot_first() {
+        let mut engine = PnlEngine::new(CostBasisMethod::Fifo);
+        engine.acquire(TKN, 10 * ONE, 10.0, 1);
+        engine.acquire(TKN, 10 * ONE, 30.0, 2);
+        engine.dispose(TKN, 10 * ONE, 25.0, 3, "sig");
+
+        assert!((realized(&engine) - 15.0).abs() < 1e-9);
+        let report = engine.report(&HashMap::from([(TKN.mint.to_string(), 4.0)]));
+        assert_eq!(report.tokens[0].quantity, "10");
+        assert!((report.tokens[0].cost_basis_usd - 30.0).abs() < 1e-9);
+        assert!((report.totals.unrealized_pnl_usd - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn average_cost_pools_lots() {
+        let mut engine = PnlEngine::new(CostBasisMethod::Average);
+        engine.acquire(TKN, 10 * ONE, 10.0, 1);
+        engine.acquire(TKN, 10 * ONE, 30.0, 2);
+        engine.dispose(TKN, 10 * ONE, 25.0, 3, "sig");
+
+        assert!((realized(&engine) - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn unmatched_disposal_is_untracked() {
+        let mut engine = PnlEngine::new(CostBasisMethod::Fifo);
+        engine.acquire(TKN, 5 * ONE, 5.0, 1);
+        engine.dispose(TKN, 10 * ONE, 20.0, 2, "sig");
+
+        let report = engine.report(&HashMap::new());
+        assert!((report.totals.realized_pnl_usd - 5.0).abs() < 1e-9);
+        assert_eq!(report.tokens[0].untracked_quantity, "5");
+        assert_eq!(report.tokens[0].raw_quantity, 0);
+    }
+}
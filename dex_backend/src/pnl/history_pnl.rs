@@ -0,0 +1,186 @@
+use crate::api_methods::handle_history::{
+    is_fee_change, mint_from_token_id, BalanceChange, NormalizedTx, USD_STABLE_MINTS,
+};
+use crate::pnl::cost_basis::{Asset, PnlEngine};
+use crate::utils::token_amount::to_ui_f64;
+use std::collections::{HashMap, HashSet};
+
+const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// Unit prices seen so far while replaying history. Swaps against a
+/// stablecoin price the other leg; that price then values later swaps that
+/// have no stable leg. Mints never priced this way fall back to the current
+/// price and are reported as estimated.
+pub struct PriceBook {
+    last_seen: HashMap<String, f64>,
+    current: HashMap<String, f64>,
+    pub estimated_mints: HashSet<String>,
+}
+
+impl PriceBook {
+    pub fn new(current: HashMap<String, f64>) -> Self {
+        Self {
+            last_seen: HashMap::new(),
+            current,
+            estimated_mints: HashSet::new(),
+        }
+    }
+
+    fn price(&mut self, mint: &str) -> Option<f64> {
+        if USD_STABLE_MINTS.contains(&mint) {
+            return Some(1.0);
+        }
+        if let Some(price) = self.last_seen.get(mint) {
+            return Some(*price);
+        }
+        let price = self.current.get(mint).copied()?;
+        self.estimated_mints.insert(mint.to_string());
+        Some(price)
+    }
+
+    fn observe(&mut self, mint: &str, unit_price: f64) {
+        if unit_price.is_finite() && unit_price > 0.0 && !USD_STABLE_MINTS.contains(&mint) {
+            self.last_seen.insert(mint.to_string(), unit_price);
+        }
+    }
+}
+
+struct Leg<'a> {
+    mint: String,
+    change: &'a BalanceChange,
+}
+
+impl Leg<'_> {
+    fn asset(&self) -> Asset<'_> {
+        Asset {
+            mint: &self.mint,
+            symbol: &self.change.token.symbol,
+            decimals: self.change.token.decimals,
+        }
+    }
+
+    fn quantity(&self) -> u128 {
+        self.change.rawAmount
+    }
+
+    fn ui_quantity(&self) -> f64 {
+        to_ui_f64(self.change.rawAmount, self.change.token.decimals)
+    }
+}
+
+const SOL_ASSET: Asset = Asset {
+    mint: SOL_MINT,
+    symbol: "SOL",
+    decimals: 9,
+};
+
+/// Feeds one normalized transaction into the engine.
+///
+/// Swaps (tokens both in and out) dispose of the outgoing legs and open lots
+/// for the incoming ones at the same USD value, with the network fee
+/// capitalised into the acquired lots. Incoming transfers whose fee payer is
+/// in `reward_payers` are arbitrage rewards; other transfers move basis in
+/// or out without realizing anything.
+pub fn apply_transaction(
+    engine: &mut PnlEngine,
+    prices: &mut PriceBook,
+    tx: &NormalizedTx,
+    wallet: &str,
+    reward_payers: &HashSet<String>,
+) {
+    let wallet_address = format!("solana:101/address:{}", wallet);
+    let signature = tx.chainMeta.transactionId.as_str();
+    let timestamp = tx.timestamp;
+
+    let mut ins = Vec::new();
+    let mut outs = Vec::new();
+    let mut fee_lamports: u128 = 0;
+
+    for change in &tx.interactionData.balanceChanges {
+        let mint = mint_from_token_id(&change.token.id);
+        if is_fee_change(change) {
+            fee_lamports += change.rawAmount;
+        } else if change.to == wallet_address {
+            ins.push(Leg { mint, change });
+        } else if change.from == wallet_address {
+            outs.push(Leg { mint, change });
+        }
+    }
+
+    let fee_usd = prices
+        .price(SOL_MINT)
+        .map(|p| p * to_ui_f64(fee_lamports, 9))
+        .unwrap_or(0.0);
+    let succeeded = tx.chainMeta.status == "success";
+
+    if succeeded && !ins.is_empty() && !outs.is_empty() {
+        apply_swap(engine, prices, &ins, &outs, fee_usd, timestamp, signature);
+    } else {
+        if fee_usd > 0.0 {
+            engine.record_fee(SOL_ASSET, fee_usd);
+        }
+        if succeeded {
+            let is_reward = tx
+                .chainMeta
+                .feePayer
+                .as_ref()
+                .is_some_and(|payer| reward_payers.contains(payer));
+
+            for leg in &ins {
+                let value = prices.price(&leg.mint).unwrap_or(0.0) * leg.ui_quantity();
+                if is_reward {
+                    engine.reward(leg.asset(), leg.quantity(), value, timestamp);
+                } else {
+                    engine.acquire(leg.asset(), leg.quantity(), value, timestamp);
+                }
+            }
+            for leg in &outs {
+                engine.remove(leg.asset(), leg.quantity());
+            }
+        }
+    }
+
+    engine.checkpoint(timestamp, signature);
+}
+
+fn apply_swap(
+    engine: &mut PnlEngine,
+    prices: &mut PriceBook,
+    ins: &[Leg],
+    outs: &[Leg],
+    fee_usd: f64,
+    timestamp: u64,
+    signature: &str,
+) {
+    // Value the trade from whichever side has a known price, preferring
+    // stablecoin legs since they are exact.
+    let side_value = |prices: &mut PriceBook, legs: &[Leg]| -> Option<f64> {
+        legs.iter()
+            .map(|l| prices.price(&l.mint).map(|p| p * l.ui_quantity()))
+            .sum::<Option<f64>>()
+    };
+    let has_stable = |legs: &[Leg]| legs.iter().any(|l| USD_STABLE_MINTS.contains(&l.mint.as_str()));
+
+    let value = if has_stable(outs) {
+        side_value(prices, outs)
+    } else if has_stable(ins) {
+        side_value(prices, ins)
+    } else {
+        side_value(prices, outs).or_else(|| side_value(prices, ins))
+    }
+    .unwrap_or(0.0);
+
+    // Multi-leg swaps mix mints, so split the value evenly per leg.
+    for leg in outs {
+        let share = value / outs.len() as f64;
+        engine.dispose(leg.asset(), leg.quantity(), share, timestamp, signature);
+        prices.observe(&leg.mint, share / leg.ui_quantity());
+    }
+    for leg in ins {
+        let share = value / ins.len() as f64;
+        let fee_share = fee_usd / ins.len() as f64;
+        engine.acquire(leg.asset(), leg.quantity(), share + fee_share, timestamp);
+        engine.track_capitalised_fee(leg.asset(), fee_share);
+        prices.observe(&leg.mint, share / leg.ui_quantity());
+    }
+}
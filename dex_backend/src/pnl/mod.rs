@@ -0,0 +1,2 @@
+pub mod cost_basis;
+pub mod history_pnl;
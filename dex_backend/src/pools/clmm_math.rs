@@ -0,0 +1,385 @@
+//! Concentrated-liquidity swap math shared by Raydium CLMM and Orca
+//! Whirlpool. Both store `sqrt(price)` as Q64.64, charge fees in millionths
+//! of the input and follow the Uniswap v3 step logic; they differ only in
+//! their tick-to-price tables, which each venue supplies as a [`TickMath`].
+
+use crate::pools::math::{div_ceil_256, mul_div_ceil, mul_div_floor, narrow, to_u64};
+use anyhow::{anyhow, Result};
+use ethnum::U256;
+use std::collections::BTreeMap;
+
+/// Fee rates are expressed in millionths on both venues.
+pub const FEE_RATE_DENOMINATOR: u32 = 1_000_000;
+
+/// A venue's tick range and its on-chain `sqrt_price_at_tick`.
+pub struct TickMath {
+    pub min_tick: i32,
+    pub max_tick: i32,
+    pub min_sqrt_price: u128,
+    pub max_sqrt_price: u128,
+    pub sqrt_price_at_tick: fn(i32) -> u128,
+}
+
+impl TickMath {
+    pub fn sqrt_price_at_tick(&self, tick: i32) -> Result<u128> {
+        if tick < self.min_tick || tick > self.max_tick {
+            return Err(anyhow!("tick {} is out of range", tick));
+        }
+        Ok((self.sqrt_price_at_tick)(tick))
+    }
+
+    /// Greatest tick whose sqrt price is at or below `sqrt_price`, which is
+    /// what the programs' log2 approximations settle on.
+    pub fn tick_at_sqrt_price(&self, sqrt_price: u128) -> Result<i32> {
+        if sqrt_price < self.min_sqrt_price || sqrt_price > self.max_sqrt_price {
+            return Err(anyhow!("sqrt price {} is out of range", sqrt_price));
+        }
+        let (mut low, mut high) = (self.min_tick, self.max_tick);
+        while low < high {
+            let mid = low + (high - low + 1) / 2;
+            if (self.sqrt_price_at_tick)(mid) <= sqrt_price {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        Ok(low)
+    }
+}
+
+/// Initialized ticks (tick -> liquidity_net) over the range covered by the
+/// loaded tick arrays, `[lower, upper)`.
+#[derive(Debug, Clone, Default)]
+pub struct TickLiquidity {
+    ticks: BTreeMap<i32, i128>,
+    lower: i32,
+    upper: i32,
+}
+
+impl TickLiquidity {
+    pub fn new(lower: i32, upper: i32) -> Self {
+        Self { ticks: BTreeMap::new(), lower, upper }
+    }
+
+    pub fn insert(&mut self, tick: i32, liquidity_net: i128) {
+        self.ticks.insert(tick, liquidity_net);
+    }
+
+    /// Next initialized tick in the swap direction: at or below `tick` when
+    /// selling A, strictly above it when selling B. Falls back to the edge
+    /// of the loaded range, uninitialized.
+    fn next_initialized(&self, tick: i32, a_to_b: bool) -> Result<(i32, bool)> {
+        if tick < self.lower || tick >= self.upper {
+            return Err(anyhow!("swap runs past the loaded tick arrays"));
+        }
+        let found = if a_to_b {
+            self.ticks.range(self.lower..=tick).next_back()
+        } else {
+            self.ticks.range(tick + 1..self.upper).next()
+        };
+        Ok(match found {
+            Some((tick, _)) => (*tick, true),
+            None if a_to_b => (self.lower, false),
+            None => (self.upper, false),
+        })
+    }
+}
+
+/// Pool state a swap starts from.
+pub struct ClmmState<'a> {
+    pub sqrt_price: u128,
+    pub tick: i32,
+    pub liquidity: u128,
+    pub fee_rate: u32,
+    pub ticks: &'a TickLiquidity,
+    pub tick_math: &'a TickMath,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClmmSwap {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+}
+
+/// Where the price ends up after a swap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClmmPosition {
+    pub sqrt_price: u128,
+    pub tick: i32,
+    pub liquidity: u128,
+}
+
+/// Walks the swap across initialized ticks, returning it with the pool's
+/// price and liquidity afterwards. `amount` is the input for exact-in swaps
+/// and the output for exact-out ones; a swap that cannot be filled within
+/// the loaded liquidity is an error rather than a partial fill.
+pub fn swap_to_position(
+    state: &ClmmState,
+    amount: u64,
+    exact_in: bool,
+    a_to_b: bool,
+) -> Result<(ClmmSwap, ClmmPosition)> {
+    let math = state.tick_math;
+    let limit = if a_to_b { math.min_sqrt_price } else { math.max_sqrt_price };
+    let mut remaining = amount as u128;
+    let mut calculated: u128 = 0;
+    let mut fees: u128 = 0;
+    let mut sqrt_price = state.sqrt_price;
+    let mut tick = state.tick;
+    let mut liquidity = state.liquidity;
+
+    while remaining > 0 && sqrt_price != limit {
+        let (next_tick, initialized) = state.ticks.next_initialized(tick, a_to_b)?;
+        let next_tick = next_tick.clamp(math.min_tick, math.max_tick);
+        let next_sqrt_price = math.sqrt_price_at_tick(next_tick)?;
+        let target = if a_to_b { next_sqrt_price.max(limit) } else { next_sqrt_price.min(limit) };
+
+        let step = swap_step(sqrt_price, target, liquidity, remaining, state.fee_rate, exact_in)?;
+        if exact_in {
+            remaining -= step.amount_in + step.fee;
+            calculated += step.amount_out;
+        } else {
+            remaining -= step.amount_out;
+            calculated += step.amount_in + step.fee;
+        }
+        fees += step.fee;
+
+        if step.sqrt_price_next == next_sqrt_price {
+            if initialized {
+                let net = state.ticks.ticks[&next_tick];
+                let net = if a_to_b { -net } else { net };
+                liquidity = liquidity
+                    .checked_add_signed(net)
+                    .ok_or_else(|| anyhow!("liquidity underflow crossing tick {}", next_tick))?;
+            }
+            tick = if a_to_b { next_tick - 1 } else { next_tick };
+        } else if step.sqrt_price_next != sqrt_price {
+            tick = math.tick_at_sqrt_price(step.sqrt_price_next)?;
+        }
+        sqrt_price = step.sqrt_price_next;
+    }
+
+    if remaining > 0 {
+        return Err(anyhow!("not enough liquidity for the requested amount"));
+    }
+    let (amount_in, amount_out) = if exact_in {
+        (amount as u128, calculated)
+    } else {
+        (calculated, amount as u128)
+    };
+    Ok((
+        ClmmSwap {
+            amount_in: to_u64(amount_in)?,
+            amount_out: to_u64(amount_out)?,
+            fee: to_u64(fees)?,
+        },
+        ClmmPosition { sqrt_price, tick, liquidity },
+    ))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SwapStep {
+    sqrt_price_next: u128,
+    amount_in: u128,
+    amount_out: u128,
+    fee: u128,
+}
+
+fn swap_step(
+    sqrt_price: u128,
+    target: u128,
+    liquidity: u128,
+    remaining: u128,
+    fee_rate: u32,
+    exact_in: bool,
+) -> Result<SwapStep> {
+    let a_to_b = sqrt_price >= target;
+    let fee_rate = fee_rate as u128;
+    let denominator = FEE_RATE_DENOMINATOR as u128;
+
+    let sqrt_price_next = if exact_in {
+        let remaining_less_fee = mul_div_floor(remaining, denominator - fee_rate, denominator)?;
+        let to_target = input_delta(sqrt_price, target, liquidity, a_to_b)?;
+        if remaining_less_fee >= to_target {
+            target
+        } else {
+            next_sqrt_price_from_input(sqrt_price, liquidity, remaining_less_fee, a_to_b)?
+        }
+    } else {
+        let to_target = output_delta(sqrt_price, target, liquidity, a_to_b)?;
+        if remaining >= to_target {
+            target
+        } else {
+            next_sqrt_price_from_output(sqrt_price, liquidity, remaining, a_to_b)?
+        }
+    };
+
+    let amount_in = input_delta(sqrt_price, sqrt_price_next, liquidity, a_to_b)?;
+    let mut amount_out = output_delta(sqrt_price, sqrt_price_next, liquidity, a_to_b)?;
+    if !exact_in {
+        amount_out = amount_out.min(remaining);
+    }
+    let fee = if exact_in && sqrt_price_next != target {
+        remaining - amount_in
+    } else {
+        mul_div_ceil(amount_in, fee_rate, denominator - fee_rate)?
+    };
+
+    Ok(SwapStep { sqrt_price_next, amount_in, amount_out, fee })
+}
+
+/// Input needed to move between two prices, rounded up.
+fn input_delta(from: u128, to: u128, liquidity: u128, a_to_b: bool) -> Result<u128> {
+    if a_to_b {
+        delta_a(to, from, liquidity, true)
+    } else {
+        delta_b(from, to, liquidity, true)
+    }
+}
+
+/// Output released moving between two prices, rounded down.
+fn output_delta(from: u128, to: u128, liquidity: u128, a_to_b: bool) -> Result<u128> {
+    if a_to_b {
+        delta_b(to, from, liquidity, false)
+    } else {
+        delta_a(from, to, liquidity, false)
+    }
+}
+
+/// `L * (upper - lower) / (upper * lower)` in token A units.
+pub fn delta_a(lower: u128, upper: u128, liquidity: u128, round_up: bool) -> Result<u128> {
+    let (lower, upper) = if lower <= upper { (lower, upper) } else { (upper, lower) };
+    if lower == 0 {
+        return Err(anyhow!("sqrt price is zero"));
+    }
+    let numerator = (U256::from(liquidity) << 64u32)
+        .checked_mul(U256::from(upper - lower))
+        .ok_or_else(|| anyhow!("token A delta overflows"))?;
+    let denominator = U256::from(upper) * U256::from(lower);
+    let quotient = if round_up {
+        div_ceil_256(numerator, denominator)?
+    } else {
+        numerator / denominator
+    };
+    narrow(quotient)
+}
+
+/// `L * (upper - lower)` in token B units.
+pub fn delta_b(lower: u128, upper: u128, liquidity: u128, round_up: bool) -> Result<u128> {
+    let (lower, upper) = if lower <= upper { (lower, upper) } else { (upper, lower) };
+    let product = U256::from(liquidity) * U256::from(upper - lower);
+    let quotient = if round_up {
+        div_ceil_256(product, U256::ONE << 64u32)?
+    } else {
+        product >> 64u32
+    };
+    narrow(quotient)
+}
+
+fn next_sqrt_price_from_input(sqrt_price: u128, liquidity: u128, amount: u128, a_to_b: bool) -> Result<u128> {
+    if a_to_b {
+        next_sqrt_price_from_a(sqrt_price, liquidity, amount, true)
+    } else {
+        next_sqrt_price_from_b(sqrt_price, liquidity, amount, true)
+    }
+}
+
+fn next_sqrt_price_from_output(sqrt_price: u128, liquidity: u128, amount: u128, a_to_b: bool) -> Result<u128> {
+    if a_to_b {
+        next_sqrt_price_from_b(sqrt_price, liquidity, amount, false)
+    } else {
+        next_sqrt_price_from_a(sqrt_price, liquidity, amount, false)
+    }
+}
+
+/// Rounds up so the pool never gives away more than the A amount covers.
+fn next_sqrt_price_from_a(sqrt_price: u128, liquidity: u128, amount: u128, add: bool) -> Result<u128> {
+    if amount == 0 {
+        return Ok(sqrt_price);
+    }
+    let scaled_liquidity = U256::from(liquidity) << 64u32;
+    let product = U256::from(amount) * U256::from(sqrt_price);
+    let numerator = scaled_liquidity
+        .checked_mul(U256::from(sqrt_price))
+        .ok_or_else(|| anyhow!("sqrt price overflows"))?;
+    let denominator = if add {
+        scaled_liquidity + product
+    } else {
+        if product >= scaled_liquidity {
+            return Err(anyhow!("not enough liquidity for the requested amount"));
+        }
+        scaled_liquidity - product
+    };
+    narrow(div_ceil_256(numerator, denominator)?)
+}
+
+/// Rounds down so the pool never gives away more than the B amount covers.
+fn next_sqrt_price_from_b(sqrt_price: u128, liquidity: u128, amount: u128, add: bool) -> Result<u128> {
+    if liquidity == 0 {
+        return Err(anyhow!("no liquidity at the current price"));
+    }
+    let shifted = U256::from(amount) << 64u32;
+    let liquidity = U256::from(liquidity);
+    if add {
+        let delta = narrow(shifted / liquidity)?;
+        sqrt_price.checked_add(delta).ok_or_else(|| anyhow!("sqrt price overflows"))
+    } else {
+        let delta = narrow(div_ceil_256(shifted, liquidity)?)?;
+        if delta >= sqrt_price {
+            return Err(anyhow!("not enough liquidity for the requested amount"));
+        }
+        Ok(sqrt_price - delta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool_math() -> TickMath {
+        crate::pools::whirlpool::TICK_MATH
+    }
+
+    #[test]
+    fn step_rounding_matches_reference() {
+        let math = pool_math();
+        // Price 1.0 with one position ending at tick 500 and another
+        // adding liquidity below tick -500.
+        let mut ticks = TickLiquidity::new(-1000, 1000);
+        ticks.insert(500, -1_000_000_000);
+        ticks.insert(-500, 500_000_000);
+        let state = ClmmState {
+            sqrt_price: 1 << 64,
+            tick: 0,
+            liquidity: 1_000_000_000,
+            fee_rate: 3000,
+            ticks: &ticks,
+            tick_math: &math,
+        };
+
+        // Stays inside the first range: 1000 in, 0.3% fee, ~997 out.
+        let small = swap_to_position(&state, 1000, true, true).unwrap().0;
+        assert_eq!(small, ClmmSwap { amount_in: 1000, amount_out: 996, fee: 3 });
+
+        // Exact-out must cost at least as much as the matching exact-in.
+        let back = swap_to_position(&state, 996, false, true).unwrap().0;
+        assert_eq!(back.amount_out, 996);
+        assert!(back.amount_in <= 1000 && back.amount_in >= 999);
+
+        // Selling B past tick 500 drains the only position.
+        assert!(swap_to_position(&state, 40_000_000, true, false).is_err());
+    }
+
+    #[test]
+    fn tick_at_sqrt_price_inverts_the_table() {
+        let math = pool_math();
+        for tick in [-443_636, -70_000, -1, 0, 1, 12_345, 443_636] {
+            let price = math.sqrt_price_at_tick(tick).unwrap();
+            assert_eq!(math.tick_at_sqrt_price(price).unwrap(), tick);
+            if tick < math.max_tick {
+                assert_eq!(math.tick_at_sqrt_price(price + 1).unwrap(), tick);
+            }
+        }
+    }
+}
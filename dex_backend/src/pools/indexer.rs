@@ -0,0 +1,366 @@
+use crate::pools::layout::token_account_amount;
+use crate::pools::registry::{pool_registry, PoolEntry};
+use crate::pools::loader::{update_pools, LoadedPools};
+use crate::pools::{decode_pool, AccountMap, Pool, PoolAccountKind, Venue};
+use crate::rpc::accounts::{get_multiple_accounts, get_program_accounts};
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use futures::future::join_all;
+use serde_json::{json, Value};
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::time::Instant;
+use tokio::time::{interval, Duration};
+
+pub const SOL_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+pub const USDC_MINT: Pubkey = pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+pub const USDT_MINT: Pubkey = pubkey!("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB");
+
+/// Stablecoins valued at $1, with their decimals.
+pub(crate) const STABLE_MINTS: [(Pubkey, u8); 2] = [(USDC_MINT, 6), (USDT_MINT, 6)];
+pub(crate) const SOL_DECIMALS: u8 = 9;
+/// Indexed on startup and every discovery round. The reference mints have
+/// far too many pools to discover by a single mint, so only these pairs
+/// and pools found through other tracked mints are kept for them.
+const BASE_PAIRS: [(Pubkey, Pubkey); 3] = [(SOL_MINT, USDC_MINT), (SOL_MINT, USDT_MINT), (USDC_MINT, USDT_MINT)];
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+/// Pools re-read per refresh tick, stalest first.
+const REFRESH_BATCH: usize = 100;
+const DISCOVERY_INTERVAL: Duration = Duration::from_secs(15 * 60);
+/// Amount sold through the deepest SOL/stable pool to price SOL (0.01 SOL).
+const PRICE_PROBE_LAMPORTS: u64 = 10_000_000;
+/// Shallower pools are too easy to push around to price SOL from.
+const MIN_PRICE_DEPTH_USD: f64 = 100_000.0;
+/// Share of a pool's `mint_a` reserve sold to read its spot price: small
+/// enough not to move it, large enough to survive integer rounding.
+const SPOT_PROBE_DIVISOR: u64 = 100_000;
+
+pub fn is_reference_mint(mint: &Pubkey) -> bool {
+    *mint == SOL_MINT || STABLE_MINTS.iter().any(|(stable, _)| stable == mint)
+}
+
+/// Keeps the pool registry current: rediscovers pools for the base pairs
+/// and tracked mints every [`DISCOVERY_INTERVAL`], and in between re-reads
+/// the stalest pools so reserves and TVL stay fresh without a full sweep.
+pub fn spawn_pool_indexer() {
+    tokio::spawn(async move {
+        let registry = pool_registry();
+        let mut ticker = interval(REFRESH_INTERVAL);
+        let mut last_discovery: Option<Instant> = None;
+        loop {
+            ticker.tick().await;
+            if last_discovery.is_none_or(|at| at.elapsed() >= DISCOVERY_INTERVAL) {
+                for (a, b) in BASE_PAIRS {
+                    if let Err(e) = discover_pair(&a, &b).await {
+                        eprintln!("[pools] Discovery of {}/{} failed: {}", a, b, e);
+                    }
+                }
+                for mint in registry.tracked_mints() {
+                    let Ok(mint) = Pubkey::from_str(&mint) else { continue };
+                    if let Err(e) = discover_mint(&mint).await {
+                        eprintln!("[pools] Discovery of {} failed: {}", mint, e);
+                    }
+                }
+                last_discovery = Some(Instant::now());
+            } else if let Err(e) = refresh_stalest(REFRESH_BATCH).await {
+                eprintln!("[pools] Refresh failed: {}", e);
+            }
+            if let Err(e) = registry.save() {
+                eprintln!("[pools] Failed to save registry: {}", e);
+            }
+        }
+    });
+}
+
+/// Discovers `mint`'s pools the first time it is asked for and keeps it
+/// tracked from then on. Reference mints are covered by [`BASE_PAIRS`].
+pub async fn ensure_discovered(mint: &Pubkey) -> Result<()> {
+    let registry = pool_registry();
+    if is_reference_mint(mint) || !registry.track(&mint.to_string()) {
+        return Ok(());
+    }
+    if let Err(e) = discover_mint(mint).await {
+        registry.untrack(&mint.to_string());
+        return Err(e);
+    }
+    if let Err(e) = registry.save() {
+        eprintln!("[pools] Failed to save registry: {}", e);
+    }
+    Ok(())
+}
+
+/// Finds every pool with `mint` on either side, across all venues.
+pub async fn discover_mint(mint: &Pubkey) -> Result<usize> {
+    let queries = Venue::ALL
+        .into_iter()
+        .flat_map(|venue| {
+            venue.mint_offsets().map(|offset| (venue, vec![mint_filter(offset, mint)]))
+        })
+        .collect();
+    discover(queries).await
+}
+
+/// Finds the pools trading `a` against `b`, in either mint order.
+pub async fn discover_pair(a: &Pubkey, b: &Pubkey) -> Result<usize> {
+    let queries = Venue::ALL
+        .into_iter()
+        .flat_map(|venue| {
+            let [first, second] = venue.mint_offsets();
+            [
+                (venue, vec![mint_filter(first, a), mint_filter(second, b)]),
+                (venue, vec![mint_filter(first, b), mint_filter(second, a)]),
+            ]
+        })
+        .collect();
+    discover(queries).await
+}
+
+async fn discover(queries: Vec<(Venue, Vec<Value>)>) -> Result<usize> {
+    let requests = queries.into_iter().map(|(venue, mut filters)| async move {
+        filters.insert(0, pool_account_filter(venue));
+        (venue, get_program_accounts(&venue.program_id(), filters).await)
+    });
+
+    let mut seen = HashSet::new();
+    let mut pools = Vec::new();
+    for (venue, result) in join_all(requests).await {
+        let (_, accounts) = result?;
+        for (address, account) in accounts {
+            if !seen.insert(address) {
+                continue;
+            }
+            match decode_pool(address, &venue.program_id(), &account.data) {
+                Ok(pool) => pools.push(pool),
+                Err(e) => eprintln!("[pools] Skipping {} pool {}: {}", venue.label(), address, e),
+            }
+        }
+    }
+    index_pools(pools).await
+}
+
+/// Re-reads the `limit` stalest registry entries, dropping pools that were
+/// closed since.
+pub async fn refresh_stalest(limit: usize) -> Result<usize> {
+    let registry = pool_registry();
+    let addresses: Vec<Pubkey> = registry
+        .stalest(limit)
+        .iter()
+        .filter_map(|address| Pubkey::from_str(address).ok())
+        .collect();
+    let fetched = get_multiple_accounts(&addresses).await?;
+
+    let mut pools = Vec::new();
+    for address in addresses {
+        let Some(account) = fetched.accounts.get(&address) else {
+            println!("[pools] {} no longer exists, dropping it", address);
+            registry.remove(&address.to_string());
+            continue;
+        };
+        match decode_pool(address, &account.owner, &account.data) {
+            Ok(pool) => pools.push(pool),
+            Err(e) => {
+                eprintln!("[pools] Dropping {}: {}", address, e);
+                registry.remove(&address.to_string());
+            }
+        }
+    }
+    index_pools(pools).await
+}
+
+/// Loads the state each pool depends on and records it in the registry.
+async fn index_pools(pools: Vec<Box<dyn Pool>>) -> Result<usize> {
+    if pools.is_empty() {
+        return Ok(0);
+    }
+    let LoadedPools { slot, pools, accounts, .. } = update_pools(pools).await?;
+
+    let registry = pool_registry();
+    if let Some(price) = sol_usd_from(&pools, &accounts) {
+        registry.set_sol_usd(price);
+    }
+    let mut indexed = 0;
+    for pool in &pools {
+        match entry_from_pool(pool.as_ref(), &accounts, slot, registry.sol_usd()) {
+            Ok(entry) => {
+                registry.upsert(entry);
+                indexed += 1;
+            }
+            Err(e) => eprintln!("[pools] Skipping {}: {}", pool.address(), e),
+        }
+    }
+    println!("[pools] Indexed {} pools at slot {}", indexed, slot);
+    Ok(indexed)
+}
+
+fn entry_from_pool(
+    pool: &dyn Pool,
+    accounts: &AccountMap,
+    slot: u64,
+    sol_usd: Option<f64>,
+) -> Result<PoolEntry> {
+    let mints = pool.mints();
+    let [reserve_a, reserve_b] = reserves(pool, accounts)?;
+    Ok(PoolEntry {
+        address: pool.address().to_string(),
+        venue: pool.venue(),
+        mint_a: mints[0].to_string(),
+        mint_b: mints[1].to_string(),
+        reserve_a,
+        reserve_b,
+        fee_bps: pool.fee_bps(),
+        spot_price: spot_price(pool, reserve_a),
+        tvl_usd: pool_tvl(mints, [reserve_a, reserve_b], sol_usd),
+        last_update_slot: slot,
+    })
+}
+
+fn reserves(pool: &dyn Pool, accounts: &AccountMap) -> Result<[u64; 2]> {
+    let [a, b] = pool.reserve_vaults();
+    let amount = |vault: Pubkey| {
+        accounts
+            .get(&vault)
+            .ok_or_else(|| anyhow::anyhow!("vault {} not loaded", vault))
+            .and_then(|data| token_account_amount(data))
+    };
+    Ok([amount(a)?, amount(b)?])
+}
+
+/// Raw `mint_b` per raw `mint_a` from a small sale of `mint_a`, with the
+/// fee added back.
+fn spot_price(pool: &dyn Pool, reserve_a: u64) -> Option<f64> {
+    let probe = (reserve_a / SPOT_PROBE_DIVISOR).max(1);
+    let quote = pool.quote_exact_in(&pool.mints()[0], probe).ok()?;
+    if quote.amount_out == 0 {
+        return None;
+    }
+    Some(quote.amount_out as f64 / probe as f64 / (1.0 - pool.fee_bps() / 10_000.0))
+}
+
+fn usd_price(mint: &Pubkey, sol_usd: Option<f64>) -> Option<(f64, u8)> {
+    if *mint == SOL_MINT {
+        return sol_usd.map(|price| (price, SOL_DECIMALS));
+    }
+    STABLE_MINTS
+        .iter()
+        .find(|(stable, _)| stable == mint)
+        .map(|(_, decimals)| (1.0, *decimals))
+}
+
+/// Both sides when both are priced; otherwise twice the priced side, which
+/// assumes a balanced pool and is only an estimate for concentrated ones.
+fn pool_tvl(mints: [Pubkey; 2], reserves: [u64; 2], sol_usd: Option<f64>) -> Option<f64> {
+    let value = |i: usize| {
+        usd_price(&mints[i], sol_usd)
+            .map(|(price, decimals)| reserves[i] as f64 / 10f64.powi(decimals as i32) * price)
+    };
+    match (value(0), value(1)) {
+        (Some(a), Some(b)) => Some(a + b),
+        (Some(side), None) | (None, Some(side)) => Some(side * 2.0),
+        (None, None) => None,
+    }
+}
+
+/// SOL price from a small sale through the SOL/stable pool holding the
+/// most stablecoin, with the fee added back.
+fn sol_usd_from(pools: &[Box<dyn Pool>], accounts: &AccountMap) -> Option<f64> {
+    let deepest = pools
+        .iter()
+        .filter_map(|pool| {
+            let mints = pool.mints();
+            let stable_side = mints.iter().position(|mint| STABLE_MINTS.iter().any(|(s, _)| s == mint))?;
+            if !mints.contains(&SOL_MINT) {
+                return None;
+            }
+            let depth = reserves(pool.as_ref(), accounts).ok()?[stable_side];
+            Some((depth, pool, mints[stable_side]))
+        })
+        .max_by_key(|(depth, _, _)| *depth)?;
+
+    let (depth, pool, stable) = deepest;
+    let (_, stable_decimals) = usd_price(&stable, None)?;
+    if (depth as f64) / 10f64.powi(stable_decimals as i32) < MIN_PRICE_DEPTH_USD {
+        return None;
+    }
+    let quote = pool.quote_exact_in(&SOL_MINT, PRICE_PROBE_LAMPORTS).ok()?;
+    let sold = PRICE_PROBE_LAMPORTS as f64 / 10f64.powi(SOL_DECIMALS as i32);
+    let received = quote.amount_out as f64 / 10f64.powi(stable_decimals as i32);
+    Some(received / sold / (1.0 - pool.fee_bps() / 10_000.0))
+}
+
+fn pool_account_filter(venue: Venue) -> Value {
+    match venue.pool_account_kind() {
+        PoolAccountKind::Size(size) => json!({ "dataSize": size }),
+        PoolAccountKind::Discriminator(bytes) => json!({
+            "memcmp": { "offset": 0, "bytes": BASE64.encode(bytes), "encoding": "base64" }
+        }),
+    }
+}
+
+fn mint_filter(offset: usize, mint: &Pubkey) -> Value {
+    json!({ "memcmp": { "offset": offset, "bytes": mint.to_string() } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::{load_snapshot, pool_from_snapshot};
+
+    const SNAPSHOTS: [(&str, &str); 5] = [
+        ("raydium_amm_v4_sol_usdc", "58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2"),
+        ("raydium_cpmm_usdc_bonk", "7JuwJuNU88gurFnyWeiyGKbFmExMWcmRZntn9imEzdny"),
+        ("raydium_clmm_sol_usdc", "3ucNos4NbumPLZNWztqGHNFFgkHeRMBQAVemeeomsUxv"),
+        ("whirlpool_sol_usdc", "Czfq3xZZDmsdGdUyrNLtRhGc47cXcZtLG4crryfu44zE"),
+        ("meteora_dlmm_sol_usdc", "5rCf1DM8LjKTw4YqhnoLcngyZYeNnQqztScTogYHAS6"),
+    ];
+
+    #[test]
+    fn discovery_filters_match_pool_accounts() {
+        for (name, address) in SNAPSHOTS {
+            let (accounts, owners) = load_snapshot(name);
+            let address = Pubkey::from_str(address).unwrap();
+            let data = &accounts[&address];
+            let venue = Venue::from_program_id(&owners[&address]).unwrap();
+
+            match venue.pool_account_kind() {
+                PoolAccountKind::Size(size) => assert_eq!(data.len(), size, "{}", name),
+                PoolAccountKind::Discriminator(bytes) => assert_eq!(data[..8], bytes, "{}", name),
+            }
+            let pool = pool_from_snapshot(name, &address.to_string());
+            for (offset, mint) in venue.mint_offsets().into_iter().zip(pool.mints()) {
+                assert_eq!(data[offset..offset + 32], mint.to_bytes(), "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn entries_price_tvl_from_reference_mints() {
+        let (name, address) = SNAPSHOTS[0];
+        let (accounts, _) = load_snapshot(name);
+        let pool = pool_from_snapshot(name, address);
+
+        let sol_usd = sol_usd_from(std::slice::from_ref(&pool), &accounts).unwrap();
+        assert!((149.0..151.0).contains(&sol_usd), "{}", sol_usd);
+
+        let entry = entry_from_pool(pool.as_ref(), &accounts, 42, Some(150.0)).unwrap();
+        assert_eq!(entry.fee_bps, 25.0);
+        assert_eq!(entry.last_update_slot, 42);
+        let expected = entry.reserve_a as f64 / 1e9 * 150.0 + entry.reserve_b as f64 / 1e6;
+        assert_eq!(entry.tvl_usd, Some(expected));
+        let spot = entry.spot_price.unwrap() * 1e9 / 1e6;
+        assert!((spot - sol_usd).abs() < 0.01, "{} vs {}", spot, sol_usd);
+
+        let unpriced = entry_from_pool(pool.as_ref(), &accounts, 42, None).unwrap();
+        assert_eq!(unpriced.tvl_usd, Some(entry.reserve_b as f64 / 1e6 * 2.0));
+    }
+
+    #[test]
+    fn base_mints_are_canonical() {
+        assert_eq!(SOL_MINT.to_string(), "So11111111111111111111111111111111111111112");
+        assert_eq!(USDC_MINT.to_string(), "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+        assert_eq!(USDT_MINT.to_string(), "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB");
+    }
+}
@@ -0,0 +1,97 @@
+use anyhow::{anyhow, Result};
+use solana_sdk::pubkey::Pubkey;
+
+/// Length of an Anchor account discriminator.
+pub const DISCRIMINATOR_LEN: usize = 8;
+
+/// First 8 bytes of `sha256("account:<name>")`, which Anchor prefixes to
+/// every account of that type.
+pub fn anchor_discriminator(account_name: &str) -> [u8; DISCRIMINATOR_LEN] {
+    anchor_hash("account", account_name)
+}
+
+/// First 8 bytes of `sha256("global:<name>")`, which Anchor prefixes to the
+/// data of that instruction.
+pub fn anchor_instruction(name: &str) -> [u8; DISCRIMINATOR_LEN] {
+    anchor_hash("global", name)
+}
+
+fn anchor_hash(namespace: &str, name: &str) -> [u8; DISCRIMINATOR_LEN] {
+    let hash = solana_sdk::hash::hash(format!("{}:{}", namespace, name).as_bytes());
+    hash.to_bytes()[..DISCRIMINATOR_LEN].try_into().unwrap()
+}
+
+/// Bounds-checked little-endian reads over raw account data.
+pub struct Layout<'a> {
+    data: &'a [u8],
+    name: &'static str,
+}
+
+impl<'a> Layout<'a> {
+    /// Fails early when the account is shorter than the documented layout.
+    pub fn new(data: &'a [u8], name: &'static str, min_len: usize) -> Result<Self> {
+        if data.len() < min_len {
+            return Err(anyhow!(
+                "{} account is {} bytes, expected at least {}",
+                name,
+                data.len(),
+                min_len
+            ));
+        }
+        Ok(Self { data, name })
+    }
+
+    fn bytes<const N: usize>(&self, offset: usize) -> Result<[u8; N]> {
+        self.data
+            .get(offset..offset + N)
+            .and_then(|slice| slice.try_into().ok())
+            .ok_or_else(|| anyhow!("{} account ends before offset {}", self.name, offset + N))
+    }
+
+    pub fn u8(&self, offset: usize) -> Result<u8> {
+        Ok(self.bytes::<1>(offset)?[0])
+    }
+
+    pub fn u16(&self, offset: usize) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes(offset)?))
+    }
+
+    pub fn u32(&self, offset: usize) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(offset)?))
+    }
+
+    pub fn i32(&self, offset: usize) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.bytes(offset)?))
+    }
+
+    pub fn u64(&self, offset: usize) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(offset)?))
+    }
+
+    pub fn i64(&self, offset: usize) -> Result<i64> {
+        Ok(i64::from_le_bytes(self.bytes(offset)?))
+    }
+
+    pub fn u128(&self, offset: usize) -> Result<u128> {
+        Ok(u128::from_le_bytes(self.bytes(offset)?))
+    }
+
+    pub fn i128(&self, offset: usize) -> Result<i128> {
+        Ok(i128::from_le_bytes(self.bytes(offset)?))
+    }
+
+    pub fn pubkey(&self, offset: usize) -> Result<Pubkey> {
+        Ok(Pubkey::new_from_array(self.bytes(offset)?))
+    }
+}
+
+/// `amount` of an SPL Token or Token-2022 account (extensions follow the
+/// base layout, so the offset is the same for both).
+pub fn token_account_amount(data: &[u8]) -> Result<u64> {
+    Layout::new(data, "token", 72)?.u64(64)
+}
+
+/// `unix_timestamp` of the Clock sysvar.
+pub fn clock_unix_timestamp(data: &[u8]) -> Result<i64> {
+    Layout::new(data, "clock", 40)?.i64(32)
+}
@@ -0,0 +1,69 @@
+use crate::pools::{decode_pool, AccountMap, Pool};
+use crate::rpc::accounts::get_multiple_accounts;
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+
+/// Pools brought up to date from one round of account fetches.
+pub struct LoadedPools {
+    /// Lowest context slot the state was read at.
+    pub slot: u64,
+    pub pools: Vec<Box<dyn Pool>>,
+    /// Every account fetched for them, vaults included.
+    pub accounts: AccountMap,
+    /// Owning program of each account in `accounts`.
+    pub owners: HashMap<Pubkey, Pubkey>,
+}
+
+/// Decodes the pools at `addresses` and loads the state they quote from.
+/// Addresses that don't exist or aren't supported pools are skipped.
+pub async fn load_pools(addresses: &[Pubkey]) -> Result<LoadedPools> {
+    let fetched = get_multiple_accounts(addresses).await?;
+    let mut pools = Vec::new();
+    for address in addresses {
+        let Some(account) = fetched.accounts.get(address) else {
+            continue;
+        };
+        match decode_pool(*address, &account.owner, &account.data) {
+            Ok(pool) => pools.push(pool),
+            Err(e) => eprintln!("[pools] Skipping {}: {}", address, e),
+        }
+    }
+    update_pools(pools).await
+}
+
+/// Fetches every account the pools depend on, plus their reserve vaults,
+/// and applies them. Pools that fail to update are dropped.
+pub async fn update_pools(mut pools: Vec<Box<dyn Pool>>) -> Result<LoadedPools> {
+    if pools.is_empty() {
+        return Ok(LoadedPools { slot: 0, pools, accounts: AccountMap::new(), owners: HashMap::new() });
+    }
+    let mut keys: Vec<Pubkey> = pools
+        .iter()
+        .flat_map(|pool| {
+            let mut keys = pool.accounts_to_update();
+            keys.extend(pool.reserve_vaults());
+            keys
+        })
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    keys.sort();
+
+    let fetched = get_multiple_accounts(&keys).await?;
+    let owners = fetched.accounts.iter().map(|(address, account)| (*address, account.owner)).collect();
+    let accounts: AccountMap = fetched
+        .accounts
+        .into_iter()
+        .map(|(address, account)| (address, account.data))
+        .collect();
+
+    pools.retain_mut(|pool| match pool.update(&accounts) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("[pools] Skipping {} pool {}: {}", pool.venue().label(), pool.address(), e);
+            false
+        }
+    });
+    Ok(LoadedPools { slot: fetched.slot, pools, accounts, owners })
+}
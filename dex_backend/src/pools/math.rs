@@ -0,0 +1,80 @@
+use anyhow::{anyhow, Result};
+use ethnum::U256;
+
+/// `a * b / d` rounded down, with a 256-bit intermediate.
+pub fn mul_div_floor(a: u128, b: u128, d: u128) -> Result<u128> {
+    mul_div(a, b, d, false)
+}
+
+/// `a * b / d` rounded up, with a 256-bit intermediate.
+pub fn mul_div_ceil(a: u128, b: u128, d: u128) -> Result<u128> {
+    mul_div(a, b, d, true)
+}
+
+fn mul_div(a: u128, b: u128, d: u128, round_up: bool) -> Result<u128> {
+    if d == 0 {
+        return Err(anyhow!("division by zero"));
+    }
+    let product = U256::from(a) * U256::from(b);
+    let d = U256::from(d);
+    let mut quotient = product / d;
+    if round_up && product % d != 0 {
+        quotient += 1;
+    }
+    narrow(quotient)
+}
+
+/// `numerator / denominator` rounded up, on 256-bit operands.
+pub fn div_ceil_256(numerator: U256, denominator: U256) -> Result<U256> {
+    if denominator == 0 {
+        return Err(anyhow!("division by zero"));
+    }
+    let quotient = numerator / denominator;
+    Ok(if numerator % denominator != 0 { quotient + 1 } else { quotient })
+}
+
+pub fn narrow(value: U256) -> Result<u128> {
+    u128::try_from(value).map_err(|_| anyhow!("intermediate amount overflows u128"))
+}
+
+pub fn to_u64(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| anyhow!("amount overflows u64"))
+}
+
+/// The ceiling division used by the Raydium AMM v4 program (inherited from
+/// SPL token-swap). Unlike a plain ceiling it rounds a zero quotient to
+/// nearest, so tiny fees can round down to nothing.
+pub fn raydium_ceil_div(numerator: u128, denominator: u128) -> Result<u128> {
+    if denominator == 0 {
+        return Err(anyhow!("division by zero"));
+    }
+    let quotient = numerator / denominator;
+    if quotient == 0 {
+        return Ok(if numerator.saturating_mul(2) >= denominator && numerator != 0 { 1 } else { 0 });
+    }
+    Ok(if numerator.is_multiple_of(denominator) { quotient } else { quotient + 1 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mul_div_rounds_in_the_requested_direction() {
+        assert_eq!(mul_div_floor(10, 10, 3).unwrap(), 33);
+        assert_eq!(mul_div_ceil(10, 10, 3).unwrap(), 34);
+        assert_eq!(mul_div_ceil(10, 9, 3).unwrap(), 30);
+        assert_eq!(mul_div_floor(u128::MAX, u128::MAX, u128::MAX).unwrap(), u128::MAX);
+        assert!(mul_div_floor(u128::MAX, 2, 1).is_err());
+        assert!(mul_div_floor(1, 1, 0).is_err());
+    }
+
+    #[test]
+    fn raydium_ceil_div_rounds_zero_quotients_to_nearest() {
+        assert_eq!(raydium_ceil_div(7, 2).unwrap(), 4);
+        assert_eq!(raydium_ceil_div(8, 2).unwrap(), 4);
+        assert_eq!(raydium_ceil_div(4, 10).unwrap(), 0);
+        assert_eq!(raydium_ceil_div(5, 10).unwrap(), 1);
+        assert_eq!(raydium_ceil_div(0, 10).unwrap(), 0);
+    }
+}
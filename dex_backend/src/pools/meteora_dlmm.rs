@@ -0,0 +1,503 @@
+use crate::pools::layout::{anchor_instruction, clock_unix_timestamp, Layout};
+use crate::pools::math::{narrow, to_u64};
+use crate::pools::{
+    instruction_data, sells_first_mint, AccountMap, Pool, PoolQuote, SwapAccounts, Venue, TOKEN_2022_PROGRAM, TOKEN_PROGRAM,
+};
+use anyhow::{anyhow, Result};
+use ethnum::U256;
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::sysvar;
+use std::collections::BTreeMap;
+
+pub const PROGRAM_ID: Pubkey = pubkey!("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo");
+
+/// `LbPair` size, discriminator included.
+pub const POOL_LEN: usize = 904;
+pub const TOKEN_X_MINT_OFFSET: usize = 88;
+pub const TOKEN_Y_MINT_OFFSET: usize = 120;
+const BIN_ARRAY_LEN: usize = 10136;
+const BINS_PER_ARRAY: i32 = 70;
+const BIN_LEN: usize = 144;
+/// Bin arrays loaded on each side of the active one.
+const BIN_ARRAY_RADIUS: i64 = 3;
+/// The swap instruction takes at most this many bin arrays.
+const SWAP_BIN_ARRAYS: usize = 3;
+
+const BASIS_POINT_MAX: u64 = 10_000;
+const FEE_PRECISION: u128 = 1_000_000_000;
+const MAX_FEE_RATE: u128 = 100_000_000;
+/// Bin prices are Q64.64.
+const SCALE_OFFSET: u32 = 64;
+
+#[derive(Debug, Clone, Copy)]
+struct Bin {
+    amount_x: u64,
+    amount_y: u64,
+    price: u128,
+}
+
+/// The dynamic-fee inputs of `LbPair`, updated as the swap walks bins.
+#[derive(Debug, Clone, Copy)]
+struct FeeParameters {
+    base_factor: u16,
+    filter_period: u16,
+    decay_period: u16,
+    reduction_factor: u16,
+    variable_fee_control: u32,
+    max_volatility_accumulator: u32,
+    base_fee_power_factor: u8,
+    volatility_accumulator: u32,
+    volatility_reference: u32,
+    index_reference: i32,
+    last_update_timestamp: i64,
+    bin_step: u16,
+}
+
+impl FeeParameters {
+    /// Decays the reference volatility by the time since the last swap.
+    fn update_references(&mut self, active_id: i32, now: i64) {
+        let elapsed = now.saturating_sub(self.last_update_timestamp);
+        if elapsed >= self.filter_period as i64 {
+            self.index_reference = active_id;
+            self.volatility_reference = if elapsed < self.decay_period as i64 {
+                (self.volatility_accumulator as u64 * self.reduction_factor as u64 / BASIS_POINT_MAX) as u32
+            } else {
+                0
+            };
+        }
+    }
+
+    fn update_volatility_accumulator(&mut self, active_id: i32) {
+        let delta_id = (self.index_reference as i64 - active_id as i64).unsigned_abs();
+        let accumulator = self.volatility_reference as u64 + delta_id * BASIS_POINT_MAX;
+        self.volatility_accumulator = accumulator.min(self.max_volatility_accumulator as u64) as u32;
+    }
+
+    /// The fee tier, before the volatility surcharge.
+    fn base_fee_rate(&self) -> u128 {
+        self.base_factor as u128 * self.bin_step as u128 * 10 * 10u128.pow(self.base_fee_power_factor as u32)
+    }
+
+    fn total_fee_rate(&self) -> u128 {
+        let base = self.base_fee_rate();
+        let variable = if self.variable_fee_control > 0 {
+            let square = (self.volatility_accumulator as u128 * self.bin_step as u128).pow(2);
+            (self.variable_fee_control as u128 * square).div_ceil(100_000_000_000)
+        } else {
+            0
+        };
+        (base + variable).min(MAX_FEE_RATE)
+    }
+
+    /// Fee to add on top of a net amount.
+    fn fee_on_top(&self, amount: u128) -> u128 {
+        let rate = self.total_fee_rate();
+        (amount * rate).div_ceil(FEE_PRECISION - rate)
+    }
+
+    /// Fee contained in a gross amount.
+    fn fee_included(&self, amount: u128) -> u128 {
+        (amount * self.total_fee_rate()).div_ceil(FEE_PRECISION)
+    }
+}
+
+/// Where a swap leaves the pool: the new active bin, the fee state after the
+/// volatility updates, and every bin whose balances changed.
+struct DlmmPosition {
+    active_id: i32,
+    fees: FeeParameters,
+    bins: Vec<(i32, Bin)>,
+}
+
+/// Meteora's liquidity-book pool: constant-sum bins at fixed price steps.
+#[derive(Debug, Clone)]
+pub struct MeteoraDlmm {
+    address: Pubkey,
+    fees: FeeParameters,
+    active_id: i32,
+    status: u8,
+    token_x_mint: Pubkey,
+    token_y_mint: Pubkey,
+    reserve_x: Pubkey,
+    reserve_y: Pubkey,
+    oracle: Pubkey,
+    token_x_program: Pubkey,
+    token_y_program: Pubkey,
+    bins: BTreeMap<i32, Bin>,
+    /// Bin array indexes that exist on chain.
+    loaded_arrays: Vec<i64>,
+    /// Bin ids covered by the requested arrays, `[lower, upper)`.
+    covered: (i32, i32),
+    now: Option<i64>,
+}
+
+impl MeteoraDlmm {
+    pub fn decode(address: Pubkey, data: &[u8]) -> Result<Self> {
+        let layout = Layout::new(data, "Meteora DLMM", POOL_LEN)?;
+        let token_program = |flag: u8| if flag == 1 { TOKEN_2022_PROGRAM } else { TOKEN_PROGRAM };
+        Ok(Self {
+            address,
+            fees: FeeParameters {
+                base_factor: layout.u16(8)?,
+                filter_period: layout.u16(10)?,
+                decay_period: layout.u16(12)?,
+                reduction_factor: layout.u16(14)?,
+                variable_fee_control: layout.u32(16)?,
+                max_volatility_accumulator: layout.u32(20)?,
+                base_fee_power_factor: layout.u8(34)?,
+                volatility_accumulator: layout.u32(40)?,
+                volatility_reference: layout.u32(44)?,
+                index_reference: layout.i32(48)?,
+                last_update_timestamp: layout.i64(56)?,
+                bin_step: layout.u16(80)?,
+            },
+            active_id: layout.i32(76)?,
+            status: layout.u8(82)?,
+            token_x_mint: layout.pubkey(TOKEN_X_MINT_OFFSET)?,
+            token_y_mint: layout.pubkey(TOKEN_Y_MINT_OFFSET)?,
+            reserve_x: layout.pubkey(152)?,
+            reserve_y: layout.pubkey(184)?,
+            oracle: layout.pubkey(552)?,
+            token_x_program: token_program(layout.u8(880)?),
+            token_y_program: token_program(layout.u8(881)?),
+            bins: BTreeMap::new(),
+            loaded_arrays: Vec::new(),
+            covered: (0, 0),
+            now: None,
+        })
+    }
+
+    fn array_index(bin_id: i32) -> i64 {
+        bin_id.div_euclid(BINS_PER_ARRAY) as i64
+    }
+
+    fn array_indexes(&self) -> Vec<i64> {
+        let active = Self::array_index(self.active_id);
+        (active - BIN_ARRAY_RADIUS..=active + BIN_ARRAY_RADIUS).collect()
+    }
+
+    fn bin_array_address(&self, index: i64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"bin_array", self.address.as_ref(), &index.to_le_bytes()],
+            &PROGRAM_ID,
+        )
+        .0
+    }
+
+    fn event_authority() -> Pubkey {
+        Pubkey::find_program_address(&[b"__event_authority"], &PROGRAM_ID).0
+    }
+
+    fn decode_bin_array(&mut self, index: i64, data: &[u8]) -> Result<()> {
+        let layout = Layout::new(data, "Meteora bin array", BIN_ARRAY_LEN)?;
+        if layout.i64(8)? != index || layout.pubkey(24)? != self.address {
+            return Err(anyhow!("bin array {} does not belong to {}", index, self.address));
+        }
+        let lower_bin = index as i32 * BINS_PER_ARRAY;
+        for i in 0..BINS_PER_ARRAY {
+            let offset = 56 + i as usize * BIN_LEN;
+            let bin = Bin {
+                amount_x: layout.u64(offset)?,
+                amount_y: layout.u64(offset + 8)?,
+                price: layout.u128(offset + 16)?,
+            };
+            if bin.amount_x > 0 || bin.amount_y > 0 {
+                self.bins.insert(lower_bin + i, bin);
+            }
+        }
+        Ok(())
+    }
+
+    /// Walks bins away from the active one, mirroring the program's swap
+    /// loop including its per-bin volatility update.
+    fn quote(&self, input_mint: &Pubkey, amount: u64, exact_in: bool) -> Result<(PoolQuote, DlmmPosition)> {
+        let swap_for_y = sells_first_mint(self.mints(), input_mint)?;
+        if self.status != 0 {
+            return Err(anyhow!("Meteora pool {} is disabled", self.address));
+        }
+
+        let mut fees = self.fees;
+        let mut active_id = self.active_id;
+        let now = self.now.unwrap_or(fees.last_update_timestamp);
+        fees.update_references(active_id, now);
+
+        let mut remaining = amount as u128;
+        let mut calculated: u128 = 0;
+        let mut fee_total: u128 = 0;
+        let mut touched = Vec::new();
+        while remaining > 0 {
+            if active_id < self.covered.0 || active_id >= self.covered.1 {
+                return Err(anyhow!("not enough liquidity in the loaded bin arrays"));
+            }
+            fees.update_volatility_accumulator(active_id);
+
+            if let Some(&bin) = self.bins.get(&active_id) {
+                let max_out = if swap_for_y { bin.amount_y } else { bin.amount_x } as u128;
+                if max_out > 0 {
+                    let price = bin.price;
+                    if price == 0 {
+                        return Err(anyhow!("bin {} has liquidity but no price", active_id));
+                    }
+                    let max_in = amount_in_for(max_out, price, swap_for_y, true)?;
+                    if exact_in {
+                        let max_fee = fees.fee_on_top(max_in);
+                        if remaining > max_in + max_fee {
+                            remaining -= max_in + max_fee;
+                            calculated += max_out;
+                            fee_total += max_fee;
+                            touched.push((active_id, traded(bin, swap_for_y, max_in, max_out)?));
+                        } else {
+                            let fee = fees.fee_included(remaining);
+                            let out = amount_out_for(remaining - fee, price, swap_for_y)?.min(max_out);
+                            calculated += out;
+                            fee_total += fee;
+                            touched.push((active_id, traded(bin, swap_for_y, remaining - fee, out)?));
+                            remaining = 0;
+                        }
+                    } else if remaining >= max_out {
+                        let max_fee = fees.fee_on_top(max_in);
+                        calculated += max_in + max_fee;
+                        fee_total += max_fee;
+                        remaining -= max_out;
+                        touched.push((active_id, traded(bin, swap_for_y, max_in, max_out)?));
+                    } else {
+                        let amount_in = amount_in_for(remaining, price, swap_for_y, true)?;
+                        let fee = fees.fee_on_top(amount_in);
+                        calculated += amount_in + fee;
+                        fee_total += fee;
+                        touched.push((active_id, traded(bin, swap_for_y, amount_in, remaining)?));
+                        remaining = 0;
+                    }
+                }
+            }
+
+            if remaining > 0 {
+                active_id += if swap_for_y { -1 } else { 1 };
+            }
+        }
+
+        let (amount_in, amount_out) = if exact_in {
+            (amount as u128, calculated)
+        } else {
+            (calculated, amount as u128)
+        };
+        let quote = PoolQuote {
+            amount_in: to_u64(amount_in)?,
+            amount_out: to_u64(amount_out)?,
+            fee_amount: to_u64(fee_total)?,
+            fee_mint: *input_mint,
+        };
+        fees.last_update_timestamp = now;
+        Ok((quote, DlmmPosition { active_id, fees, bins: touched }))
+    }
+}
+
+/// A bin after it takes `amount_in` (fee excluded) and pays `amount_out`.
+fn traded(bin: Bin, swap_for_y: bool, amount_in: u128, amount_out: u128) -> Result<Bin> {
+    let (amount_in, amount_out) = (to_u64(amount_in)?, to_u64(amount_out)?);
+    Ok(if swap_for_y {
+        Bin { amount_x: bin.amount_x + amount_in, amount_y: bin.amount_y - amount_out, ..bin }
+    } else {
+        Bin { amount_x: bin.amount_x - amount_out, amount_y: bin.amount_y + amount_in, ..bin }
+    })
+}
+
+/// Input that buys `amount_out` from a bin at `price` (Y per X, Q64.64).
+fn amount_in_for(amount_out: u128, price: u128, swap_for_y: bool, round_up: bool) -> Result<u128> {
+    if swap_for_y {
+        shl_div(amount_out, price, round_up)
+    } else {
+        mul_shr(amount_out, price, round_up)
+    }
+}
+
+/// Output a bin at `price` pays for `amount_in`, rounded down.
+fn amount_out_for(amount_in: u128, price: u128, swap_for_y: bool) -> Result<u128> {
+    if swap_for_y {
+        mul_shr(amount_in, price, false)
+    } else {
+        shl_div(amount_in, price, false)
+    }
+}
+
+fn mul_shr(x: u128, y: u128, round_up: bool) -> Result<u128> {
+    let product = U256::from(x) * U256::from(y);
+    let mut result = product >> SCALE_OFFSET;
+    if round_up && product & ((U256::ONE << SCALE_OFFSET) - 1) != 0 {
+        result += 1;
+    }
+    narrow(result)
+}
+
+fn shl_div(x: u128, y: u128, round_up: bool) -> Result<u128> {
+    let numerator = U256::from(x) << SCALE_OFFSET;
+    let denominator = U256::from(y);
+    let mut result = numerator / denominator;
+    if round_up && numerator % denominator != 0 {
+        result += 1;
+    }
+    narrow(result)
+}
+
+impl Pool for MeteoraDlmm {
+    fn venue(&self) -> Venue {
+        Venue::MeteoraDlmm
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Pool> {
+        Box::new(self.clone())
+    }
+
+    fn address(&self) -> Pubkey {
+        self.address
+    }
+
+    fn mints(&self) -> [Pubkey; 2] {
+        [self.token_x_mint, self.token_y_mint]
+    }
+
+    fn reserve_vaults(&self) -> [Pubkey; 2] {
+        [self.reserve_x, self.reserve_y]
+    }
+
+    fn fee_bps(&self) -> f64 {
+        self.fees.base_fee_rate() as f64 * 10_000.0 / FEE_PRECISION as f64
+    }
+
+    fn accounts_to_update(&self) -> Vec<Pubkey> {
+        let mut accounts = vec![self.address, sysvar::clock::ID];
+        accounts.extend(self.array_indexes().into_iter().map(|index| self.bin_array_address(index)));
+        accounts
+    }
+
+    fn update(&mut self, accounts: &AccountMap) -> Result<()> {
+        if let Some(data) = accounts.get(&self.address) {
+            *self = Self { now: self.now, ..Self::decode(self.address, data)? };
+        }
+        if let Some(data) = accounts.get(&sysvar::clock::ID) {
+            self.now = Some(clock_unix_timestamp(data)?);
+        }
+
+        let indexes = self.array_indexes();
+        self.covered = (
+            indexes[0] as i32 * BINS_PER_ARRAY,
+            (indexes[indexes.len() - 1] as i32 + 1) * BINS_PER_ARRAY,
+        );
+        self.bins.clear();
+        self.loaded_arrays.clear();
+        for index in indexes {
+            if let Some(data) = accounts.get(&self.bin_array_address(index)) {
+                self.decode_bin_array(index, data)?;
+                self.loaded_arrays.push(index);
+            }
+        }
+        Ok(())
+    }
+
+    fn quote_exact_in(&self, input_mint: &Pubkey, amount_in: u64) -> Result<PoolQuote> {
+        self.quote(input_mint, amount_in, true).map(|(quote, _)| quote)
+    }
+
+    fn quote_exact_out(&self, input_mint: &Pubkey, amount_out: u64) -> Result<PoolQuote> {
+        self.quote(input_mint, amount_out, false).map(|(quote, _)| quote)
+    }
+
+    fn apply_swap(&mut self, input_mint: &Pubkey, amount_in: u64) -> Result<PoolQuote> {
+        let (quote, position) = self.quote(input_mint, amount_in, true)?;
+        self.active_id = position.active_id;
+        self.fees = position.fees;
+        self.bins.extend(position.bins);
+        Ok(quote)
+    }
+
+    fn swap_data(&self, _input_mint: &Pubkey, amount_in: u64, minimum_out: u64) -> Result<Vec<u8>> {
+        Ok(instruction_data(
+            &anchor_instruction("swap"),
+            &[&amount_in.to_le_bytes(), &minimum_out.to_le_bytes()],
+        ))
+    }
+
+    fn accounts_for_swap(&self, swap: &SwapAccounts) -> Result<Vec<AccountMeta>> {
+        let swap_for_y = sells_first_mint(self.mints(), &swap.input_mint)?;
+        let active = Self::array_index(self.active_id);
+        let mut arrays: Vec<i64> = self
+            .loaded_arrays
+            .iter()
+            .copied()
+            .filter(|index| if swap_for_y { *index <= active } else { *index >= active })
+            .collect();
+        if swap_for_y {
+            arrays.reverse();
+        }
+        if arrays.is_empty() {
+            return Err(anyhow!("no bin arrays loaded for {}", self.address));
+        }
+
+        let mut metas = vec![
+            AccountMeta::new(self.address, false),
+            // Optional bitmap extension and host fee account: the program id
+            // stands in for "none".
+            AccountMeta::new_readonly(PROGRAM_ID, false),
+            AccountMeta::new(self.reserve_x, false),
+            AccountMeta::new(self.reserve_y, false),
+            AccountMeta::new(swap.user_source, false),
+            AccountMeta::new(swap.user_destination, false),
+            AccountMeta::new_readonly(self.token_x_mint, false),
+            AccountMeta::new_readonly(self.token_y_mint, false),
+            AccountMeta::new(self.oracle, false),
+            AccountMeta::new_readonly(PROGRAM_ID, false),
+            AccountMeta::new_readonly(swap.user, true),
+            AccountMeta::new_readonly(self.token_x_program, false),
+            AccountMeta::new_readonly(self.token_y_program, false),
+            AccountMeta::new_readonly(Self::event_authority(), false),
+            AccountMeta::new_readonly(PROGRAM_ID, false),
+        ];
+        metas.extend(
+            arrays
+                .into_iter()
+                .take(SWAP_BIN_ARRAYS)
+                .map(|index| AccountMeta::new(self.bin_array_address(index), false)),
+        );
+        Ok(metas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::{load_snapshot, pool_from_snapshot};
+    use std::str::FromStr;
+
+    const POOL: &str = "5rCf1DM8LjKTw4YqhnoLcngyZYeNnQqztScTogYHAS6";
+    const SOL: &str = "So11111111111111111111111111111111111111112";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn mint(s: &str) -> Pubkey {
+        Pubkey::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn fee_rate_grows_with_volatility() {
+        let (accounts, _) = load_snapshot("meteora_dlmm_sol_usdc");
+        let address = Pubkey::from_str(POOL).unwrap();
+        let pair = MeteoraDlmm::decode(address, &accounts[&address]).unwrap();
+
+        let mut fees = pair.fees;
+        let calm = fees.total_fee_rate();
+        fees.update_volatility_accumulator(pair.active_id - 5);
+        assert!(fees.total_fee_rate() > calm);
+    }
+
+    #[test]
+    fn quotes_walk_bins() {
+        let pool = pool_from_snapshot("meteora_dlmm_sol_usdc", POOL);
+
+        let sell = pool.quote_exact_in(&mint(SOL), 400_000_000_000).unwrap();
+        assert_eq!((sell.amount_out, sell.fee_amount), (59_854_163_377, 402_144_918));
+
+        let buy = pool.quote_exact_out(&mint(USDC), 200_000_000_000).unwrap();
+        assert_eq!((buy.amount_in, buy.fee_amount), (30_057_697_660, 30_142_264));
+    }
+}
@@ -0,0 +1,275 @@
+use crate::pools::indexer::{SOL_MINT, STABLE_MINTS};
+use crate::pools::registry::{pool_registry, PoolEntry};
+use crate::pools::Venue;
+use crate::rpc::mint_decimals::get_mint_decimals;
+use crate::PRICE_ORACLE;
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+use tokio::time::{interval, Duration};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(15);
+/// Pools shallower than this are too cheap to push around to price from.
+const MIN_SOURCE_LIQUIDITY_USD: f64 = 1_000.0;
+/// Sources further than this from the liquidity-weighted median price are
+/// rejected as outliers.
+const MAX_DEVIATION: f64 = 0.05;
+/// Liquidity behind a price at which its confidence stops growing.
+const FULL_CONFIDENCE_LIQUIDITY_USD: f64 = 1_000_000.0;
+
+/// One pool a price was read from.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceSource {
+    pub pool: String,
+    pub venue: Venue,
+    /// The other side of the pool, which the price is converted through.
+    pub quote_mint: String,
+    pub price_usd: f64,
+    pub liquidity_usd: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MintPrice {
+    pub mint: String,
+    pub price_usd: f64,
+    /// 0 to 1: the share of the liquidity that agreed on the price, scaled
+    /// down while that liquidity is under [`FULL_CONFIDENCE_LIQUIDITY_USD`].
+    /// The stablecoin anchors are 1.
+    pub confidence: f64,
+    /// The pools the price is averaged over, deepest first.
+    pub sources: Vec<PriceSource>,
+    /// Pools left out as outliers.
+    pub rejected: usize,
+    /// Oldest state among the sources.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slot: Option<u64>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// USD prices of every indexed mint, recomputed from the pool registry
+/// every [`REFRESH_INTERVAL`].
+#[derive(Default)]
+pub struct PriceOracle {
+    prices: RwLock<HashMap<String, MintPrice>>,
+}
+
+pub fn price_oracle() -> &'static PriceOracle {
+    PRICE_ORACLE.get_or_init(PriceOracle::default)
+}
+
+impl PriceOracle {
+    pub fn price(&self, mint: &str) -> Option<MintPrice> {
+        self.prices.read().unwrap().get(mint).cloned()
+    }
+
+    fn replace(&self, prices: HashMap<String, MintPrice>) {
+        *self.prices.write().unwrap() = prices;
+    }
+}
+
+pub fn spawn_price_oracle() {
+    tokio::spawn(async move {
+        let mut ticker = interval(REFRESH_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let entries = pool_registry().entries();
+            let mints: HashSet<&String> = entries.iter().flat_map(|e| [&e.mint_a, &e.mint_b]).collect();
+            // Decimals are cached after the first round.
+            let decimals: HashMap<String, u8> = join_all(mints.into_iter().map(|mint| async move {
+                get_mint_decimals(mint).await.ok().map(|decimals| (mint.clone(), decimals))
+            }))
+            .await
+            .into_iter()
+            .flatten()
+            .collect();
+
+            let prices = compute_prices(&entries, &decimals, Utc::now());
+            println!("[oracle] Priced {} mints from {} pools", prices.len(), entries.len());
+            price_oracle().replace(prices);
+        }
+    });
+}
+
+/// Prices the stablecoins at $1, SOL from its stablecoin pools, then every
+/// other mint from its pools against any of those three.
+pub fn compute_prices(
+    entries: &[PoolEntry],
+    decimals: &HashMap<String, u8>,
+    timestamp: DateTime<Utc>,
+) -> HashMap<String, MintPrice> {
+    let mut by_mint: HashMap<&str, Vec<&PoolEntry>> = HashMap::new();
+    for entry in entries {
+        by_mint.entry(&entry.mint_a).or_default().push(entry);
+        by_mint.entry(&entry.mint_b).or_default().push(entry);
+    }
+
+    let mut prices: HashMap<String, MintPrice> = STABLE_MINTS
+        .iter()
+        .map(|(stable, _)| {
+            let mint = stable.to_string();
+            let anchor = MintPrice {
+                mint: mint.clone(),
+                price_usd: 1.0,
+                confidence: 1.0,
+                sources: Vec::new(),
+                rejected: 0,
+                slot: None,
+                timestamp,
+            };
+            (mint, anchor)
+        })
+        .collect();
+    let sol = SOL_MINT.to_string();
+    if let Some(price) = price_mint(&sol, &by_mint, &prices, decimals, timestamp) {
+        prices.insert(sol, price);
+    }
+
+    let anchors = prices.clone();
+    for mint in by_mint.keys() {
+        if anchors.contains_key(*mint) {
+            continue;
+        }
+        if let Some(price) = price_mint(mint, &by_mint, &anchors, decimals, timestamp) {
+            prices.insert(mint.to_string(), price);
+        }
+    }
+    prices
+}
+
+fn price_mint(
+    mint: &str,
+    by_mint: &HashMap<&str, Vec<&PoolEntry>>,
+    anchors: &HashMap<String, MintPrice>,
+    decimals: &HashMap<String, u8>,
+    timestamp: DateTime<Utc>,
+) -> Option<MintPrice> {
+    let mut candidates: Vec<(PriceSource, u64)> = by_mint
+        .get(mint)?
+        .iter()
+        .filter_map(|entry| {
+            let liquidity_usd = entry.tvl_usd.filter(|tvl| *tvl >= MIN_SOURCE_LIQUIDITY_USD)?;
+            let spot = entry.spot_price.filter(|spot| *spot > 0.0)?;
+            let scale = |m: &String| decimals.get(m).map(|d| 10f64.powi(*d as i32));
+            // UI units of mint_b per UI unit of mint_a.
+            let price_a = spot * scale(&entry.mint_a)? / scale(&entry.mint_b)?;
+            let (quote_mint, price_in_quote) =
+                if entry.mint_a == mint { (&entry.mint_b, price_a) } else { (&entry.mint_a, 1.0 / price_a) };
+            let quote_usd = anchors.get(quote_mint)?.price_usd;
+            let source = PriceSource {
+                pool: entry.address.clone(),
+                venue: entry.venue,
+                quote_mint: quote_mint.clone(),
+                price_usd: price_in_quote * quote_usd,
+                liquidity_usd,
+            };
+            Some((source, entry.last_update_slot))
+        })
+        .collect();
+    if candidates.is_empty() {
+        return None;
+    }
+
+    let total_liquidity: f64 = candidates.iter().map(|(source, _)| source.liquidity_usd).sum();
+    let median = weighted_median(&candidates);
+    let count = candidates.len();
+    candidates.retain(|(source, _)| (source.price_usd - median).abs() <= median * MAX_DEVIATION);
+    candidates.sort_by(|(x, _), (y, _)| y.liquidity_usd.total_cmp(&x.liquidity_usd));
+
+    let kept_liquidity: f64 = candidates.iter().map(|(source, _)| source.liquidity_usd).sum();
+    let price_usd =
+        candidates.iter().map(|(source, _)| source.price_usd * source.liquidity_usd).sum::<f64>() / kept_liquidity;
+    let confidence = kept_liquidity / total_liquidity * (kept_liquidity / FULL_CONFIDENCE_LIQUIDITY_USD).min(1.0);
+    Some(MintPrice {
+        mint: mint.to_string(),
+        price_usd,
+        confidence,
+        rejected: count - candidates.len(),
+        slot: candidates.iter().map(|(_, slot)| *slot).min(),
+        sources: candidates.into_iter().map(|(source, _)| source).collect(),
+        timestamp,
+    })
+}
+
+/// The price half the liquidity sits at or below.
+fn weighted_median(candidates: &[(PriceSource, u64)]) -> f64 {
+    let mut sorted: Vec<&PriceSource> = candidates.iter().map(|(source, _)| source).collect();
+    sorted.sort_by(|x, y| x.price_usd.total_cmp(&y.price_usd));
+    let half = sorted.iter().map(|source| source.liquidity_usd).sum::<f64>() / 2.0;
+    let mut cumulative = 0.0;
+    for source in &sorted {
+        cumulative += source.liquidity_usd;
+        if cumulative >= half {
+            return source.price_usd;
+        }
+    }
+    sorted.last().map_or(0.0, |source| source.price_usd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::indexer::USDC_MINT;
+
+    const BONK: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+
+    /// `price` is in UI units of `mint_b` per `mint_a`.
+    fn entry(address: &str, mint_a: &str, mint_b: &str, price: f64, tvl_usd: f64) -> PoolEntry {
+        let raw = |mint: &str| if mint == BONK { 1e5 } else if mint == SOL_MINT.to_string() { 1e9 } else { 1e6 };
+        PoolEntry {
+            address: address.to_string(),
+            venue: Venue::Whirlpool,
+            mint_a: mint_a.to_string(),
+            mint_b: mint_b.to_string(),
+            reserve_a: 1,
+            reserve_b: 1,
+            fee_bps: 4.0,
+            spot_price: Some(price * raw(mint_b) / raw(mint_a)),
+            tvl_usd: Some(tvl_usd),
+            last_update_slot: address.len() as u64,
+        }
+    }
+
+    fn decimals() -> HashMap<String, u8> {
+        HashMap::from([(SOL_MINT.to_string(), 9), (USDC_MINT.to_string(), 6), (BONK.to_string(), 5)])
+    }
+
+    #[test]
+    fn prices_are_liquidity_weighted_without_outliers() {
+        let (sol, usdc) = (SOL_MINT.to_string(), USDC_MINT.to_string());
+        let entries = vec![
+            entry("deep", &sol, &usdc, 150.0, 3_000_000.0),
+            entry("mid", &usdc, &sol, 1.0 / 152.0, 1_000_000.0),
+            entry("skewed", &sol, &usdc, 190.0, 500_000.0),
+            entry("dust", &sol, &usdc, 10.0, 100.0),
+        ];
+        let prices = compute_prices(&entries, &decimals(), Utc::now());
+
+        let price = &prices[&sol];
+        assert!((price.price_usd - 150.5).abs() < 1e-6, "{}", price.price_usd);
+        assert_eq!(price.rejected, 1);
+        assert_eq!(price.sources.iter().map(|s| s.pool.as_str()).collect::<Vec<_>>(), ["deep", "mid"]);
+        assert!((price.confidence - 4.0 / 4.5).abs() < 1e-9);
+        assert_eq!(price.slot, Some(3));
+        assert_eq!(prices[&usdc].price_usd, 1.0);
+    }
+
+    #[test]
+    fn other_mints_are_priced_through_sol() {
+        let (sol, usdc) = (SOL_MINT.to_string(), USDC_MINT.to_string());
+        let entries = vec![
+            entry("sol-usdc", &sol, &usdc, 200.0, 2_000_000.0),
+            entry("bonk-sol", BONK, &sol, 0.0000001, 100_000.0),
+        ];
+        let prices = compute_prices(&entries, &decimals(), Utc::now());
+
+        let bonk = &prices[BONK];
+        assert!((bonk.price_usd - 0.00002).abs() < 1e-12, "{}", bonk.price_usd);
+        assert_eq!(bonk.sources[0].quote_mint, sol);
+        assert!((bonk.confidence - 0.1).abs() < 1e-9);
+        assert!(!compute_prices(&entries, &HashMap::new(), Utc::now()).contains_key(BONK));
+    }
+}
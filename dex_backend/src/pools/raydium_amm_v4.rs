@@ -0,0 +1,319 @@
+use crate::pools::layout::{token_account_amount, Layout};
+use crate::pools::math::{raydium_ceil_div, to_u64};
+use crate::pools::{
+    instruction_data, sells_first_mint, AccountMap, Pool, PoolQuote, SwapAccounts, Venue, TOKEN_PROGRAM,
+};
+use anyhow::{anyhow, Result};
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+pub const PROGRAM_ID: Pubkey = pubkey!("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8");
+/// PDA of the "amm authority" seed; the same for every pool.
+const AUTHORITY: Pubkey = pubkey!("5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1");
+
+/// `AmmInfo` size.
+pub const POOL_LEN: usize = 752;
+/// Offsets of the mints, for `getProgramAccounts` filters.
+pub const COIN_MINT_OFFSET: usize = 400;
+pub const PC_MINT_OFFSET: usize = 432;
+/// OpenBook `MarketState` size, including its 5 + 7 padding bytes.
+const MARKET_LEN: usize = 388;
+/// OpenBook `OpenOrders` size.
+const OPEN_ORDERS_LEN: usize = 3228;
+
+/// Status values that let the pool trade and that route part of the
+/// liquidity through OpenBook orders.
+const STATUS_INITIALIZED: u64 = 1;
+const STATUS_ORDER_BOOK_ONLY: u64 = 5;
+const STATUS_SWAP_ONLY: u64 = 6;
+const STATUS_WAITING_TRADE: u64 = 7;
+
+/// OpenBook accounts the swap instruction still expects.
+#[derive(Debug, Clone, Copy)]
+struct MarketAccounts {
+    bids: Pubkey,
+    asks: Pubkey,
+    event_queue: Pubkey,
+    coin_vault: Pubkey,
+    pc_vault: Pubkey,
+    vault_signer: Pubkey,
+}
+
+/// Raydium's constant-product AMM ("coin" is the base mint, "pc" the quote).
+#[derive(Debug, Clone)]
+pub struct RaydiumAmmV4 {
+    address: Pubkey,
+    status: u64,
+    swap_fee_numerator: u64,
+    swap_fee_denominator: u64,
+    need_take_pnl_coin: u64,
+    need_take_pnl_pc: u64,
+    coin_vault: Pubkey,
+    pc_vault: Pubkey,
+    coin_mint: Pubkey,
+    pc_mint: Pubkey,
+    open_orders: Pubkey,
+    market: Pubkey,
+    market_program: Pubkey,
+    target_orders: Pubkey,
+    coin_reserve: u64,
+    pc_reserve: u64,
+    market_accounts: Option<MarketAccounts>,
+}
+
+impl RaydiumAmmV4 {
+    pub fn decode(address: Pubkey, data: &[u8]) -> Result<Self> {
+        let layout = Layout::new(data, "Raydium AMM v4", POOL_LEN)?;
+        Ok(Self {
+            address,
+            status: layout.u64(0)?,
+            swap_fee_numerator: layout.u64(176)?,
+            swap_fee_denominator: layout.u64(184)?,
+            need_take_pnl_coin: layout.u64(192)?,
+            need_take_pnl_pc: layout.u64(200)?,
+            coin_vault: layout.pubkey(336)?,
+            pc_vault: layout.pubkey(368)?,
+            coin_mint: layout.pubkey(COIN_MINT_OFFSET)?,
+            pc_mint: layout.pubkey(PC_MINT_OFFSET)?,
+            open_orders: layout.pubkey(496)?,
+            market: layout.pubkey(528)?,
+            market_program: layout.pubkey(560)?,
+            target_orders: layout.pubkey(592)?,
+            coin_reserve: 0,
+            pc_reserve: 0,
+            market_accounts: None,
+        })
+    }
+
+    fn uses_order_book(&self) -> bool {
+        matches!(self.status, STATUS_INITIALIZED | STATUS_ORDER_BOOK_ONLY)
+    }
+
+    fn can_swap(&self) -> bool {
+        matches!(self.status, STATUS_INITIALIZED | STATUS_SWAP_ONLY | STATUS_WAITING_TRADE)
+    }
+
+    fn reserves(&self, coin_to_pc: bool) -> Result<(u128, u128)> {
+        if !self.can_swap() {
+            return Err(anyhow!("Raydium pool {} is not accepting swaps", self.address));
+        }
+        if self.coin_reserve == 0 || self.pc_reserve == 0 {
+            return Err(anyhow!("Raydium pool {} has no liquidity", self.address));
+        }
+        let (coin, pc) = (self.coin_reserve as u128, self.pc_reserve as u128);
+        Ok(if coin_to_pc { (coin, pc) } else { (pc, coin) })
+    }
+
+    fn decode_market(&self, data: &[u8]) -> Result<MarketAccounts> {
+        let layout = Layout::new(data, "OpenBook market", MARKET_LEN)?;
+        let nonce = layout.u64(45)?;
+        let vault_signer =
+            Pubkey::create_program_address(&[self.market.as_ref(), &nonce.to_le_bytes()], &self.market_program)
+                .map_err(|e| anyhow!("invalid OpenBook vault signer nonce: {}", e))?;
+        Ok(MarketAccounts {
+            coin_vault: layout.pubkey(117)?,
+            pc_vault: layout.pubkey(165)?,
+            event_queue: layout.pubkey(253)?,
+            bids: layout.pubkey(285)?,
+            asks: layout.pubkey(317)?,
+            vault_signer,
+        })
+    }
+}
+
+impl Pool for RaydiumAmmV4 {
+    fn venue(&self) -> Venue {
+        Venue::RaydiumAmmV4
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Pool> {
+        Box::new(self.clone())
+    }
+
+    fn address(&self) -> Pubkey {
+        self.address
+    }
+
+    fn mints(&self) -> [Pubkey; 2] {
+        [self.coin_mint, self.pc_mint]
+    }
+
+    fn reserve_vaults(&self) -> [Pubkey; 2] {
+        [self.coin_vault, self.pc_vault]
+    }
+
+    fn fee_bps(&self) -> f64 {
+        if self.swap_fee_denominator == 0 {
+            return 0.0;
+        }
+        self.swap_fee_numerator as f64 * 10_000.0 / self.swap_fee_denominator as f64
+    }
+
+    fn accounts_to_update(&self) -> Vec<Pubkey> {
+        vec![self.address, self.coin_vault, self.pc_vault, self.open_orders, self.market]
+    }
+
+    fn update(&mut self, accounts: &AccountMap) -> Result<()> {
+        if let Some(data) = accounts.get(&self.address) {
+            *self = Self { market_accounts: self.market_accounts, ..Self::decode(self.address, data)? };
+        }
+        let vault = |key: &Pubkey| -> Result<u64> {
+            let data = accounts.get(key).ok_or_else(|| anyhow!("vault {} not loaded", key))?;
+            token_account_amount(data)
+        };
+        let mut coin_total = vault(&self.coin_vault)?;
+        let mut pc_total = vault(&self.pc_vault)?;
+
+        // Liquidity resting on OpenBook still belongs to the pool.
+        if self.uses_order_book() {
+            if let Some(data) = accounts.get(&self.open_orders) {
+                let open_orders = Layout::new(data, "OpenBook open orders", OPEN_ORDERS_LEN)?;
+                coin_total += open_orders.u64(85)?;
+                pc_total += open_orders.u64(101)?;
+            }
+        }
+        self.coin_reserve = coin_total.saturating_sub(self.need_take_pnl_coin);
+        self.pc_reserve = pc_total.saturating_sub(self.need_take_pnl_pc);
+
+        if let Some(data) = accounts.get(&self.market) {
+            self.market_accounts = Some(self.decode_market(data)?);
+        }
+        Ok(())
+    }
+
+    fn quote_exact_in(&self, input_mint: &Pubkey, amount_in: u64) -> Result<PoolQuote> {
+        let coin_to_pc = sells_first_mint(self.mints(), input_mint)?;
+        let (source, destination) = self.reserves(coin_to_pc)?;
+        let amount = amount_in as u128;
+
+        let fee = raydium_ceil_div(
+            amount * self.swap_fee_numerator as u128,
+            self.swap_fee_denominator as u128,
+        )?;
+        let after_fee = amount - fee;
+        let amount_out = destination * after_fee / (source + after_fee);
+
+        Ok(PoolQuote {
+            amount_in,
+            amount_out: to_u64(amount_out)?,
+            fee_amount: to_u64(fee)?,
+            fee_mint: *input_mint,
+        })
+    }
+
+    fn quote_exact_out(&self, input_mint: &Pubkey, amount_out: u64) -> Result<PoolQuote> {
+        let coin_to_pc = sells_first_mint(self.mints(), input_mint)?;
+        let (source, destination) = self.reserves(coin_to_pc)?;
+        let amount = amount_out as u128;
+        if amount >= destination {
+            return Err(anyhow!("not enough liquidity for the requested amount"));
+        }
+
+        let before_fee = raydium_ceil_div(source * amount, destination - amount)?;
+        let denominator = self.swap_fee_denominator as u128;
+        let amount_in = raydium_ceil_div(
+            before_fee * denominator,
+            denominator - self.swap_fee_numerator as u128,
+        )?;
+
+        Ok(PoolQuote {
+            amount_in: to_u64(amount_in)?,
+            amount_out,
+            fee_amount: to_u64(amount_in - before_fee)?,
+            fee_mint: *input_mint,
+        })
+    }
+
+    fn apply_swap(&mut self, input_mint: &Pubkey, amount_in: u64) -> Result<PoolQuote> {
+        let quote = self.quote_exact_in(input_mint, amount_in)?;
+        // The fee stays in the vault with the rest of the input.
+        if sells_first_mint(self.mints(), input_mint)? {
+            self.coin_reserve += amount_in;
+            self.pc_reserve -= quote.amount_out;
+        } else {
+            self.pc_reserve += amount_in;
+            self.coin_reserve -= quote.amount_out;
+        }
+        Ok(quote)
+    }
+
+    /// `SwapBaseIn`: tag 9, amount in, minimum out.
+    fn swap_data(&self, _input_mint: &Pubkey, amount_in: u64, minimum_out: u64) -> Result<Vec<u8>> {
+        Ok(instruction_data(&[9], &[&amount_in.to_le_bytes(), &minimum_out.to_le_bytes()]))
+    }
+
+    fn accounts_for_swap(&self, swap: &SwapAccounts) -> Result<Vec<AccountMeta>> {
+        sells_first_mint(self.mints(), &swap.input_mint)?;
+        let market = self
+            .market_accounts
+            .ok_or_else(|| anyhow!("OpenBook market {} not loaded", self.market))?;
+        Ok(vec![
+            AccountMeta::new_readonly(TOKEN_PROGRAM, false),
+            AccountMeta::new(self.address, false),
+            AccountMeta::new_readonly(AUTHORITY, false),
+            AccountMeta::new(self.open_orders, false),
+            AccountMeta::new(self.target_orders, false),
+            AccountMeta::new(self.coin_vault, false),
+            AccountMeta::new(self.pc_vault, false),
+            AccountMeta::new_readonly(self.market_program, false),
+            AccountMeta::new(self.market, false),
+            AccountMeta::new(market.bids, false),
+            AccountMeta::new(market.asks, false),
+            AccountMeta::new(market.event_queue, false),
+            AccountMeta::new(market.coin_vault, false),
+            AccountMeta::new(market.pc_vault, false),
+            AccountMeta::new_readonly(market.vault_signer, false),
+            AccountMeta::new(swap.user_source, false),
+            AccountMeta::new(swap.user_destination, false),
+            AccountMeta::new_readonly(swap.user, true),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pools::pool_from_snapshot;
+    use solana_sdk::pubkey::Pubkey;
+    use std::str::FromStr;
+
+    const POOL: &str = "58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2";
+    const SOL: &str = "So11111111111111111111111111111111111111112";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn mint(s: &str) -> Pubkey {
+        Pubkey::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn quotes_match_program_rounding() {
+        let pool = pool_from_snapshot("raydium_amm_v4_sol_usdc", POOL);
+        assert_eq!(pool.mints(), [mint(SOL), mint(USDC)]);
+
+        let sell = pool.quote_exact_in(&mint(SOL), 1_000_000_000).unwrap();
+        assert_eq!((sell.amount_out, sell.fee_amount), (149_644_338, 2_500_000));
+
+        let buy = pool.quote_exact_out(&mint(USDC), 1_000_000_000).unwrap();
+        assert_eq!(buy.amount_in, 150_397_033);
+        // Paying the exact-out input back in yields at least the target.
+        let check = pool.quote_exact_in(&mint(USDC), buy.amount_in).unwrap();
+        assert!(check.amount_out >= 1_000_000_000);
+    }
+
+    #[test]
+    fn swap_accounts_follow_instruction_order() {
+        let pool = pool_from_snapshot("raydium_amm_v4_sol_usdc", POOL);
+        let user = Pubkey::new_unique();
+        let metas = pool
+            .accounts_for_swap(&crate::pools::SwapAccounts {
+                user,
+                input_mint: mint(SOL),
+                user_source: Pubkey::new_unique(),
+                user_destination: Pubkey::new_unique(),
+            })
+            .unwrap();
+        assert_eq!(metas.len(), 18);
+        assert_eq!(metas[1].pubkey, mint(POOL));
+        assert!(metas[17].is_signer && metas[17].pubkey == user);
+    }
+}
@@ -0,0 +1,346 @@
+use crate::pools::clmm_math::{
+    swap_to_position, ClmmPosition, ClmmState, TickLiquidity, TickMath, FEE_RATE_DENOMINATOR,
+};
+use crate::pools::layout::{anchor_instruction, Layout, DISCRIMINATOR_LEN};
+use crate::pools::{
+    instruction_data, sells_first_mint, AccountMap, Pool, PoolQuote, SwapAccounts, Venue, TOKEN_PROGRAM,
+};
+use anyhow::{anyhow, Result};
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+pub const PROGRAM_ID: Pubkey = pubkey!("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK");
+
+/// `PoolState` size, discriminator included.
+pub const POOL_LEN: usize = 1544;
+pub const MINT_0_OFFSET: usize = 73;
+pub const MINT_1_OFFSET: usize = 105;
+const AMM_CONFIG_LEN: usize = 117;
+const TICK_ARRAY_LEN: usize = 10240;
+const TICKS_PER_ARRAY: i32 = 60;
+const TICK_LEN: usize = 168;
+/// Tick arrays loaded on each side of the current one.
+const TICK_ARRAY_RADIUS: i32 = 3;
+/// `status` bit that disables swaps.
+const STATUS_SWAP_DISABLED: u8 = 1 << 4;
+
+/// The program's Q64.64 tick table. Its constants were generated in f64,
+/// so they drift from Orca's exact table in the low bits; quotes have to
+/// use these to land on the same prices as the program.
+pub const TICK_MATH: TickMath = TickMath {
+    min_tick: -443_636,
+    max_tick: 443_636,
+    min_sqrt_price: 4_295_048_016,
+    max_sqrt_price: 79_226_673_521_066_979_257_578_248_091,
+    sqrt_price_at_tick,
+};
+
+fn sqrt_price_at_tick(tick: i32) -> u128 {
+    const FACTORS: [u128; 19] = [
+        0xfffcb933bd6fb800,
+        0xfff97272373d4000,
+        0xfff2e50f5f657000,
+        0xffe5caca7e10f000,
+        0xffcb9843d60f7000,
+        0xff973b41fa98e800,
+        0xff2ea16466c9b000,
+        0xfe5dee046a9a3800,
+        0xfcbe86c7900bb000,
+        0xf987a7253ac65800,
+        0xf3392b0822bb6000,
+        0xe7159475a2caf000,
+        0xd097f3bdfd2f2000,
+        0xa9f746462d9f8000,
+        0x70d869a156f31c00,
+        0x31be135f97ed3200,
+        0x9aa508b5b85a500,
+        0x5d6af8dedc582c,
+        0x2216e584f5fa,
+    ];
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio: u128 = if abs_tick & 1 != 0 { FACTORS[0] } else { 1 << 64 };
+    for (bit, factor) in FACTORS.iter().enumerate().skip(1) {
+        if abs_tick & (1 << bit) != 0 {
+            ratio = (ratio * factor) >> 64;
+        }
+    }
+    if tick > 0 {
+        ratio = u128::MAX / ratio;
+    }
+    ratio
+}
+
+/// Raydium's concentrated-liquidity AMM.
+#[derive(Debug, Clone)]
+pub struct RaydiumClmm {
+    address: Pubkey,
+    amm_config: Pubkey,
+    mint_0: Pubkey,
+    mint_1: Pubkey,
+    vault_0: Pubkey,
+    vault_1: Pubkey,
+    observation_key: Pubkey,
+    tick_spacing: u16,
+    liquidity: u128,
+    sqrt_price: u128,
+    tick_current: i32,
+    status: u8,
+    trade_fee_rate: u32,
+    ticks: TickLiquidity,
+    /// Start indexes of the tick arrays that exist on chain.
+    loaded_arrays: Vec<i32>,
+}
+
+impl RaydiumClmm {
+    pub fn decode(address: Pubkey, data: &[u8]) -> Result<Self> {
+        let layout = Layout::new(data, "Raydium CLMM", POOL_LEN)?;
+        let base = DISCRIMINATOR_LEN;
+        let tick_spacing = layout.u16(base + 227)?;
+        if tick_spacing == 0 {
+            return Err(anyhow!("Raydium CLMM pool {} has no tick spacing", address));
+        }
+        Ok(Self {
+            address,
+            amm_config: layout.pubkey(base + 1)?,
+            mint_0: layout.pubkey(MINT_0_OFFSET)?,
+            mint_1: layout.pubkey(MINT_1_OFFSET)?,
+            vault_0: layout.pubkey(base + 129)?,
+            vault_1: layout.pubkey(base + 161)?,
+            observation_key: layout.pubkey(base + 193)?,
+            tick_spacing,
+            liquidity: layout.u128(base + 229)?,
+            sqrt_price: layout.u128(base + 245)?,
+            tick_current: layout.i32(base + 261)?,
+            status: layout.u8(base + 381)?,
+            trade_fee_rate: 0,
+            ticks: TickLiquidity::default(),
+            loaded_arrays: Vec::new(),
+        })
+    }
+
+    fn ticks_per_array(&self) -> i32 {
+        self.tick_spacing as i32 * TICKS_PER_ARRAY
+    }
+
+    fn array_start(&self, tick: i32) -> i32 {
+        tick.div_euclid(self.ticks_per_array()) * self.ticks_per_array()
+    }
+
+    fn array_starts(&self) -> Vec<i32> {
+        let current = self.array_start(self.tick_current);
+        (-TICK_ARRAY_RADIUS..=TICK_ARRAY_RADIUS)
+            .map(|offset| current + offset * self.ticks_per_array())
+            .collect()
+    }
+
+    fn tick_array_address(&self, start: i32) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"tick_array", self.address.as_ref(), &start.to_be_bytes()],
+            &PROGRAM_ID,
+        )
+        .0
+    }
+
+    fn bitmap_extension(&self) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"pool_tick_array_bitmap_extension", self.address.as_ref()],
+            &PROGRAM_ID,
+        )
+        .0
+    }
+
+    fn decode_tick_array(&mut self, start: i32, data: &[u8]) -> Result<()> {
+        let layout = Layout::new(data, "Raydium CLMM tick array", TICK_ARRAY_LEN)?;
+        if layout.pubkey(8)? != self.address || layout.i32(40)? != start {
+            return Err(anyhow!("tick array {} does not belong to pool {}", start, self.address));
+        }
+        for i in 0..TICKS_PER_ARRAY as usize {
+            let offset = 44 + i * TICK_LEN;
+            if layout.u128(offset + 20)? != 0 {
+                self.ticks.insert(layout.i32(offset)?, layout.i128(offset + 4)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn quote(&self, input_mint: &Pubkey, amount: u64, exact_in: bool) -> Result<(PoolQuote, ClmmPosition)> {
+        let zero_for_one = sells_first_mint(self.mints(), input_mint)?;
+        if self.status & STATUS_SWAP_DISABLED != 0 {
+            return Err(anyhow!("Raydium CLMM pool {} has swaps disabled", self.address));
+        }
+        let state = ClmmState {
+            sqrt_price: self.sqrt_price,
+            tick: self.tick_current,
+            liquidity: self.liquidity,
+            fee_rate: self.trade_fee_rate,
+            ticks: &self.ticks,
+            tick_math: &TICK_MATH,
+        };
+        let (result, position) = swap_to_position(&state, amount, exact_in, zero_for_one)?;
+        let quote = PoolQuote {
+            amount_in: result.amount_in,
+            amount_out: result.amount_out,
+            fee_amount: result.fee,
+            fee_mint: *input_mint,
+        };
+        Ok((quote, position))
+    }
+}
+
+impl Pool for RaydiumClmm {
+    fn venue(&self) -> Venue {
+        Venue::RaydiumClmm
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Pool> {
+        Box::new(self.clone())
+    }
+
+    fn address(&self) -> Pubkey {
+        self.address
+    }
+
+    fn mints(&self) -> [Pubkey; 2] {
+        [self.mint_0, self.mint_1]
+    }
+
+    fn reserve_vaults(&self) -> [Pubkey; 2] {
+        [self.vault_0, self.vault_1]
+    }
+
+    fn fee_bps(&self) -> f64 {
+        self.trade_fee_rate as f64 * 10_000.0 / FEE_RATE_DENOMINATOR as f64
+    }
+
+    fn accounts_to_update(&self) -> Vec<Pubkey> {
+        let mut accounts = vec![self.address, self.amm_config];
+        accounts.extend(self.array_starts().into_iter().map(|start| self.tick_array_address(start)));
+        accounts
+    }
+
+    fn update(&mut self, accounts: &AccountMap) -> Result<()> {
+        if let Some(data) = accounts.get(&self.address) {
+            *self = Self { trade_fee_rate: self.trade_fee_rate, ..Self::decode(self.address, data)? };
+        }
+        if let Some(data) = accounts.get(&self.amm_config) {
+            self.trade_fee_rate = Layout::new(data, "Raydium CLMM config", AMM_CONFIG_LEN)?.u32(47)?;
+        }
+
+        let starts = self.array_starts();
+        self.ticks = TickLiquidity::new(starts[0], starts[starts.len() - 1] + self.ticks_per_array());
+        self.loaded_arrays.clear();
+        for start in starts {
+            if let Some(data) = accounts.get(&self.tick_array_address(start)) {
+                self.decode_tick_array(start, data)?;
+                self.loaded_arrays.push(start);
+            }
+        }
+        Ok(())
+    }
+
+    fn quote_exact_in(&self, input_mint: &Pubkey, amount_in: u64) -> Result<PoolQuote> {
+        self.quote(input_mint, amount_in, true).map(|(quote, _)| quote)
+    }
+
+    fn quote_exact_out(&self, input_mint: &Pubkey, amount_out: u64) -> Result<PoolQuote> {
+        self.quote(input_mint, amount_out, false).map(|(quote, _)| quote)
+    }
+
+    fn apply_swap(&mut self, input_mint: &Pubkey, amount_in: u64) -> Result<PoolQuote> {
+        let (quote, position) = self.quote(input_mint, amount_in, true)?;
+        self.sqrt_price = position.sqrt_price;
+        self.tick_current = position.tick;
+        self.liquidity = position.liquidity;
+        Ok(quote)
+    }
+
+    /// `swap` (v1): amount, threshold, no price limit, base input.
+    fn swap_data(&self, _input_mint: &Pubkey, amount_in: u64, minimum_out: u64) -> Result<Vec<u8>> {
+        Ok(instruction_data(
+            &anchor_instruction("swap"),
+            &[&amount_in.to_le_bytes(), &minimum_out.to_le_bytes(), &0u128.to_le_bytes(), &[1]],
+        ))
+    }
+
+    fn accounts_for_swap(&self, swap: &SwapAccounts) -> Result<Vec<AccountMeta>> {
+        let zero_for_one = sells_first_mint(self.mints(), &swap.input_mint)?;
+        let (input_vault, output_vault) = if zero_for_one {
+            (self.vault_0, self.vault_1)
+        } else {
+            (self.vault_1, self.vault_0)
+        };
+
+        // Existing arrays from the current one onwards, in swap direction.
+        let current = self.array_start(self.tick_current);
+        let mut arrays: Vec<i32> = self
+            .loaded_arrays
+            .iter()
+            .copied()
+            .filter(|start| if zero_for_one { *start <= current } else { *start >= current })
+            .collect();
+        if zero_for_one {
+            arrays.reverse();
+        }
+        if arrays.is_empty() {
+            return Err(anyhow!("no initialized tick arrays for pool {}", self.address));
+        }
+
+        let mut metas = vec![
+            AccountMeta::new_readonly(swap.user, true),
+            AccountMeta::new_readonly(self.amm_config, false),
+            AccountMeta::new(self.address, false),
+            AccountMeta::new(swap.user_source, false),
+            AccountMeta::new(swap.user_destination, false),
+            AccountMeta::new(input_vault, false),
+            AccountMeta::new(output_vault, false),
+            AccountMeta::new(self.observation_key, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM, false),
+            AccountMeta::new(self.bitmap_extension(), false),
+        ];
+        metas.extend(
+            arrays
+                .into_iter()
+                .take(3)
+                .map(|start| AccountMeta::new(self.tick_array_address(start), false)),
+        );
+        Ok(metas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::pool_from_snapshot;
+    use std::str::FromStr;
+
+    const POOL: &str = "3ucNos4NbumPLZNWztqGHNFFgkHeRMBQAVemeeomsUxv";
+    const SOL: &str = "So11111111111111111111111111111111111111112";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn mint(s: &str) -> Pubkey {
+        Pubkey::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn tick_table_matches_program_bounds() {
+        assert_eq!(sqrt_price_at_tick(TICK_MATH.min_tick), TICK_MATH.min_sqrt_price);
+        assert_eq!(sqrt_price_at_tick(TICK_MATH.max_tick), TICK_MATH.max_sqrt_price);
+        assert_eq!(sqrt_price_at_tick(0), 1 << 64);
+    }
+
+    #[test]
+    fn quotes_cross_initialized_ticks() {
+        let pool = pool_from_snapshot("raydium_clmm_sol_usdc", POOL);
+
+        let small = pool.quote_exact_in(&mint(SOL), 1_000_000_000).unwrap();
+        assert_eq!((small.amount_out, small.fee_amount), (149_924_956, 500_000));
+
+        // Large enough to cross the position boundary below the price.
+        let large = pool.quote_exact_in(&mint(SOL), 30_000_000_000_000).unwrap();
+        assert_eq!(large.amount_out, 4_455_242_633_258);
+
+        let exact_out = pool.quote_exact_out(&mint(USDC), 500_000_000).unwrap();
+        assert_eq!(exact_out.amount_in, 75_037_530);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::pools::layout::{anchor_instruction, token_account_amount, Layout, DISCRIMINATOR_LEN};
+use crate::pools::math::{mul_div_ceil, to_u64};
+use crate::pools::{instruction_data, sells_first_mint, AccountMap, Pool, PoolQuote, SwapAccounts, Venue};
+use anyhow::{anyhow, Result};
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+pub const PROGRAM_ID: Pubkey = pubkey!("CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C");
+
+/// `PoolState` size, discriminator included.
+pub const POOL_LEN: usize = 637;
+pub const TOKEN_0_MINT_OFFSET: usize = 168;
+pub const TOKEN_1_MINT_OFFSET: usize = 200;
+const AMM_CONFIG_LEN: usize = 236;
+const FEE_RATE_DENOMINATOR: u128 = 1_000_000;
+/// `status` bit that disables swaps.
+const STATUS_SWAP_DISABLED: u8 = 1 << 2;
+
+/// Raydium's Anchor constant-product AMM, with Token-2022 support.
+#[derive(Debug, Clone)]
+pub struct RaydiumCpmm {
+    address: Pubkey,
+    amm_config: Pubkey,
+    token_0_vault: Pubkey,
+    token_1_vault: Pubkey,
+    token_0_mint: Pubkey,
+    token_1_mint: Pubkey,
+    token_0_program: Pubkey,
+    token_1_program: Pubkey,
+    observation_key: Pubkey,
+    status: u8,
+    /// Protocol plus fund fees still sitting in each vault.
+    owed_fees_0: u64,
+    owed_fees_1: u64,
+    trade_fee_rate: u64,
+    reserve_0: u64,
+    reserve_1: u64,
+}
+
+impl RaydiumCpmm {
+    pub fn decode(address: Pubkey, data: &[u8]) -> Result<Self> {
+        let layout = Layout::new(data, "Raydium CPMM", POOL_LEN)?;
+        let base = DISCRIMINATOR_LEN;
+        Ok(Self {
+            address,
+            amm_config: layout.pubkey(base)?,
+            token_0_vault: layout.pubkey(base + 64)?,
+            token_1_vault: layout.pubkey(base + 96)?,
+            token_0_mint: layout.pubkey(TOKEN_0_MINT_OFFSET)?,
+            token_1_mint: layout.pubkey(TOKEN_1_MINT_OFFSET)?,
+            token_0_program: layout.pubkey(base + 224)?,
+            token_1_program: layout.pubkey(base + 256)?,
+            observation_key: layout.pubkey(base + 288)?,
+            status: layout.u8(base + 321)?,
+            owed_fees_0: layout.u64(base + 333)?.saturating_add(layout.u64(base + 349)?),
+            owed_fees_1: layout.u64(base + 341)?.saturating_add(layout.u64(base + 357)?),
+            trade_fee_rate: 0,
+            reserve_0: 0,
+            reserve_1: 0,
+        })
+    }
+
+    fn reserves(&self, zero_to_one: bool) -> Result<(u128, u128)> {
+        if self.status & STATUS_SWAP_DISABLED != 0 {
+            return Err(anyhow!("Raydium CPMM pool {} has swaps disabled", self.address));
+        }
+        if self.reserve_0 == 0 || self.reserve_1 == 0 {
+            return Err(anyhow!("Raydium CPMM pool {} has no liquidity", self.address));
+        }
+        let (r0, r1) = (self.reserve_0 as u128, self.reserve_1 as u128);
+        Ok(if zero_to_one { (r0, r1) } else { (r1, r0) })
+    }
+
+    fn authority() -> Pubkey {
+        Pubkey::find_program_address(&[b"vault_and_lp_mint_auth_seed"], &PROGRAM_ID).0
+    }
+}
+
+impl Pool for RaydiumCpmm {
+    fn venue(&self) -> Venue {
+        Venue::RaydiumCpmm
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Pool> {
+        Box::new(self.clone())
+    }
+
+    fn address(&self) -> Pubkey {
+        self.address
+    }
+
+    fn mints(&self) -> [Pubkey; 2] {
+        [self.token_0_mint, self.token_1_mint]
+    }
+
+    fn reserve_vaults(&self) -> [Pubkey; 2] {
+        [self.token_0_vault, self.token_1_vault]
+    }
+
+    fn fee_bps(&self) -> f64 {
+        self.trade_fee_rate as f64 * 10_000.0 / FEE_RATE_DENOMINATOR as f64
+    }
+
+    fn accounts_to_update(&self) -> Vec<Pubkey> {
+        vec![self.address, self.amm_config, self.token_0_vault, self.token_1_vault]
+    }
+
+    fn update(&mut self, accounts: &AccountMap) -> Result<()> {
+        if let Some(data) = accounts.get(&self.address) {
+            *self = Self { trade_fee_rate: self.trade_fee_rate, ..Self::decode(self.address, data)? };
+        }
+        if let Some(data) = accounts.get(&self.amm_config) {
+            self.trade_fee_rate = Layout::new(data, "Raydium CPMM config", AMM_CONFIG_LEN)?.u64(12)?;
+        }
+        let vault = |key: &Pubkey| -> Result<u64> {
+            let data = accounts.get(key).ok_or_else(|| anyhow!("vault {} not loaded", key))?;
+            token_account_amount(data)
+        };
+        self.reserve_0 = vault(&self.token_0_vault)?.saturating_sub(self.owed_fees_0);
+        self.reserve_1 = vault(&self.token_1_vault)?.saturating_sub(self.owed_fees_1);
+        Ok(())
+    }
+
+    fn quote_exact_in(&self, input_mint: &Pubkey, amount_in: u64) -> Result<PoolQuote> {
+        let zero_to_one = sells_first_mint(self.mints(), input_mint)?;
+        let (source, destination) = self.reserves(zero_to_one)?;
+        let amount = amount_in as u128;
+
+        let fee = mul_div_ceil(amount, self.trade_fee_rate as u128, FEE_RATE_DENOMINATOR)?;
+        let after_fee = amount - fee;
+        let amount_out = after_fee * destination / (source + after_fee);
+
+        Ok(PoolQuote {
+            amount_in,
+            amount_out: to_u64(amount_out)?,
+            fee_amount: to_u64(fee)?,
+            fee_mint: *input_mint,
+        })
+    }
+
+    fn quote_exact_out(&self, input_mint: &Pubkey, amount_out: u64) -> Result<PoolQuote> {
+        let zero_to_one = sells_first_mint(self.mints(), input_mint)?;
+        let (source, destination) = self.reserves(zero_to_one)?;
+        let amount = amount_out as u128;
+        if amount >= destination {
+            return Err(anyhow!("not enough liquidity for the requested amount"));
+        }
+
+        let before_fee = (source * amount).div_ceil(destination - amount);
+        let amount_in = if self.trade_fee_rate == 0 {
+            before_fee
+        } else {
+            mul_div_ceil(
+                before_fee,
+                FEE_RATE_DENOMINATOR,
+                FEE_RATE_DENOMINATOR - self.trade_fee_rate as u128,
+            )?
+        };
+
+        Ok(PoolQuote {
+            amount_in: to_u64(amount_in)?,
+            amount_out,
+            fee_amount: to_u64(amount_in - before_fee)?,
+            fee_mint: *input_mint,
+        })
+    }
+
+    fn apply_swap(&mut self, input_mint: &Pubkey, amount_in: u64) -> Result<PoolQuote> {
+        let quote = self.quote_exact_in(input_mint, amount_in)?;
+        if sells_first_mint(self.mints(), input_mint)? {
+            self.reserve_0 += amount_in;
+            self.reserve_1 -= quote.amount_out;
+        } else {
+            self.reserve_1 += amount_in;
+            self.reserve_0 -= quote.amount_out;
+        }
+        Ok(quote)
+    }
+
+    fn swap_data(&self, _input_mint: &Pubkey, amount_in: u64, minimum_out: u64) -> Result<Vec<u8>> {
+        Ok(instruction_data(
+            &anchor_instruction("swap_base_input"),
+            &[&amount_in.to_le_bytes(), &minimum_out.to_le_bytes()],
+        ))
+    }
+
+    fn accounts_for_swap(&self, swap: &SwapAccounts) -> Result<Vec<AccountMeta>> {
+        let zero_to_one = sells_first_mint(self.mints(), &swap.input_mint)?;
+        let zero = (self.token_0_vault, self.token_0_program, self.token_0_mint);
+        let one = (self.token_1_vault, self.token_1_program, self.token_1_mint);
+        let (input, output) = if zero_to_one { (zero, one) } else { (one, zero) };
+        Ok(vec![
+            AccountMeta::new_readonly(swap.user, true),
+            AccountMeta::new_readonly(Self::authority(), false),
+            AccountMeta::new_readonly(self.amm_config, false),
+            AccountMeta::new(self.address, false),
+            AccountMeta::new(swap.user_source, false),
+            AccountMeta::new(swap.user_destination, false),
+            AccountMeta::new(input.0, false),
+            AccountMeta::new(output.0, false),
+            AccountMeta::new_readonly(input.1, false),
+            AccountMeta::new_readonly(output.1, false),
+            AccountMeta::new_readonly(input.2, false),
+            AccountMeta::new_readonly(output.2, false),
+            AccountMeta::new(self.observation_key, false),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pools::{pool_from_snapshot, SwapAccounts};
+    use solana_sdk::pubkey::Pubkey;
+    use std::str::FromStr;
+
+    const POOL: &str = "7JuwJuNU88gurFnyWeiyGKbFmExMWcmRZntn9imEzdny";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const BONK: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+
+    fn mint(s: &str) -> Pubkey {
+        Pubkey::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn quotes_net_out_owed_fees() {
+        let pool = pool_from_snapshot("raydium_cpmm_usdc_bonk", POOL);
+
+        let sell = pool.quote_exact_in(&mint(USDC), 250_000_000).unwrap();
+        assert_eq!((sell.amount_out, sell.fee_amount), (12_466_166_797_784, 625_000));
+
+        let buy = pool.quote_exact_out(&mint(BONK), 1_000_000_000).unwrap();
+        assert_eq!(buy.amount_in, 50_166_999_327_244);
+    }
+
+    #[test]
+    fn swap_accounts_put_input_side_first() {
+        let pool = pool_from_snapshot("raydium_cpmm_usdc_bonk", POOL);
+        let swap = SwapAccounts {
+            user: Pubkey::new_unique(),
+            input_mint: mint(BONK),
+            user_source: Pubkey::new_unique(),
+            user_destination: Pubkey::new_unique(),
+        };
+        let metas = pool.accounts_for_swap(&swap).unwrap();
+        assert_eq!(metas.len(), 13);
+        assert_eq!(metas[10].pubkey, mint(BONK));
+        assert_eq!(metas[11].pubkey, mint(USDC));
+    }
+}
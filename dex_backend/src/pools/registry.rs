@@ -0,0 +1,276 @@
+use crate::pools::Venue;
+use crate::POOL_REGISTRY;
+use dashmap::{DashMap, DashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+
+/// What the indexer knows about one pool, as of `last_update_slot`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolEntry {
+    pub address: String,
+    pub venue: Venue,
+    /// Mints in the order the program stores them.
+    pub mint_a: String,
+    pub mint_b: String,
+    /// Raw vault balances.
+    pub reserve_a: u64,
+    pub reserve_b: u64,
+    pub fee_bps: f64,
+    /// Raw units of `mint_b` one raw unit of `mint_a` is worth at the
+    /// pool's current price, before fees.
+    #[serde(default)]
+    pub spot_price: Option<f64>,
+    /// `None` until one side of the pair has a reference price.
+    pub tvl_usd: Option<f64>,
+    pub last_update_slot: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SavedRegistry {
+    tracked_mints: Vec<String>,
+    pools: Vec<PoolEntry>,
+}
+
+/// Mints ordered so either direction of a pair finds the same pools.
+pub fn pair_key(a: &str, b: &str) -> (String, String) {
+    if a <= b {
+        (a.to_string(), b.to_string())
+    } else {
+        (b.to_string(), a.to_string())
+    }
+}
+
+/// Every pool the indexer has found, keyed by mint pair. Shared by the
+/// router and the analytics endpoints, and saved to disk after each
+/// indexing round so a restart doesn't rediscover everything.
+pub struct PoolRegistry {
+    pools: DashMap<String, PoolEntry>,
+    by_pair: DashMap<(String, String), HashSet<String>>,
+    by_mint: DashMap<String, HashSet<String>>,
+    /// Mints whose pools get rediscovered on every discovery round.
+    tracked_mints: DashSet<String>,
+    /// USD price of SOL from the deepest SOL/stable pool, used for TVL.
+    sol_usd: RwLock<Option<f64>>,
+    path: PathBuf,
+    save_lock: Mutex<()>,
+}
+
+impl PoolRegistry {
+    pub fn load() -> Self {
+        Self::load_from(PathBuf::from(
+            std::env::var("POOL_REGISTRY_PATH").unwrap_or_else(|_| "pool_registry.json".into()),
+        ))
+    }
+
+    fn load_from(path: PathBuf) -> Self {
+        let registry = Self::empty(path);
+        match std::fs::read_to_string(&registry.path) {
+            Ok(raw) => match serde_json::from_str::<SavedRegistry>(&raw) {
+                Ok(saved) => {
+                    for mint in saved.tracked_mints {
+                        registry.tracked_mints.insert(mint);
+                    }
+                    for entry in saved.pools {
+                        registry.upsert(entry);
+                    }
+                }
+                Err(e) => eprintln!("[pools] Ignoring malformed {}: {}", registry.path.display(), e),
+            },
+            Err(_) => println!("[pools] No saved registry at {}", registry.path.display()),
+        }
+        println!(
+            "[pools] Loaded {} pools, {} tracked mints",
+            registry.pools.len(),
+            registry.tracked_mints.len()
+        );
+        registry
+    }
+
+    fn empty(path: PathBuf) -> Self {
+        Self {
+            pools: DashMap::new(),
+            by_pair: DashMap::new(),
+            by_mint: DashMap::new(),
+            tracked_mints: DashSet::new(),
+            sol_usd: RwLock::new(None),
+            path,
+            save_lock: Mutex::new(()),
+        }
+    }
+
+    pub fn upsert(&self, entry: PoolEntry) {
+        let address = entry.address.clone();
+        self.by_pair
+            .entry(pair_key(&entry.mint_a, &entry.mint_b))
+            .or_default()
+            .insert(address.clone());
+        for mint in [&entry.mint_a, &entry.mint_b] {
+            self.by_mint.entry(mint.clone()).or_default().insert(address.clone());
+        }
+        self.pools.insert(address, entry);
+    }
+
+    /// Drops a pool whose account no longer exists.
+    pub fn remove(&self, address: &str) {
+        let Some((_, entry)) = self.pools.remove(address) else {
+            return;
+        };
+        if let Some(mut pools) = self.by_pair.get_mut(&pair_key(&entry.mint_a, &entry.mint_b)) {
+            pools.remove(address);
+        }
+        for mint in [&entry.mint_a, &entry.mint_b] {
+            if let Some(mut pools) = self.by_mint.get_mut(mint) {
+                pools.remove(address);
+            }
+        }
+    }
+
+    pub fn get(&self, address: &str) -> Option<PoolEntry> {
+        self.pools.get(address).map(|entry| entry.clone())
+    }
+
+    pub fn entries(&self) -> Vec<PoolEntry> {
+        self.pools.iter().map(|entry| entry.clone()).collect()
+    }
+
+    /// Pools trading `mint`, deepest first.
+    pub fn pools_for_mint(&self, mint: &str) -> Vec<PoolEntry> {
+        let addresses = self.by_mint.get(mint).map(|set| set.clone()).unwrap_or_default();
+        self.entries_by_tvl(addresses)
+    }
+
+    /// Pools trading exactly `a` against `b`, deepest first.
+    pub fn pools_for_pair(&self, a: &str, b: &str) -> Vec<PoolEntry> {
+        let addresses = self.by_pair.get(&pair_key(a, b)).map(|set| set.clone()).unwrap_or_default();
+        self.entries_by_tvl(addresses)
+    }
+
+    fn entries_by_tvl(&self, addresses: HashSet<String>) -> Vec<PoolEntry> {
+        let mut entries: Vec<PoolEntry> = addresses.iter().filter_map(|a| self.get(a)).collect();
+        entries.sort_by(|x, y| {
+            y.tvl_usd
+                .unwrap_or(-1.0)
+                .total_cmp(&x.tvl_usd.unwrap_or(-1.0))
+                .then_with(|| x.address.cmp(&y.address))
+        });
+        entries
+    }
+
+    /// The `limit` pools refreshed longest ago.
+    pub fn stalest(&self, limit: usize) -> Vec<String> {
+        let mut by_age: Vec<(u64, String)> = self
+            .pools
+            .iter()
+            .map(|entry| (entry.last_update_slot, entry.key().clone()))
+            .collect();
+        by_age.sort();
+        by_age.into_iter().take(limit).map(|(_, address)| address).collect()
+    }
+
+    /// Returns whether the mint was newly tracked.
+    pub fn track(&self, mint: &str) -> bool {
+        self.tracked_mints.insert(mint.to_string())
+    }
+
+    pub fn untrack(&self, mint: &str) {
+        self.tracked_mints.remove(mint);
+    }
+
+    pub fn is_tracked(&self, mint: &str) -> bool {
+        self.tracked_mints.contains(mint)
+    }
+
+    pub fn tracked_mints(&self) -> Vec<String> {
+        self.tracked_mints.iter().map(|mint| mint.clone()).collect()
+    }
+
+    pub fn sol_usd(&self) -> Option<f64> {
+        *self.sol_usd.read().unwrap()
+    }
+
+    pub fn set_sol_usd(&self, price: f64) {
+        *self.sol_usd.write().unwrap() = Some(price);
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let _guard = self.save_lock.lock().unwrap();
+        let mut saved = SavedRegistry {
+            tracked_mints: self.tracked_mints(),
+            pools: self.entries(),
+        };
+        saved.tracked_mints.sort();
+        saved.pools.sort_by(|x, y| x.address.cmp(&y.address));
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&saved)?)?;
+        std::fs::rename(tmp, &self.path)
+    }
+}
+
+pub fn pool_registry() -> &'static PoolRegistry {
+    POOL_REGISTRY.get_or_init(PoolRegistry::load)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOL: &str = "So11111111111111111111111111111111111111112";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const BONK: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+
+    fn entry(address: &str, mint_a: &str, mint_b: &str, tvl_usd: Option<f64>, slot: u64) -> PoolEntry {
+        PoolEntry {
+            address: address.to_string(),
+            venue: Venue::Whirlpool,
+            mint_a: mint_a.to_string(),
+            mint_b: mint_b.to_string(),
+            reserve_a: 1,
+            reserve_b: 1,
+            fee_bps: 4.0,
+            spot_price: None,
+            tvl_usd,
+            last_update_slot: slot,
+        }
+    }
+
+    fn registry(name: &str) -> PoolRegistry {
+        PoolRegistry::empty(std::env::temp_dir().join(format!("{}-{}.json", name, std::process::id())))
+    }
+
+    #[test]
+    fn indexes_by_pair_in_either_order_deepest_first() {
+        let registry = registry("pools-index");
+        registry.upsert(entry("shallow", SOL, USDC, Some(10.0), 5));
+        registry.upsert(entry("deep", USDC, SOL, Some(1_000.0), 7));
+        registry.upsert(entry("unpriced", SOL, USDC, None, 3));
+        registry.upsert(entry("bonk", USDC, BONK, Some(50.0), 9));
+
+        let addresses = |entries: Vec<PoolEntry>| entries.into_iter().map(|e| e.address).collect::<Vec<_>>();
+        assert_eq!(addresses(registry.pools_for_pair(USDC, SOL)), ["deep", "shallow", "unpriced"]);
+        assert_eq!(addresses(registry.pools_for_mint(BONK)), ["bonk"]);
+        assert_eq!(registry.pools_for_mint(USDC).len(), 4);
+        assert_eq!(registry.stalest(2), ["unpriced", "shallow"]);
+
+        registry.remove("deep");
+        assert_eq!(addresses(registry.pools_for_pair(SOL, USDC)), ["shallow", "unpriced"]);
+        assert_eq!(registry.pools_for_mint(USDC).len(), 3);
+    }
+
+    #[test]
+    fn saved_registry_round_trips() {
+        let registry = registry("pools-save");
+        registry.upsert(entry("bonk", USDC, BONK, Some(50.0), 9));
+        registry.track(BONK);
+        registry.save().unwrap();
+
+        let reloaded = PoolRegistry::load_from(registry.path.clone());
+
+        assert!(reloaded.is_tracked(BONK));
+        assert_eq!(reloaded.pools_for_pair(BONK, USDC), registry.pools_for_pair(USDC, BONK));
+        std::fs::remove_file(&registry.path).unwrap();
+    }
+}
@@ -0,0 +1,447 @@
+//! Pool state kept current by account streams, so quotes read memory
+//! instead of fetching pool accounts per request.
+//!
+//! A pool is tracked the first time it is loaded: its accounts are fetched
+//! once and subscribed to. Updates apply in slot order, dropping any older
+//! than what the cache holds, and wake the quote sessions watching the
+//! account. When a pool moves far enough to quote from other tick or bin
+//! arrays, those are fetched and subscribed in turn. After a reconnect every
+//! subscribed account is fetched again to cover the gap.
+
+use crate::pools::loader::{load_pools, LoadedPools};
+use crate::pools::{decode_pool, AccountMap, Pool, PoolAccountKind, Venue};
+use crate::rpc::accounts::get_multiple_accounts;
+use crate::stream::account_source::{account_source, AccountUpdate, SourceCommand, Subscription};
+use crate::POOL_STATE_CACHE;
+use anyhow::Result;
+use dashmap::{DashMap, DashSet};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Updates buffered between the source and the cache.
+const UPDATE_CAPACITY: usize = 4_096;
+const RECONNECT_DELAYS: [u64; 5] = [1, 2, 5, 10, 30];
+
+#[derive(Debug, Clone)]
+struct CachedAccount {
+    slot: u64,
+    owner: Pubkey,
+    data: Vec<u8>,
+}
+
+struct TrackedPool {
+    venue: Venue,
+    /// Accounts the pool was last quoted from, vaults included.
+    accounts: HashSet<Pubkey>,
+}
+
+struct Watcher {
+    accounts: HashSet<Pubkey>,
+    changed: mpsc::Sender<()>,
+}
+
+pub struct PoolStateCache {
+    accounts: DashMap<Pubkey, CachedAccount>,
+    pools: DashMap<Pubkey, TrackedPool>,
+    /// Subscribed accounts, with how many pools and watchers need each.
+    interest: DashMap<Pubkey, usize>,
+    watchers: DashMap<u64, Watcher>,
+    next_watcher: AtomicU64,
+    /// Pools whose dependencies are being fetched.
+    refreshing: DashSet<Pubkey>,
+    stale_updates: AtomicU64,
+    /// Pool accounts arrive through one `programSubscribe` per venue rather
+    /// than an `accountSubscribe` each.
+    program_subscriptions: bool,
+    commands: mpsc::UnboundedSender<SourceCommand>,
+    /// Taken by the stream task when it starts.
+    command_receiver: Mutex<Option<mpsc::UnboundedReceiver<SourceCommand>>>,
+}
+
+pub fn pool_state_cache() -> &'static PoolStateCache {
+    POOL_STATE_CACHE.get_or_init(|| {
+        let program_subscriptions = std::env::var("POOL_CACHE_PROGRAM_SUBSCRIBE")
+            .is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+        PoolStateCache::new(program_subscriptions)
+    })
+}
+
+/// Wakes a quote session when an account it watches changes; unwatches on
+/// drop.
+pub struct WatchHandle {
+    cache: &'static PoolStateCache,
+    id: u64,
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.cache.unwatch(self.id);
+    }
+}
+
+impl PoolStateCache {
+    pub fn new(program_subscriptions: bool) -> Self {
+        let (commands, command_receiver) = mpsc::unbounded_channel();
+        Self {
+            accounts: DashMap::new(),
+            pools: DashMap::new(),
+            interest: DashMap::new(),
+            watchers: DashMap::new(),
+            next_watcher: AtomicU64::new(0),
+            refreshing: DashSet::new(),
+            stale_updates: AtomicU64::new(0),
+            program_subscriptions,
+            commands,
+            command_receiver: Mutex::new(Some(command_receiver)),
+        }
+    }
+
+    /// Stores the update unless the account isn't subscribed or the cache
+    /// already holds a later slot. Returns whether it was stored.
+    pub fn apply(&self, update: AccountUpdate) -> bool {
+        if !self.interest.contains_key(&update.address) {
+            return false;
+        }
+        match self.accounts.entry(update.address) {
+            dashmap::mapref::entry::Entry::Occupied(mut cached) => {
+                if cached.get().slot > update.slot {
+                    self.stale_updates.fetch_add(1, Ordering::Relaxed);
+                    return false;
+                }
+                cached.insert(CachedAccount { slot: update.slot, owner: update.owner, data: update.data });
+            }
+            dashmap::mapref::entry::Entry::Vacant(vacant) => {
+                vacant.insert(CachedAccount { slot: update.slot, owner: update.owner, data: update.data });
+            }
+        }
+        for watcher in self.watchers.iter() {
+            if watcher.accounts.contains(&update.address) {
+                // A full channel already means "requote".
+                let _ = watcher.changed.try_send(());
+            }
+        }
+        true
+    }
+
+    /// Updates dropped for arriving behind the cached slot.
+    pub fn stale_updates(&self) -> u64 {
+        self.stale_updates.load(Ordering::Relaxed)
+    }
+
+    /// The pools at `addresses` from cached state, tracking any not seen
+    /// before. Addresses that aren't supported pools are skipped.
+    pub async fn load(&self, addresses: &[Pubkey]) -> Result<LoadedPools> {
+        let untracked: Vec<Pubkey> =
+            addresses.iter().filter(|address| !self.pools.contains_key(address)).copied().collect();
+        if !untracked.is_empty() {
+            self.track(&load_pools(&untracked).await?);
+        }
+        Ok(self.cached_pools(addresses))
+    }
+
+    /// Starts keeping freshly loaded pools current.
+    fn track(&self, loaded: &LoadedPools) {
+        for pool in &loaded.pools {
+            let address = pool.address();
+            if self.pools.contains_key(&address) {
+                continue;
+            }
+            let accounts = dependencies(pool.as_ref());
+            self.add_interest(accounts.iter().copied(), |account| self.subscribes_to(address, account));
+            for account in &accounts {
+                if let (Some(data), Some(owner)) = (loaded.accounts.get(account), loaded.owners.get(account)) {
+                    self.apply(AccountUpdate { address: *account, slot: loaded.slot, owner: *owner, data: data.clone() });
+                }
+            }
+            self.pools.insert(address, TrackedPool { venue: pool.venue(), accounts });
+        }
+    }
+
+    /// Whether `account` of the pool needs its own subscription.
+    fn subscribes_to(&self, pool: Pubkey, account: Pubkey) -> bool {
+        !(self.program_subscriptions && account == pool)
+    }
+
+    fn cached_pools(&self, addresses: &[Pubkey]) -> LoadedPools {
+        let mut loaded =
+            LoadedPools { slot: u64::MAX, pools: Vec::new(), accounts: AccountMap::new(), owners: HashMap::new() };
+        for address in addresses {
+            let Some(tracked) = self.pools.get(address) else {
+                continue;
+            };
+            let mut accounts = AccountMap::new();
+            for account in &tracked.accounts {
+                if let Some(cached) = self.accounts.get(account) {
+                    loaded.slot = loaded.slot.min(cached.slot);
+                    accounts.insert(*account, cached.data.clone());
+                    loaded.owners.insert(*account, cached.owner);
+                }
+            }
+            let Some(data) = accounts.get(address) else {
+                continue;
+            };
+            let pool = decode_pool(*address, &tracked.venue.program_id(), data).and_then(|mut pool| {
+                pool.update(&accounts)?;
+                Ok(pool)
+            });
+            match pool {
+                Ok(pool) => loaded.pools.push(pool),
+                Err(e) => eprintln!("[pools] Cached {} pool {} is unusable: {}", tracked.venue.label(), address, e),
+            }
+            loaded.accounts.extend(accounts);
+        }
+        if loaded.slot == u64::MAX {
+            loaded.slot = 0;
+        }
+        loaded
+    }
+
+    /// Accounts the tracked pool quotes from now but wasn't subscribed to,
+    /// or `None` when nothing changed.
+    fn moved_dependencies(&self, address: &Pubkey) -> Option<HashSet<Pubkey>> {
+        let tracked = self.pools.get(address)?;
+        let data = self.accounts.get(address)?.data.clone();
+        let pool = decode_pool(*address, &tracked.venue.program_id(), &data).ok()?;
+        let needed = dependencies(pool.as_ref());
+        (needed != tracked.accounts).then_some(needed)
+    }
+
+    /// Fetches and subscribes to the arrays a moved pool now quotes from,
+    /// and lets go of the ones it left behind.
+    async fn refresh_pool(&self, address: Pubkey, needed: HashSet<Pubkey>) -> Result<()> {
+        let Some(previous) = self.pools.get(&address).map(|tracked| tracked.accounts.clone()) else {
+            return Ok(());
+        };
+        let added: Vec<Pubkey> = needed.difference(&previous).copied().collect();
+        self.add_interest(added.iter().copied(), |account| self.subscribes_to(address, account));
+        let fetched = get_multiple_accounts(&added).await?;
+        for (account, raw) in fetched.accounts {
+            self.apply(AccountUpdate { address: account, slot: fetched.slot, owner: raw.owner, data: raw.data });
+        }
+        self.remove_interest(previous.difference(&needed).copied());
+        if let Some(mut tracked) = self.pools.get_mut(&address) {
+            tracked.accounts = needed;
+        }
+        Ok(())
+    }
+
+    /// Wakes `changed` whenever one of `accounts` is updated.
+    pub fn watch(&'static self, accounts: Vec<Pubkey>, changed: mpsc::Sender<()>) -> WatchHandle {
+        let accounts: HashSet<Pubkey> = accounts.into_iter().collect();
+        self.add_interest(accounts.iter().copied(), |_| true);
+        let id = self.next_watcher.fetch_add(1, Ordering::Relaxed);
+        self.watchers.insert(id, Watcher { accounts, changed });
+        WatchHandle { cache: self, id }
+    }
+
+    fn unwatch(&self, id: u64) {
+        if let Some((_, watcher)) = self.watchers.remove(&id) {
+            self.remove_interest(watcher.accounts.into_iter());
+        }
+    }
+
+    fn add_interest(&self, accounts: impl Iterator<Item = Pubkey>, subscribe: impl Fn(Pubkey) -> bool) {
+        for account in accounts {
+            let mut count = self.interest.entry(account).or_insert(0);
+            *count += 1;
+            if *count == 1 && subscribe(account) {
+                let _ = self.commands.send(SourceCommand::Subscribe(Subscription::Account(account)));
+            }
+        }
+    }
+
+    fn remove_interest(&self, accounts: impl Iterator<Item = Pubkey>) {
+        for account in accounts {
+            let unused = self
+                .interest
+                .remove_if_mut(&account, |_, count| {
+                    *count -= 1;
+                    *count == 0
+                })
+                .is_some();
+            if unused {
+                self.accounts.remove(&account);
+                let _ = self.commands.send(SourceCommand::Unsubscribe(Subscription::Account(account)));
+            }
+        }
+    }
+
+    /// Everything to subscribe to on a fresh connection.
+    fn subscriptions(&self) -> Vec<Subscription> {
+        let mut subscriptions = Vec::new();
+        let mut streamed_pools = HashSet::new();
+        if self.program_subscriptions {
+            for venue in Venue::ALL {
+                let filter = match venue.pool_account_kind() {
+                    PoolAccountKind::Size(size) => RpcFilterType::DataSize(size as u64),
+                    PoolAccountKind::Discriminator(bytes) => {
+                        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, bytes.to_vec()))
+                    }
+                };
+                subscriptions.push(Subscription::Program { program: venue.program_id(), filters: vec![filter] });
+            }
+            streamed_pools.extend(self.pools.iter().map(|pool| *pool.key()));
+        }
+        let watched: HashSet<Pubkey> =
+            self.watchers.iter().flat_map(|watcher| watcher.accounts.clone()).collect();
+        for account in self.interest.iter().map(|entry| *entry.key()) {
+            if !streamed_pools.contains(&account) || watched.contains(&account) {
+                subscriptions.push(Subscription::Account(account));
+            }
+        }
+        subscriptions
+    }
+
+    /// Refetches every subscribed account; updates that landed meanwhile
+    /// win through the slot order.
+    async fn resync(&self) -> Result<()> {
+        let keys: Vec<Pubkey> = self.interest.iter().map(|entry| *entry.key()).collect();
+        let fetched = get_multiple_accounts(&keys).await?;
+        for (address, raw) in fetched.accounts {
+            self.apply(AccountUpdate { address, slot: fetched.slot, owner: raw.owner, data: raw.data });
+        }
+        Ok(())
+    }
+}
+
+/// Accounts the pool's quote reads, including itself and its vaults.
+fn dependencies(pool: &dyn Pool) -> HashSet<Pubkey> {
+    let mut accounts: HashSet<Pubkey> = pool.accounts_to_update().into_iter().collect();
+    accounts.insert(pool.address());
+    accounts.extend(pool.reserve_vaults());
+    accounts
+}
+
+/// Streams account updates into the cache, reconnecting with every current
+/// subscription when the source drops.
+pub fn spawn_pool_state_cache() {
+    let cache = pool_state_cache();
+    let Some(mut commands) = cache.command_receiver.lock().unwrap().take() else {
+        return;
+    };
+    let (updates_tx, mut updates_rx) = mpsc::channel::<AccountUpdate>(UPDATE_CAPACITY);
+
+    tokio::spawn(async move {
+        while let Some(update) = updates_rx.recv().await {
+            let address = update.address;
+            if !cache.apply(update) || !cache.pools.contains_key(&address) {
+                continue;
+            }
+            if let Some(needed) = cache.moved_dependencies(&address) {
+                if cache.refreshing.insert(address) {
+                    tokio::spawn(async move {
+                        if let Err(e) = cache.refresh_pool(address, needed).await {
+                            eprintln!("[pools] Failed to follow pool {}: {}", address, e);
+                        }
+                        cache.refreshing.remove(&address);
+                    });
+                }
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        let source = account_source();
+        let mut failures = 0usize;
+        loop {
+            let subscriptions = cache.subscriptions();
+            println!("[pools] Streaming {} subscriptions from {}", subscriptions.len(), source.name());
+            match source.stream(subscriptions, &mut commands, updates_tx.clone()).await {
+                Ok(()) => break,
+                Err(e) => {
+                    let delay = RECONNECT_DELAYS[failures.min(RECONNECT_DELAYS.len() - 1)];
+                    eprintln!("[pools] Account stream dropped: {}; reconnecting in {}s", e, delay);
+                    failures += 1;
+                    tokio::time::sleep(Duration::from_secs(delay)).await;
+                }
+            }
+            // Runs while the new connection subscribes; whichever of the two
+            // lands later for an account wins by slot.
+            tokio::spawn(async move {
+                if let Err(e) = cache.resync().await {
+                    eprintln!("[pools] Resync after reconnect failed: {}", e);
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::indexer::SOL_MINT;
+    use crate::pools::{load_snapshot, pool_from_snapshot};
+
+    const POOL: &str = "Czfq3xZZDmsdGdUyrNLtRhGc47cXcZtLG4crryfu44zE";
+
+    fn update(address: Pubkey, slot: u64, data: Vec<u8>) -> AccountUpdate {
+        AccountUpdate { address, slot, owner: Pubkey::default(), data }
+    }
+
+    #[test]
+    fn updates_apply_in_slot_order_and_wake_watchers() {
+        let cache: &'static PoolStateCache = Box::leak(Box::new(PoolStateCache::new(false)));
+        let mut commands = cache.command_receiver.lock().unwrap().take().unwrap();
+        let (watched, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (changed, mut wakeups) = mpsc::channel(1);
+        let handle = cache.watch(vec![watched], changed);
+        assert_eq!(commands.try_recv().unwrap(), SourceCommand::Subscribe(Subscription::Account(watched)));
+
+        assert!(cache.apply(update(watched, 10, vec![1])));
+        assert!(wakeups.try_recv().is_ok());
+        assert!(!cache.apply(update(watched, 9, vec![2])));
+        assert_eq!(cache.stale_updates(), 1);
+        assert!(wakeups.try_recv().is_err());
+        assert!(cache.apply(update(watched, 10, vec![3])));
+        assert_eq!(cache.accounts.get(&watched).unwrap().data, [3]);
+        assert!(!cache.apply(update(other, 11, vec![4])));
+
+        drop(handle);
+        assert_eq!(commands.try_recv().unwrap(), SourceCommand::Unsubscribe(Subscription::Account(watched)));
+        assert!(!cache.apply(update(watched, 12, vec![5])));
+        assert!(cache.subscriptions().is_empty());
+    }
+
+    #[test]
+    fn tracked_pools_quote_from_cached_accounts() {
+        let (accounts, owners) = load_snapshot("whirlpool_sol_usdc");
+        let snapshot = pool_from_snapshot("whirlpool_sol_usdc", POOL);
+        let address = snapshot.address();
+        let cache = PoolStateCache::new(true);
+        cache.track(&LoadedPools {
+            slot: 100,
+            pools: vec![snapshot.boxed_clone()],
+            accounts: accounts.clone(),
+            owners: owners.clone(),
+        });
+
+        let loaded = cache.cached_pools(&[address, Pubkey::new_unique()]);
+        assert_eq!(loaded.slot, 100);
+        assert_eq!(loaded.pools.len(), 1);
+        let amount = 1_000_000_000;
+        assert_eq!(
+            loaded.pools[0].quote_exact_in(&SOL_MINT, amount).unwrap(),
+            snapshot.quote_exact_in(&SOL_MINT, amount).unwrap()
+        );
+        // Owners come through as the snapshot has them.
+        assert_eq!(loaded.owners.len(), loaded.accounts.len());
+        assert!(loaded.owners.iter().all(|(account, owner)| owners[account] == *owner));
+        assert_eq!(loaded.owners[&address], Venue::Whirlpool.program_id());
+        assert!(cache.moved_dependencies(&address).is_none());
+        assert!(!cache.apply(update(address, 99, vec![0; 10])));
+
+        // Program subscriptions stream the pool; its arrays and vaults stay
+        // individual.
+        let subscriptions = cache.subscriptions();
+        assert!(subscriptions.contains(&Subscription::Account(snapshot.reserve_vaults()[0])));
+        assert!(!subscriptions.contains(&Subscription::Account(address)));
+        assert!(subscriptions.iter().any(|subscription| {
+            matches!(subscription, Subscription::Program { program, .. } if *program == Venue::Whirlpool.program_id())
+        }));
+    }
+}
@@ -0,0 +1,455 @@
+use crate::pools::clmm_math::{
+    swap_to_position, ClmmPosition, ClmmState, TickLiquidity, TickMath, FEE_RATE_DENOMINATOR,
+};
+use crate::pools::layout::{anchor_instruction, Layout};
+use crate::pools::{
+    instruction_data, sells_first_mint, AccountMap, Pool, PoolQuote, SwapAccounts, SwapTransfers, Venue,
+    TOKEN_PROGRAM,
+};
+use anyhow::{anyhow, Result};
+use ethnum::U256;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+pub const PROGRAM_ID: Pubkey = pubkey!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
+/// `swap_v2` takes the memo program for mints that require transfer memos.
+const MEMO_PROGRAM: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+/// `swap_v2` remaining-account slice kinds for mint A's and B's hooks.
+const TRANSFER_HOOK_A: u8 = 0;
+const TRANSFER_HOOK_B: u8 = 1;
+
+/// `Whirlpool` size, discriminator included.
+pub const POOL_LEN: usize = 653;
+pub const MINT_A_OFFSET: usize = 101;
+pub const MINT_B_OFFSET: usize = 181;
+const TICK_ARRAY_LEN: usize = 9988;
+const TICKS_PER_ARRAY: i32 = 88;
+const TICK_LEN: usize = 113;
+/// Tick arrays loaded on each side of the current one.
+const TICK_ARRAY_RADIUS: i32 = 3;
+/// The swap instruction takes exactly this many tick arrays.
+const SWAP_TICK_ARRAYS: usize = 3;
+
+/// Orca's exact Q64.64 tick table: positive ticks are computed in Q96 and
+/// shifted down, negative ones directly in Q64, both truncating.
+pub const TICK_MATH: TickMath = TickMath {
+    min_tick: -443_636,
+    max_tick: 443_636,
+    min_sqrt_price: 4_295_048_016,
+    max_sqrt_price: 79_226_673_515_401_279_992_447_579_055,
+    sqrt_price_at_tick,
+};
+
+fn sqrt_price_at_tick(tick: i32) -> u128 {
+    if tick >= 0 {
+        sqrt_price_at_positive_tick(tick)
+    } else {
+        sqrt_price_at_negative_tick(tick)
+    }
+}
+
+fn sqrt_price_at_positive_tick(tick: i32) -> u128 {
+    const FACTORS: [u128; 19] = [
+        79232123823359799118286999567,
+        79236085330515764027303304731,
+        79244008939048815603706035061,
+        79259858533276714757314932305,
+        79291567232598584799939703904,
+        79355022692464371645785046466,
+        79482085999252804386437311141,
+        79736823300114093921829183326,
+        80248749790819932309965073892,
+        81282483887344747381513967011,
+        83390072131320151908154831281,
+        87770609709833776024991924138,
+        97234110755111693312479820773,
+        119332217159966728226237229890,
+        179736315981702064433883588727,
+        407748233172238350107850275304,
+        2098478828474011932436660412517,
+        55581415166113811149459800483533,
+        38992368544603139932233054999993551,
+    ];
+    let mut ratio: u128 = if tick & 1 != 0 { FACTORS[0] } else { 1 << 96 };
+    for (bit, factor) in FACTORS.iter().enumerate().skip(1) {
+        if tick & (1 << bit) != 0 {
+            ratio = ((U256::from(ratio) * U256::from(*factor)) >> 96u32).as_u128();
+        }
+    }
+    ratio >> 32
+}
+
+fn sqrt_price_at_negative_tick(tick: i32) -> u128 {
+    const FACTORS: [u128; 19] = [
+        18445821805675392311,
+        18444899583751176498,
+        18443055278223354162,
+        18439367220385604838,
+        18431993317065449817,
+        18417254355718160513,
+        18387811781193591352,
+        18329067761203520168,
+        18212142134806087854,
+        17980523815641551639,
+        17526086738831147013,
+        16651378430235024244,
+        15030750278693429944,
+        12247334978882834399,
+        8131365268884726200,
+        3584323654723342297,
+        696457651847595233,
+        26294789957452057,
+        37481735321082,
+    ];
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio: u128 = if abs_tick & 1 != 0 { FACTORS[0] } else { 1 << 64 };
+    for (bit, factor) in FACTORS.iter().enumerate().skip(1) {
+        if abs_tick & (1 << bit) != 0 {
+            ratio = (ratio * factor) >> 64;
+        }
+    }
+    ratio
+}
+
+/// Orca's concentrated-liquidity pool.
+#[derive(Debug, Clone)]
+pub struct Whirlpool {
+    address: Pubkey,
+    tick_spacing: u16,
+    fee_rate: u16,
+    liquidity: u128,
+    sqrt_price: u128,
+    tick_current: i32,
+    mint_a: Pubkey,
+    vault_a: Pubkey,
+    mint_b: Pubkey,
+    vault_b: Pubkey,
+    ticks: TickLiquidity,
+}
+
+impl Whirlpool {
+    pub fn decode(address: Pubkey, data: &[u8]) -> Result<Self> {
+        let layout = Layout::new(data, "Orca Whirlpool", POOL_LEN)?;
+        let tick_spacing = layout.u16(41)?;
+        if tick_spacing == 0 {
+            return Err(anyhow!("Whirlpool {} has no tick spacing", address));
+        }
+        Ok(Self {
+            address,
+            tick_spacing,
+            fee_rate: layout.u16(45)?,
+            liquidity: layout.u128(49)?,
+            sqrt_price: layout.u128(65)?,
+            tick_current: layout.i32(81)?,
+            mint_a: layout.pubkey(MINT_A_OFFSET)?,
+            vault_a: layout.pubkey(133)?,
+            mint_b: layout.pubkey(MINT_B_OFFSET)?,
+            vault_b: layout.pubkey(213)?,
+            ticks: TickLiquidity::default(),
+        })
+    }
+
+    fn ticks_per_array(&self) -> i32 {
+        self.tick_spacing as i32 * TICKS_PER_ARRAY
+    }
+
+    fn array_start(&self, tick: i32) -> i32 {
+        tick.div_euclid(self.ticks_per_array()) * self.ticks_per_array()
+    }
+
+    fn array_starts(&self) -> Vec<i32> {
+        let current = self.array_start(self.tick_current);
+        (-TICK_ARRAY_RADIUS..=TICK_ARRAY_RADIUS)
+            .map(|offset| current + offset * self.ticks_per_array())
+            .collect()
+    }
+
+    /// Orca seeds tick arrays with the decimal string of the start index.
+    fn tick_array_address(&self, start: i32) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"tick_array", self.address.as_ref(), start.to_string().as_bytes()],
+            &PROGRAM_ID,
+        )
+        .0
+    }
+
+    /// The current tick array and the next two in the swap's direction.
+    fn swap_tick_arrays(&self, a_to_b: bool) -> impl Iterator<Item = Pubkey> + '_ {
+        let step = if a_to_b { -self.ticks_per_array() } else { self.ticks_per_array() };
+        let current = self.array_start(self.tick_current);
+        (0..SWAP_TICK_ARRAYS as i32).map(move |i| self.tick_array_address(current + i * step))
+    }
+
+    fn oracle(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"oracle", self.address.as_ref()], &PROGRAM_ID).0
+    }
+
+    fn decode_tick_array(&mut self, start: i32, data: &[u8]) -> Result<()> {
+        let layout = Layout::new(data, "Whirlpool tick array", TICK_ARRAY_LEN)?;
+        let whirlpool_offset = 12 + TICKS_PER_ARRAY as usize * TICK_LEN;
+        if layout.i32(8)? != start || layout.pubkey(whirlpool_offset)? != self.address {
+            return Err(anyhow!("tick array {} does not belong to {}", start, self.address));
+        }
+        for i in 0..TICKS_PER_ARRAY {
+            let offset = 12 + i as usize * TICK_LEN;
+            if layout.u8(offset)? != 0 {
+                let tick = start + i * self.tick_spacing as i32;
+                self.ticks.insert(tick, layout.i128(offset + 1)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn quote(&self, input_mint: &Pubkey, amount: u64, exact_in: bool) -> Result<(PoolQuote, ClmmPosition)> {
+        let a_to_b = sells_first_mint(self.mints(), input_mint)?;
+        let state = ClmmState {
+            sqrt_price: self.sqrt_price,
+            tick: self.tick_current,
+            liquidity: self.liquidity,
+            fee_rate: self.fee_rate as u32,
+            ticks: &self.ticks,
+            tick_math: &TICK_MATH,
+        };
+        let (result, position) = swap_to_position(&state, amount, exact_in, a_to_b)?;
+        let quote = PoolQuote {
+            amount_in: result.amount_in,
+            amount_out: result.amount_out,
+            fee_amount: result.fee,
+            fee_mint: *input_mint,
+        };
+        Ok((quote, position))
+    }
+}
+
+impl Pool for Whirlpool {
+    fn venue(&self) -> Venue {
+        Venue::Whirlpool
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Pool> {
+        Box::new(self.clone())
+    }
+
+    fn address(&self) -> Pubkey {
+        self.address
+    }
+
+    fn mints(&self) -> [Pubkey; 2] {
+        [self.mint_a, self.mint_b]
+    }
+
+    fn reserve_vaults(&self) -> [Pubkey; 2] {
+        [self.vault_a, self.vault_b]
+    }
+
+    fn fee_bps(&self) -> f64 {
+        self.fee_rate as f64 * 10_000.0 / FEE_RATE_DENOMINATOR as f64
+    }
+
+    fn accounts_to_update(&self) -> Vec<Pubkey> {
+        let mut accounts = vec![self.address];
+        accounts.extend(self.array_starts().into_iter().map(|start| self.tick_array_address(start)));
+        accounts
+    }
+
+    fn update(&mut self, accounts: &AccountMap) -> Result<()> {
+        if let Some(data) = accounts.get(&self.address) {
+            *self = Self::decode(self.address, data)?;
+        }
+        let starts = self.array_starts();
+        self.ticks = TickLiquidity::new(starts[0], starts[starts.len() - 1] + self.ticks_per_array());
+        for start in starts {
+            if let Some(data) = accounts.get(&self.tick_array_address(start)) {
+                self.decode_tick_array(start, data)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn quote_exact_in(&self, input_mint: &Pubkey, amount_in: u64) -> Result<PoolQuote> {
+        self.quote(input_mint, amount_in, true).map(|(quote, _)| quote)
+    }
+
+    fn quote_exact_out(&self, input_mint: &Pubkey, amount_out: u64) -> Result<PoolQuote> {
+        self.quote(input_mint, amount_out, false).map(|(quote, _)| quote)
+    }
+
+    fn apply_swap(&mut self, input_mint: &Pubkey, amount_in: u64) -> Result<PoolQuote> {
+        let (quote, position) = self.quote(input_mint, amount_in, true)?;
+        self.sqrt_price = position.sqrt_price;
+        self.tick_current = position.tick;
+        self.liquidity = position.liquidity;
+        Ok(quote)
+    }
+
+    /// `swap`: amount, threshold, price limit at the end of the table, input
+    /// specified, direction.
+    fn swap_data(&self, input_mint: &Pubkey, amount_in: u64, minimum_out: u64) -> Result<Vec<u8>> {
+        let a_to_b = sells_first_mint(self.mints(), input_mint)?;
+        let limit = if a_to_b { TICK_MATH.min_sqrt_price } else { TICK_MATH.max_sqrt_price };
+        Ok(instruction_data(
+            &anchor_instruction("swap"),
+            &[
+                &amount_in.to_le_bytes(),
+                &minimum_out.to_le_bytes(),
+                &limit.to_le_bytes(),
+                &[1],
+                &[a_to_b as u8],
+            ],
+        ))
+    }
+
+    fn accounts_for_swap(&self, swap: &SwapAccounts) -> Result<Vec<AccountMeta>> {
+        let a_to_b = sells_first_mint(self.mints(), &swap.input_mint)?;
+        let (owner_a, owner_b) = if a_to_b {
+            (swap.user_source, swap.user_destination)
+        } else {
+            (swap.user_destination, swap.user_source)
+        };
+
+        let mut metas = vec![
+            AccountMeta::new_readonly(TOKEN_PROGRAM, false),
+            AccountMeta::new_readonly(swap.user, true),
+            AccountMeta::new(self.address, false),
+            AccountMeta::new(owner_a, false),
+            AccountMeta::new(self.vault_a, false),
+            AccountMeta::new(owner_b, false),
+            AccountMeta::new(self.vault_b, false),
+        ];
+        metas.extend(self.swap_tick_arrays(a_to_b).map(|address| AccountMeta::new(address, false)));
+        metas.push(AccountMeta::new_readonly(self.oracle(), false));
+        Ok(metas)
+    }
+
+    /// `swap` for SPL Token mints, `swap_v2` otherwise: it takes both
+    /// mints' programs and their hooks' accounts after its own.
+    fn swap_instruction_for(
+        &self,
+        swap: &SwapAccounts,
+        transfers: &SwapTransfers,
+        amount_in: u64,
+        minimum_out: u64,
+    ) -> Result<Instruction> {
+        let plain = transfers.token_programs.iter().all(|program| *program == TOKEN_PROGRAM)
+            && transfers.hook_accounts.iter().all(Vec::is_empty);
+        if plain {
+            return self.swap_instruction(swap, amount_in, minimum_out);
+        }
+
+        let a_to_b = sells_first_mint(self.mints(), &swap.input_mint)?;
+        let [input, output] = [0, 1].map(|side| (transfers.token_programs[side], &transfers.hook_accounts[side]));
+        let ((program_a, hooks_a), (program_b, hooks_b), owner_a, owner_b) = if a_to_b {
+            (input, output, swap.user_source, swap.user_destination)
+        } else {
+            (output, input, swap.user_destination, swap.user_source)
+        };
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(program_a, false),
+            AccountMeta::new_readonly(program_b, false),
+            AccountMeta::new_readonly(MEMO_PROGRAM, false),
+            AccountMeta::new_readonly(swap.user, true),
+            AccountMeta::new(self.address, false),
+            AccountMeta::new_readonly(self.mint_a, false),
+            AccountMeta::new_readonly(self.mint_b, false),
+            AccountMeta::new(owner_a, false),
+            AccountMeta::new(self.vault_a, false),
+            AccountMeta::new(owner_b, false),
+            AccountMeta::new(self.vault_b, false),
+        ];
+        accounts.extend(self.swap_tick_arrays(a_to_b).map(|address| AccountMeta::new(address, false)));
+        accounts.push(AccountMeta::new(self.oracle(), false));
+        accounts.extend(hooks_a.iter().chain(hooks_b).cloned());
+
+        // `Option<RemainingAccountsInfo>`: which remaining accounts are whose.
+        let slices: Vec<[u8; 2]> = [(TRANSFER_HOOK_A, hooks_a), (TRANSFER_HOOK_B, hooks_b)]
+            .into_iter()
+            .filter(|(_, hooks)| !hooks.is_empty())
+            .map(|(kind, hooks)| u8::try_from(hooks.len()).map(|len| [kind, len]))
+            .collect::<Result<_, _>>()
+            .map_err(|_| anyhow!("too many transfer hook accounts"))?;
+        let mut remaining = vec![!slices.is_empty() as u8];
+        if !slices.is_empty() {
+            remaining.extend_from_slice(&(slices.len() as u32).to_le_bytes());
+            remaining.extend(slices.concat());
+        }
+
+        let limit = if a_to_b { TICK_MATH.min_sqrt_price } else { TICK_MATH.max_sqrt_price };
+        Ok(Instruction {
+            program_id: PROGRAM_ID,
+            accounts,
+            data: instruction_data(
+                &anchor_instruction("swap_v2"),
+                &[
+                    &amount_in.to_le_bytes(),
+                    &minimum_out.to_le_bytes(),
+                    &limit.to_le_bytes(),
+                    &[1],
+                    &[a_to_b as u8],
+                    &remaining,
+                ],
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::pool_from_snapshot;
+    use std::str::FromStr;
+
+    const POOL: &str = "Czfq3xZZDmsdGdUyrNLtRhGc47cXcZtLG4crryfu44zE";
+    const SOL: &str = "So11111111111111111111111111111111111111112";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn mint(s: &str) -> Pubkey {
+        Pubkey::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn tick_table_matches_program_bounds() {
+        assert_eq!(sqrt_price_at_tick(TICK_MATH.min_tick), TICK_MATH.min_sqrt_price);
+        assert_eq!(sqrt_price_at_tick(TICK_MATH.max_tick), TICK_MATH.max_sqrt_price);
+        assert_eq!(sqrt_price_at_tick(-1), 18445821805675392311);
+    }
+
+    #[test]
+    fn quotes_both_directions() {
+        let pool = pool_from_snapshot("whirlpool_sol_usdc", POOL);
+
+        let sell = pool.quote_exact_in(&mint(SOL), 5_000_000_000_000).unwrap();
+        assert_eq!((sell.amount_out, sell.fee_amount), (749_727_045_520, 2_500_000_000));
+
+        let buy = pool.quote_exact_in(&mint(USDC), 3_000_000_000_000).unwrap();
+        assert_eq!(buy.amount_out, 19_830_066_243_142);
+
+        let exact_out = pool.quote_exact_out(&mint(SOL), 100_000_000).unwrap();
+        assert_eq!(exact_out.amount_in, 666_112_125);
+    }
+
+    #[test]
+    fn token_2022_swaps_use_swap_v2_with_hook_accounts() {
+        let pool = pool_from_snapshot("whirlpool_sol_usdc", POOL);
+        let swap = SwapAccounts {
+            user: Pubkey::new_unique(),
+            input_mint: mint(USDC),
+            user_source: Pubkey::new_unique(),
+            user_destination: Pubkey::new_unique(),
+        };
+        let plain = pool.swap_instruction_for(&swap, &SwapTransfers::default(), 1_000, 900).unwrap();
+        assert_eq!(plain, pool.swap_instruction(&swap, 1_000, 900).unwrap());
+
+        let token_2022 = crate::pools::TOKEN_2022_PROGRAM;
+        let hooks = vec![AccountMeta::new_readonly(Pubkey::new_unique(), false); 3];
+        let transfers = SwapTransfers { token_programs: [token_2022, TOKEN_PROGRAM], hook_accounts: [hooks, vec![]] };
+        let v2 = pool.swap_instruction_for(&swap, &transfers, 1_000, 900).unwrap();
+        assert_eq!(v2.data[..8], anchor_instruction("swap_v2"));
+        // Selling B: its program is second, and its hooks are slice kind 1.
+        let programs: Vec<Pubkey> = v2.accounts[..2].iter().map(|meta| meta.pubkey).collect();
+        assert_eq!(programs, [TOKEN_PROGRAM, token_2022]);
+        assert_eq!(v2.accounts.len(), 15 + 3);
+        assert_eq!(v2.data[v2.data.len() - 7..], [1, 1, 0, 0, 0, TRANSFER_HOOK_B, 3]);
+    }
+}
@@ -0,0 +1,249 @@
+use crate::arb::ArbQuote;
+use crate::quote::{QuoteHop, SwapQuote};
+use crate::tx::builder::min_out;
+use crate::tx::fees::{network_fee, SIGNATURE_FEE_LAMPORTS};
+use crate::tx::token_2022::TokenMints;
+use crate::utils::token_amount::format_token_amount;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Serialize;
+use solana_sdk::message::VersionedMessage;
+
+/// Quotes moving the price by more than this, in percent, are blocked
+/// unless `QUOTE_MAX_PRICE_IMPACT_PCT` says otherwise.
+const DEFAULT_MAX_PRICE_IMPACT_PCT: f64 = 15.0;
+
+pub fn max_price_impact_pct() -> f64 {
+    std::env::var("QUOTE_MAX_PRICE_IMPACT_PCT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_PRICE_IMPACT_PCT)
+}
+
+/// What landing the swap costs, in lamports.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeeBreakdown {
+    /// Base fee for the swap's signatures.
+    pub network_fee_lamports: u64,
+    /// Compute-unit price times the swap's unit limit.
+    pub priority_fee_lamports: u64,
+    /// Tip the back-run pays out of its profit; the user doesn't pay it.
+    pub jito_tip_lamports: u64,
+    /// Network and priority fee: what the user's own transaction pays.
+    pub total_user_fee_lamports: u64,
+}
+
+/// What the mints' Token-2022 extensions change about the swap. Raw
+/// amounts are strings like the rest of the frame's.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TokenAdjustments {
+    /// Withheld from the input on its way into the first pool.
+    pub input_transfer_fee_raw: String,
+    /// Withheld from the output on its way to the wallet; already taken off
+    /// the expected and minimum output.
+    pub output_transfer_fee_raw: String,
+    /// Mints whose transfer hooks run during the swap.
+    pub transfer_hook_mints: Vec<String>,
+    /// UI amount per raw amount of interest-bearing mints, 1 for others;
+    /// prices and UI amounts include it.
+    pub input_ui_scale: f64,
+    pub output_ui_scale: f64,
+}
+
+/// Prices are in units of the output token per unit of the input token.
+#[derive(Debug, Clone, Serialize)]
+pub struct QuoteBreakdown {
+    /// Output that reaches the wallet.
+    pub expected_out: f64,
+    pub expected_out_raw: String,
+    /// Price before the swap moves the pools.
+    pub mid_price: f64,
+    /// Price the whole swap fills at.
+    pub execution_price: f64,
+    /// How far the execution price falls below the mid price.
+    pub price_impact_pct: f64,
+    /// Output reaching the wallet at the requested slippage; the swap fails
+    /// below this.
+    pub min_out: f64,
+    pub min_out_raw: String,
+    pub hops: Vec<QuoteHop>,
+    pub fees: FeeBreakdown,
+    pub token_extensions: TokenAdjustments,
+    /// Set when the impact is over [`max_price_impact_pct`]; the frame
+    /// then carries no message to sign.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_reason: Option<String>,
+}
+
+impl QuoteBreakdown {
+    pub fn blocked(&self) -> bool {
+        self.block_reason.is_some()
+    }
+}
+
+/// The quote's amounts are what the pools take and pay; the mints' transfer
+/// fees come off on top, as `mints` has them for this epoch.
+pub fn quote_breakdown(
+    quote: &SwapQuote,
+    mints: &TokenMints,
+    arb: Option<&ArbQuote>,
+    slippage_bps: u16,
+    max_impact_pct: f64,
+) -> QuoteBreakdown {
+    let in_amount = u64::try_from(quote.in_amount).unwrap_or(u64::MAX);
+    let out_amount = u64::try_from(quote.out_amount).unwrap_or(u64::MAX);
+    let output_fee = mints.transfer_fee(&quote.output_mint, out_amount);
+    let expected_out_raw = (out_amount - output_fee) as u128;
+    let adjustments = TokenAdjustments {
+        input_transfer_fee_raw: mints.transfer_fee(&quote.input_mint, in_amount).to_string(),
+        output_transfer_fee_raw: output_fee.to_string(),
+        transfer_hook_mints: [quote.input_mint, quote.output_mint]
+            .iter()
+            .filter(|mint| mints.transfer_hook(mint).is_some())
+            .map(|mint| mint.to_string())
+            .collect(),
+        input_ui_scale: mints.ui_scale(&quote.input_mint),
+        output_ui_scale: mints.ui_scale(&quote.output_mint),
+    };
+
+    let ui = |raw: u128, decimals: u8, scale: f64| {
+        format_token_amount(raw, decimals).parse::<f64>().unwrap_or(0.0) * scale
+    };
+    let in_ui = ui(quote.in_amount, quote.in_decimals, adjustments.input_ui_scale);
+    let expected_out = ui(expected_out_raw, quote.out_decimals, adjustments.output_ui_scale);
+    let execution_price = if in_ui > 0.0 { expected_out / in_ui } else { 0.0 };
+    // The impact is reported against the mid price, so the mid price
+    // follows from the fill.
+    let price_impact = quote.price_impact.clamp(0.0, 1.0);
+    let mid_price = if price_impact < 1.0 { execution_price / (1.0 - price_impact) } else { execution_price };
+    let price_impact_pct = price_impact * 100.0;
+
+    let min_gross = min_out(out_amount, slippage_bps);
+    let min_out_raw = (min_gross - mints.transfer_fee(&quote.output_mint, min_gross)) as u128;
+    let block_reason = (price_impact_pct > max_impact_pct).then(|| {
+        format!("price impact of {:.2}% is over the {:.2}% limit", price_impact_pct, max_impact_pct)
+    });
+
+    QuoteBreakdown {
+        expected_out,
+        expected_out_raw: expected_out_raw.to_string(),
+        mid_price,
+        execution_price,
+        price_impact_pct,
+        min_out: ui(min_out_raw, quote.out_decimals, adjustments.output_ui_scale),
+        min_out_raw: min_out_raw.to_string(),
+        hops: quote.route.clone(),
+        fees: fee_breakdown(&quote.message, arb),
+        token_extensions: adjustments,
+        block_reason,
+    }
+}
+
+fn fee_breakdown(message_b64: &str, arb: Option<&ArbQuote>) -> FeeBreakdown {
+    let message = BASE64
+        .decode(message_b64)
+        .ok()
+        .and_then(|bytes| bincode::deserialize::<VersionedMessage>(&bytes).ok());
+    let (network_fee_lamports, priority_fee_lamports) = match &message {
+        Some(message) => {
+            let base = message.header().num_required_signatures as u64 * SIGNATURE_FEE_LAMPORTS;
+            (base, network_fee(message).saturating_sub(base))
+        }
+        None => (SIGNATURE_FEE_LAMPORTS, 0),
+    };
+    FeeBreakdown {
+        network_fee_lamports,
+        priority_fee_lamports,
+        jito_tip_lamports: arb.map_or(0, |arb| arb.tip_lamports),
+        total_user_fee_lamports: network_fee_lamports + priority_fee_lamports,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::indexer::{SOL_MINT, USDC_MINT};
+    use crate::pools::TOKEN_2022_PROGRAM;
+    use crate::tx::builder::compute_budget_instructions;
+    use crate::tx::system;
+    use crate::tx::token_2022::{MintExtensions, TransferFee, TransferFeeConfig};
+    use std::collections::HashMap;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::v0;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn swap_quote(price_impact: f64) -> SwapQuote {
+        let payer = Pubkey::new_unique();
+        let mut instructions = compute_budget_instructions(200_000, 40_000);
+        instructions.push(system::transfer(&payer, &Pubkey::new_unique(), 1));
+        let message = v0::Message::try_compile(&payer, &instructions, &[], Hash::new_unique()).unwrap();
+        SwapQuote {
+            input_mint: SOL_MINT,
+            output_mint: USDC_MINT,
+            // 2 SOL for 300 USDC
+            in_amount: 2_000_000_000,
+            in_decimals: 9,
+            out_amount: 300_000_000,
+            out_decimals: 6,
+            price_impact,
+            pools: vec![],
+            route: vec![],
+            hops: vec![],
+            message: BASE64.encode(VersionedMessage::V0(message).serialize()),
+        }
+    }
+
+    #[test]
+    fn breaks_down_prices_slippage_and_fees() {
+        let breakdown = quote_breakdown(&swap_quote(0.004), &TokenMints::default(), None, 50, 15.0);
+        assert_eq!(breakdown.execution_price, 150.0);
+        assert!((breakdown.mid_price - 150.0 / 0.996).abs() < 1e-9);
+        assert!((breakdown.price_impact_pct - 0.4).abs() < 1e-9);
+        assert_eq!(breakdown.min_out_raw, "298500000");
+        assert_eq!(breakdown.min_out, 298.5);
+        assert_eq!(
+            breakdown.fees,
+            FeeBreakdown {
+                network_fee_lamports: 5_000,
+                priority_fee_lamports: 40_000,
+                jito_tip_lamports: 0,
+                total_user_fee_lamports: 45_000,
+            }
+        );
+        assert!(!breakdown.blocked());
+        assert_eq!(breakdown.expected_out_raw, "300000000");
+        assert!(breakdown.token_extensions.transfer_hook_mints.is_empty());
+    }
+
+    #[test]
+    fn takes_output_transfer_fees_and_reports_hooks() {
+        let fee = TransferFee { epoch: 0, maximum_fee: 2_000_000, basis_points: 100 };
+        let usdc = MintExtensions {
+            token_program: TOKEN_2022_PROGRAM,
+            transfer_fee: Some(TransferFeeConfig { older: fee, newer: fee }),
+            ..MintExtensions::default()
+        };
+        let sol = MintExtensions { transfer_hook: Some(Pubkey::new_unique()), ..MintExtensions::default() };
+        let mints = TokenMints::new(HashMap::from([(USDC_MINT, usdc), (SOL_MINT, sol)]), 0, 0);
+
+        let breakdown = quote_breakdown(&swap_quote(0.004), &mints, None, 50, 15.0);
+        // 1% of 300 USDC is over the 2 USDC cap.
+        assert_eq!(breakdown.expected_out_raw, "298000000");
+        assert_eq!(breakdown.expected_out, 298.0);
+        assert_eq!(breakdown.execution_price, 149.0);
+        assert_eq!(breakdown.min_out_raw, "296500000");
+        let adjustments = &breakdown.token_extensions;
+        assert_eq!(adjustments.input_transfer_fee_raw, "0");
+        assert_eq!(adjustments.output_transfer_fee_raw, "2000000");
+        assert_eq!(adjustments.transfer_hook_mints, [SOL_MINT.to_string()]);
+    }
+
+    #[test]
+    fn blocks_quotes_over_the_impact_limit() {
+        let mints = TokenMints::default();
+        let breakdown = quote_breakdown(&swap_quote(0.2), &mints, None, 50, 15.0);
+        assert!(breakdown.blocked());
+        assert!(breakdown.block_reason.unwrap().contains("20.00%"));
+        assert!(!quote_breakdown(&swap_quote(0.2), &mints, None, 50, 25.0).blocked());
+    }
+}
@@ -0,0 +1,134 @@
+use crate::arb::search::UserHop;
+use crate::quote::params::QuoteParams;
+use crate::quote::{swap_amount_in, QuoteHop, SwapQuote};
+use crate::REQWEST_CLIENT;
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use reqwest::Client;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::VersionedTransaction;
+use std::str::FromStr;
+use std::time::Duration;
+
+const DEFAULT_JUPITER_API: &str = "https://lite-api.jup.ag/swap/v1";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Jupiter swap API base; override with `JUPITER_API_URL`.
+fn jupiter_api() -> String {
+    std::env::var("JUPITER_API_URL").unwrap_or_else(|_| DEFAULT_JUPITER_API.to_string())
+}
+
+/// Whether quotes may come from Jupiter; off unless `JUPITER_FALLBACK` is
+/// `1` or `true`.
+pub fn jupiter_fallback_enabled() -> bool {
+    std::env::var("JUPITER_FALLBACK").is_ok_and(|v| matches!(v.trim(), "1" | "true"))
+}
+
+pub async fn fetch_swap_quote(params: &QuoteParams) -> Result<SwapQuote> {
+    let client = REQWEST_CLIENT.get_or_init(Client::new);
+    let x_mint = params.x_mint.to_string();
+    let y_mint = params.y_mint.to_string();
+
+    let (in_amount, in_decimals, out_decimals) = swap_amount_in(params).await?;
+
+    let quote: Value = client
+        .get(format!("{}/quote", jupiter_api()))
+        .query(&[
+            ("inputMint", x_mint.as_str()),
+            ("outputMint", y_mint.as_str()),
+            ("amount", &in_amount.to_string()),
+            ("slippageBps", &params.slippage_bps.to_string()),
+        ])
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await?
+        .json()
+        .await?;
+    if let Some(error) = quote["error"].as_str() {
+        return Err(anyhow!("no route: {}", error));
+    }
+
+    let amount_field = |key: &str| -> Result<u128> {
+        quote[key]
+            .as_str()
+            .and_then(|v| v.parse::<u128>().ok())
+            .ok_or_else(|| anyhow!("quote response is missing '{}'", key))
+    };
+    let out_amount = amount_field("outAmount")?;
+
+    let route = quote["routePlan"].as_array().cloned().unwrap_or_default();
+    let pools = route
+        .iter()
+        .filter_map(|hop| hop["swapInfo"]["ammKey"].as_str().map(String::from))
+        .collect();
+    let hops = route.iter().filter_map(|hop| user_hop(&hop["swapInfo"])).collect();
+    let route = route.iter().filter_map(|hop| route_hop(&hop["swapInfo"])).collect();
+    let price_impact = quote["priceImpactPct"]
+        .as_str()
+        .and_then(|v| v.parse::<f64>().ok())
+        .unwrap_or(0.0);
+
+    let swap: Value = client
+        .post(format!("{}/swap", jupiter_api()))
+        .json(&json!({
+            "quoteResponse": quote,
+            "userPublicKey": params.signer.to_string(),
+            "wrapAndUnwrapSol": true,
+            "dynamicComputeUnitLimit": true,
+            "prioritizationFeeLamports": params.priority_fee_lamports,
+        }))
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let swap_transaction = swap["swapTransaction"]
+        .as_str()
+        .ok_or_else(|| anyhow!("swap response is missing 'swapTransaction'"))?;
+
+    Ok(SwapQuote {
+        input_mint: params.x_mint,
+        output_mint: params.y_mint,
+        in_amount: in_amount as u128,
+        in_decimals,
+        out_amount,
+        out_decimals,
+        price_impact,
+        pools,
+        route,
+        hops,
+        message: unsigned_message(swap_transaction)?,
+    })
+}
+
+fn route_hop(swap_info: &Value) -> Option<QuoteHop> {
+    let field = |key: &str| swap_info[key].as_str().map(String::from);
+    Some(QuoteHop {
+        venue: field("label").unwrap_or_else(|| "Unknown".to_string()),
+        pool: field("ammKey")?,
+        input_mint: field("inputMint")?,
+        output_mint: field("outputMint")?,
+        in_amount_raw: field("inAmount")?,
+        out_amount_raw: field("outAmount")?,
+    })
+}
+
+fn user_hop(swap_info: &Value) -> Option<UserHop> {
+    let pubkey = |key: &str| swap_info[key].as_str().and_then(|s| Pubkey::from_str(s).ok());
+    Some(UserHop {
+        pool: pubkey("ammKey")?,
+        input_mint: pubkey("inputMint")?,
+        output_mint: pubkey("outputMint")?,
+        amount_in: swap_info["inAmount"].as_str()?.parse().ok()?,
+    })
+}
+
+/// Jupiter returns a transaction with empty signature slots; the client
+/// signs the message itself.
+fn unsigned_message(transaction_b64: &str) -> Result<String> {
+    let bytes = BASE64.decode(transaction_b64)?;
+    let transaction: VersionedTransaction = bincode::deserialize(&bytes)?;
+    Ok(BASE64.encode(transaction.message.serialize()))
+}
@@ -0,0 +1,169 @@
+pub mod breakdown;
+pub mod jupiter;
+pub mod params;
+pub mod pool_watch;
+pub mod router;
+
+use crate::arb::search::UserHop;
+use crate::quote::jupiter::{fetch_swap_quote, jupiter_fallback_enabled};
+use crate::quote::params::QuoteParams;
+use crate::quote::router::{best_route, Route, RouterConfig};
+use crate::rpc::mint_decimals::get_mint_decimals;
+use crate::tx::builder::{build_swap_message, SwapTxParams};
+use crate::tx::token_2022::TokenMints;
+use crate::utils::token_amount::parse_token_amount;
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Serialize;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::pubkey::Pubkey;
+
+/// One swap of a quoted route, as the frame reports it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuoteHop {
+    pub venue: String,
+    pub pool: String,
+    pub input_mint: String,
+    pub output_mint: String,
+    pub in_amount_raw: String,
+    pub out_amount_raw: String,
+}
+
+/// A routed quote together with the unsigned swap message for the signer.
+#[derive(Debug, Clone)]
+pub struct SwapQuote {
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub in_amount: u128,
+    pub in_decimals: u8,
+    /// What the pools pay out, before the output's transfer fee.
+    pub out_amount: u128,
+    pub out_decimals: u8,
+    /// Price impact as a fraction of the mid price.
+    pub price_impact: f64,
+    /// Pool accounts the route trades through.
+    pub pools: Vec<String>,
+    pub route: Vec<QuoteHop>,
+    /// The route's hops, for replaying the swap against pool state.
+    pub hops: Vec<UserHop>,
+    /// Base64 of the serialized, unsigned `VersionedMessage`.
+    pub message: String,
+}
+
+impl SwapQuote {
+    /// `route` as the router found it, to be signed as `message`.
+    pub fn routed(route: &Route, in_decimals: u8, out_decimals: u8, message: &VersionedMessage) -> Self {
+        let output_fees: u64 = route.legs.iter().filter_map(|leg| leg.hops.last()).map(|hop| hop.output_transfer_fee).sum();
+        Self {
+            input_mint: route.input_mint,
+            output_mint: route.output_mint,
+            in_amount: route.amount_in as u128,
+            in_decimals,
+            out_amount: (route.amount_out + output_fees) as u128,
+            out_decimals,
+            price_impact: route.price_impact,
+            pools: route.pools().iter().map(|pool| pool.to_string()).collect(),
+            route: route
+                .hops()
+                .map(|hop| QuoteHop {
+                    venue: hop.venue.label().to_string(),
+                    pool: hop.pool.to_string(),
+                    input_mint: hop.input_mint.to_string(),
+                    output_mint: hop.output_mint.to_string(),
+                    in_amount_raw: hop.amount_in.to_string(),
+                    out_amount_raw: hop.amount_out.to_string(),
+                })
+                .collect(),
+            hops: route
+                .hops()
+                .map(|hop| UserHop {
+                    pool: hop.pool,
+                    input_mint: hop.input_mint,
+                    output_mint: hop.output_mint,
+                    amount_in: hop.amount_in,
+                })
+                .collect(),
+            message: BASE64.encode(message.serialize()),
+        }
+    }
+
+    pub fn route_labels(&self) -> Vec<String> {
+        self.route.iter().map(|hop| hop.venue.clone()).collect()
+    }
+}
+
+/// The raw input amount with both mints' decimals.
+pub async fn swap_amount_in(params: &QuoteParams) -> Result<(u64, u8, u8)> {
+    let (x_mint, y_mint) = (params.x_mint.to_string(), params.y_mint.to_string());
+    let (in_decimals, out_decimals) = tokio::try_join!(get_mint_decimals(&x_mint), get_mint_decimals(&y_mint))?;
+    let in_amount = parse_token_amount(&params.amount.to_string(), in_decimals)
+        .and_then(|amount| u64::try_from(amount).ok())
+        .filter(|amount| *amount > 0)
+        .ok_or_else(|| anyhow!("amount is below the smallest unit of the input token"))?;
+    Ok((in_amount, in_decimals, out_decimals))
+}
+
+/// Quotes the swap over the indexed pools and builds its message; Jupiter
+/// only answers when the router can't and `JUPITER_FALLBACK` allows it.
+/// Returns the extensions of the mints involved with the quote.
+pub async fn fetch_quote(params: &QuoteParams) -> Result<(SwapQuote, TokenMints)> {
+    match routed_quote(params).await {
+        Ok(quoted) => Ok(quoted),
+        Err(e) if jupiter_fallback_enabled() => {
+            eprintln!("[quote] Router failed for {}, asking Jupiter: {}", params.signer, e);
+            let swap_mints = [params.x_mint, params.y_mint];
+            Ok(tokio::try_join!(fetch_swap_quote(params), TokenMints::fetch(&swap_mints))?)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+async fn routed_quote(params: &QuoteParams) -> Result<(SwapQuote, TokenMints)> {
+    let (in_amount, in_decimals, out_decimals) = swap_amount_in(params).await?;
+    let (route, loaded, mints) =
+        best_route(params.x_mint, params.y_mint, in_amount, &RouterConfig::default()).await?;
+    let swap = SwapTxParams {
+        user: params.signer,
+        slippage_bps: params.slippage_bps,
+        priority_fee_lamports: params.priority_fee_lamports,
+    };
+    let message = build_swap_message(&route, &loaded.pools, &mints, &swap).await?;
+    Ok((SwapQuote::routed(&route, in_decimals, out_decimals, &message), mints))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::indexer::{SOL_MINT, USDC_MINT};
+    use crate::pools::pool_from_snapshot;
+    use crate::quote::router::find_route;
+    use crate::tx::builder::{compile_message, compute_budget_instructions, swap_instructions};
+    use solana_sdk::hash::Hash;
+
+    #[test]
+    fn routed_quotes_sign_the_routed_hops() {
+        let pools = vec![pool_from_snapshot("whirlpool_sol_usdc", "Czfq3xZZDmsdGdUyrNLtRhGc47cXcZtLG4crryfu44zE")];
+        let mints = TokenMints::default();
+        let route = find_route(&pools, SOL_MINT, USDC_MINT, 1_000_000_000, &RouterConfig::default(), &mints).unwrap();
+        let params = SwapTxParams { user: Pubkey::new_unique(), slippage_bps: 50, priority_fee_lamports: 0 };
+        let mut instructions = compute_budget_instructions(200_000, 0);
+        instructions.extend(swap_instructions(&route, &pools, &params, &mints).unwrap());
+        let message = compile_message(&params.user, &instructions, &[], Hash::new_unique()).unwrap();
+
+        let quote = SwapQuote::routed(&route, 9, 6, &message);
+        assert_eq!(quote.pools, [pools[0].address().to_string()]);
+        assert_eq!(quote.route_labels(), [pools[0].venue().label()]);
+        assert_eq!(quote.out_amount, route.amount_out as u128);
+        assert_eq!(quote.hops[0].amount_in, 1_000_000_000);
+
+        let bytes = BASE64.decode(&quote.message).unwrap();
+        let decoded: VersionedMessage = bincode::deserialize(&bytes).unwrap();
+        let programs: Vec<Pubkey> = decoded
+            .instructions()
+            .iter()
+            .map(|ix| decoded.static_account_keys()[ix.program_id_index as usize])
+            .collect();
+        assert!(programs.contains(&pools[0].venue().program_id()));
+    }
+}
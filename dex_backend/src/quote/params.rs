@@ -0,0 +1,166 @@
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+/// Slippage above 50% is almost certainly a unit mistake.
+pub const MAX_SLIPPAGE_BPS: u16 = 5_000;
+/// 0.1 SOL; the frontend converts SOL to lamports and adds 20% headroom.
+pub const MAX_PRIORITY_FEE_LAMPORTS: u64 = 100_000_000;
+
+/// Quote subscription parameters, as sent by `useQuoteWebSocket`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoteParams {
+    pub signer: Pubkey,
+    pub x_mint: Pubkey,
+    pub y_mint: Pubkey,
+    /// Input amount in UI units of `x_mint`.
+    pub amount: f64,
+    pub slippage_bps: u16,
+    pub priority_fee_lamports: u64,
+}
+
+impl QuoteParams {
+    /// From the connection query string.
+    pub fn from_query(query: &HashMap<String, String>) -> Result<Self, String> {
+        Self::parse(|key| query.get(key).cloned())
+    }
+
+    /// Applies a re-subscription frame on top of the current parameters;
+    /// fields the frame leaves out keep their value. Numbers may be JSON
+    /// numbers or strings.
+    pub fn updated_with(&self, frame: &Value) -> Result<Self, String> {
+        let mut fields = HashMap::from([
+            ("signer".to_string(), self.signer.to_string()),
+            ("x_mint".to_string(), self.x_mint.to_string()),
+            ("y_mint".to_string(), self.y_mint.to_string()),
+            ("amount".to_string(), self.amount.to_string()),
+            ("slippage".to_string(), self.slippage_bps.to_string()),
+            ("priority_fee".to_string(), self.priority_fee_lamports.to_string()),
+        ]);
+        let Some(frame) = frame.as_object() else {
+            return Err("re-subscription must be a JSON object".into());
+        };
+        for (key, value) in frame {
+            match value {
+                Value::String(s) => fields.insert(key.clone(), s.clone()),
+                Value::Number(n) => fields.insert(key.clone(), n.to_string()),
+                _ => None,
+            };
+        }
+        Self::from_query(&fields)
+    }
+
+    fn parse(get: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let pubkey = |key: &str| -> Result<Pubkey, String> {
+            get(key)
+                .ok_or_else(|| format!("missing '{}'", key))?
+                .trim()
+                .parse::<Pubkey>()
+                .map_err(|_| format!("'{}' is not a valid address", key))
+        };
+        let number = |key: &str| -> Result<f64, String> {
+            get(key)
+                .ok_or_else(|| format!("missing '{}'", key))?
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite() && *n >= 0.0)
+                .ok_or_else(|| format!("'{}' must be a non-negative number", key))
+        };
+
+        let signer = pubkey("signer")?;
+        let x_mint = pubkey("x_mint")?;
+        let y_mint = pubkey("y_mint")?;
+        if x_mint == y_mint {
+            return Err("'x_mint' and 'y_mint' must differ".into());
+        }
+
+        let amount = number("amount")?;
+        if amount <= 0.0 {
+            return Err("'amount' must be greater than zero".into());
+        }
+
+        let slippage = number("slippage")?.round();
+        if slippage > MAX_SLIPPAGE_BPS as f64 {
+            return Err(format!("'slippage' must be at most {} bps", MAX_SLIPPAGE_BPS));
+        }
+
+        // Optional: quotes without a fee are still useful for display.
+        let priority_fee = match get("priority_fee") {
+            Some(_) => number("priority_fee")?.round(),
+            None => 0.0,
+        };
+        if priority_fee > MAX_PRIORITY_FEE_LAMPORTS as f64 {
+            return Err(format!(
+                "'priority_fee' must be at most {} lamports",
+                MAX_PRIORITY_FEE_LAMPORTS
+            ));
+        }
+
+        Ok(Self {
+            signer,
+            x_mint,
+            y_mint,
+            amount,
+            slippage_bps: slippage as u16,
+            priority_fee_lamports: priority_fee as u64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SOL: &str = "So11111111111111111111111111111111111111112";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn query(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn frontend_query() -> HashMap<String, String> {
+        query(&[
+            ("signer", "GfsJWjmGXMfct8JMR9Lm9ySUnniZbnGUTQDbT8ipWf9U"),
+            ("x_mint", SOL),
+            ("y_mint", USDC),
+            ("amount", "1.5"),
+            ("slippage", "50"),
+            // Math.round(fee * 1e9) * 1.2 is not always an integer
+            ("priority_fee", "1199999.9999999998"),
+        ])
+    }
+
+    #[test]
+    fn parses_frontend_query() {
+        let params = QuoteParams::from_query(&frontend_query()).unwrap();
+        assert_eq!(params.amount, 1.5);
+        assert_eq!(params.slippage_bps, 50);
+        assert_eq!(params.priority_fee_lamports, 1_200_000);
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        let with = |key: &str, value: &str| {
+            let mut q = frontend_query();
+            q.insert(key.to_string(), value.to_string());
+            QuoteParams::from_query(&q)
+        };
+        assert!(with("y_mint", SOL).is_err());
+        assert!(with("amount", "0").is_err());
+        assert!(with("amount", "NaN").is_err());
+        assert!(with("slippage", "10001").is_err());
+        assert!(with("signer", "not-a-key").is_err());
+    }
+
+    #[test]
+    fn resubscription_keeps_missing_fields() {
+        let params = QuoteParams::from_query(&frontend_query()).unwrap();
+        let updated = params.updated_with(&json!({ "amount": 2, "slippage": "100" })).unwrap();
+        assert_eq!(updated.amount, 2.0);
+        assert_eq!(updated.slippage_bps, 100);
+        assert_eq!(updated.x_mint, params.x_mint);
+        assert!(params.updated_with(&json!(["amount"])).is_err());
+    }
+}
@@ -0,0 +1,24 @@
+use crate::pools::state_cache::{pool_state_cache, WatchHandle};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use tokio::sync::mpsc;
+
+/// Watches the pool accounts of the current route through the pool-state
+/// cache and signals on any change the cache accepts. Signals coalesce: a
+/// full channel already means "requote".
+pub struct PoolWatch {
+    pools: Vec<String>,
+    _handle: WatchHandle,
+}
+
+impl PoolWatch {
+    pub fn start(pools: Vec<String>, changed: mpsc::Sender<()>) -> Self {
+        let keys: Vec<Pubkey> = pools.iter().filter_map(|p| Pubkey::from_str(p).ok()).collect();
+        let handle = pool_state_cache().watch(keys, changed);
+        Self { pools, _handle: handle }
+    }
+
+    pub fn pools(&self) -> &[String] {
+        &self.pools
+    }
+}
@@ -0,0 +1,528 @@
+use crate::pools::indexer::{ensure_discovered, SOL_MINT, USDC_MINT, USDT_MINT};
+use crate::pools::loader::LoadedPools;
+use crate::pools::state_cache::pool_state_cache;
+use crate::pools::registry::pool_registry;
+use crate::pools::{pubkey_string, Pool, PoolQuote, Venue};
+use crate::tx::token_2022::TokenMints;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Mints a route may pass through between the input and the output.
+pub const INTERMEDIATE_MINTS: [Pubkey; 3] = [SOL_MINT, USDC_MINT, USDT_MINT];
+/// Deepest pools per mint pair loaded for the search.
+const MAX_POOLS_PER_PAIR: usize = 4;
+/// The input is split in steps of 1/SPLIT_PARTS (5%).
+const SPLIT_PARTS: u64 = 20;
+/// Best single paths considered for splitting.
+const SPLIT_CANDIDATES: usize = 12;
+/// The marginal rate is read from a swap of 1/IMPACT_PROBE_DIVISOR of each
+/// leg's input.
+const IMPACT_PROBE_DIVISOR: u64 = 1_000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RouterConfig {
+    /// Pools traded through per path, 1 to 3.
+    pub max_hops: usize,
+    /// Paths the input may be divided across; 1 disables splitting.
+    pub max_splits: usize,
+    /// Wall-clock budget for the search, not counting account loading.
+    /// Once spent, the best route found so far is returned.
+    pub budget: Duration,
+}
+
+impl Default for RouterConfig {
+    /// Sized for the quote stream, which requotes every 500ms at most.
+    fn default() -> Self {
+        Self {
+            max_hops: 3,
+            max_splits: 3,
+            budget: Duration::from_millis(50),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteHop {
+    pub venue: Venue,
+    #[serde(with = "pubkey_string")]
+    pub pool: Pubkey,
+    #[serde(with = "pubkey_string")]
+    pub input_mint: Pubkey,
+    #[serde(with = "pubkey_string")]
+    pub output_mint: Pubkey,
+    pub amount_in: u64,
+    /// What arrives after the output's transfer fee.
+    pub amount_out: u64,
+    pub fee_amount: u64,
+    #[serde(with = "pubkey_string")]
+    pub fee_mint: Pubkey,
+    /// Token-2022 transfer fees withheld on the way into the pool and out
+    /// of it.
+    pub input_transfer_fee: u64,
+    pub output_transfer_fee: u64,
+}
+
+/// One path of pools the input (or a share of it) is swapped through.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteLeg {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub hops: Vec<RouteHop>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Route {
+    #[serde(with = "pubkey_string")]
+    pub input_mint: Pubkey,
+    #[serde(with = "pubkey_string")]
+    pub output_mint: Pubkey,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    /// How far the fill falls below the route's marginal rate, as a
+    /// fraction.
+    pub price_impact: f64,
+    /// More than one leg when the input is split.
+    pub legs: Vec<RouteLeg>,
+}
+
+impl Route {
+    /// Every pool the route trades through, in leg and hop order.
+    pub fn pools(&self) -> Vec<Pubkey> {
+        self.hops().map(|hop| hop.pool).collect()
+    }
+
+    pub fn hops(&self) -> impl Iterator<Item = &RouteHop> {
+        self.legs.iter().flat_map(|leg| leg.hops.iter())
+    }
+}
+
+/// Best route for `amount_in` of `input` into `output` over the indexed
+/// pools, loading their current state and their mints' extensions first.
+/// Pools whose venue can't move one of their mints are left out. The loaded
+/// pools and mints, hook lists included, come back with it for building the
+/// swap.
+pub async fn best_route(
+    input: Pubkey,
+    output: Pubkey,
+    amount_in: u64,
+    config: &RouterConfig,
+) -> Result<(Route, LoadedPools, TokenMints)> {
+    ensure_discovered(&input).await?;
+    ensure_discovered(&output).await?;
+
+    let registry = pool_registry();
+    let mut mints = vec![input, output];
+    mints.extend(INTERMEDIATE_MINTS.into_iter().filter(|mint| *mint != input && *mint != output));
+    let mut addresses = Vec::new();
+    for (i, a) in mints.iter().enumerate() {
+        for b in &mints[i + 1..] {
+            addresses.extend(
+                registry
+                    .pools_for_pair(&a.to_string(), &b.to_string())
+                    .into_iter()
+                    .take(MAX_POOLS_PER_PAIR)
+                    .filter_map(|entry| Pubkey::from_str(&entry.address).ok()),
+            );
+        }
+    }
+
+    let mut loaded = pool_state_cache().load(&addresses).await?;
+    let pool_mints: Vec<Pubkey> = loaded.pools.iter().flat_map(|pool| pool.mints()).collect();
+    let mut token_mints = TokenMints::fetch(&pool_mints).await?;
+    token_mints.fetch_hook_lists().await?;
+    loaded.pools.retain(|pool| token_mints.tradable(pool.as_ref()));
+    let route = find_route(&loaded.pools, input, output, amount_in, config, &token_mints)?;
+    Ok((route, loaded, token_mints))
+}
+
+/// One pool's quote net of transfer fees: the pool gets `amount_in` less
+/// the input's fee, and the output's fee comes off what it sends. Returns
+/// the quote with both fees.
+fn quote_hop(
+    pool: &dyn Pool,
+    mints: &TokenMints,
+    input: &Pubkey,
+    output: &Pubkey,
+    amount_in: u64,
+) -> Result<(PoolQuote, u64, u64)> {
+    let input_fee = mints.transfer_fee(input, amount_in);
+    let quote = pool.quote_exact_in(input, amount_in - input_fee)?;
+    let output_fee = mints.transfer_fee(output, quote.amount_out);
+    Ok((quote, input_fee, output_fee))
+}
+
+/// A sequence of pools from the input to the output mint.
+#[derive(Debug, Clone)]
+struct Path {
+    /// Mints visited, input first; one longer than `pools`.
+    mints: Vec<Pubkey>,
+    /// Indexes into the pool slice.
+    pools: Vec<usize>,
+}
+
+impl Path {
+    fn quote(&self, pools: &[Box<dyn Pool>], mints: &TokenMints, amount_in: u64) -> Result<u64> {
+        let mut amount = amount_in;
+        for (hop, &pool) in self.pools.iter().enumerate() {
+            let (quote, _, output_fee) =
+                quote_hop(pools[pool].as_ref(), mints, &self.mints[hop], &self.mints[hop + 1], amount)?;
+            amount = quote.amount_out - output_fee;
+        }
+        Ok(amount)
+    }
+
+    fn leg(&self, pools: &[Box<dyn Pool>], mints: &TokenMints, amount_in: u64) -> Result<RouteLeg> {
+        let mut amount = amount_in;
+        let mut hops = Vec::with_capacity(self.pools.len());
+        for (hop, &index) in self.pools.iter().enumerate() {
+            let pool = &pools[index];
+            let (input_mint, output_mint) = (self.mints[hop], self.mints[hop + 1]);
+            let (quote, input_fee, output_fee) = quote_hop(pool.as_ref(), mints, &input_mint, &output_mint, amount)?;
+            hops.push(RouteHop {
+                venue: pool.venue(),
+                pool: pool.address(),
+                input_mint,
+                output_mint,
+                amount_in: quote.amount_in + input_fee,
+                amount_out: quote.amount_out - output_fee,
+                fee_amount: quote.fee_amount,
+                fee_mint: quote.fee_mint,
+                input_transfer_fee: input_fee,
+                output_transfer_fee: output_fee,
+            });
+            amount = quote.amount_out - output_fee;
+        }
+        Ok(RouteLeg { amount_in, amount_out: amount, hops })
+    }
+
+    fn shares_pool_with(&self, other: &Path) -> bool {
+        self.pools.iter().any(|pool| other.pools.contains(pool))
+    }
+}
+
+/// Searches 1 to `max_hops` hop paths through [`INTERMEDIATE_MINTS`], then
+/// tries spreading the input over up to `max_splits` paths that share no
+/// pool. Quotes use the pools' exact math and take the mints' transfer
+/// fees on every hop, so the result is what the swap would return against
+/// the same state.
+pub fn find_route(
+    pools: &[Box<dyn Pool>],
+    input: Pubkey,
+    output: Pubkey,
+    amount_in: u64,
+    config: &RouterConfig,
+    mints: &TokenMints,
+) -> Result<Route> {
+    if amount_in == 0 {
+        return Err(anyhow!("amount must be greater than zero"));
+    }
+    let deadline = Instant::now() + config.budget;
+
+    // Every path is quoted for the full amount; at least one always is, so
+    // an exhausted budget still yields a route.
+    let mut scored: Vec<(u64, Path)> = Vec::new();
+    for path in candidate_paths(pools, input, output, config.max_hops) {
+        if !scored.is_empty() && Instant::now() >= deadline {
+            break;
+        }
+        if let Ok(out) = path.quote(pools, mints, amount_in) {
+            scored.push((out, path));
+        }
+    }
+    scored.sort_by_key(|(out, _)| Reverse(*out));
+    let Some((_, best)) = scored.first() else {
+        return Err(anyhow!("no route from {} to {}", input, output));
+    };
+    let single = vec![best.leg(pools, mints, amount_in)?];
+
+    let legs = match split_route(pools, mints, &scored, amount_in, config, deadline) {
+        Some(split) if total_out(&split) > total_out(&single) => split,
+        _ => single,
+    };
+    Ok(Route {
+        input_mint: input,
+        output_mint: output,
+        amount_in,
+        amount_out: total_out(&legs),
+        price_impact: price_impact(pools, mints, &legs),
+        legs,
+    })
+}
+
+fn total_out(legs: &[RouteLeg]) -> u64 {
+    legs.iter().map(|leg| leg.amount_out).sum()
+}
+
+/// 1 - output / (input at the marginal rate), over all legs. Probes too
+/// small to come out as anything report no impact.
+fn price_impact(pools: &[Box<dyn Pool>], mints: &TokenMints, legs: &[RouteLeg]) -> f64 {
+    let mut at_marginal = 0.0;
+    for leg in legs {
+        let probe = (leg.amount_in / IMPACT_PROBE_DIVISOR).max(1);
+        let mut amount = probe;
+        for hop in &leg.hops {
+            let Some(pool) = pools.iter().find(|pool| pool.address() == hop.pool) else {
+                return 0.0;
+            };
+            let Ok((quote, _, output_fee)) = quote_hop(pool.as_ref(), mints, &hop.input_mint, &hop.output_mint, amount)
+            else {
+                return 0.0;
+            };
+            amount = quote.amount_out - output_fee;
+        }
+        at_marginal += amount as f64 / probe as f64 * leg.amount_in as f64;
+    }
+    if at_marginal <= 0.0 {
+        return 0.0;
+    }
+    (1.0 - total_out(legs) as f64 / at_marginal).max(0.0)
+}
+
+/// Pool paths between `input` and `output`, each hop through any pool
+/// trading that pair, never revisiting a mint.
+fn candidate_paths(pools: &[Box<dyn Pool>], input: Pubkey, output: Pubkey, max_hops: usize) -> Vec<Path> {
+    let mut by_pair: HashMap<(Pubkey, Pubkey), Vec<usize>> = HashMap::new();
+    for (index, pool) in pools.iter().enumerate() {
+        let [a, b] = pool.mints();
+        by_pair.entry((a, b)).or_default().push(index);
+        by_pair.entry((b, a)).or_default().push(index);
+    }
+
+    let intermediates: Vec<Pubkey> = INTERMEDIATE_MINTS
+        .into_iter()
+        .filter(|mint| *mint != input && *mint != output)
+        .collect();
+    let mut mint_paths = vec![vec![input, output]];
+    if max_hops >= 2 {
+        mint_paths.extend(intermediates.iter().map(|&mid| vec![input, mid, output]));
+    }
+    if max_hops >= 3 {
+        for &first in &intermediates {
+            for &second in intermediates.iter().filter(|&&m| m != first) {
+                mint_paths.push(vec![input, first, second, output]);
+            }
+        }
+    }
+
+    let mut paths = Vec::new();
+    for mints in mint_paths {
+        let mut partial: Vec<Vec<usize>> = vec![Vec::new()];
+        for hop in mints.windows(2) {
+            let options = by_pair.get(&(hop[0], hop[1])).map(Vec::as_slice).unwrap_or_default();
+            partial = partial
+                .into_iter()
+                .flat_map(|prefix| {
+                    options.iter().map(move |&pool| {
+                        let mut next = prefix.clone();
+                        next.push(pool);
+                        next
+                    })
+                })
+                .collect();
+        }
+        paths.extend(partial.into_iter().map(|pools| Path { mints: mints.clone(), pools }));
+    }
+    paths
+}
+
+/// Hands out the input one part at a time to whichever path gains the most
+/// output from it, opening a new path only while fewer than `max_splits`
+/// are in use and it shares no pool with them. `None` when splitting
+/// doesn't apply or the budget runs out.
+fn split_route(
+    pools: &[Box<dyn Pool>],
+    mints: &TokenMints,
+    scored: &[(u64, Path)],
+    amount_in: u64,
+    config: &RouterConfig,
+    deadline: Instant,
+) -> Option<Vec<RouteLeg>> {
+    if config.max_splits < 2 || scored.len() < 2 || amount_in < SPLIT_PARTS {
+        return None;
+    }
+    let candidates: Vec<&Path> = scored.iter().take(SPLIT_CANDIDATES).map(|(_, path)| path).collect();
+    let part_amount = |parts: u64| (amount_in as u128 * parts as u128 / SPLIT_PARTS as u128) as u64;
+
+    let mut outputs: Vec<HashMap<u64, Option<u64>>> = vec![HashMap::new(); candidates.len()];
+    let mut output_at = |path: usize, parts: u64| -> Option<u64> {
+        if parts == 0 {
+            return Some(0);
+        }
+        *outputs[path]
+            .entry(parts)
+            .or_insert_with(|| candidates[path].quote(pools, mints, part_amount(parts)).ok())
+    };
+
+    let mut allocation = vec![0u64; candidates.len()];
+    for _ in 0..SPLIT_PARTS {
+        if Instant::now() >= deadline {
+            return None;
+        }
+        let active: Vec<usize> = (0..candidates.len()).filter(|&p| allocation[p] > 0).collect();
+        let mut best: Option<(u64, usize)> = None;
+        for path in 0..candidates.len() {
+            let opens_new = allocation[path] == 0;
+            if opens_new
+                && (active.len() >= config.max_splits
+                    || active.iter().any(|&other| candidates[path].shares_pool_with(candidates[other])))
+            {
+                continue;
+            }
+            let (Some(before), Some(after)) = (output_at(path, allocation[path]), output_at(path, allocation[path] + 1))
+            else {
+                continue;
+            };
+            let gain = after.saturating_sub(before);
+            if best.is_none_or(|(best_gain, _)| gain > best_gain) {
+                best = Some((gain, path));
+            }
+        }
+        allocation[best?.1] += 1;
+    }
+
+    let used: Vec<usize> = (0..candidates.len()).filter(|&p| allocation[p] > 0).collect();
+    if used.len() < 2 {
+        return None;
+    }
+    // The last leg takes the rounding remainder so the legs add up exactly.
+    let mut remaining = amount_in;
+    let mut legs = Vec::with_capacity(used.len());
+    for (i, &path) in used.iter().enumerate() {
+        let amount = if i + 1 == used.len() { remaining } else { part_amount(allocation[path]) };
+        remaining -= amount;
+        legs.push(candidates[path].leg(pools, mints, amount).ok()?);
+    }
+    Some(legs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::{pool_from_snapshot, TOKEN_2022_PROGRAM};
+    use crate::tx::token_2022::{MintExtensions, TransferFee, TransferFeeConfig};
+    use std::collections::HashSet;
+
+    const BONK: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+
+    fn snapshot_pools() -> Vec<Box<dyn Pool>> {
+        vec![
+            pool_from_snapshot("raydium_amm_v4_sol_usdc", "58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2"),
+            pool_from_snapshot("raydium_cpmm_usdc_bonk", "7JuwJuNU88gurFnyWeiyGKbFmExMWcmRZntn9imEzdny"),
+            pool_from_snapshot("raydium_clmm_sol_usdc", "3ucNos4NbumPLZNWztqGHNFFgkHeRMBQAVemeeomsUxv"),
+            pool_from_snapshot("whirlpool_sol_usdc", "Czfq3xZZDmsdGdUyrNLtRhGc47cXcZtLG4crryfu44zE"),
+            pool_from_snapshot("meteora_dlmm_sol_usdc", "5rCf1DM8LjKTw4YqhnoLcngyZYeNnQqztScTogYHAS6"),
+        ]
+    }
+
+    fn assert_consistent(route: &Route) {
+        let leg_inputs: u64 = route.legs.iter().map(|leg| leg.amount_in).sum();
+        assert_eq!(leg_inputs, route.amount_in);
+        for leg in &route.legs {
+            assert_eq!(leg.hops.first().unwrap().amount_in, leg.amount_in);
+            assert_eq!(leg.hops.last().unwrap().amount_out, leg.amount_out);
+            for pair in leg.hops.windows(2) {
+                assert_eq!(pair[0].amount_out, pair[1].amount_in);
+                assert_eq!(pair[0].output_mint, pair[1].input_mint);
+            }
+        }
+    }
+
+    #[test]
+    fn splits_large_orders_across_disjoint_pools() {
+        let pools = snapshot_pools();
+        let amount = 30_000_000_000_000; // 30,000 SOL
+        let best_single = pools
+            .iter()
+            .filter_map(|pool| pool.quote_exact_in(&SOL_MINT, amount).ok())
+            .map(|quote| quote.amount_out)
+            .max()
+            .unwrap();
+
+        // Generous budget so a slow test machine doesn't cut the search short.
+        let config = RouterConfig { budget: Duration::from_secs(5), ..RouterConfig::default() };
+        let route = find_route(&pools, SOL_MINT, USDC_MINT, amount, &config, &TokenMints::default()).unwrap();
+        assert_consistent(&route);
+        assert!(route.legs.len() > 1 && route.legs.len() <= 3, "{} legs", route.legs.len());
+        assert!(route.amount_out > best_single, "{} <= {}", route.amount_out, best_single);
+        let used = route.pools();
+        assert_eq!(used.iter().collect::<HashSet<_>>().len(), used.len());
+
+        let split_impact = route.price_impact;
+
+        let single = RouterConfig { max_splits: 1, ..config };
+        let route = find_route(&pools, SOL_MINT, USDC_MINT, amount, &single, &TokenMints::default()).unwrap();
+        assert_eq!((route.legs.len(), route.amount_out), (1, best_single));
+        assert!(route.price_impact > split_impact, "{} <= {}", route.price_impact, split_impact);
+
+        let small = find_route(&pools, SOL_MINT, USDC_MINT, 1_000_000_000, &config, &TokenMints::default()).unwrap();
+        assert!(small.price_impact < 0.001, "{}", small.price_impact);
+    }
+
+    #[test]
+    fn routes_long_tail_tokens_through_intermediates() {
+        let pools = snapshot_pools();
+        let bonk = Pubkey::from_str(BONK).unwrap();
+        let mints = TokenMints::default();
+        let route = find_route(&pools, SOL_MINT, bonk, 1_000_000_000, &RouterConfig::default(), &mints).unwrap();
+        assert_consistent(&route);
+
+        let leg = &route.legs[0];
+        assert_eq!(leg.hops.len(), 2);
+        assert_eq!(leg.hops[0].output_mint, USDC_MINT);
+        let direct = pools[1].quote_exact_in(&USDC_MINT, leg.hops[0].amount_out).unwrap();
+        assert_eq!(leg.hops[1].amount_out, direct.amount_out);
+
+        let direct_only = RouterConfig { max_hops: 1, ..RouterConfig::default() };
+        assert!(find_route(&pools, SOL_MINT, bonk, 1_000_000_000, &direct_only, &mints).is_err());
+    }
+
+    #[test]
+    fn transfer_fees_come_off_every_hop() {
+        let pools = snapshot_pools();
+        let bonk = Pubkey::from_str(BONK).unwrap();
+        let fee = TransferFee { epoch: 0, maximum_fee: u64::MAX, basis_points: 100 };
+        let extensions = MintExtensions {
+            token_program: TOKEN_2022_PROGRAM,
+            transfer_fee: Some(TransferFeeConfig { older: fee, newer: fee }),
+            ..MintExtensions::default()
+        };
+        let mints = TokenMints::new(HashMap::from([(bonk, extensions)]), 0, 0);
+        let config = RouterConfig { max_splits: 1, ..RouterConfig::default() };
+
+        let route = find_route(&pools, SOL_MINT, bonk, 1_000_000_000, &config, &mints).unwrap();
+        assert_consistent(&route);
+        let hops = &route.legs[0].hops;
+        let gross = pools[1].quote_exact_in(&USDC_MINT, hops[0].amount_out).unwrap().amount_out;
+        assert_eq!(hops[1].output_transfer_fee, gross.div_ceil(100));
+        assert_eq!(hops[1].amount_out, gross - hops[1].output_transfer_fee);
+
+        let back = find_route(&pools, bonk, USDC_MINT, 1_000_000_000, &config, &mints).unwrap();
+        assert_eq!(back.legs[0].hops[0].input_transfer_fee, 10_000_000);
+        let net_in = pools[1].quote_exact_in(&bonk, 990_000_000).unwrap().amount_out;
+        assert_eq!(back.amount_out, net_in);
+
+        // AMM v4 can't move Token-2022 mints; CPMM can.
+        let token_2022_usdc = MintExtensions { token_program: TOKEN_2022_PROGRAM, ..MintExtensions::default() };
+        let mints = TokenMints::new(HashMap::from([(USDC_MINT, token_2022_usdc)]), 0, 0);
+        assert!(!mints.tradable(pools[0].as_ref()));
+        assert!(mints.tradable(pools[1].as_ref()));
+    }
+
+    #[test]
+    fn exhausted_budget_still_returns_a_route() {
+        let pools = snapshot_pools();
+        let config = RouterConfig { budget: Duration::ZERO, ..RouterConfig::default() };
+        let route =
+            find_route(&pools, SOL_MINT, USDC_MINT, 30_000_000_000_000, &config, &TokenMints::default()).unwrap();
+        assert_consistent(&route);
+        assert_eq!(route.legs.len(), 1);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::rpc::rpc_call;
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// `getMultipleAccounts` accepts at most this many keys per call.
+const MULTIPLE_ACCOUNTS_LIMIT: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct RawAccount {
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+}
+
+/// Accounts fetched together, with the lowest context slot across the
+/// batches so callers never claim fresher state than they have.
+#[derive(Debug, Default)]
+pub struct AccountsAtSlot {
+    pub slot: u64,
+    pub accounts: HashMap<Pubkey, RawAccount>,
+}
+
+/// Fetches `keys` in batches; accounts that don't exist are left out.
+pub async fn get_multiple_accounts(keys: &[Pubkey]) -> Result<AccountsAtSlot> {
+    let mut result = AccountsAtSlot::default();
+    for batch in keys.chunks(MULTIPLE_ACCOUNTS_LIMIT) {
+        let addresses: Vec<String> = batch.iter().map(|key| key.to_string()).collect();
+        let response = rpc_call(
+            "getMultipleAccounts",
+            json!([addresses, { "encoding": "base64", "commitment": "confirmed" }]),
+        )
+        .await?;
+
+        let slot = response["context"]["slot"].as_u64().unwrap_or_default();
+        result.slot = if result.slot == 0 { slot } else { result.slot.min(slot) };
+
+        let values = response["value"]
+            .as_array()
+            .ok_or_else(|| anyhow!("getMultipleAccounts returned no value"))?;
+        for (key, value) in batch.iter().zip(values) {
+            if !value.is_null() {
+                result.accounts.insert(*key, parse_account(value)?);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// `getProgramAccounts` with server-side filters, e.g.
+/// `[{"memcmp": {"offset": 0, "bytes": "..."}}]`.
+pub async fn get_program_accounts(
+    program: &Pubkey,
+    filters: Vec<Value>,
+) -> Result<(u64, Vec<(Pubkey, RawAccount)>)> {
+    let response = rpc_call(
+        "getProgramAccounts",
+        json!([program.to_string(), {
+            "encoding": "base64",
+            "commitment": "confirmed",
+            "withContext": true,
+            "filters": filters,
+        }]),
+    )
+    .await?;
+
+    let slot = response["context"]["slot"].as_u64().unwrap_or_default();
+    let entries = response["value"]
+        .as_array()
+        .ok_or_else(|| anyhow!("getProgramAccounts returned no value"))?;
+    let accounts = entries
+        .iter()
+        .map(|entry| {
+            let address = entry["pubkey"]
+                .as_str()
+                .and_then(|s| Pubkey::from_str(s).ok())
+                .ok_or_else(|| anyhow!("getProgramAccounts entry without pubkey"))?;
+            Ok((address, parse_account(&entry["account"])?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((slot, accounts))
+}
+
+/// Addresses of `owner`'s token accounts under `program`.
+pub async fn get_token_accounts_by_owner(owner: &Pubkey, program: &Pubkey) -> Result<Vec<Pubkey>> {
+    let response = rpc_call(
+        "getTokenAccountsByOwner",
+        json!([
+            owner.to_string(),
+            { "programId": program.to_string() },
+            { "encoding": "base64", "commitment": "confirmed", "dataSlice": { "offset": 0, "length": 0 } },
+        ]),
+    )
+    .await?;
+    let entries = response["value"]
+        .as_array()
+        .ok_or_else(|| anyhow!("getTokenAccountsByOwner returned no value"))?;
+    Ok(entries
+        .iter()
+        .filter_map(|entry| entry["pubkey"].as_str().and_then(|s| Pubkey::from_str(s).ok()))
+        .collect())
+}
+
+pub(crate) fn parse_account(value: &Value) -> Result<RawAccount> {
+    let owner = value["owner"]
+        .as_str()
+        .and_then(|s| Pubkey::from_str(s).ok())
+        .ok_or_else(|| anyhow!("account without owner"))?;
+    let data = value["data"][0]
+        .as_str()
+        .ok_or_else(|| anyhow!("account without base64 data"))?;
+    let lamports = value["lamports"].as_u64().ok_or_else(|| anyhow!("account without lamports"))?;
+    Ok(RawAccount { owner, lamports, data: BASE64.decode(data)? })
+}
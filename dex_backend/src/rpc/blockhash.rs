@@ -0,0 +1,14 @@
+use crate::rpc::rpc_call;
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use solana_sdk::hash::Hash;
+use std::str::FromStr;
+
+/// Latest confirmed blockhash, for transactions the backend builds itself.
+pub async fn get_latest_blockhash() -> Result<Hash> {
+    let result = rpc_call("getLatestBlockhash", json!([{ "commitment": "confirmed" }])).await?;
+    result["value"]["blockhash"]
+        .as_str()
+        .and_then(|s| Hash::from_str(s).ok())
+        .ok_or_else(|| anyhow!("getLatestBlockhash returned no blockhash"))
+}
@@ -0,0 +1,36 @@
+use crate::rpc::rpc_url::get_rpc_url;
+use crate::{MINT_DECIMALS_CACHE, REQWEST_CLIENT};
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use reqwest::Client;
+use serde_json::{json, Value};
+
+/// Decimals of an SPL Token or Token-2022 mint, read once from chain and
+/// cached for the process lifetime.
+pub async fn get_mint_decimals(mint: &str) -> Result<u8> {
+    let cache = MINT_DECIMALS_CACHE.get_or_init(DashMap::new);
+    if let Some(decimals) = cache.get(mint) {
+        return Ok(*decimals);
+    }
+
+    let client = REQWEST_CLIENT.get_or_init(Client::new);
+    let response: Value = client
+        .post(get_rpc_url())
+        .json(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "getAccountInfo",
+            "params": [mint, { "encoding": "jsonParsed" }]
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    let decimals = response["result"]["value"]["data"]["parsed"]["info"]["decimals"]
+        .as_u64()
+        .ok_or_else(|| anyhow!("{} is not a token mint", mint))? as u8;
+
+    cache.insert(mint.to_string(), decimals);
+    Ok(decimals)
+}
@@ -1,2 +1,31 @@
+pub mod spawn_rpc_health_checker;
+pub mod rpc_url;
+pub mod mint_decimals;
+pub mod accounts;
+pub mod blockhash;
+pub mod prioritization_fees;
+pub mod signatures;
+pub mod simulate;
 
-pub mod spawn_rpc_health_checker;
\ No newline at end of file
+use crate::rpc::rpc_url::get_rpc_url;
+use crate::REQWEST_CLIENT;
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde_json::{json, Value};
+
+/// Posts a JSON-RPC request and returns its `result`, or the RPC error.
+pub(crate) async fn rpc_call(method: &str, params: Value) -> Result<Value> {
+    let client = REQWEST_CLIENT.get_or_init(Client::new);
+    let mut response: Value = client
+        .post(get_rpc_url())
+        .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    if let Some(error) = response.get("error") {
+        return Err(anyhow!("{} failed: {}", method, error));
+    }
+    Ok(response["result"].take())
+}
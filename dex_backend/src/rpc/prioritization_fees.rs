@@ -0,0 +1,19 @@
+use crate::rpc::rpc_call;
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use solana_sdk::pubkey::Pubkey;
+
+/// `getRecentPrioritizationFees` accepts at most this many accounts.
+pub const PRIORITIZATION_FEE_ACCOUNTS_LIMIT: usize = 128;
+
+/// Lowest compute-unit price, in micro-lamports, that landed a transaction
+/// write-locking `accounts` in each recent slot the node remembers.
+pub async fn get_recent_prioritization_fees(accounts: &[Pubkey]) -> Result<Vec<u64>> {
+    let addresses: Vec<String> =
+        accounts.iter().take(PRIORITIZATION_FEE_ACCOUNTS_LIMIT).map(|key| key.to_string()).collect();
+    let result = rpc_call("getRecentPrioritizationFees", json!([addresses])).await?;
+    let entries = result
+        .as_array()
+        .ok_or_else(|| anyhow!("getRecentPrioritizationFees returned no fees"))?;
+    Ok(entries.iter().filter_map(|entry| entry["prioritizationFee"].as_u64()).collect())
+}
@@ -0,0 +1,29 @@
+const DEFAULT_RPC_URL: &str = "http://frankfurt.o7node.com:7799";
+
+/// RPC endpoint used for transaction and signature lookups.
+/// Override with `RPC_URL` in `.env`.
+pub fn get_rpc_url() -> String {
+    std::env::var("RPC_URL").unwrap_or_else(|_| DEFAULT_RPC_URL.to_string())
+}
+
+/// PubSub endpoint for subscriptions. Override with `RPC_WS_URL`; otherwise
+/// derived from the RPC URL the way validators expose it (ws scheme, RPC
+/// port + 1 when the port is explicit).
+pub fn get_ws_url() -> String {
+    if let Ok(url) = std::env::var("RPC_WS_URL") {
+        return url;
+    }
+
+    let rpc_url = get_rpc_url();
+    match reqwest::Url::parse(&rpc_url) {
+        Ok(mut url) => {
+            let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+            let _ = url.set_scheme(scheme);
+            if let Some(port) = url.port() {
+                let _ = url.set_port(Some(port + 1));
+            }
+            url.to_string()
+        }
+        Err(_) => rpc_url.replacen("http", "ws", 1),
+    }
+}
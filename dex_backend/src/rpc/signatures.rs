@@ -0,0 +1,48 @@
+use crate::rpc::rpc_call;
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+/// `getSignatureStatuses` accepts at most this many signatures per call.
+const SIGNATURE_STATUSES_LIMIT: usize = 256;
+
+/// What the node knows about a signature it has seen land.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignatureLookup {
+    pub slot: u64,
+    /// `processed`, `confirmed` or `finalized`.
+    pub confirmation_status: String,
+    /// `err` as the RPC reports it; `None` when the transaction succeeded.
+    pub err: Option<Value>,
+}
+
+/// Statuses of `signatures` in order; `None` for those the node hasn't
+/// seen. Only recent signatures are found unless `search_history` is set.
+pub async fn get_signature_statuses(
+    signatures: &[String],
+    search_history: bool,
+) -> Result<Vec<Option<SignatureLookup>>> {
+    let mut statuses = Vec::with_capacity(signatures.len());
+    for batch in signatures.chunks(SIGNATURE_STATUSES_LIMIT) {
+        let result = rpc_call(
+            "getSignatureStatuses",
+            json!([batch, { "searchTransactionHistory": search_history }]),
+        )
+        .await?;
+        let values = result["value"]
+            .as_array()
+            .ok_or_else(|| anyhow!("getSignatureStatuses returned no value"))?;
+        statuses.extend(values.iter().map(|value| {
+            Some(SignatureLookup {
+                slot: value["slot"].as_u64()?,
+                confirmation_status: value["confirmationStatus"].as_str()?.to_string(),
+                err: Some(value["err"].clone()).filter(|err| !err.is_null()),
+            })
+        }));
+    }
+    Ok(statuses)
+}
+
+pub async fn get_block_height() -> Result<u64> {
+    let result = rpc_call("getBlockHeight", json!([{ "commitment": "confirmed" }])).await?;
+    result.as_u64().ok_or_else(|| anyhow!("getBlockHeight returned no height"))
+}
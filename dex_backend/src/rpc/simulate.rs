@@ -0,0 +1,60 @@
+use crate::rpc::accounts::{parse_account, RawAccount};
+use crate::rpc::rpc_call;
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::VersionedTransaction;
+
+/// Outcome of `simulateTransaction`.
+#[derive(Debug, Clone, Default)]
+pub struct Simulation {
+    pub slot: u64,
+    /// `err` as the RPC reports it; `None` when the transaction succeeds.
+    pub err: Option<Value>,
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+    /// State of the requested accounts after the transaction, in request
+    /// order; `None` for accounts that don't exist afterwards.
+    pub accounts: Vec<Option<RawAccount>>,
+}
+
+/// Simulates against the latest blockhash without checking signatures, so
+/// unsigned messages can be simulated too.
+pub async fn simulate_transaction(transaction: &VersionedTransaction, accounts: &[Pubkey]) -> Result<Simulation> {
+    let mut config = json!({
+        "encoding": "base64",
+        "commitment": "confirmed",
+        "sigVerify": false,
+        "replaceRecentBlockhash": true,
+    });
+    if !accounts.is_empty() {
+        let addresses: Vec<String> = accounts.iter().map(|key| key.to_string()).collect();
+        config["accounts"] = json!({ "encoding": "base64", "addresses": addresses });
+    }
+    let encoded = BASE64.encode(bincode::serialize(transaction)?);
+    let response = rpc_call("simulateTransaction", json!([encoded, config])).await?;
+
+    let value = &response["value"];
+    if value.is_null() {
+        return Err(anyhow!("simulateTransaction returned no value"));
+    }
+    let returned_accounts = match value["accounts"].as_array() {
+        Some(returned) => returned
+            .iter()
+            .map(|account| if account.is_null() { Ok(None) } else { parse_account(account).map(Some) })
+            .collect::<Result<_>>()?,
+        None => Vec::new(),
+    };
+    Ok(Simulation {
+        slot: response["context"]["slot"].as_u64().unwrap_or_default(),
+        err: Some(value["err"].clone()).filter(|err| !err.is_null()),
+        logs: value["logs"]
+            .as_array()
+            .map(|logs| logs.iter().filter_map(|log| log.as_str().map(String::from)).collect())
+            .unwrap_or_default(),
+        units_consumed: value["unitsConsumed"].as_u64(),
+        accounts: returned_accounts,
+    })
+}
@@ -0,0 +1,181 @@
+//! Streams of account changes for the pool-state cache. The WebSocket
+//! source uses the node's `accountSubscribe` and `programSubscribe`; a
+//! Yellowstone gRPC feed fits the same [`AccountSource`] trait, taking the
+//! subscription set as its request.
+
+use crate::rpc::rpc_url::get_ws_url;
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use futures::stream::{BoxStream, SelectAll, StreamExt};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::RpcFilterType;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::str::FromStr;
+use tokio::sync::mpsc;
+
+type Unsubscribe = Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send>;
+
+/// New contents of an account, as of `slot`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountUpdate {
+    pub address: Pubkey,
+    pub slot: u64,
+    pub owner: Pubkey,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Subscription {
+    Account(Pubkey),
+    /// Every account of `program` passing all the filters.
+    Program { program: Pubkey, filters: Vec<RpcFilterType> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceCommand {
+    Subscribe(Subscription),
+    Unsubscribe(Subscription),
+}
+
+pub trait AccountSource: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Subscribes to `initial` and follows `commands`, sending every update
+    /// to `updates`. Returns `Ok` once the cache stops listening and `Err`
+    /// when the connection fails; the caller then reconnects with its
+    /// current subscriptions.
+    fn stream<'a>(
+        &'a self,
+        initial: Vec<Subscription>,
+        commands: &'a mut mpsc::UnboundedReceiver<SourceCommand>,
+        updates: mpsc::Sender<AccountUpdate>,
+    ) -> BoxFuture<'a, Result<()>>;
+}
+
+/// The source `ACCOUNT_SOURCE` names; only `websocket` is built in.
+pub fn account_source() -> Box<dyn AccountSource> {
+    let name = std::env::var("ACCOUNT_SOURCE").unwrap_or_else(|_| "websocket".to_string());
+    if name != "websocket" {
+        eprintln!("[stream] Account source '{}' is not built in; using websocket", name);
+    }
+    Box::new(WebSocketSource { url: get_ws_url() })
+}
+
+/// PubSub subscriptions over one connection per [`AccountSource::stream`].
+pub struct WebSocketSource {
+    url: String,
+}
+
+impl AccountSource for WebSocketSource {
+    fn name(&self) -> &'static str {
+        "websocket"
+    }
+
+    fn stream<'a>(
+        &'a self,
+        initial: Vec<Subscription>,
+        commands: &'a mut mpsc::UnboundedReceiver<SourceCommand>,
+        updates: mpsc::Sender<AccountUpdate>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let client = PubsubClient::new(&self.url).await?;
+            let result = stream_subscriptions(&client, initial, commands, updates).await;
+            let _ = client.shutdown().await;
+            result
+        })
+    }
+}
+
+async fn stream_subscriptions(
+    client: &PubsubClient,
+    initial: Vec<Subscription>,
+    commands: &mut mpsc::UnboundedReceiver<SourceCommand>,
+    updates: mpsc::Sender<AccountUpdate>,
+) -> Result<()> {
+    let mut streams: SelectAll<BoxStream<'_, AccountUpdate>> = SelectAll::new();
+    let mut active: HashMap<Subscription, Unsubscribe> = HashMap::new();
+    for subscription in initial {
+        if let Entry::Vacant(slot) = active.entry(subscription) {
+            let (stream, unsubscribe) = subscribe(client, slot.key()).await?;
+            streams.push(stream);
+            slot.insert(unsubscribe);
+        }
+    }
+
+    loop {
+        tokio::select! {
+            update = streams.next(), if !streams.is_empty() => {
+                let Some(update) = update else {
+                    return Err(anyhow!("subscriptions closed by the node"));
+                };
+                if updates.send(update).await.is_err() {
+                    return Ok(());
+                }
+            }
+            command = commands.recv() => match command {
+                Some(SourceCommand::Subscribe(subscription)) => {
+                    if let Entry::Vacant(slot) = active.entry(subscription) {
+                        let (stream, unsubscribe) = subscribe(client, slot.key()).await?;
+                        streams.push(stream);
+                        slot.insert(unsubscribe);
+                    }
+                }
+                Some(SourceCommand::Unsubscribe(subscription)) => {
+                    if let Some(unsubscribe) = active.remove(&subscription) {
+                        unsubscribe().await;
+                    }
+                }
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+async fn subscribe<'a>(
+    client: &'a PubsubClient,
+    subscription: &Subscription,
+) -> Result<(BoxStream<'a, AccountUpdate>, Unsubscribe)> {
+    let account_config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(CommitmentConfig::processed()),
+        ..RpcAccountInfoConfig::default()
+    };
+    match subscription {
+        Subscription::Account(address) => {
+            let address = *address;
+            let (stream, unsubscribe) = client.account_subscribe(&address, Some(account_config)).await?;
+            let updates = stream.filter_map(move |notification| async move {
+                account_update(address, notification.context.slot, &notification.value)
+            });
+            Ok((updates.boxed(), unsubscribe))
+        }
+        Subscription::Program { program, filters } => {
+            let config = RpcProgramAccountsConfig {
+                filters: Some(filters.clone()),
+                account_config,
+                with_context: Some(true),
+                ..RpcProgramAccountsConfig::default()
+            };
+            let (stream, unsubscribe) = client.program_subscribe(program, Some(config)).await?;
+            let updates = stream.filter_map(|notification| async move {
+                let address = Pubkey::from_str(&notification.value.pubkey).ok()?;
+                account_update(address, notification.context.slot, &notification.value.account)
+            });
+            Ok((updates.boxed(), unsubscribe))
+        }
+    }
+}
+
+fn account_update(address: Pubkey, slot: u64, account: &UiAccount) -> Option<AccountUpdate> {
+    Some(AccountUpdate {
+        address,
+        slot,
+        owner: Pubkey::from_str(&account.owner).ok()?,
+        data: account.data.decode()?,
+    })
+}
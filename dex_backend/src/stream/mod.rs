@@ -0,0 +1,3 @@
+pub mod account_source;
+pub mod signature_tracker;
+pub mod wallet_feed;
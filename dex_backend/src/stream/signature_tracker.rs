@@ -0,0 +1,317 @@
+use crate::rpc::rpc_url::get_ws_url;
+use crate::rpc::signatures::{get_block_height, get_signature_statuses, SignatureLookup};
+use crate::tx_errors::decode::{decode_error, TxFailure};
+use crate::SIGNATURE_TRACKER;
+use dashmap::DashMap;
+use futures::stream::{select_all, BoxStream, StreamExt};
+use serde::Serialize;
+use serde_json::Value;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::RpcSignatureSubscribeConfig;
+use solana_client::rpc_response::RpcSignatureResult;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Polled even while subscribed, in case a notification is missed.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Blocks a blockhash stays valid for; bounds a swap's lifetime when the
+/// caller doesn't know its `lastValidBlockHeight`.
+const MAX_PROCESSING_AGE: u64 = 150;
+/// Finished signatures stay queryable this long.
+const RETENTION: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureStage {
+    Pending,
+    Processed,
+    Confirmed,
+    Finalized,
+    /// The blockhash expired before the transaction landed; it never will.
+    Expired,
+}
+
+impl SignatureStage {
+    pub fn is_final(self) -> bool {
+        matches!(self, SignatureStage::Finalized | SignatureStage::Expired)
+    }
+
+    fn from_confirmation_status(status: &str) -> Option<Self> {
+        match status {
+            "processed" => Some(SignatureStage::Processed),
+            "confirmed" => Some(SignatureStage::Confirmed),
+            "finalized" => Some(SignatureStage::Finalized),
+            _ => None,
+        }
+    }
+
+    fn commitment(self) -> CommitmentConfig {
+        match self {
+            SignatureStage::Confirmed => CommitmentConfig::confirmed(),
+            SignatureStage::Finalized => CommitmentConfig::finalized(),
+            _ => CommitmentConfig::processed(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureStatus {
+    pub signature: String,
+    pub stage: SignatureStage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slot: Option<u64>,
+    /// Landed but failed; the transaction still moves through the stages.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<TxFailure>,
+    pub last_valid_block_height: u64,
+    /// Signatures tracked together with this one, e.g. resends of a swap.
+    pub group: Vec<String>,
+}
+
+impl SignatureStatus {
+    /// Moves forward to an observed stage; stages never go back. Returns
+    /// whether anything changed.
+    fn observe(&mut self, stage: SignatureStage, slot: u64, err: Option<&Value>) -> bool {
+        if self.stage.is_final() || stage <= self.stage {
+            return false;
+        }
+        self.stage = stage;
+        self.slot = Some(slot);
+        self.failure = err.map(|err| decode_error(err, &[], &[]));
+        true
+    }
+
+    /// Expires the signature if its blockhash is past and no node has it
+    /// at confirmed or beyond; a processed fork can still be dropped.
+    fn expire(&mut self, block_height: u64) -> bool {
+        if self.stage >= SignatureStage::Confirmed || block_height <= self.last_valid_block_height {
+            return false;
+        }
+        self.stage = SignatureStage::Expired;
+        self.slot = None;
+        self.failure = None;
+        true
+    }
+}
+
+/// Signatures sent by users and how far each has landed. Each group is
+/// followed with `signatureSubscribe`, backed by polling that also detects
+/// expiry.
+#[derive(Default)]
+pub struct SignatureTracker {
+    statuses: Arc<DashMap<String, watch::Receiver<SignatureStatus>>>,
+}
+
+pub fn signature_tracker() -> &'static SignatureTracker {
+    SIGNATURE_TRACKER.get_or_init(SignatureTracker::default)
+}
+
+impl SignatureTracker {
+    /// Starts following `signatures` as one group. Without a
+    /// `last_valid_block_height` the blockhash is assumed to be at most
+    /// fresh. Signatures already tracked are left as they are.
+    pub async fn track(
+        &self,
+        signatures: Vec<String>,
+        last_valid_block_height: Option<u64>,
+    ) -> anyhow::Result<Vec<SignatureStatus>> {
+        for signature in &signatures {
+            Signature::from_str(signature).map_err(|_| anyhow::anyhow!("invalid signature {}", signature))?;
+        }
+        let last_valid_block_height = match last_valid_block_height {
+            Some(height) => height,
+            None => get_block_height().await? + MAX_PROCESSING_AGE,
+        };
+
+        let mut senders = Vec::new();
+        for signature in &signatures {
+            if self.statuses.contains_key(signature) {
+                continue;
+            }
+            let (sender, receiver) = watch::channel(SignatureStatus {
+                signature: signature.clone(),
+                stage: SignatureStage::Pending,
+                slot: None,
+                failure: None,
+                last_valid_block_height,
+                group: signatures.clone(),
+            });
+            self.statuses.insert(signature.clone(), receiver);
+            senders.push(sender);
+        }
+        if !senders.is_empty() {
+            tokio::spawn(follow(self.statuses.clone(), senders));
+        }
+        Ok(signatures.iter().filter_map(|signature| self.status(signature)).collect())
+    }
+
+    pub fn status(&self, signature: &str) -> Option<SignatureStatus> {
+        self.statuses.get(signature).map(|receiver| receiver.borrow().clone())
+    }
+
+    /// Follows a signature's status changes; the current status is marked
+    /// unseen so it is the first one received.
+    pub fn watch(&self, signature: &str) -> Option<watch::Receiver<SignatureStatus>> {
+        self.statuses.get(signature).map(|receiver| {
+            let mut receiver = receiver.clone();
+            receiver.mark_changed();
+            receiver
+        })
+    }
+}
+
+/// Stage of a signature nobody is tracking, from one status lookup.
+pub async fn lookup_signature(signature: &str) -> anyhow::Result<Option<(SignatureStage, SignatureLookup)>> {
+    let lookup = get_signature_statuses(&[signature.to_string()], true).await?.pop().flatten();
+    Ok(lookup.and_then(|lookup| {
+        let stage = SignatureStage::from_confirmation_status(&lookup.confirmation_status)?;
+        Some((stage, lookup))
+    }))
+}
+
+type Notification = (usize, SignatureStage, Option<Value>, u64);
+
+async fn follow(statuses: Arc<DashMap<String, watch::Receiver<SignatureStatus>>>, senders: Vec<watch::Sender<SignatureStatus>>) {
+    let signatures: Vec<String> = senders.iter().map(|sender| sender.borrow().signature.clone()).collect();
+    let client = match PubsubClient::new(&get_ws_url()).await {
+        Ok(client) => Some(client),
+        Err(e) => {
+            eprintln!("[tracker] Subscriptions unavailable, polling {:?}: {}", signatures, e);
+            None
+        }
+    };
+
+    let mut streams: Vec<BoxStream<'_, Notification>> = Vec::new();
+    let mut unsubscribers = Vec::new();
+    if let Some(client) = &client {
+        for (index, signature) in signatures.iter().enumerate() {
+            let signature = Signature::from_str(signature).expect("checked on track");
+            for stage in [SignatureStage::Processed, SignatureStage::Confirmed, SignatureStage::Finalized] {
+                let config = RpcSignatureSubscribeConfig {
+                    commitment: Some(stage.commitment()),
+                    enable_received_notification: Some(false),
+                };
+                match client.signature_subscribe(&signature, Some(config)).await {
+                    Ok((stream, unsubscribe)) => {
+                        streams.push(
+                            stream
+                                .filter_map(move |response| async move {
+                                    let RpcSignatureResult::ProcessedSignature(result) = response.value else {
+                                        return None;
+                                    };
+                                    let err = result.err.and_then(|err| serde_json::to_value(err).ok());
+                                    Some((index, stage, err, response.context.slot))
+                                })
+                                .boxed(),
+                        );
+                        unsubscribers.push(unsubscribe);
+                    }
+                    Err(e) => eprintln!("[tracker] Failed to subscribe to {}: {}", signature, e),
+                }
+            }
+        }
+    }
+    let mut notifications = select_all(streams);
+    let mut poll = tokio::time::interval(POLL_INTERVAL);
+
+    while !senders.iter().all(|sender| sender.borrow().stage.is_final()) {
+        tokio::select! {
+            Some((index, stage, err, slot)) = notifications.next() => {
+                senders[index].send_if_modified(|status| status.observe(stage, slot, err.as_ref()));
+            }
+            _ = poll.tick() => {
+                if let Err(e) = poll_statuses(&senders).await {
+                    eprintln!("[tracker] Polling {:?} failed: {}", signatures, e);
+                }
+            }
+        }
+    }
+
+    drop(notifications);
+    for unsubscribe in unsubscribers {
+        unsubscribe().await;
+    }
+    if let Some(client) = client {
+        let _ = client.shutdown().await;
+    }
+    for sender in &senders {
+        let status = sender.borrow();
+        println!("[tracker] {} {:?}", status.signature, status.stage);
+    }
+
+    // Receivers keep the final status after the senders are gone.
+    drop(senders);
+    tokio::time::sleep(RETENTION).await;
+    for signature in signatures {
+        statuses.remove(&signature);
+    }
+}
+
+async fn poll_statuses(senders: &[watch::Sender<SignatureStatus>]) -> anyhow::Result<()> {
+    let open: Vec<&watch::Sender<SignatureStatus>> =
+        senders.iter().filter(|sender| !sender.borrow().stage.is_final()).collect();
+    let signatures: Vec<String> = open.iter().map(|sender| sender.borrow().signature.clone()).collect();
+    let (lookups, block_height) = tokio::try_join!(get_signature_statuses(&signatures, false), get_block_height())?;
+
+    for (sender, lookup) in open.into_iter().zip(lookups) {
+        sender.send_if_modified(|status| match &lookup {
+            Some(lookup) => match SignatureStage::from_confirmation_status(&lookup.confirmation_status) {
+                Some(stage) => status.observe(stage, lookup.slot, lookup.err.as_ref()),
+                None => false,
+            },
+            None => status.expire(block_height),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pending(last_valid_block_height: u64) -> SignatureStatus {
+        SignatureStatus {
+            signature: "sig".into(),
+            stage: SignatureStage::Pending,
+            slot: None,
+            failure: None,
+            last_valid_block_height,
+            group: vec!["sig".into()],
+        }
+    }
+
+    #[test]
+    fn stages_only_move_forward() {
+        let mut status = pending(1_000);
+        assert!(status.observe(SignatureStage::Confirmed, 10, None));
+        assert!(!status.observe(SignatureStage::Processed, 9, None));
+        assert_eq!(status.stage, SignatureStage::Confirmed);
+        assert!(!status.expire(2_000));
+
+        let err = json!({ "InstructionError": [2, { "Custom": 1 }] });
+        assert!(status.observe(SignatureStage::Finalized, 11, Some(&err)));
+        assert_eq!(status.slot, Some(11));
+        assert_eq!(status.failure.as_ref().unwrap().instruction_index, Some(2));
+        assert!(!status.observe(SignatureStage::Finalized, 12, None));
+    }
+
+    #[test]
+    fn unlanded_signatures_expire_after_their_blockhash() {
+        let mut status = pending(1_000);
+        assert!(!status.expire(1_000));
+        assert!(status.expire(1_001));
+        assert_eq!(status.stage, SignatureStage::Expired);
+        assert!(!status.observe(SignatureStage::Processed, 5, None));
+
+        // A processed fork that was dropped expires too.
+        let mut forked = pending(1_000);
+        forked.observe(SignatureStage::Processed, 5, None);
+        assert!(forked.expire(1_001));
+    }
+}
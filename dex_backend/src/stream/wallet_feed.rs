@@ -0,0 +1,322 @@
+use crate::api_methods::handle_history::{
+    async_normalize_transaction, get_parsed_transaction, NormalizedTx,
+};
+use crate::rpc::rpc_url::get_ws_url;
+use crate::WALLET_FEEDS;
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use futures::stream::{select_all, StreamExt};
+use serde::Serialize;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+const FEED_CAPACITY: usize = 256;
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+const RECONNECT_DELAYS: [u64; 5] = [1, 2, 5, 10, 30];
+/// `getTransaction` can lag the confirmed notification by a few slots.
+const FETCH_RETRY_DELAYS_MS: [u64; 4] = [400, 800, 1600, 3200];
+/// How long a signature's progress is remembered; well past finalization.
+const SIGNATURE_TTL: Duration = Duration::from_secs(10 * 60);
+
+pub type FeedSender = broadcast::Sender<Arc<String>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxStage {
+    Pending,
+    Confirmed,
+    Finalized,
+}
+
+impl TxStage {
+    fn commitment(self) -> CommitmentConfig {
+        match self {
+            TxStage::Pending => CommitmentConfig::processed(),
+            TxStage::Confirmed => CommitmentConfig::confirmed(),
+            TxStage::Finalized => CommitmentConfig::finalized(),
+        }
+    }
+}
+
+/// Where a signature is in the feed. Stages go out in order: a finalized
+/// notification that beats the confirmed fetch waits for it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Progress {
+    Sent(TxStage),
+    /// The confirmed transaction is being fetched; holds a finalized
+    /// notification's slot and failure that arrived meanwhile.
+    Confirming(Option<(u64, bool)>),
+}
+
+/// Progress per signature of one feed, with when it was first seen.
+type Signatures = Arc<DashMap<String, (Progress, Instant)>>;
+
+/// One push to wallet stream clients. `transaction` is only filled on the
+/// confirmed stage; the other stages refer back to it by signature.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletEvent<'a> {
+    pub status: TxStage,
+    pub signature: &'a str,
+    pub slot: u64,
+    pub failed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<&'a NormalizedTx>,
+}
+
+/// Joins the live feed for `wallet`, starting its upstream subscription if
+/// this is the first client. The feed stops once its last client leaves.
+pub fn subscribe(wallet: Pubkey) -> broadcast::Receiver<Arc<String>> {
+    let feeds = WALLET_FEEDS.get_or_init(DashMap::new);
+    let entry = feeds.entry(wallet.to_string()).or_insert_with(|| {
+        let (sender, _) = broadcast::channel(FEED_CAPACITY);
+        tokio::spawn(run_feed(wallet, sender.clone()));
+        sender
+    });
+    entry.subscribe()
+}
+
+async fn run_feed(wallet: Pubkey, sender: FeedSender) {
+    let address = wallet.to_string();
+    let signatures = Signatures::default();
+    let mut failures = 0usize;
+
+    println!("[stream] Starting feed for {}", address);
+
+    loop {
+        match watch_wallet(&wallet, &sender, &signatures).await {
+            Ok(()) => break, // no clients left
+            Err(e) => {
+                let delay = RECONNECT_DELAYS[failures.min(RECONNECT_DELAYS.len() - 1)];
+                eprintln!(
+                    "[stream] Feed for {} dropped: {}; reconnecting in {}s",
+                    address, e, delay
+                );
+                failures += 1;
+                tokio::time::sleep(Duration::from_secs(delay)).await;
+            }
+        }
+        if no_clients(&address, &sender) {
+            break;
+        }
+    }
+
+    println!("[stream] Stopped feed for {}", address);
+}
+
+/// Removes the feed when nobody listens. Runs under the map's entry lock so
+/// a client subscribing concurrently either keeps it alive or starts a new one.
+fn no_clients(address: &str, sender: &FeedSender) -> bool {
+    if sender.receiver_count() > 0 {
+        return false;
+    }
+    WALLET_FEEDS
+        .get_or_init(DashMap::new)
+        .remove_if(address, |_, s| s.receiver_count() == 0)
+        .is_some()
+}
+
+/// Streams log notifications mentioning the wallet at all three commitment
+/// levels over one PubSub connection. Returns `Ok` when the feed went idle.
+async fn watch_wallet(
+    wallet: &Pubkey,
+    sender: &FeedSender,
+    signatures: &Signatures,
+) -> anyhow::Result<()> {
+    let address = wallet.to_string();
+    let client = PubsubClient::new(&get_ws_url()).await?;
+
+    let mut subscriptions = Vec::new();
+    let mut unsubscribers = Vec::new();
+    for stage in [TxStage::Pending, TxStage::Confirmed, TxStage::Finalized] {
+        let (stream, unsubscribe) = client
+            .logs_subscribe(
+                RpcTransactionLogsFilter::Mentions(vec![address.clone()]),
+                RpcTransactionLogsConfig {
+                    commitment: Some(stage.commitment()),
+                },
+            )
+            .await?;
+        subscriptions.push(stream.map(move |notification| (stage, notification)).boxed());
+        unsubscribers.push(unsubscribe);
+    }
+    let mut notifications = select_all(subscriptions);
+    let mut idle_check = tokio::time::interval(IDLE_CHECK_INTERVAL);
+
+    let result = loop {
+        tokio::select! {
+            next = notifications.next() => {
+                let Some((stage, notification)) = next else {
+                    break Err(anyhow::anyhow!("subscription closed by the node"));
+                };
+                let signature = notification.value.signature;
+                let slot = notification.context.slot;
+                let failed = notification.value.err.is_some();
+                match advance(signatures, &signature, stage, slot, failed) {
+                    Some(TxStage::Confirmed) => {
+                        tokio::spawn(push_confirmed(
+                            *wallet,
+                            signature,
+                            slot,
+                            failed,
+                            sender.clone(),
+                            signatures.clone(),
+                        ));
+                    }
+                    Some(stage) => publish(sender, &WalletEvent {
+                        status: stage,
+                        signature: &signature,
+                        slot,
+                        failed,
+                        transaction: None,
+                    }),
+                    None => {}
+                }
+            }
+            _ = idle_check.tick() => {
+                if no_clients(&address, sender) {
+                    break Ok(());
+                }
+                signatures.retain(|_, (_, seen)| seen.elapsed() < SIGNATURE_TTL);
+            }
+        }
+    };
+
+    drop(notifications);
+    for unsubscribe in unsubscribers {
+        unsubscribe().await;
+    }
+    let _ = client.shutdown().await;
+    result
+}
+
+/// Records a notification for `stage` and returns the stage to act on, if
+/// any. Repeats and stages behind one already sent are dropped; a finalized
+/// notification during the confirmed fetch is held for [`push_confirmed`].
+fn advance(signatures: &Signatures, signature: &str, stage: TxStage, slot: u64, failed: bool) -> Option<TxStage> {
+    let mut entry = match signatures.entry(signature.to_string()) {
+        Entry::Vacant(vacant) => {
+            let progress = match stage {
+                TxStage::Confirmed => Progress::Confirming(None),
+                stage => Progress::Sent(stage),
+            };
+            vacant.insert((progress, Instant::now()));
+            return Some(stage);
+        }
+        Entry::Occupied(occupied) => occupied,
+    };
+    let progress = &mut entry.get_mut().0;
+    match (*progress, stage) {
+        (Progress::Sent(TxStage::Pending), TxStage::Confirmed) => {
+            *progress = Progress::Confirming(None);
+            Some(TxStage::Confirmed)
+        }
+        (Progress::Confirming(_), TxStage::Finalized) => {
+            *progress = Progress::Confirming(Some((slot, failed)));
+            None
+        }
+        (Progress::Sent(sent), TxStage::Finalized) if sent < TxStage::Finalized => {
+            *progress = Progress::Sent(TxStage::Finalized);
+            Some(TxStage::Finalized)
+        }
+        _ => None,
+    }
+}
+
+/// Fetches and normalizes a confirmed transaction through the history
+/// pipeline, then pushes it, followed by a finalized notification that
+/// arrived while it was fetched.
+async fn push_confirmed(
+    wallet: Pubkey,
+    signature: String,
+    slot: u64,
+    failed: bool,
+    sender: FeedSender,
+    signatures: Signatures,
+) {
+    let mut tx_data = None;
+    for delay in FETCH_RETRY_DELAYS_MS {
+        match get_parsed_transaction(&signature).await {
+            Ok(data) => {
+                tx_data = Some(data);
+                break;
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(delay)).await,
+        }
+    }
+
+    let normalized = match tx_data {
+        Some(data) => async_normalize_transaction(data, &wallet).await,
+        None => {
+            eprintln!("[stream] Could not fetch confirmed transaction {}", signature);
+            None
+        }
+    };
+
+    publish(
+        &sender,
+        &WalletEvent {
+            status: TxStage::Confirmed,
+            signature: &signature,
+            slot,
+            failed,
+            transaction: normalized.as_ref(),
+        },
+    );
+
+    let held = signatures.get_mut(&signature).and_then(|mut entry| match entry.0 {
+        Progress::Confirming(held) => {
+            entry.0 = Progress::Sent(held.map_or(TxStage::Confirmed, |_| TxStage::Finalized));
+            held
+        }
+        Progress::Sent(_) => None,
+    });
+    if let Some((slot, failed)) = held {
+        publish(
+            &sender,
+            &WalletEvent {
+                status: TxStage::Finalized,
+                signature: &signature,
+                slot,
+                failed,
+                transaction: None,
+            },
+        );
+    }
+}
+
+fn publish(sender: &FeedSender, event: &WalletEvent) {
+    match serde_json::to_string(event) {
+        // Err only means nobody is listening right now.
+        Ok(json) => {
+            let _ = sender.send(Arc::new(json));
+        }
+        Err(e) => eprintln!("[stream] Failed to serialize event: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_advance_in_order() {
+        let signatures = Signatures::default();
+        assert_eq!(advance(&signatures, "a", TxStage::Pending, 1, false), Some(TxStage::Pending));
+        assert_eq!(advance(&signatures, "a", TxStage::Pending, 1, false), None);
+        assert_eq!(advance(&signatures, "a", TxStage::Confirmed, 2, false), Some(TxStage::Confirmed));
+        // Finalized before the confirmed fetch is done waits for it.
+        assert_eq!(advance(&signatures, "a", TxStage::Finalized, 3, false), None);
+        assert_eq!(signatures.get("a").unwrap().0, Progress::Confirming(Some((3, false))));
+
+        // A finalized transaction first seen late drops its earlier stages.
+        assert_eq!(advance(&signatures, "b", TxStage::Finalized, 3, true), Some(TxStage::Finalized));
+        assert_eq!(advance(&signatures, "b", TxStage::Confirmed, 2, true), None);
+        assert_eq!(advance(&signatures, "b", TxStage::Pending, 1, true), None);
+    }
+}
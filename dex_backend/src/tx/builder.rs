@@ -0,0 +1,359 @@
+use crate::pools::indexer::SOL_MINT;
+use crate::pools::{sells_first_mint, Pool, SwapAccounts, SwapTransfers, TOKEN_PROGRAM};
+use crate::quote::router::Route;
+use crate::rpc::blockhash::get_latest_blockhash;
+use crate::rpc::simulate::simulate_transaction;
+use crate::tx::ata::{associated_token_address, create_ata_idempotent};
+use crate::tx::lookup_tables::{configured_lookup_tables, fetch_lookup_tables, select_lookup_tables};
+use crate::tx::token_2022::TokenMints;
+use crate::tx::transfer_hook::HookTransfer;
+use crate::tx::{system, token};
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::{v0, AddressLookupTableAccount, CompileError, VersionedMessage};
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Most compute a transaction may request.
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+/// Headroom over the simulated units, in percent, for state that moves
+/// between simulation and landing.
+const COMPUTE_UNIT_MARGIN_PCT: u64 = 20;
+
+/// Why a transaction couldn't be built.
+#[derive(Debug)]
+pub enum TxBuildError {
+    /// The route references a pool that isn't loaded, or a hop the venue
+    /// can't build a swap for.
+    Route(String),
+    /// The instructions don't fit the v0 message format.
+    Compile(CompileError),
+    /// The signed transaction would exceed the packet size.
+    TooLarge { size: usize, limit: usize },
+    /// Simulating for the compute-unit limit failed or the swap would fail.
+    Simulation(String),
+    /// Accounts, lookup tables or the blockhash couldn't be fetched.
+    Rpc(String),
+}
+
+impl fmt::Display for TxBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TxBuildError::Route(e) => write!(f, "cannot build the route: {}", e),
+            TxBuildError::Compile(e) => write!(f, "cannot compile the message: {}", e),
+            TxBuildError::TooLarge { size, limit } => {
+                write!(f, "transaction is {} bytes, over the {} byte limit", size, limit)
+            }
+            TxBuildError::Simulation(e) => write!(f, "simulation failed: {}", e),
+            TxBuildError::Rpc(e) => write!(f, "RPC request failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TxBuildError {}
+
+/// The swapper's side of a routed swap.
+#[derive(Debug, Clone, Copy)]
+pub struct SwapTxParams {
+    pub user: Pubkey,
+    pub slippage_bps: u16,
+    /// Total priority fee; spread over the compute-unit limit.
+    pub priority_fee_lamports: u64,
+}
+
+/// `amount` less `slippage_bps`, rounded down.
+pub fn min_out(amount: u64, slippage_bps: u16) -> u64 {
+    (amount as u128 * (10_000 - slippage_bps.min(10_000)) as u128 / 10_000) as u64
+}
+
+/// `SetComputeUnitLimit` and, for a non-zero fee, `SetComputeUnitPrice`
+/// with the micro-lamport price that makes the limit cost the fee.
+pub fn compute_budget_instructions(unit_limit: u32, priority_fee_lamports: u64) -> Vec<Instruction> {
+    let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(unit_limit)];
+    if priority_fee_lamports > 0 {
+        let micro_lamports = priority_fee_lamports as u128 * 1_000_000 / unit_limit.max(1) as u128;
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(micro_lamports as u64));
+    }
+    instructions
+}
+
+/// Simulated units plus the margin, within the protocol maximum.
+pub fn compute_unit_limit(units_consumed: u64) -> u32 {
+    let limit = units_consumed + units_consumed * COMPUTE_UNIT_MARGIN_PCT / 100;
+    limit.min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32
+}
+
+/// The route's instructions without compute budget: token accounts the
+/// route lands in are created if missing, a SOL input is wrapped first and
+/// any wrapped SOL left is unwrapped at the end.
+///
+/// Each hop after the first spends the previous hop's minimum, so a leg
+/// still lands when earlier hops fill at the slippage bound; whatever they
+/// return above it stays in the intermediate token account. Hops moving a
+/// hooked mint carry the hook's accounts.
+pub fn swap_instructions(
+    route: &Route,
+    pools: &[Box<dyn Pool>],
+    params: &SwapTxParams,
+    mints: &TokenMints,
+) -> Result<Vec<Instruction>, TxBuildError> {
+    let user = params.user;
+    let program = |mint: &Pubkey| mints.token_program(mint);
+    let ata = |mint: &Pubkey| associated_token_address(&user, mint, &program(mint));
+
+    let touches_sol = route.hops().any(|hop| hop.input_mint == SOL_MINT || hop.output_mint == SOL_MINT);
+    let mut instructions = Vec::new();
+    let mut created = HashSet::new();
+    for mint in route.hops().map(|hop| hop.output_mint).chain(touches_sol.then_some(SOL_MINT)) {
+        if created.insert(mint) {
+            instructions.push(create_ata_idempotent(&user, &user, &mint, &program(&mint)));
+        }
+    }
+    if route.input_mint == SOL_MINT {
+        let wsol = ata(&SOL_MINT);
+        instructions.push(system::transfer(&user, &wsol, route.amount_in));
+        instructions.push(token::sync_native(&wsol, &TOKEN_PROGRAM));
+    }
+
+    for leg in &route.legs {
+        let mut amount_in = leg.amount_in;
+        for hop in &leg.hops {
+            let pool = pools
+                .iter()
+                .find(|pool| pool.address() == hop.pool)
+                .ok_or_else(|| TxBuildError::Route(format!("pool {} is not loaded", hop.pool)))?;
+            let route_error =
+                |e: anyhow::Error| TxBuildError::Route(format!("{} pool {}: {}", hop.venue.label(), hop.pool, e));
+            let swap = SwapAccounts {
+                user,
+                input_mint: hop.input_mint,
+                user_source: ata(&hop.input_mint),
+                user_destination: ata(&hop.output_mint),
+            };
+            let minimum_out = min_out(hop.amount_out, params.slippage_bps);
+            let transfers = swap_transfers(
+                pool.as_ref(),
+                mints,
+                &swap,
+                &hop.output_mint,
+                amount_in,
+                hop.amount_out + hop.output_transfer_fee,
+            )
+            .map_err(route_error)?;
+            let instruction =
+                pool.swap_instruction_for(&swap, &transfers, amount_in, minimum_out).map_err(route_error)?;
+            instructions.push(instruction);
+            amount_in = minimum_out;
+        }
+    }
+
+    if touches_sol {
+        instructions.push(token::close_account(&ata(&SOL_MINT), &user, &user, &TOKEN_PROGRAM));
+    }
+    Ok(instructions)
+}
+
+/// Token programs and hook accounts for `swap` through `pool`: `amount_in`
+/// of the input going into the pool's vault and `amount_out` of
+/// `output_mint` coming out of the other.
+pub fn swap_transfers(
+    pool: &dyn Pool,
+    mints: &TokenMints,
+    swap: &SwapAccounts,
+    output_mint: &Pubkey,
+    amount_in: u64,
+    amount_out: u64,
+) -> anyhow::Result<SwapTransfers> {
+    let vaults = pool.reserve_vaults();
+    let input_side = if sells_first_mint(pool.mints(), &swap.input_mint)? { 0 } else { 1 };
+    // The pool signs for its vaults: Whirlpool, the one venue that forwards
+    // hook accounts, owns them directly.
+    Ok(SwapTransfers {
+        token_programs: [mints.token_program(&swap.input_mint), mints.token_program(output_mint)],
+        hook_accounts: [
+            mints.hook_accounts(&HookTransfer {
+                source: swap.user_source,
+                mint: swap.input_mint,
+                destination: vaults[input_side],
+                authority: swap.user,
+                amount: amount_in,
+            })?,
+            mints.hook_accounts(&HookTransfer {
+                source: vaults[1 - input_side],
+                mint: *output_mint,
+                destination: swap.user_destination,
+                authority: pool.address(),
+                amount: amount_out,
+            })?,
+        ],
+    })
+}
+
+/// Compiles a v0 message using whichever of `lookup_tables` shrink it, and
+/// checks that the signed transaction fits in a packet.
+pub fn compile_message(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    blockhash: Hash,
+) -> Result<VersionedMessage, TxBuildError> {
+    let tables = select_lookup_tables(payer, instructions, lookup_tables);
+    let message = v0::Message::try_compile(payer, instructions, &tables, blockhash).map_err(TxBuildError::Compile)?;
+    let message = VersionedMessage::V0(message);
+    let size = transaction_size(&message);
+    if size > PACKET_DATA_SIZE {
+        return Err(TxBuildError::TooLarge { size, limit: PACKET_DATA_SIZE });
+    }
+    Ok(message)
+}
+
+/// Serialized size of the transaction once every signer has signed.
+fn transaction_size(message: &VersionedMessage) -> usize {
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); message.header().num_required_signatures as usize],
+        message: message.clone(),
+    };
+    bincode::serialized_size(&transaction).map_or(usize::MAX, |size| size as usize)
+}
+
+/// Builds the unsigned swap message for `route`: simulated once at the
+/// maximum compute-unit limit, then rebuilt with the simulated units plus
+/// the margin and the caller's priority fee priced over them. `mints` are
+/// the ones the route was found with, hook lists included.
+pub async fn build_swap_message(
+    route: &Route,
+    pools: &[Box<dyn Pool>],
+    mints: &TokenMints,
+    params: &SwapTxParams,
+) -> Result<VersionedMessage, TxBuildError> {
+    let rpc = |e: anyhow::Error| TxBuildError::Rpc(e.to_string());
+    let table_keys = configured_lookup_tables();
+    let (lookup_tables, blockhash) =
+        tokio::try_join!(fetch_lookup_tables(&table_keys), get_latest_blockhash()).map_err(rpc)?;
+
+    let swap = swap_instructions(route, pools, params, mints)?;
+    let with_budget = |unit_limit: u32| {
+        let mut instructions = compute_budget_instructions(unit_limit, params.priority_fee_lamports);
+        instructions.extend(swap.iter().cloned());
+        instructions
+    };
+
+    let draft = compile_message(&params.user, &with_budget(MAX_COMPUTE_UNIT_LIMIT), &lookup_tables, blockhash)?;
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); draft.header().num_required_signatures as usize],
+        message: draft,
+    };
+    let simulation = simulate_transaction(&transaction, &[]).await.map_err(rpc)?;
+    if let Some(err) = simulation.err {
+        let last_log = simulation.logs.last().cloned().unwrap_or_default();
+        return Err(TxBuildError::Simulation(format!("{} {}", err, last_log).trim_end().to_string()));
+    }
+    let units = simulation
+        .units_consumed
+        .ok_or_else(|| TxBuildError::Simulation("no unitsConsumed in the result".into()))?;
+
+    compile_message(&params.user, &with_budget(compute_unit_limit(units)), &lookup_tables, blockhash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::pool_from_snapshot;
+    use crate::quote::router::{find_route, RouterConfig};
+    use std::str::FromStr;
+
+    const BONK: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+
+    fn sol_to_bonk() -> (Route, Vec<Box<dyn Pool>>) {
+        let pools = vec![
+            pool_from_snapshot("raydium_cpmm_usdc_bonk", "7JuwJuNU88gurFnyWeiyGKbFmExMWcmRZntn9imEzdny"),
+            pool_from_snapshot("whirlpool_sol_usdc", "Czfq3xZZDmsdGdUyrNLtRhGc47cXcZtLG4crryfu44zE"),
+        ];
+        let bonk = Pubkey::from_str(BONK).unwrap();
+        let route =
+            find_route(&pools, SOL_MINT, bonk, 1_000_000_000, &RouterConfig::default(), &TokenMints::default())
+                .unwrap();
+        (route, pools)
+    }
+
+    fn params() -> SwapTxParams {
+        SwapTxParams { user: Pubkey::new_unique(), slippage_bps: 50, priority_fee_lamports: 100_000 }
+    }
+
+    #[test]
+    fn wraps_sol_chains_minimums_and_unwraps() {
+        let (route, pools) = sol_to_bonk();
+        let params = params();
+        let instructions = swap_instructions(&route, &pools, &params, &TokenMints::default()).unwrap();
+
+        let programs: Vec<Pubkey> = instructions.iter().map(|ix| ix.program_id).collect();
+        let ata_program = crate::tx::ata::ASSOCIATED_TOKEN_PROGRAM;
+        let hops: Vec<_> = route.hops().collect();
+        assert_eq!(
+            programs,
+            [
+                ata_program,
+                ata_program,
+                ata_program,
+                system::SYSTEM_PROGRAM,
+                TOKEN_PROGRAM,
+                hops[0].venue.program_id(),
+                hops[1].venue.program_id(),
+                TOKEN_PROGRAM,
+            ]
+        );
+        // The second hop spends exactly what the first guarantees.
+        let first_min = min_out(hops[0].amount_out, params.slippage_bps);
+        let second_min = min_out(hops[1].amount_out, params.slippage_bps);
+        assert_eq!(instructions[6].data, pools[0].swap_data(&hops[1].input_mint, first_min, second_min).unwrap());
+    }
+
+    #[test]
+    fn lookup_tables_bring_oversized_messages_under_the_limit() {
+        let (route, pools) = sol_to_bonk();
+        let params = params();
+        let mut instructions = compute_budget_instructions(MAX_COMPUTE_UNIT_LIMIT, params.priority_fee_lamports);
+        instructions.extend(swap_instructions(&route, &pools, &params, &TokenMints::default()).unwrap());
+        // Payouts to more accounts than the rest of the packet holds.
+        for _ in 0..30 {
+            instructions.push(system::transfer(&params.user, &Pubkey::new_unique(), 1));
+        }
+        let blockhash = Hash::new_unique();
+        assert!(matches!(
+            compile_message(&params.user, &instructions, &[], blockhash),
+            Err(TxBuildError::TooLarge { limit: PACKET_DATA_SIZE, .. })
+        ));
+
+        let programs: HashSet<Pubkey> = instructions.iter().map(|ix| ix.program_id).collect();
+        let table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: instructions
+                .iter()
+                .flat_map(|ix| ix.accounts.iter().map(|meta| meta.pubkey))
+                .filter(|key| *key != params.user && !programs.contains(key))
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect(),
+        };
+        let unrelated = AddressLookupTableAccount { key: Pubkey::new_unique(), addresses: vec![Pubkey::new_unique()] };
+        let message = compile_message(&params.user, &instructions, &[unrelated, table.clone()], blockhash).unwrap();
+        let lookups = message.address_table_lookups().unwrap();
+        assert_eq!(lookups.len(), 1);
+        assert_eq!(lookups[0].account_key, table.key);
+    }
+
+    #[test]
+    fn priority_fee_is_spread_over_the_limit() {
+        assert_eq!(compute_unit_limit(100_000), 120_000);
+        assert_eq!(compute_unit_limit(1_300_000), MAX_COMPUTE_UNIT_LIMIT);
+
+        let instructions = compute_budget_instructions(200_000, 100_000);
+        assert_eq!(instructions[1], ComputeBudgetInstruction::set_compute_unit_price(500_000));
+        assert_eq!(compute_budget_instructions(200_000, 0).len(), 1);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::tx::builder::MAX_COMPUTE_UNIT_LIMIT;
+use serde::Serialize;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+pub const COMPUTE_BUDGET_PROGRAM: Pubkey = pubkey!("ComputeBudget111111111111111111111111111111");
+pub const SIGNATURE_FEE_LAMPORTS: u64 = 5_000;
+/// Units each non-budget instruction gets when no limit is set.
+const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+const SET_COMPUTE_UNIT_LIMIT_TAG: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE_TAG: u8 = 3;
+/// Quiet accounts often show no fees at all; recommending at least this
+/// keeps the transaction ahead of those paying nothing.
+const MIN_RECOMMENDED_MICRO_LAMPORTS: u64 = 1_000;
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+
+/// The fee a message pays to land: a base fee per signature plus the
+/// priority fee its compute-unit price sets on its limit.
+pub fn network_fee(message: &VersionedMessage) -> u64 {
+    let keys = message.static_account_keys();
+    let mut unit_limit = None;
+    let mut micro_lamports: u64 = 0;
+    let mut other_instructions: u32 = 0;
+    for ix in message.instructions() {
+        if keys.get(ix.program_id_index as usize) != Some(&COMPUTE_BUDGET_PROGRAM) {
+            other_instructions += 1;
+            continue;
+        }
+        match ix.data.split_first() {
+            Some((&SET_COMPUTE_UNIT_LIMIT_TAG, rest)) if rest.len() >= 4 => {
+                unit_limit = Some(u32::from_le_bytes(rest[..4].try_into().unwrap()));
+            }
+            Some((&SET_COMPUTE_UNIT_PRICE_TAG, rest)) if rest.len() >= 8 => {
+                micro_lamports = u64::from_le_bytes(rest[..8].try_into().unwrap());
+            }
+            _ => {}
+        }
+    }
+    let unit_limit = unit_limit
+        .unwrap_or(other_instructions.saturating_mul(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT))
+        .min(MAX_COMPUTE_UNIT_LIMIT);
+    let priority_fee = (micro_lamports as u128 * unit_limit as u128).div_ceil(1_000_000) as u64;
+    message.header().num_required_signatures as u64 * SIGNATURE_FEE_LAMPORTS + priority_fee
+}
+
+/// Accounts the message may write, without the programs it invokes: the
+/// ones whose local fee markets decide what it pays to land.
+pub fn writable_accounts(message: &VersionedMessage) -> Vec<Pubkey> {
+    let keys = message.static_account_keys();
+    let programs: Vec<Pubkey> =
+        message.instructions().iter().filter_map(|ix| keys.get(ix.program_id_index as usize).copied()).collect();
+    keys.iter()
+        .enumerate()
+        .filter(|(i, key)| message.is_maybe_writable(*i, None) && !programs.contains(key))
+        .map(|(_, key)| *key)
+        .collect()
+}
+
+/// A compute-unit price and what it costs at the estimate's unit limit.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeLevel {
+    pub micro_lamports: u64,
+    pub priority_fee_lamports: u64,
+    /// Priority fee plus the signature fees.
+    pub total_fee_lamports: u64,
+    pub total_fee_sol: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriorityFeeEstimate {
+    pub unit_limit: u32,
+    pub low: FeeLevel,
+    pub medium: FeeLevel,
+    pub high: FeeLevel,
+    pub very_high: FeeLevel,
+    pub recommended: FeeLevel,
+}
+
+/// Prices at the 25th, 50th, 75th and 95th percentile of `recent_fees`
+/// (micro-lamports per unit, one per recent slot), priced on `unit_limit`.
+pub fn estimate_priority_fee(recent_fees: &[u64], unit_limit: u32, signatures: u64) -> PriorityFeeEstimate {
+    let mut sorted = recent_fees.to_vec();
+    sorted.sort_unstable();
+    let level = |micro_lamports: u64| {
+        let priority_fee_lamports = (micro_lamports as u128 * unit_limit as u128).div_ceil(1_000_000) as u64;
+        let total_fee_lamports = priority_fee_lamports + signatures * SIGNATURE_FEE_LAMPORTS;
+        FeeLevel {
+            micro_lamports,
+            priority_fee_lamports,
+            total_fee_lamports,
+            total_fee_sol: total_fee_lamports as f64 / LAMPORTS_PER_SOL,
+        }
+    };
+    let medium = percentile(&sorted, 50);
+    PriorityFeeEstimate {
+        unit_limit,
+        low: level(percentile(&sorted, 25)),
+        medium: level(medium),
+        high: level(percentile(&sorted, 75)),
+        very_high: level(percentile(&sorted, 95)),
+        recommended: level(medium.max(MIN_RECOMMENDED_MICRO_LAMPORTS)),
+    }
+}
+
+/// Nearest-rank percentile of sorted values; 0 when there are none.
+fn percentile(sorted: &[u64], pct: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (pct * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tx::builder::compute_budget_instructions;
+    use crate::tx::system;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::{v0, Message};
+
+    #[test]
+    fn network_fee_adds_the_priority_fee_to_the_signature_fee() {
+        let payer = Pubkey::new_unique();
+        let transfer = system::transfer(&payer, &Pubkey::new_unique(), 1);
+
+        let mut instructions = compute_budget_instructions(100_000, 50_000);
+        instructions.push(transfer.clone());
+        let message = v0::Message::try_compile(&payer, &instructions, &[], Hash::new_unique()).unwrap();
+        assert_eq!(network_fee(&VersionedMessage::V0(message)), 55_000);
+
+        // Without a limit, the price applies to 200k units per instruction.
+        let price = compute_budget_instructions(1_000_000, 1_000)[1].clone();
+        let message = Message::new(&[price, transfer], Some(&payer));
+        assert_eq!(network_fee(&VersionedMessage::Legacy(message)), 5_200);
+    }
+
+    #[test]
+    fn unsanitized_program_indexes_are_skipped() {
+        let payer = Pubkey::new_unique();
+        let mut message = Message::new(&[system::transfer(&payer, &Pubkey::new_unique(), 1)], Some(&payer));
+        message.instructions[0].program_id_index = 200;
+        let message = VersionedMessage::Legacy(message);
+        assert!(writable_accounts(&message).contains(&payer));
+        assert_eq!(network_fee(&message), SIGNATURE_FEE_LAMPORTS);
+    }
+
+    #[test]
+    fn priority_fee_levels_follow_the_recent_fee_percentiles() {
+        let recent: Vec<u64> = (0..100).rev().map(|i| i * 1_000).collect();
+        let estimate = estimate_priority_fee(&recent, 200_000, 1);
+        assert_eq!(estimate.low.micro_lamports, 24_000);
+        assert_eq!(estimate.medium.micro_lamports, 49_000);
+        assert_eq!(estimate.very_high.micro_lamports, 94_000);
+        assert_eq!(estimate.medium.priority_fee_lamports, 9_800);
+        assert_eq!(estimate.medium.total_fee_lamports, 14_800);
+        assert_eq!(estimate.recommended, estimate.medium);
+
+        let quiet = estimate_priority_fee(&[0, 0, 0], 200_000, 2);
+        assert_eq!(quiet.high.total_fee_lamports, 10_000);
+        assert_eq!(quiet.recommended.micro_lamports, MIN_RECOMMENDED_MICRO_LAMPORTS);
+        assert_eq!(estimate_priority_fee(&[], 200_000, 1).very_high.micro_lamports, 0);
+    }
+}
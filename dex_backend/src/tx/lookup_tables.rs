@@ -0,0 +1,101 @@
+use crate::pools::layout::Layout;
+use crate::rpc::accounts::get_multiple_accounts;
+use anyhow::{anyhow, Result};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::AddressLookupTableAccount;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// `LookupTableMeta` plus the state enum tag; addresses follow it.
+const LOOKUP_TABLE_META_LEN: usize = 56;
+/// `ProgramState::LookupTable`.
+const LOOKUP_TABLE_STATE: u32 = 1;
+/// A table costs its 32-byte key plus two length prefixes in the message,
+/// and saves 31 bytes per key it replaces, so one key doesn't pay for it.
+const MIN_KEYS_PER_TABLE: usize = 2;
+
+/// Lookup tables swaps may use, comma separated in `SWAP_LOOKUP_TABLES`.
+pub fn configured_lookup_tables() -> Vec<Pubkey> {
+    std::env::var("SWAP_LOOKUP_TABLES")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|s| Pubkey::from_str(s.trim()).ok())
+        .collect()
+}
+
+/// Decodes an address lookup table account. Deactivated tables are
+/// rejected since transactions can't load from them once they close.
+pub fn parse_lookup_table(key: Pubkey, data: &[u8]) -> Result<AddressLookupTableAccount> {
+    let layout = Layout::new(data, "address lookup table", LOOKUP_TABLE_META_LEN)?;
+    if layout.u32(0)? != LOOKUP_TABLE_STATE {
+        return Err(anyhow!("{} is not an initialized lookup table", key));
+    }
+    if layout.u64(4)? != u64::MAX {
+        return Err(anyhow!("lookup table {} is deactivated", key));
+    }
+    let addresses = data[LOOKUP_TABLE_META_LEN..]
+        .chunks_exact(32)
+        .map(|chunk| Pubkey::try_from(chunk).expect("32-byte chunk"))
+        .collect();
+    Ok(AddressLookupTableAccount { key, addresses })
+}
+
+/// Fetches and decodes the tables at `keys`, skipping any that are missing
+/// or unusable.
+pub async fn fetch_lookup_tables(keys: &[Pubkey]) -> Result<Vec<AddressLookupTableAccount>> {
+    if keys.is_empty() {
+        return Ok(Vec::new());
+    }
+    let fetched = get_multiple_accounts(keys).await?;
+    Ok(keys
+        .iter()
+        .filter_map(|key| {
+            let account = fetched.accounts.get(key)?;
+            parse_lookup_table(*key, &account.data)
+                .map_err(|e| eprintln!("[tx] Skipping lookup table {}: {}", key, e))
+                .ok()
+        })
+        .collect())
+}
+
+/// Greedily picks the tables covering the most keys the instructions could
+/// load from a table: anything that isn't a signer or an invoked program.
+pub fn select_lookup_tables(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    candidates: &[AddressLookupTableAccount],
+) -> Vec<AddressLookupTableAccount> {
+    let programs: HashSet<Pubkey> = instructions.iter().map(|ix| ix.program_id).collect();
+    let mut uncovered: HashSet<Pubkey> = instructions
+        .iter()
+        .flat_map(|ix| ix.accounts.iter())
+        .filter(|meta| !meta.is_signer && meta.pubkey != *payer && !programs.contains(&meta.pubkey))
+        .map(|meta| meta.pubkey)
+        .collect();
+
+    let mut selected = Vec::new();
+    let mut remaining: Vec<&AddressLookupTableAccount> = candidates.iter().collect();
+    loop {
+        let coverage = |table: &AddressLookupTableAccount| {
+            table.addresses.iter().filter(|address| uncovered.contains(address)).count()
+        };
+        let Some((best, covered)) = remaining
+            .iter()
+            .enumerate()
+            .map(|(i, table)| (i, coverage(table)))
+            .max_by_key(|(_, covered)| *covered)
+        else {
+            break;
+        };
+        if covered < MIN_KEYS_PER_TABLE {
+            break;
+        }
+        let table = remaining.swap_remove(best);
+        for address in &table.addresses {
+            uncovered.remove(address);
+        }
+        selected.push(table.clone());
+    }
+    selected
+}
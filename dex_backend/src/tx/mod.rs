@@ -0,0 +1,10 @@
+//! Instructions and transactions built by the backend.
+
+pub mod ata;
+pub mod builder;
+pub mod fees;
+pub mod lookup_tables;
+pub mod system;
+pub mod token;
+pub mod token_2022;
+pub mod transfer_hook;
@@ -0,0 +1,19 @@
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+pub const SYSTEM_PROGRAM: Pubkey = pubkey!("11111111111111111111111111111111");
+
+/// `Transfer` of the system program.
+const TRANSFER: u32 = 2;
+
+/// Moves lamports out of a system-owned account.
+pub fn transfer(from: &Pubkey, to: &Pubkey, lamports: u64) -> Instruction {
+    let mut data = TRANSFER.to_le_bytes().to_vec();
+    data.extend_from_slice(&lamports.to_le_bytes());
+    Instruction {
+        program_id: SYSTEM_PROGRAM,
+        accounts: vec![AccountMeta::new(*from, true), AccountMeta::new(*to, false)],
+        data,
+    }
+}
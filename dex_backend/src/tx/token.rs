@@ -0,0 +1,51 @@
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+
+// Instruction tags shared by SPL Token and Token-2022.
+const TRANSFER: u8 = 3;
+const CLOSE_ACCOUNT: u8 = 9;
+const SYNC_NATIVE: u8 = 17;
+
+/// Unchecked token transfer; fine for mints without transfer extensions.
+pub fn transfer(
+    source: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut data = vec![TRANSFER];
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: *token_program,
+        accounts: vec![
+            AccountMeta::new(*source, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+        data,
+    }
+}
+
+/// Closes a token account, sending its lamports (and any wrapped SOL) to
+/// `destination`.
+pub fn close_account(account: &Pubkey, destination: &Pubkey, owner: &Pubkey, token_program: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *token_program,
+        accounts: vec![
+            AccountMeta::new(*account, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data: vec![CLOSE_ACCOUNT],
+    }
+}
+
+/// Credits lamports sent to a wrapped SOL account to its token balance.
+pub fn sync_native(account: &Pubkey, token_program: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *token_program,
+        accounts: vec![AccountMeta::new(*account, false)],
+        data: vec![SYNC_NATIVE],
+    }
+}
@@ -0,0 +1,331 @@
+//! Token-2022 mint extensions that change what a swap moves: transfer fees
+//! withheld from every transfer, interest that grows the UI amount, and
+//! transfer hooks that need their own accounts on every transfer.
+
+use crate::pools::layout::Layout;
+use crate::pools::{Pool, TOKEN_2022_PROGRAM, TOKEN_PROGRAM};
+use crate::rpc::accounts::get_multiple_accounts;
+use crate::tx::transfer_hook::{extra_account_metas_address, hook_accounts, HookTransfer};
+use anyhow::{anyhow, Result};
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+/// Mainnet has had fixed-length epochs since genesis.
+pub const SLOTS_PER_EPOCH: u64 = 432_000;
+/// Extensions start after the account type, which follows the base mint
+/// padded to a token account's length.
+const ACCOUNT_TYPE_OFFSET: usize = 165;
+const MINT_ACCOUNT_TYPE: u8 = 1;
+const TRANSFER_FEE_CONFIG: u16 = 1;
+const INTEREST_BEARING_CONFIG: u16 = 10;
+const TRANSFER_HOOK: u16 = 14;
+const MAX_FEE_BASIS_POINTS: u128 = 10_000;
+/// The year Token-2022 compounds interest over.
+const SECONDS_PER_YEAR: f64 = 60.0 * 60.0 * 24.0 * 365.24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferFee {
+    /// First epoch the fee applies in.
+    pub epoch: u64,
+    pub maximum_fee: u64,
+    pub basis_points: u16,
+}
+
+impl TransferFee {
+    /// Withheld from a transfer of `amount`: rounded up, capped at the
+    /// maximum.
+    pub fn fee(&self, amount: u64) -> u64 {
+        if self.basis_points == 0 || amount == 0 {
+            return 0;
+        }
+        let fee = (amount as u128 * self.basis_points as u128).div_ceil(MAX_FEE_BASIS_POINTS);
+        fee.min(self.maximum_fee as u128) as u64
+    }
+}
+
+/// The fee in force and the one scheduled to replace it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferFeeConfig {
+    pub older: TransferFee,
+    pub newer: TransferFee,
+}
+
+impl TransferFeeConfig {
+    pub fn at_epoch(&self, epoch: u64) -> &TransferFee {
+        if epoch >= self.newer.epoch {
+            &self.newer
+        } else {
+            &self.older
+        }
+    }
+}
+
+/// Rates are in basis points a year, compounded continuously.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterestBearing {
+    pub initialized_at: i64,
+    /// Average rate from initialization to the last rate change.
+    pub pre_update_average_rate: i16,
+    pub last_update_at: i64,
+    pub current_rate: i16,
+}
+
+impl InterestBearing {
+    /// Factor the raw amount is shown at in UI units at `now`.
+    pub fn ui_scale(&self, now: i64) -> f64 {
+        let growth = |rate: i16, from: i64, to: i64| {
+            (rate as f64 / 10_000.0 * (to - from).max(0) as f64 / SECONDS_PER_YEAR).exp()
+        };
+        growth(self.pre_update_average_rate, self.initialized_at, self.last_update_at)
+            * growth(self.current_rate, self.last_update_at, now)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MintExtensions {
+    pub token_program: Pubkey,
+    pub transfer_fee: Option<TransferFeeConfig>,
+    pub interest_bearing: Option<InterestBearing>,
+    /// Program the token program calls on every transfer.
+    pub transfer_hook: Option<Pubkey>,
+}
+
+impl Default for MintExtensions {
+    /// A plain SPL Token mint.
+    fn default() -> Self {
+        Self { token_program: TOKEN_PROGRAM, transfer_fee: None, interest_bearing: None, transfer_hook: None }
+    }
+}
+
+impl MintExtensions {
+    /// Reads the extensions of a mint account owned by `owner`; extensions
+    /// that don't change a swap are skipped.
+    pub fn parse(owner: &Pubkey, data: &[u8]) -> Result<Self> {
+        let mut extensions = MintExtensions { token_program: *owner, ..Default::default() };
+        if *owner == TOKEN_PROGRAM || data.len() <= ACCOUNT_TYPE_OFFSET {
+            return Ok(extensions);
+        }
+        if *owner != TOKEN_2022_PROGRAM {
+            return Err(anyhow!("mint is owned by {}, not a token program", owner));
+        }
+        if data[ACCOUNT_TYPE_OFFSET] != MINT_ACCOUNT_TYPE {
+            return Err(anyhow!("account type {} is not a mint", data[ACCOUNT_TYPE_OFFSET]));
+        }
+
+        let layout = Layout::new(data, "mint", ACCOUNT_TYPE_OFFSET + 1)?;
+        let mut offset = ACCOUNT_TYPE_OFFSET + 1;
+        while offset + 4 <= data.len() {
+            let kind = layout.u16(offset)?;
+            let length = layout.u16(offset + 2)? as usize;
+            let value = offset + 4;
+            if kind == 0 {
+                break;
+            }
+            match kind {
+                TRANSFER_FEE_CONFIG => {
+                    let fee = |at: usize| -> Result<TransferFee> {
+                        Ok(TransferFee {
+                            epoch: layout.u64(at)?,
+                            maximum_fee: layout.u64(at + 8)?,
+                            basis_points: layout.u16(at + 16)?,
+                        })
+                    };
+                    // Two authorities and the withheld amount come first.
+                    extensions.transfer_fee =
+                        Some(TransferFeeConfig { older: fee(value + 72)?, newer: fee(value + 90)? });
+                }
+                INTEREST_BEARING_CONFIG => {
+                    extensions.interest_bearing = Some(InterestBearing {
+                        initialized_at: layout.i64(value + 32)?,
+                        pre_update_average_rate: layout.u16(value + 40)? as i16,
+                        last_update_at: layout.i64(value + 42)?,
+                        current_rate: layout.u16(value + 50)? as i16,
+                    });
+                }
+                TRANSFER_HOOK => {
+                    let program = layout.pubkey(value + 32)?;
+                    extensions.transfer_hook = (program != Pubkey::default()).then_some(program);
+                }
+                _ => {}
+            }
+            offset = value + length;
+        }
+        Ok(extensions)
+    }
+}
+
+/// Extensions of the mints a swap touches, with the extra account lists of
+/// their transfer hooks once fetched. Mints it doesn't know are treated as
+/// plain SPL Token mints.
+#[derive(Debug, Clone, Default)]
+pub struct TokenMints {
+    mints: HashMap<Pubkey, MintExtensions>,
+    /// Extra account list data, by hooked mint.
+    hook_lists: HashMap<Pubkey, Vec<u8>>,
+    /// Epoch transfer fees are taken at.
+    pub epoch: u64,
+    /// Unix seconds interest is accrued to.
+    pub timestamp: i64,
+}
+
+impl TokenMints {
+    pub async fn fetch(mints: &[Pubkey]) -> Result<Self> {
+        let fetched = get_multiple_accounts(mints).await?;
+        let mut parsed = HashMap::new();
+        for (mint, account) in fetched.accounts {
+            parsed.insert(mint, MintExtensions::parse(&account.owner, &account.data)?);
+        }
+        Ok(Self::new(parsed, fetched.slot / SLOTS_PER_EPOCH, chrono::Utc::now().timestamp()))
+    }
+
+    pub fn new(mints: HashMap<Pubkey, MintExtensions>, epoch: u64, timestamp: i64) -> Self {
+        Self { mints, hook_lists: HashMap::new(), epoch, timestamp }
+    }
+
+    /// Fetches the extra account list of every hooked mint, which
+    /// [`TokenMints::hook_accounts`] needs.
+    pub async fn fetch_hook_lists(&mut self) -> Result<()> {
+        let lists: HashMap<Pubkey, Pubkey> = self
+            .mints
+            .iter()
+            .filter_map(|(mint, extensions)| {
+                extensions.transfer_hook.map(|program| (extra_account_metas_address(mint, &program), *mint))
+            })
+            .collect();
+        if lists.is_empty() {
+            return Ok(());
+        }
+        let addresses: Vec<Pubkey> = lists.keys().copied().collect();
+        for (address, account) in get_multiple_accounts(&addresses).await?.accounts {
+            self.hook_lists.insert(lists[&address], account.data);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, mint: &Pubkey) -> Option<&MintExtensions> {
+        self.mints.get(mint)
+    }
+
+    pub fn token_program(&self, mint: &Pubkey) -> Pubkey {
+        self.get(mint).map_or(TOKEN_PROGRAM, |extensions| extensions.token_program)
+    }
+
+    /// Withheld when `amount` of `mint` is transferred this epoch.
+    pub fn transfer_fee(&self, mint: &Pubkey, amount: u64) -> u64 {
+        self.get(mint)
+            .and_then(|extensions| extensions.transfer_fee)
+            .map_or(0, |config| config.at_epoch(self.epoch).fee(amount))
+    }
+
+    /// UI units per raw unit over the decimals: above 1 for mints that have
+    /// accrued interest.
+    pub fn ui_scale(&self, mint: &Pubkey) -> f64 {
+        self.get(mint)
+            .and_then(|extensions| extensions.interest_bearing)
+            .map_or(1.0, |interest| interest.ui_scale(self.timestamp))
+    }
+
+    pub fn transfer_hook(&self, mint: &Pubkey) -> Option<Pubkey> {
+        self.get(mint).and_then(|extensions| extensions.transfer_hook)
+    }
+
+    /// Whether the pool's venue can swap both of its mints: Token-2022 and
+    /// hooked mints need a swap instruction that takes their programs and
+    /// hook accounts.
+    pub fn tradable(&self, pool: &dyn Pool) -> bool {
+        let venue = pool.venue();
+        pool.mints().iter().all(|mint| {
+            (self.token_program(mint) == TOKEN_PROGRAM || venue.supports_token_2022())
+                && (self.transfer_hook(mint).is_none() || venue.supports_transfer_hooks())
+        })
+    }
+
+    /// Accounts the mint's transfer hook needs for `transfer`; empty for
+    /// mints without one.
+    pub fn hook_accounts(&self, transfer: &HookTransfer) -> Result<Vec<AccountMeta>> {
+        let Some(program) = self.transfer_hook(&transfer.mint) else {
+            return Ok(Vec::new());
+        };
+        let list = self
+            .hook_lists
+            .get(&transfer.mint)
+            .ok_or_else(|| anyhow!("no extra account list for the transfer hook of {}", transfer.mint))?;
+        hook_accounts(list, &program, transfer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Token-2022 mint with the given extensions as `(type, value)`.
+    fn mint_data(extensions: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut data = vec![0u8; ACCOUNT_TYPE_OFFSET];
+        data.push(MINT_ACCOUNT_TYPE);
+        for (kind, value) in extensions {
+            data.extend_from_slice(&kind.to_le_bytes());
+            data.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            data.extend_from_slice(value);
+        }
+        data
+    }
+
+    fn transfer_fee_config(older: TransferFee, newer: TransferFee) -> Vec<u8> {
+        let mut value = vec![0u8; 72];
+        for fee in [older, newer] {
+            value.extend_from_slice(&fee.epoch.to_le_bytes());
+            value.extend_from_slice(&fee.maximum_fee.to_le_bytes());
+            value.extend_from_slice(&fee.basis_points.to_le_bytes());
+        }
+        value
+    }
+
+    #[test]
+    fn parses_fees_interest_and_hooks() {
+        let older = TransferFee { epoch: 0, maximum_fee: 5_000, basis_points: 100 };
+        let newer = TransferFee { epoch: 700, maximum_fee: u64::MAX, basis_points: 250 };
+        let mut interest = vec![0u8; 32];
+        interest.extend_from_slice(&0i64.to_le_bytes());
+        interest.extend_from_slice(&500i16.to_le_bytes());
+        interest.extend_from_slice(&(SECONDS_PER_YEAR as i64).to_le_bytes());
+        interest.extend_from_slice(&(-500i16).to_le_bytes());
+        let hook = Pubkey::new_unique();
+        let mut hook_value = vec![0u8; 32];
+        hook_value.extend_from_slice(hook.as_ref());
+        let data = mint_data(&[
+            (TRANSFER_FEE_CONFIG, transfer_fee_config(older, newer)),
+            (3, vec![0u8; 32]),
+            (INTEREST_BEARING_CONFIG, interest),
+            (TRANSFER_HOOK, hook_value),
+        ]);
+
+        let extensions = MintExtensions::parse(&TOKEN_2022_PROGRAM, &data).unwrap();
+        assert_eq!(extensions.transfer_fee, Some(TransferFeeConfig { older, newer }));
+        assert_eq!(extensions.transfer_hook, Some(hook));
+        // 5% for a year, then -5% for a year.
+        let interest = extensions.interest_bearing.unwrap();
+        assert!((interest.ui_scale(2 * SECONDS_PER_YEAR as i64) - 1.0).abs() < 1e-9);
+        assert!((interest.ui_scale(SECONDS_PER_YEAR as i64) - 0.05f64.exp()).abs() < 1e-9);
+
+        assert_eq!(MintExtensions::parse(&TOKEN_PROGRAM, &[0u8; 82]).unwrap(), MintExtensions::default());
+        assert!(MintExtensions::parse(&Pubkey::new_unique(), &data).is_err());
+    }
+
+    #[test]
+    fn transfer_fees_round_up_and_switch_at_the_newer_epoch() {
+        let mint = Pubkey::new_unique();
+        let config = TransferFeeConfig {
+            older: TransferFee { epoch: 0, maximum_fee: 5_000, basis_points: 100 },
+            newer: TransferFee { epoch: 700, maximum_fee: u64::MAX, basis_points: 250 },
+        };
+        let extensions = MintExtensions { transfer_fee: Some(config), ..Default::default() };
+        let mints = |epoch| TokenMints::new(HashMap::from([(mint, extensions.clone())]), epoch, 0);
+
+        assert_eq!(mints(699).transfer_fee(&mint, 10_001), 101);
+        assert_eq!(mints(699).transfer_fee(&mint, 1_000_000), 5_000);
+        assert_eq!(mints(700).transfer_fee(&mint, 1_000_000), 25_000);
+        assert_eq!(mints(700).transfer_fee(&Pubkey::new_unique(), 1_000_000), 0);
+        assert_eq!(mints(700).token_program(&Pubkey::new_unique()), TOKEN_PROGRAM);
+    }
+}
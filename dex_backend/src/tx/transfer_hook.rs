@@ -0,0 +1,196 @@
+//! Accounts a Token-2022 transfer hook reads, resolved from the mint's
+//! `ExtraAccountMetaList` the way the token program resolves them when it
+//! calls the hook.
+
+use anyhow::{anyhow, Result};
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::pubkey::Pubkey;
+
+/// Bytes of one `ExtraAccountMeta`: discriminator, address config, signer
+/// and writable flags.
+const EXTRA_ACCOUNT_META_LEN: usize = 35;
+/// Discriminators from this one up derive the address from the program at
+/// account index `discriminator - EXTERNAL_PDA`.
+const EXTERNAL_PDA: u8 = 128;
+
+/// One transfer of a hooked mint, as the hook's `Execute` sees it.
+#[derive(Debug, Clone, Copy)]
+pub struct HookTransfer {
+    pub source: Pubkey,
+    pub mint: Pubkey,
+    pub destination: Pubkey,
+    /// Owner or delegate signing for `source`.
+    pub authority: Pubkey,
+    pub amount: u64,
+}
+
+/// Account holding the mint's extra account list under its hook program.
+pub fn extra_account_metas_address(mint: &Pubkey, hook_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"extra-account-metas", mint.as_ref()], hook_program).0
+}
+
+/// `Execute`'s interface discriminator, which keys its entry in the list.
+fn execute_discriminator() -> [u8; 8] {
+    let hash = solana_sdk::hash::hash(b"spl-transfer-hook-interface:execute");
+    hash.to_bytes()[..8].try_into().unwrap()
+}
+
+/// The extra accounts in list order, then the hook program and the list
+/// account: what the token program expects after a transfer's own accounts.
+/// Seeds reading other accounts' data aren't supported.
+pub fn hook_accounts(list: &[u8], hook_program: &Pubkey, transfer: &HookTransfer) -> Result<Vec<AccountMeta>> {
+    let validation = extra_account_metas_address(&transfer.mint, hook_program);
+    let entries = execute_entries(list)?;
+
+    let mut data = execute_discriminator().to_vec();
+    data.extend_from_slice(&transfer.amount.to_le_bytes());
+    // `Execute`'s accounts; seeds index into these and the extras resolved so far.
+    let mut keys = vec![transfer.source, transfer.mint, transfer.destination, transfer.authority, validation];
+    let mut metas = Vec::with_capacity(entries.len() + 2);
+    for entry in entries.chunks_exact(EXTRA_ACCOUNT_META_LEN) {
+        let config: &[u8; 32] = entry[1..33].try_into().unwrap();
+        let address = match entry[0] {
+            0 => Pubkey::new_from_array(*config),
+            1 => derive(config, hook_program, &keys, &data)?,
+            discriminator if discriminator >= EXTERNAL_PDA => {
+                let index = (discriminator - EXTERNAL_PDA) as usize;
+                let program = keys.get(index).ok_or_else(|| anyhow!("extra account program index {} is out of range", index))?;
+                derive(config, program, &keys, &data)?
+            }
+            discriminator => return Err(anyhow!("unknown extra account discriminator {}", discriminator)),
+        };
+        keys.push(address);
+        metas.push(AccountMeta { pubkey: address, is_signer: entry[33] != 0, is_writable: entry[34] != 0 });
+    }
+    metas.push(AccountMeta::new_readonly(*hook_program, false));
+    metas.push(AccountMeta::new_readonly(validation, false));
+    Ok(metas)
+}
+
+/// The packed `ExtraAccountMeta`s of the list's `Execute` entry.
+fn execute_entries(list: &[u8]) -> Result<&[u8]> {
+    let discriminator = execute_discriminator();
+    let mut offset = 0;
+    while offset + 12 <= list.len() {
+        let length = u32::from_le_bytes(list[offset + 8..offset + 12].try_into().unwrap()) as usize;
+        let value = list
+            .get(offset + 12..offset + 12 + length)
+            .ok_or_else(|| anyhow!("extra account list entry overruns the account"))?;
+        if list[offset..offset + 8] == discriminator {
+            let count = u32::from_le_bytes(
+                value.get(..4).ok_or_else(|| anyhow!("extra account list has no count"))?.try_into().unwrap(),
+            ) as usize;
+            return value
+                .get(4..4 + count * EXTRA_ACCOUNT_META_LEN)
+                .ok_or_else(|| anyhow!("extra account list is shorter than its {} entries", count));
+        }
+        offset += 12 + length;
+    }
+    Err(anyhow!("extra account list has no Execute entry"))
+}
+
+/// Program address from packed seeds: literals, slices of `Execute`'s data
+/// and keys of earlier accounts.
+fn derive(config: &[u8; 32], program: &Pubkey, keys: &[Pubkey], data: &[u8]) -> Result<Pubkey> {
+    let mut seeds: Vec<&[u8]> = Vec::new();
+    let mut offset = 0;
+    while offset < config.len() && config[offset] != 0 {
+        let arg = |i: usize| config.get(offset + i).map(|b| *b as usize).ok_or_else(|| anyhow!("seed config is truncated"));
+        match config[offset] {
+            1 => {
+                let length = arg(1)?;
+                seeds.push(config.get(offset + 2..offset + 2 + length).ok_or_else(|| anyhow!("seed config is truncated"))?);
+                offset += 2 + length;
+            }
+            2 => {
+                let (index, length) = (arg(1)?, arg(2)?);
+                seeds.push(data.get(index..index + length).ok_or_else(|| anyhow!("seed reads past the instruction data"))?);
+                offset += 3;
+            }
+            3 => {
+                let index = arg(1)?;
+                seeds.push(keys.get(index).ok_or_else(|| anyhow!("seed account index {} is out of range", index))?.as_ref());
+                offset += 2;
+            }
+            4 => return Err(anyhow!("seeds from account data are not supported")),
+            seed => return Err(anyhow!("unknown seed type {}", seed)),
+        }
+    }
+    Pubkey::try_find_program_address(&seeds, program)
+        .map(|(address, _)| address)
+        .ok_or_else(|| anyhow!("no program address for the seeds"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(discriminator: u8, config: &[u8], writable: bool) -> Vec<u8> {
+        let mut entry = vec![discriminator];
+        entry.extend_from_slice(config);
+        entry.resize(33, 0);
+        entry.extend_from_slice(&[0, writable as u8]);
+        entry
+    }
+
+    fn list(entries: &[Vec<u8>]) -> Vec<u8> {
+        let mut value = (entries.len() as u32).to_le_bytes().to_vec();
+        entries.iter().for_each(|entry| value.extend_from_slice(entry));
+        let mut list = execute_discriminator().to_vec();
+        list.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        list.extend(value);
+        list
+    }
+
+    #[test]
+    fn resolves_fixed_and_derived_accounts() {
+        let program = Pubkey::new_unique();
+        let fixed = Pubkey::new_unique();
+        let transfer = HookTransfer {
+            source: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            destination: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            amount: 42,
+        };
+        let list = list(&[
+            entry(0, fixed.as_ref(), false),
+            // ["counter", mint]
+            entry(1, &[1, 7, b'c', b'o', b'u', b'n', b't', b'e', b'r', 3, 1], true),
+            // [authority, the fixed account, amount] under the fixed account
+            entry(EXTERNAL_PDA + 5, &[3, 3, 3, 5, 2, 8, 8], false),
+        ]);
+
+        let metas = hook_accounts(&list, &program, &transfer).unwrap();
+        let counter = Pubkey::find_program_address(&[b"counter", transfer.mint.as_ref()], &program).0;
+        let external = Pubkey::find_program_address(
+            &[transfer.authority.as_ref(), fixed.as_ref(), &42u64.to_le_bytes()],
+            &fixed,
+        )
+        .0;
+        assert_eq!(
+            metas,
+            [
+                AccountMeta::new_readonly(fixed, false),
+                AccountMeta::new(counter, false),
+                AccountMeta::new_readonly(external, false),
+                AccountMeta::new_readonly(program, false),
+                AccountMeta::new_readonly(extra_account_metas_address(&transfer.mint, &program), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_seeds_from_account_data() {
+        let transfer = HookTransfer {
+            source: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            destination: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            amount: 1,
+        };
+        let list = list(&[entry(1, &[4, 0, 32, 32], false)]);
+        assert!(hook_accounts(&list, &Pubkey::new_unique(), &transfer).is_err());
+        assert!(hook_accounts(&[], &Pubkey::new_unique(), &transfer).is_err());
+    }
+}
@@ -0,0 +1,304 @@
+use crate::tx_errors::program_errors::{custom_error, program_name};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Why a transaction failed, decoded from `meta.err` and the program logs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxFailure {
+    /// One line for users, e.g. "Jupiter: Slippage tolerance exceeded (instruction 3)".
+    pub reason: String,
+    /// Error variant or program error name, e.g. `SlippageToleranceExceeded`.
+    pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instruction_index: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub program_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub program: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<u32>,
+    pub slippage_exceeded: bool,
+}
+
+/// Decodes the failure of an RPC `getTransaction` result; `None` when it
+/// succeeded.
+pub fn decode_transaction_error(tx_data: &Value) -> Option<TxFailure> {
+    let err = &tx_data["meta"]["err"];
+    if err.is_null() {
+        return None;
+    }
+
+    let logs: Vec<&str> = tx_data["meta"]["logMessages"]
+        .as_array()
+        .map(|logs| logs.iter().filter_map(|l| l.as_str()).collect())
+        .unwrap_or_default();
+
+    Some(decode_error(err, &instruction_programs(tx_data), &logs))
+}
+
+/// Program id of every top-level instruction, for both jsonParsed
+/// (`programId`) and raw (`programIdIndex`) encodings.
+fn instruction_programs(tx_data: &Value) -> Vec<String> {
+    let message = &tx_data["transaction"]["message"];
+    let account_keys = message["accountKeys"].as_array().cloned().unwrap_or_default();
+    let key_at = |index: usize| {
+        account_keys
+            .get(index)
+            .and_then(|k| k.as_str().or_else(|| k["pubkey"].as_str()))
+            .map(String::from)
+    };
+
+    message["instructions"]
+        .as_array()
+        .map(|ixs| {
+            ixs.iter()
+                .map(|ix| {
+                    ix["programId"]
+                        .as_str()
+                        .map(String::from)
+                        .or_else(|| key_at(ix["programIdIndex"].as_u64()? as usize))
+                        .unwrap_or_default()
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn decode_error(err: &Value, instruction_programs: &[String], logs: &[&str]) -> TxFailure {
+    if let Some([index, inner]) = err["InstructionError"].as_array().map(Vec::as_slice) {
+        let index = index.as_u64().unwrap_or(0) as u8;
+        return decode_instruction_error(index, inner, instruction_programs, logs);
+    }
+
+    let (error, message) = match err {
+        Value::String(name) => (name.clone(), transaction_error_message(name, &Value::Null)),
+        Value::Object(map) => match map.iter().next() {
+            Some((name, detail)) => (name.clone(), transaction_error_message(name, detail)),
+            None => ("Unknown".to_string(), "Transaction failed".to_string()),
+        },
+        other => (other.to_string(), "Transaction failed".to_string()),
+    };
+
+    TxFailure {
+        slippage_exceeded: is_slippage(&error),
+        reason: message,
+        error,
+        instruction_index: None,
+        program_id: None,
+        program: None,
+        code: None,
+    }
+}
+
+fn decode_instruction_error(
+    index: u8,
+    inner: &Value,
+    instruction_programs: &[String],
+    logs: &[&str],
+) -> TxFailure {
+    // The error comes from the innermost program that failed, which may be a
+    // CPI (e.g. Raydium under a Jupiter route) rather than the top level one.
+    let program_id = failing_program(logs)
+        .or_else(|| instruction_programs.get(index as usize).cloned())
+        .filter(|id| !id.is_empty());
+
+    let (error, message, code) = match inner {
+        Value::Object(map) if map.contains_key("Custom") => {
+            let code = map["Custom"].as_u64().unwrap_or(0) as u32;
+            let (error, message) = program_id
+                .as_deref()
+                .and_then(|id| custom_error(id, code))
+                .map(|(name, message)| (name.to_string(), message.to_string()))
+                .or_else(|| anchor_error_from_logs(logs, code))
+                .unwrap_or_else(|| {
+                    ("Custom".to_string(), format!("Custom program error {:#x}", code))
+                });
+            (error, message, Some(code))
+        }
+        Value::String(name) => (name.clone(), instruction_error_message(name), None),
+        Value::Object(map) => match map.keys().next() {
+            Some(name) => (name.clone(), instruction_error_message(name), None),
+            None => ("Unknown".to_string(), "Instruction failed".to_string(), None),
+        },
+        other => (other.to_string(), "Instruction failed".to_string(), None),
+    };
+
+    let program = program_id.as_deref().and_then(program_name);
+    let label = match (&program, &program_id) {
+        (Some(name), _) => name.clone(),
+        (None, Some(id)) => format!("Program {}", short_id(id)),
+        (None, None) => "Instruction".to_string(),
+    };
+
+    TxFailure {
+        reason: format!("{}: {} (instruction {})", label, message, index),
+        slippage_exceeded: is_slippage(&error),
+        error,
+        instruction_index: Some(index),
+        program_id,
+        program,
+        code,
+    }
+}
+
+/// First program reported as failed in the logs; callers up the CPI stack
+/// log the same error again after it.
+fn failing_program(logs: &[&str]) -> Option<String> {
+    logs.iter().find_map(|line| {
+        let rest = line.strip_prefix("Program ")?;
+        let (program, _) = rest.split_once(" failed: ")?;
+        Some(program.to_string())
+    })
+}
+
+/// Anchor programs log `Error Code: <name>. Error Number: <n>. Error Message: <msg>.`
+fn anchor_error_from_logs(logs: &[&str], code: u32) -> Option<(String, String)> {
+    logs.iter().find_map(|line| {
+        let (_, rest) = line.split_once("Error Code: ")?;
+        let (name, rest) = rest.split_once(". Error Number: ")?;
+        let (number, message) = rest.split_once(". Error Message: ")?;
+        if number.trim().parse::<u32>().ok()? != code {
+            return None;
+        }
+        Some((name.to_string(), message.trim_end_matches('.').to_string()))
+    })
+}
+
+fn transaction_error_message(name: &str, detail: &Value) -> String {
+    match name {
+        "InsufficientFundsForFee" => "Not enough SOL to pay the network fee".into(),
+        "BlockhashNotFound" => "Blockhash expired before the transaction landed".into(),
+        "AlreadyProcessed" => "Transaction was already processed".into(),
+        "AccountInUse" => "An account was locked by another transaction".into(),
+        "InsufficientFundsForRent" => match detail["account_index"].as_u64() {
+            Some(index) => format!("Account {} would be left below the rent-exempt minimum", index),
+            None => "An account would be left below the rent-exempt minimum".into(),
+        },
+        "DuplicateInstruction" => "Transaction contains a duplicate instruction".into(),
+        _ => humanize(name),
+    }
+}
+
+fn instruction_error_message(name: &str) -> String {
+    match name {
+        "ComputationalBudgetExceeded" | "ProgramFailedToComplete" => {
+            "Ran out of compute units".into()
+        }
+        "InsufficientFunds" => "Insufficient funds".into(),
+        _ => humanize(name),
+    }
+}
+
+fn is_slippage(error: &str) -> bool {
+    error.to_lowercase().contains("slippage")
+        || matches!(
+            error,
+            "AmountOutBelowMinimum"
+                | "AmountInAboveMaximum"
+                | "TokenMinSubceeded"
+                | "TokenMaxExceeded"
+                | "TooLittleOutputReceived"
+                | "TooMuchInputPaid"
+        )
+}
+
+/// `InvalidAccountData` -> `Invalid account data`.
+fn humanize(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c.is_uppercase() && prev_lower {
+            out.push(' ');
+            out.extend(c.to_lowercase());
+        } else if out.is_empty() {
+            out.push(c);
+        } else {
+            out.extend(c.to_lowercase());
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+    }
+    out
+}
+
+fn short_id(id: &str) -> String {
+    if id.len() > 8 {
+        format!("{}..{}", &id[..4], &id[id.len() - 4..])
+    } else {
+        id.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const JUPITER: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+    const RAYDIUM: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
+
+    fn programs() -> Vec<String> {
+        vec!["ComputeBudget111111111111111111111111111111".into(), JUPITER.into()]
+    }
+
+    #[test]
+    fn maps_known_custom_codes() {
+        let failure = decode_error(&json!({"InstructionError": [1, {"Custom": 6001}]}), &programs(), &[]);
+        assert_eq!(failure.reason, "Jupiter: Slippage tolerance exceeded (instruction 1)");
+        assert_eq!(failure.error, "SlippageToleranceExceeded");
+        assert_eq!(failure.code, Some(6001));
+        assert!(failure.slippage_exceeded);
+    }
+
+    #[test]
+    fn attributes_cpi_failures_to_the_inner_program() {
+        let logs = [
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 invoke [1]",
+            "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 invoke [2]",
+            "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 failed: custom program error: 0x1e",
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 failed: custom program error: 0x1e",
+        ];
+        let failure = decode_error(&json!({"InstructionError": [1, {"Custom": 30}]}), &programs(), &logs);
+        assert_eq!(failure.program_id.as_deref(), Some(RAYDIUM));
+        assert_eq!(failure.reason, "Raydium AMM: Slippage tolerance exceeded (instruction 1)");
+        assert_eq!(failure.error, "ExceededSlippage");
+        assert!(failure.slippage_exceeded);
+    }
+
+    #[test]
+    fn decodes_whirlpool_slippage_failures() {
+        let logs = [
+            "Program whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc invoke [1]",
+            "Program log: Instruction: Swap",
+            "Program log: AnchorError occurred. Error Code: AmountOutBelowMinimum. Error Number: 6036. Error Message: Amount out below minimum threshold.",
+            "Program whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc consumed 48213 of 1399850 compute units",
+            "Program whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc failed: custom program error: 0x1794",
+        ];
+        let failure = decode_error(&json!({"InstructionError": [2, {"Custom": 0x1794}]}), &[], &logs);
+        assert_eq!(failure.error, "AmountOutBelowMinimum");
+        assert_eq!(failure.reason, "Orca Whirlpool: Slippage tolerance exceeded (instruction 2)");
+        assert!(failure.slippage_exceeded);
+
+        let failure = decode_error(&json!({"InstructionError": [2, {"Custom": 6035}]}), &[], &logs);
+        assert_eq!(failure.error, "ZeroTradableAmount");
+        assert!(!failure.slippage_exceeded);
+    }
+
+    #[test]
+    fn falls_back_to_anchor_logs_and_builtin_names() {
+        let logs = [
+            "Program log: AnchorError occurred. Error Code: PoolPaused. Error Number: 6010. Error Message: Pool is paused.",
+            "Program Eq1111111111111111111111111111111111111111 failed: custom program error: 0x177a",
+        ];
+        let failure = decode_error(&json!({"InstructionError": [0, {"Custom": 6010}]}), &[], &logs);
+        assert_eq!(failure.error, "PoolPaused");
+        assert_eq!(failure.reason, "Program Eq11..1111: Pool is paused (instruction 0)");
+
+        let failure = decode_error(&json!({"InstructionError": [2, "InvalidAccountData"]}), &programs(), &[]);
+        assert_eq!(failure.reason, "Instruction: Invalid account data (instruction 2)");
+
+        let failure = decode_error(&json!("InsufficientFundsForFee"), &[], &[]);
+        assert_eq!(failure.reason, "Not enough SOL to pay the network fee");
+        assert_eq!(failure.instruction_index, None);
+    }
+}
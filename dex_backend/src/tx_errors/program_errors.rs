@@ -0,0 +1,259 @@
+use crate::labels::registry::{labels, LabelCategory};
+use crate::SWAP_PROGRAM_ERRORS;
+use anyhow::Result;
+use serde_json::Value;
+
+/// Programs whose custom error codes we can name without their logs.
+pub struct KnownProgram {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub errors: &'static [(u32, &'static str, &'static str)],
+}
+
+pub const SPL_TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+pub const TOKEN_2022_PROGRAM: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+pub const SYSTEM_PROGRAM: &str = "11111111111111111111111111111111";
+
+// Token-2022 reuses the SPL Token error enum for the shared codes.
+const TOKEN_ERRORS: &[(u32, &str, &str)] = &[
+    (0, "NotRentExempt", "Lamport balance below rent-exempt threshold"),
+    (1, "InsufficientFunds", "Insufficient token balance"),
+    (2, "InvalidMint", "Invalid mint"),
+    (3, "MintMismatch", "Account not associated with this mint"),
+    (4, "OwnerMismatch", "Token account owner does not match"),
+    (5, "FixedSupply", "This token's supply is fixed"),
+    (6, "AlreadyInUse", "Account already in use"),
+    (7, "InvalidNumberOfProvidedSigners", "Invalid number of provided signers"),
+    (8, "InvalidNumberOfRequiredSigners", "Invalid number of required signers"),
+    (9, "UninitializedState", "Token account is not initialized"),
+    (10, "NativeNotSupported", "Instruction does not support native tokens"),
+    (11, "NonNativeHasBalance", "Non-native account can only be closed if its balance is zero"),
+    (12, "InvalidInstruction", "Invalid instruction"),
+    (13, "InvalidState", "State is invalid for requested operation"),
+    (14, "Overflow", "Operation overflowed"),
+    (15, "AuthorityTypeNotSupported", "Account does not support specified authority type"),
+    (16, "MintCannotFreeze", "This token mint cannot freeze accounts"),
+    (17, "AccountFrozen", "Token account is frozen"),
+    (18, "MintDecimalsMismatch", "Mint decimals mismatch"),
+    (19, "NonNativeNotSupported", "Instruction does not support non-native tokens"),
+];
+
+const SYSTEM_ERRORS: &[(u32, &str, &str)] = &[
+    (0, "AccountAlreadyInUse", "Account already exists"),
+    (1, "ResultWithNegativeLamports", "Insufficient SOL for this transfer"),
+    (2, "InvalidProgramId", "Cannot assign account to this program id"),
+    (3, "InvalidAccountDataLength", "Cannot allocate account data of this length"),
+    (4, "MaxSeedLengthExceeded", "Length of requested seed is too long"),
+    (5, "AddressWithSeedMismatch", "Provided address does not match addressed derived from seed"),
+    (6, "NonceNoRecentBlockhashes", "Advancing stored nonce requires a populated RecentBlockhashes sysvar"),
+    (7, "NonceBlockhashNotExpired", "Stored nonce is still in recent_blockhashes"),
+    (8, "NonceUnexpectedBlockhashValue", "Specified nonce does not match stored nonce"),
+];
+
+const RAYDIUM_AMM_ERRORS: &[(u32, &str, &str)] = &[
+    (22, "InvalidStatus", "Pool is not open for swaps"),
+    (30, "ExceededSlippage", "Slippage tolerance exceeded"),
+];
+
+const RAYDIUM_CPMM_ERRORS: &[(u32, &str, &str)] = &[
+    (6005, "ExceededSlippage", "Slippage tolerance exceeded"),
+];
+
+const RAYDIUM_CLMM_ERRORS: &[(u32, &str, &str)] = &[
+    (6011, "InvalidTickArray", "Price moved outside the provided tick arrays"),
+    (6013, "SqrtPriceLimitOverflow", "Price limit is out of range"),
+    (6019, "LiquidityInsufficient", "Not enough liquidity in the pool"),
+    (6020, "TransactionTooOld", "Transaction is too old"),
+    (6021, "PriceSlippageCheck", "Price moved past the slippage limit"),
+    (6022, "TooLittleOutputReceived", "Slippage tolerance exceeded"),
+    (6023, "TooMuchInputPaid", "Slippage tolerance exceeded"),
+    (6024, "ZeroAmountSpecified", "Swap amount is zero"),
+    (6025, "InvalidInputPoolVault", "Input vault does not belong to the pool"),
+    (6026, "TooSmallInputOrOutputAmount", "Swap amount is too small"),
+    (6027, "NotEnoughTickArrayAccount", "Not enough tick arrays for the swap"),
+    (6028, "InvalidFirstTickArrayAccount", "First tick array does not hold the current price"),
+];
+
+const WHIRLPOOL_ERRORS: &[(u32, &str, &str)] = &[
+    (6017, "TokenMaxExceeded", "Input amount above the specified maximum"),
+    (6018, "TokenMinSubceeded", "Output amount below the specified minimum"),
+    (6023, "InvalidTickArraySequence", "Price moved outside the provided tick arrays"),
+    (6034, "InvalidSqrtPriceLimitDirection", "Price limit is on the wrong side of the current price"),
+    (6035, "ZeroTradableAmount", "Swap amount is too small to trade"),
+    (6036, "AmountOutBelowMinimum", "Slippage tolerance exceeded"),
+    (6037, "AmountInAboveMaximum", "Slippage tolerance exceeded"),
+];
+
+const METEORA_DLMM_ERRORS: &[(u32, &str, &str)] = &[
+    (6002, "InvalidInput", "Invalid input"),
+    (6003, "ExceededAmountSlippageTolerance", "Slippage tolerance exceeded"),
+    (6004, "ExceededBinSlippageTolerance", "Price moved past the slippage limit"),
+    (6009, "BinArrayNotFound", "Price moved outside the provided bin arrays"),
+    (6012, "PairInsufficientLiquidity", "Not enough liquidity in the pool"),
+    (6036, "BitmapExtensionAccountIsNotProvided", "Bitmap extension account is missing"),
+    (6037, "CannotFindNonZeroLiquidityBinArrayId", "No liquidity left in the swap direction"),
+    (6039, "InsufficientOutAmount", "Swap output is zero"),
+];
+
+const JUPITER_ERRORS: &[(u32, &str, &str)] = &[
+    (6000, "EmptyRoute", "Empty route"),
+    (6001, "SlippageToleranceExceeded", "Slippage tolerance exceeded"),
+    (6002, "InvalidCalculation", "Invalid calculation"),
+    (6003, "MissingPlatformFeeAccount", "Missing platform fee account"),
+    (6004, "InvalidSlippage", "Invalid slippage"),
+    (6005, "NotEnoughPercent", "Not enough percent to 100"),
+    (6008, "NotEnoughAccountKeys", "Not enough account keys"),
+    (6014, "IncorrectTokenProgramID", "Incorrect token program ID"),
+    (6017, "ExactOutAmountNotMatched", "Exact out amount doesn't match"),
+    (6018, "SourceAndDestinationMintCannotBeTheSame", "Source and destination mint cannot be the same"),
+];
+
+pub const KNOWN_PROGRAMS: &[KnownProgram] = &[
+    KnownProgram { id: SPL_TOKEN_PROGRAM, name: "SPL Token", errors: TOKEN_ERRORS },
+    KnownProgram { id: TOKEN_2022_PROGRAM, name: "Token-2022", errors: TOKEN_ERRORS },
+    KnownProgram { id: SYSTEM_PROGRAM, name: "System Program", errors: SYSTEM_ERRORS },
+    KnownProgram {
+        id: "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8",
+        name: "Raydium AMM",
+        errors: RAYDIUM_AMM_ERRORS,
+    },
+    KnownProgram {
+        id: "CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C",
+        name: "Raydium CPMM",
+        errors: RAYDIUM_CPMM_ERRORS,
+    },
+    KnownProgram {
+        id: "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK",
+        name: "Raydium CLMM",
+        errors: RAYDIUM_CLMM_ERRORS,
+    },
+    KnownProgram {
+        id: "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc",
+        name: "Orca Whirlpool",
+        errors: WHIRLPOOL_ERRORS,
+    },
+    KnownProgram {
+        id: "LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo",
+        name: "Meteora DLMM",
+        errors: METEORA_DLMM_ERRORS,
+    },
+    KnownProgram {
+        id: "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4",
+        name: "Jupiter",
+        errors: JUPITER_ERRORS,
+    },
+    KnownProgram {
+        id: "ComputeBudget111111111111111111111111111111",
+        name: "Compute Budget",
+        errors: &[],
+    },
+    KnownProgram {
+        id: "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL",
+        name: "Associated Token Account",
+        errors: &[],
+    },
+];
+
+/// Display name of a program; anything outside the error tables (e.g. our
+/// own swap program) is named from the address labels.
+pub fn program_name(program_id: &str) -> Option<String> {
+    KNOWN_PROGRAMS
+        .iter()
+        .find(|p| p.id == program_id)
+        .map(|p| p.name.to_string())
+        .or_else(|| {
+            labels()
+                .lookup(None, program_id)
+                .filter(|l| matches!(l.category, LabelCategory::Program | LabelCategory::ArbProgram))
+                .map(|l| l.name)
+        })
+}
+
+/// Custom errors of a program whose id differs per deployment, read from
+/// its Anchor IDL.
+pub struct DeployedProgram {
+    pub id: String,
+    pub errors: Vec<(u32, String, String)>,
+}
+
+/// Our swap program: `SWAP_PROGRAM_ID` with its Anchor IDL at
+/// `SWAP_PROGRAM_IDL`. `None` when either is unset or the IDL is unreadable.
+fn swap_program() -> Option<&'static DeployedProgram> {
+    SWAP_PROGRAM_ERRORS
+        .get_or_init(|| {
+            let id = std::env::var("SWAP_PROGRAM_ID").ok()?.trim().to_string();
+            let path = std::env::var("SWAP_PROGRAM_IDL").ok()?;
+            match std::fs::read_to_string(&path).map_err(anyhow::Error::from).and_then(|idl| idl_errors(&idl)) {
+                Ok(errors) => Some(DeployedProgram { id, errors }),
+                Err(e) => {
+                    eprintln!("[tx_errors] Cannot read the swap program IDL {}: {}", path, e);
+                    None
+                }
+            }
+        })
+        .as_ref()
+}
+
+/// The IDL's `errors` as `(code, name, message)`; errors without a message
+/// are described by their name.
+fn idl_errors(idl: &str) -> Result<Vec<(u32, String, String)>> {
+    let idl: Value = serde_json::from_str(idl)?;
+    Ok(idl["errors"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|error| {
+            let code = u32::try_from(error["code"].as_u64()?).ok()?;
+            let name = error["name"].as_str()?;
+            let message = error["msg"].as_str().unwrap_or(name);
+            Some((code, name.to_string(), message.to_string()))
+        })
+        .collect())
+}
+
+/// `(name, message)` for a custom error code of a known program.
+pub fn custom_error(program_id: &str, code: u32) -> Option<(&'static str, &'static str)> {
+    if let Some(program) = KNOWN_PROGRAMS.iter().find(|p| p.id == program_id) {
+        return program.errors.iter().find(|(c, _, _)| *c == code).map(|(_, name, message)| (*name, *message));
+    }
+    swap_program()
+        .filter(|program| program.id == program_id)?
+        .errors
+        .iter()
+        .find(|(c, _, _)| *c == code)
+        .map(|(_, name, message)| (name.as_str(), message.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_errors_from_an_anchor_idl() {
+        let idl = r#"{
+            "version": "0.1.0",
+            "name": "equiswap",
+            "errors": [
+                { "code": 6000, "name": "SlippageExceeded", "msg": "Slippage tolerance exceeded" },
+                { "code": 6001, "name": "PoolPaused" }
+            ]
+        }"#;
+        assert_eq!(
+            idl_errors(idl).unwrap(),
+            [
+                (6000, "SlippageExceeded".to_string(), "Slippage tolerance exceeded".to_string()),
+                (6001, "PoolPaused".to_string(), "PoolPaused".to_string()),
+            ]
+        );
+        assert!(idl_errors(r#"{ "name": "no_errors" }"#).unwrap().is_empty());
+    }
+
+    #[test]
+    fn venue_tables_use_the_on_chain_codes() {
+        let clmm = "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK";
+        let dlmm = "LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo";
+        assert_eq!(custom_error(clmm, 0x1786).unwrap().0, "TooLittleOutputReceived");
+        assert_eq!(custom_error(dlmm, 0x1773).unwrap().0, "ExceededAmountSlippageTolerance");
+        assert_eq!(custom_error(dlmm, 6000), None);
+    }
+}
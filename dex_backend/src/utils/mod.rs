@@ -0,0 +1,2 @@
+pub mod token_amount;
+pub mod upstreams;
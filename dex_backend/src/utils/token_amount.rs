@@ -0,0 +1,118 @@
+use serde::Serializer;
+use std::fmt::Display;
+
+/// Exact decimal string for `raw` base units, e.g. `(1_500_000, 6)` → `"1.5"`.
+/// Trailing fractional zeros are dropped; no float is involved at any point.
+pub fn format_token_amount(raw: u128, decimals: u8) -> String {
+    if decimals == 0 {
+        return raw.to_string();
+    }
+
+    let digits = format!("{:0>width$}", raw, width = decimals as usize + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals as usize);
+    let fraction = fraction.trim_end_matches('0');
+
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
+
+/// Parses a UI amount string into base units. Digits beyond the mint's
+/// precision are truncated; thousands separators are ignored, and exponent
+/// form (`1.5e-7`) is shifted exactly.
+pub fn parse_token_amount(ui_amount: &str, decimals: u8) -> Option<u128> {
+    let cleaned: String = ui_amount.trim().chars().filter(|c| *c != ',').collect();
+    let cleaned = cleaned.strip_prefix('-').unwrap_or(&cleaned);
+    let (mantissa, exponent) = match cleaned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+        None => (cleaned, 0),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    if !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    // Move the point by the exponent. Past the mint's precision on the left
+    // it is all truncated; far enough right any non-zero digit overflows.
+    let digits = format!("{}{}", whole, fraction);
+    let point = (whole.len() as i64).saturating_add(exponent).clamp(-(decimals as i64), digits.len() as i64 + 40);
+    let (whole, fraction) = if point <= 0 {
+        (String::new(), format!("{}{}", "0".repeat(-point as usize), digits))
+    } else if point as usize >= digits.len() {
+        (format!("{}{}", digits, "0".repeat(point as usize - digits.len())), String::new())
+    } else {
+        let (whole, fraction) = digits.split_at(point as usize);
+        (whole.to_string(), fraction.to_string())
+    };
+
+    let mut fraction: String = fraction.chars().take(decimals as usize).collect();
+    while fraction.len() < decimals as usize {
+        fraction.push('0');
+    }
+
+    let digits = format!("{}{}", whole, fraction);
+    if digits.is_empty() {
+        return Some(0);
+    }
+    digits.parse::<u128>().ok()
+}
+
+/// Approximate UI value for display maths (USD values, charts). Never feed
+/// the result back into balances.
+pub fn to_ui_f64(raw: u128, decimals: u8) -> f64 {
+    raw as f64 / 10f64.powi(decimals as i32)
+}
+
+/// Serializes integers as JSON strings so u64/u128 base units survive
+/// JavaScript clients.
+pub fn serialize_as_string<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Display,
+{
+    serializer.collect_str(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_exact_decimals() {
+        assert_eq!(format_token_amount(300_000_000, 9), "0.3");
+        assert_eq!(format_token_amount(1, 9), "0.000000001");
+        assert_eq!(format_token_amount(5_000_000_000, 9), "5");
+        assert_eq!(format_token_amount(42, 0), "42");
+        assert_eq!(
+            format_token_amount(18_446_744_073_709_551_615_123, 9),
+            "18446744073709.551615123"
+        );
+    }
+
+    #[test]
+    fn parses_round_trip() {
+        assert_eq!(parse_token_amount("0.3", 9), Some(300_000_000));
+        assert_eq!(parse_token_amount("1,234.5", 2), Some(123_450));
+        assert_eq!(parse_token_amount("0.1234567", 6), Some(123_456));
+        assert_eq!(parse_token_amount(".5", 1), Some(5));
+        assert_eq!(parse_token_amount("abc", 6), None);
+        assert_eq!(parse_token_amount("", 6), None);
+    }
+
+    #[test]
+    fn parses_exponent_form() {
+        assert_eq!(parse_token_amount("1e-7", 9), Some(100));
+        assert_eq!(parse_token_amount("1.5E-7", 9), Some(150));
+        assert_eq!(parse_token_amount("2.5e3", 2), Some(250_000));
+        assert_eq!(parse_token_amount("1e-12", 6), Some(0));
+        assert_eq!(parse_token_amount("0e400", 6), Some(0));
+        assert_eq!(parse_token_amount("1e400", 6), None);
+        assert_eq!(parse_token_amount("1e", 6), None);
+    }
+}
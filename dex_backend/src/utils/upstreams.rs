@@ -0,0 +1,40 @@
+//! Base URLs of the third-party APIs the backend calls, each overridable
+//! from `.env`, e.g. to point tests at local stand-ins.
+
+const DEFAULT_SOLFLARE_WALLET_API_URL: &str = "https://wallet-api.solflare.com";
+const DEFAULT_SOLFLARE_ACTIVITY_API_URL: &str = "https://activity-api.solflare.com";
+const DEFAULT_MORALIS_API_URL: &str = "https://solana-gateway.moralis.io";
+const DEFAULT_JUPITER_TOKEN_LIST_URL: &str = "https://cache.jup.ag/tokens";
+const DEFAULT_JITO_TIP_FLOOR_URL: &str = "https://bundles.jito.wtf/api/v1/bundles/tip_floor";
+
+fn configured(var: &str, default: &str) -> String {
+    std::env::var(var)
+        .map(|url| url.trim_end_matches('/').to_string())
+        .unwrap_or_else(|_| default.to_string())
+}
+
+/// Portfolio and capped token search. Override with `SOLFLARE_WALLET_API_URL`.
+pub fn solflare_wallet_api_url() -> String {
+    configured("SOLFLARE_WALLET_API_URL", DEFAULT_SOLFLARE_WALLET_API_URL)
+}
+
+/// Signatures and parsed transactions. Override with
+/// `SOLFLARE_ACTIVITY_API_URL`.
+pub fn solflare_activity_api_url() -> String {
+    configured("SOLFLARE_ACTIVITY_API_URL", DEFAULT_SOLFLARE_ACTIVITY_API_URL)
+}
+
+/// Token metadata fallback. Override with `MORALIS_API_URL`.
+pub fn moralis_api_url() -> String {
+    configured("MORALIS_API_URL", DEFAULT_MORALIS_API_URL)
+}
+
+/// The full token list itself. Override with `JUPITER_TOKEN_LIST_URL`.
+pub fn jupiter_token_list_url() -> String {
+    configured("JUPITER_TOKEN_LIST_URL", DEFAULT_JUPITER_TOKEN_LIST_URL)
+}
+
+/// The tip floor endpoint itself. Override with `JITO_TIP_FLOOR_URL`.
+pub fn jito_tip_floor_url() -> String {
+    configured("JITO_TIP_FLOOR_URL", DEFAULT_JITO_TIP_FLOOR_URL)
+}
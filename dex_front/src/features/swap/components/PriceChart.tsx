@@ -12,14 +12,15 @@ import {
 } from "lightweight-charts";
 import { PriceChartProps } from "@/types";
 
-interface OhlcvItem {
-    unixTime: number;
-    c: number;
+interface OhlcvCandle {
+    time: number;
+    close: number;
 }
 
 interface OhlcvResponse {
-    data?: {
-        items?: OhlcvItem[];
+    success: boolean;
+    result?: {
+        candles?: OhlcvCandle[];
     };
 }
 
@@ -101,28 +102,26 @@ export default function PriceChart(props: PriceChartProps) {
 
     // Refresh chart data every 15s using refreshTrigger
     useEffect(() => {
-        const url = new URL("https://birdeye-proxy.raydium.io/defi/ohlcv/base_quote");
-        url.searchParams.set(
-            "base_address",
-            baseAddress === "11111111111111111111111111111111"
+        const params = new URLSearchParams({
+            base: baseAddress === "11111111111111111111111111111111"
                 ? "So11111111111111111111111111111111111111112"
-                : baseAddress
-        );
-        url.searchParams.set("quote_address", quoteAddress === "11111111111111111111111111111111"
-            ? "So11111111111111111111111111111111111111112"
-            : quoteAddress);
-        url.searchParams.set("type", interval);
-        url.searchParams.set("time_from", String(timeFrom));
-        url.searchParams.set("time_to", String(timeTo));
-
-        fetch(url.toString())
+                : baseAddress,
+            quote: quoteAddress === "11111111111111111111111111111111"
+                ? "So11111111111111111111111111111111111111112"
+                : quoteAddress,
+            interval,
+            from: String(timeFrom),
+            to: String(timeTo),
+        });
+
+        fetch(`/api/ohlcv?${params.toString()}`)
             .then((r) => r.json())
             .then((json: OhlcvResponse) => {
-                const items = json.data?.items ?? [];
-                if (items.length === 0) return;
+                const candles = json.result?.candles ?? [];
+                if (candles.length === 0) return;
 
-                const data = items
-                    .map((b) => ({ time: b.unixTime as UTCTimestamp, value: b.c }))
+                const data = candles
+                    .map((c) => ({ time: c.time as UTCTimestamp, value: c.close }))
                     .sort((a, b) => a.time - b.time);
 
                 seriesRef.current?.setData(data);
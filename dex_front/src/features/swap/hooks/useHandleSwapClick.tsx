@@ -7,15 +7,12 @@ import { useSwapStore } from "@/stores/swap-ui";
 import { toast } from "sonner";
 import { useSettingsStore } from "@/stores/settingsStore.ts";
 
-const JITO_BUNDLE_URLS = [
-    "https://mainnet.block-engine.jito.wtf",
-    "https://amsterdam.mainnet.block-engine.jito.wtf",
-    "https://frankfurt.mainnet.block-engine.jito.wtf",
-    "https://london.mainnet.block-engine.jito.wtf",
-    "https://ny.mainnet.block-engine.jito.wtf",
-    "https://tokyo.mainnet.block-engine.jito.wtf",
-    "https://slc.mainnet.block-engine.jito.wtf",
-];
+type BundleStatus = {
+    bundleId: string;
+    state: "pending" | "landed" | "failed" | "expired";
+    signatures: string[];
+    landedSlot?: number;
+};
 
 export const useHandleSwapClick = (
     refetchBalances: () => Promise<void>
@@ -50,36 +47,29 @@ export const useHandleSwapClick = (
                 toastId = toast.loading("Submitting Jito bundle...");
                 const signedTx = await signTransaction(unsignedTx);
                 const signedTxBase64 = Buffer.from(signedTx.serialize()).toString("base64");
-                let result;
-                try {
-                    result = await submitToAllJitoEndpoints(signedTxBase64, arb_transaction);
-                } catch (e) {
-                    console.error("Jito submission failed:", e);
-                    throw new Error("Failed to submit bundle to all Jito endpoints");
-                }
-
-
-                if (typeof result.bundleId === "string") {
-                    await pollBundleStatus(result.bundleId, result.url, toastId);
-                } else {
-                    toast.error("Swap sent but no bundle ID returned", { id: toastId });
-                }
+                const bundle = await submitBundle([signedTxBase64, arb_transaction]);
+                await pollBundleStatus(bundle.bundleId, toastId);
             } else {
                 toastId = toast.loading("Sending transaction...");
                 const txSig = await sendTransaction(unsignedTx, connection);
-                toast.success("Transaction sent", {
-                    id: toastId,
-                    description: (
-                        <a
-                            href={`https://solscan.io/tx/${txSig}`}
-                target="_blank"
-                rel="noopener noreferrer"
-                className="text-blue-500 hover:underline"
+                const stage = await followSignature(txSig);
+                const link = (
+                    <a
+                        href={`https://solscan.io/tx/${txSig}`}
+                        target="_blank"
+                        rel="noopener noreferrer"
+                        className="text-blue-500 hover:underline"
                     >
-                    View transaction
-                </a>
-            ),
-                });
+                        View transaction
+                    </a>
+                );
+                if (stage === "expired") {
+                    toast.error("Swap expired before landing. Try again", { id: toastId });
+                } else if (stage === "failed") {
+                    toast.error("Swap failed on chain", { id: toastId, description: link });
+                } else {
+                    toast.success("Swap confirmed", { id: toastId, description: link });
+                }
             }
 
             await refetchBalances();
@@ -93,108 +83,87 @@ export const useHandleSwapClick = (
     };
 };
 
-async function submitToAllJitoEndpoints(signedTxBase64: string, arb_transaction: string) {
-    const attempts = JITO_BUNDLE_URLS.map(async (url) => {
-        try {
-            const res = await fetch(`${url}/api/v1/bundles`, {
-                method: "POST",
-                headers: { "Content-Type": "application/json" },
-                body: JSON.stringify({
-                    jsonrpc: "2.0",
-                    id: 1,
-                    method: "sendBundle",
-                    params: [[signedTxBase64, arb_transaction], { encoding: "base64" }],
-                }),
-            });
-
-            const result = await res.json();
-            if (res.ok && !result.error) {
-                return { url, bundleId: result.result };
-            } else {
-                console.warn(`Jito failed @ ${url}`, result?.error || res.statusText);
-                return null;
-            }
-        } catch (err) {
-            console.warn(`Jito threw @ ${url}`, err);
-            return null;
-        }
+async function submitBundle(transactions: string[]): Promise<BundleStatus> {
+    const res = await fetch("/api/bundles", {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify({ transactions }),
     });
-
-    const responses = await Promise.allSettled(attempts);
-    for (const r of responses) {
-        if (r.status === "fulfilled" && r.value) {
-            return r.value; // Return first success
-        }
+    const data = await res.json();
+    if (!res.ok || !data.success) {
+        throw new Error(data?.error || "Bundle submission failed");
     }
-
-    throw new Error("All Jito endpoints failed");
+    return data.result;
 }
 
-async function pollBundleStatus(
-    bundleId: string,
-    jitoUrl: string,
-    toastId?: string | number
-): Promise<void> {
-    const maxAttempts = 30;
+async function pollBundleStatus(bundleId: string, toastId?: string | number): Promise<void> {
     const delay = 1000;
-    let attempts = 0;
-    let seenEmptyCount = 0;
-    const maxEmptyBeforeFail = 5;
-
     const waitingToastId = toast.loading("Waiting for confirmation... ", { duration: Infinity });
 
-    while (attempts < maxAttempts) {
-        try {
-            const res = await fetch(`${jitoUrl}/api/v1/getBundleStatuses`, {
-                method: "POST",
-                headers: { "Content-Type": "application/json" },
-                body: JSON.stringify({
-                    jsonrpc: "2.0",
-                    id: 1,
-                    method: "getBundleStatuses",
-                    params: [[bundleId]],
-                }),
-            });
-
-            const data = await res.json();
-            const value = data?.result?.value;
+    try {
+        // The backend expires bundles that never land, so this always ends.
+        while (true) {
+            await new Promise((res) => setTimeout(res, delay));
+            let status: BundleStatus;
+            try {
+                const res = await fetch(`/api/bundles/${encodeURIComponent(bundleId)}`);
+                const data = await res.json();
+                if (!data.success) {
+                    toast.error("Swap failed: bundle not found", { id: toastId });
+                    return;
+                }
+                status = data.result;
+            } catch (err) {
+                console.warn("Polling error (will retry)...", err);
+                continue;
+            }
 
-            if (!Array.isArray(value)) {
-                toast.dismiss(waitingToastId);
-                toast.error("Unexpected response from Jito", { id: toastId });
+            if (status.state === "landed") {
+                toast.success("Swap confirmed", {
+                    id: toastId,
+                    description: `Landed in slot ${status.landedSlot}`,
+                });
+                return;
+            }
+            if (status.state === "failed") {
+                toast.error("Swap failed: bundle rejected", { id: toastId });
+                return;
+            }
+            if (status.state === "expired") {
+                toast.error("Swap confirmation timeout", { id: toastId });
                 return;
             }
+        }
+    } finally {
+        toast.dismiss(waitingToastId);
+    }
+}
 
-            if (value.length === 0) {
-                seenEmptyCount++;
-                if (seenEmptyCount >= maxEmptyBeforeFail) {
-                    toast.dismiss(waitingToastId);
-                    toast.error("Swap failed: bundle not found", { id: toastId });
-                    return;
-                }
-            } else {
-                seenEmptyCount = 0;
-                const bundle = value[0];
-                const status = bundle?.confirmation_status;
-                console.log(bundle)
+/** Tracks the signature on the backend until it is confirmed or expires. */
+async function followSignature(signature: string): Promise<"confirmed" | "failed" | "expired"> {
+    await fetch("/api/tx/track", {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify({ signatures: [signature] }),
+    });
 
-                if (status === "confirmed" || status === "finalized") {
-                    toast.dismiss(waitingToastId);
-                    toast.success("Swap confirmed", {
-                        id: toastId,
-                        description: `Status: ${status}`,
-                    });
-                    return;
-                }
+    // The backend expires the signature with its blockhash; the cap only
+    // guards against losing the backend meanwhile.
+    for (let attempt = 0; attempt < 180; attempt++) {
+        await new Promise((res) => setTimeout(res, 1000));
+        try {
+            const res = await fetch(`/api/tx/${signature}/status`);
+            const data = await res.json();
+            const status = data?.result?.status;
+            if (!data.success || !status) continue;
+
+            if (status.stage === "expired") return "expired";
+            if (status.stage === "confirmed" || status.stage === "finalized") {
+                return status.failure ? "failed" : "confirmed";
             }
         } catch (err) {
             console.warn("Polling error (will retry)...", err);
         }
-
-        await new Promise((res) => setTimeout(res, delay));
-        attempts++;
     }
-
-    toast.dismiss(waitingToastId);
-    toast.error("Swap confirmation timeout", { id: toastId });
+    return "expired";
 }
@@ -20,6 +20,8 @@ export function useQuoteWebSocket(params: QuoteParams | null): void {
     const lastHashRef = useRef<string>("");
     const setTransaction = useSwapStore((s) => s.setTransaction);
     const setArbTransaction = useSwapStore((s) => s.setArbTransaction);
+    const clearTransaction = useSwapStore((s) => s.clearTransaction);
+    const clearArbTransaction = useSwapStore((s) => s.clearArbTransaction);
 
     useEffect(() => {
         if (!params) return;
@@ -60,6 +62,15 @@ export function useQuoteWebSocket(params: QuoteParams | null): void {
                     const tx = data.message as string | undefined;
                     const arb_tx = data.arb_transaction as string | undefined;
 
+                    if (data.blocked) {
+                        // Over the backend's price-impact limit: nothing to sign.
+                        console.warn("Quote blocked:", data.block_reason);
+                        clearTransaction();
+                        clearArbTransaction();
+                        if (!isNaN(out)) onQuote(out);
+                        return;
+                    }
+
                     if (!isNaN(out)) {
                         if (tx) setTransaction(tx);
                         if (arb_tx) setArbTransaction(arb_tx);
@@ -71,16 +71,16 @@ export async function getEffectivePriorityFee(): Promise<number> {
     }
 
     try {
-        const res = await fetch("/api/fetchJitoTipFloor");
+        const res = await fetch("/api/tips/recommend?urgency=high");
         const json = await res.json();
 
-        if (!json.success || !Array.isArray(json.result?.data)) {
-            throw new Error("Invalid JITO response");
+        if (!json.success || typeof json.result?.tipSol !== "number") {
+            throw new Error("Invalid tip recommendation");
         }
 
-        return json.result.data[0]?.landed_tips_75th_percentile ?? 0.0001;
+        return json.result.tipSol;
     } catch (e) {
-        console.error("Failed to fetch JITO tip floor:", e);
+        console.error("Failed to fetch JITO tip recommendation:", e);
         return 0.0001;
     }
 }
@@ -0,0 +1,215 @@
+//! A Jito block engine: `sendBundle`, `getInflightBundleStatuses` and the
+//! public tip floor. Every bundle gets the programmed [`BundleOutcome`]
+//! once it has been polled a set number of times.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use warp::filters::BoxedFilter;
+use warp::reply::Response;
+use warp::{Filter, Reply};
+
+/// Where bundles end up once the engine stops reporting them pending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleOutcome {
+    Landed { slot: u64 },
+    Failed,
+    /// Never leaves pending, so the bundle expires.
+    Pending,
+}
+
+/// A bundle as the engine received it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentBundle {
+    pub bundle_id: String,
+    /// Base64 transactions, in bundle order.
+    pub transactions: Vec<String>,
+}
+
+struct State {
+    outcome: BundleOutcome,
+    pending_polls: usize,
+    rejection: Option<String>,
+    bundles: Vec<SentBundle>,
+    polls: HashMap<String, usize>,
+    tip_floor: Value,
+}
+
+/// Handle to a running fake; clones share its state.
+#[derive(Clone)]
+pub struct FakeBlockEngine {
+    url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeBlockEngine {
+    /// Lands every bundle at slot 1 on its first status poll.
+    pub fn start() -> Self {
+        let state = Arc::new(Mutex::new(State {
+            outcome: BundleOutcome::Landed { slot: 1 },
+            pending_polls: 0,
+            rejection: None,
+            bundles: Vec::new(),
+            polls: HashMap::new(),
+            tip_floor: json!([{
+                "time": "2025-01-01T00:00:00Z",
+                "landed_tips_25th_percentile": 0.000001,
+                "landed_tips_50th_percentile": 0.00001,
+                "landed_tips_75th_percentile": 0.0001,
+                "landed_tips_95th_percentile": 0.001,
+                "landed_tips_99th_percentile": 0.01,
+                "ema_landed_tips_50th_percentile": 0.00001,
+            }]),
+        }));
+        FakeBlockEngine { url: crate::serve(routes(state.clone())), state }
+    }
+
+    /// Engine URL, for `JITO_BLOCK_ENGINES`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Tip floor URL, for `JITO_TIP_FLOOR_URL`.
+    pub fn tip_floor_url(&self) -> String {
+        format!("{}/api/v1/bundles/tip_floor", self.url)
+    }
+
+    /// Outcome of bundles after `pending_polls` status polls reported them
+    /// pending.
+    pub fn set_outcome(&self, outcome: BundleOutcome, pending_polls: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.outcome = outcome;
+        state.pending_polls = pending_polls;
+    }
+
+    /// Refuses every `sendBundle` with `message`, or accepts again with
+    /// `None`.
+    pub fn reject_bundles(&self, message: Option<&str>) {
+        self.state.lock().unwrap().rejection = message.map(String::from);
+    }
+
+    /// Body of the tip floor endpoint, an array of samples.
+    pub fn set_tip_floor(&self, samples: Value) {
+        self.state.lock().unwrap().tip_floor = samples;
+    }
+
+    /// Accepted bundles, oldest first.
+    pub fn bundles(&self) -> Vec<SentBundle> {
+        self.state.lock().unwrap().bundles.clone()
+    }
+}
+
+impl State {
+    fn call(&mut self, method: &str, params: &Value) -> Result<Value, Value> {
+        match method {
+            "sendBundle" => {
+                if let Some(message) = &self.rejection {
+                    return Err(json!({ "code": -32602, "message": message }));
+                }
+                let transactions: Vec<String> = params[0]
+                    .as_array()
+                    .filter(|transactions| !transactions.is_empty())
+                    .ok_or_else(|| json!({ "code": -32602, "message": "bundle has no transactions" }))?
+                    .iter()
+                    .filter_map(|transaction| transaction.as_str().map(String::from))
+                    .collect();
+                let bundle_id = format!("fake-bundle-{}", self.bundles.len() + 1);
+                self.bundles.push(SentBundle { bundle_id: bundle_id.clone(), transactions });
+                Ok(json!(bundle_id))
+            }
+            "getInflightBundleStatuses" => {
+                let ids = params[0].as_array().cloned().unwrap_or_default();
+                let value: Vec<Value> = ids
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(|bundle_id| self.poll(bundle_id))
+                    .collect();
+                Ok(json!({ "context": { "slot": 1 }, "value": value }))
+            }
+            _ => Err(json!({ "code": -32601, "message": "Method not found" })),
+        }
+    }
+
+    fn poll(&mut self, bundle_id: &str) -> Value {
+        if !self.bundles.iter().any(|bundle| bundle.bundle_id == bundle_id) {
+            return Value::Null;
+        }
+        let polls = self.polls.entry(bundle_id.to_string()).or_default();
+        *polls += 1;
+        let (status, landed_slot) = match self.outcome {
+            _ if *polls <= self.pending_polls => ("Pending", None),
+            BundleOutcome::Landed { slot } => ("Landed", Some(slot)),
+            BundleOutcome::Failed => ("Failed", None),
+            BundleOutcome::Pending => ("Pending", None),
+        };
+        json!({ "bundle_id": bundle_id, "status": status, "landed_slot": landed_slot })
+    }
+}
+
+fn routes(state: Arc<Mutex<State>>) -> BoxedFilter<(Response,)> {
+    let tip_floor = {
+        let state = state.clone();
+        warp::path!("api" / "v1" / "bundles" / "tip_floor")
+            .and(warp::get())
+            .map(move || warp::reply::json(&state.lock().unwrap().tip_floor).into_response())
+    };
+
+    let json_rpc = warp::path!("api" / "v1" / String)
+        .and(warp::post())
+        .and(warp::body::json())
+        .map(move |path: String, body: Value| {
+            let method = body["method"].as_str().unwrap_or_default();
+            let outcome = match (path.as_str(), method) {
+                ("bundles", "sendBundle") | ("getInflightBundleStatuses", "getInflightBundleStatuses") => {
+                    state.lock().unwrap().call(method, &body["params"])
+                }
+                _ => Err(json!({ "code": -32601, "message": "Method not found" })),
+            };
+            let reply = match outcome {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": body["id"], "result": result }),
+                Err(error) => json!({ "jsonrpc": "2.0", "id": body["id"], "error": error }),
+            };
+            warp::reply::json(&reply).into_response()
+        });
+
+    tip_floor.or(json_rpc).unify().boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn call(engine: &FakeBlockEngine, path: &str, method: &str, params: Value) -> Value {
+        let response = warp::test::request()
+            .method("POST")
+            .path(&format!("/api/v1/{}", path))
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .reply(&routes(engine.state.clone()))
+            .await;
+        serde_json::from_slice(response.body()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn bundles_stay_pending_then_take_the_outcome() {
+        let engine = FakeBlockEngine::start();
+        engine.set_outcome(BundleOutcome::Landed { slot: 42 }, 1);
+
+        let sent = call(&engine, "bundles", "sendBundle", json!([["dHg="], { "encoding": "base64" }])).await;
+        let bundle_id = sent["result"].as_str().unwrap().to_string();
+        assert_eq!(engine.bundles(), [SentBundle { bundle_id: bundle_id.clone(), transactions: vec!["dHg=".into()] }]);
+
+        let status = || call(&engine, "getInflightBundleStatuses", "getInflightBundleStatuses", json!([[bundle_id]]));
+        assert_eq!(status().await["result"]["value"][0]["status"], "Pending");
+        let landed = status().await;
+        assert_eq!(landed["result"]["value"][0]["status"], "Landed");
+        assert_eq!(landed["result"]["value"][0]["landed_slot"], 42);
+
+        let unknown = call(&engine, "getInflightBundleStatuses", "getInflightBundleStatuses", json!([["other"]])).await;
+        assert!(unknown["result"]["value"][0].is_null());
+
+        engine.reject_bundles(Some("bundle tip too low"));
+        let rejected = call(&engine, "bundles", "sendBundle", json!([["dHg="]])).await;
+        assert_eq!(rejected["error"]["message"], "bundle tip too low");
+        assert_eq!(engine.bundles().len(), 1);
+    }
+}
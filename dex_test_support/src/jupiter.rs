@@ -0,0 +1,53 @@
+//! Jupiter's token list: the full list as one JSON array.
+
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use warp::filters::BoxedFilter;
+use warp::reply::Response;
+use warp::{Filter, Reply};
+
+#[derive(Default)]
+struct State {
+    tokens: Vec<Value>,
+    fetches: usize,
+}
+
+/// Handle to a running fake; clones share its state.
+#[derive(Clone)]
+pub struct FakeJupiter {
+    url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeJupiter {
+    pub fn start() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        FakeJupiter { url: crate::serve(routes(state.clone())), state }
+    }
+
+    /// URL of the list itself, for `JUPITER_TOKEN_LIST_URL`.
+    pub fn token_list_url(&self) -> String {
+        format!("{}/tokens", self.url)
+    }
+
+    /// Entries with at least `address`, `symbol`, `name` and `logoURI`.
+    pub fn set_tokens(&self, tokens: Vec<Value>) {
+        self.state.lock().unwrap().tokens = tokens;
+    }
+
+    /// How often the list was downloaded.
+    pub fn fetches(&self) -> usize {
+        self.state.lock().unwrap().fetches
+    }
+}
+
+fn routes(state: Arc<Mutex<State>>) -> BoxedFilter<(Response,)> {
+    warp::path!("tokens")
+        .and(warp::get())
+        .map(move || {
+            let mut state = state.lock().unwrap();
+            state.fetches += 1;
+            warp::reply::json(&state.tokens).into_response()
+        })
+        .boxed()
+}
@@ -0,0 +1,43 @@
+//! In-process stand-ins for everything the backend talks to over HTTP: a
+//! Solana JSON-RPC node, the Solflare wallet and activity APIs, Moralis
+//! token metadata, the Jupiter token list and a Jito block engine.
+//!
+//! Each fake listens on an ephemeral local port and is programmed through
+//! its handle while it runs; point the backend at it with the matching env
+//! var (`RPC_URL`, `SOLFLARE_WALLET_API_URL`, `SOLFLARE_ACTIVITY_API_URL`,
+//! `MORALIS_API_URL`, `JUPITER_TOKEN_LIST_URL`, `JITO_BLOCK_ENGINES`,
+//! `JITO_TIP_FLOOR_URL`).
+
+pub mod block_engine;
+pub mod jupiter;
+pub mod moralis;
+pub mod rpc;
+pub mod solflare;
+
+pub use block_engine::{BundleOutcome, FakeBlockEngine};
+pub use jupiter::FakeJupiter;
+pub use moralis::FakeMoralis;
+pub use rpc::{MockAccount, MockRpc};
+pub use solflare::FakeSolflare;
+
+use warp::filters::BoxedFilter;
+use warp::reply::Response;
+
+/// Serves `routes` on 127.0.0.1 from a thread with its own runtime, so a
+/// fake started by one `#[tokio::test]` outlives that test's runtime and
+/// can be shared across tests. Returns the base URL.
+pub fn serve(routes: BoxedFilter<(Response,)>) -> String {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("fake server runtime");
+        runtime.block_on(async move {
+            let (address, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+            sender.send(address).expect("fake server address");
+            server.await;
+        });
+    });
+    format!("http://{}", receiver.recv().expect("fake server failed to start"))
+}
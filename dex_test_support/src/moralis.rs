@@ -0,0 +1,61 @@
+//! Moralis' Solana token metadata endpoint. Like the real API it needs an
+//! `X-API-Key` and answers 404 for mints it doesn't know.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Reply};
+
+#[derive(Default)]
+struct State {
+    tokens: HashMap<String, Value>,
+    requests: Vec<String>,
+}
+
+/// Handle to a running fake; clones share its state.
+#[derive(Clone)]
+pub struct FakeMoralis {
+    url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeMoralis {
+    pub fn start() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        FakeMoralis { url: crate::serve(routes(state.clone())), state }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The metadata body for `mint`, e.g. `{"name", "symbol", "logo"}`.
+    pub fn set_token(&self, mint: &str, metadata: Value) {
+        self.state.lock().unwrap().tokens.insert(mint.to_string(), metadata);
+    }
+
+    /// Mints looked up so far, oldest first.
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+fn routes(state: Arc<Mutex<State>>) -> BoxedFilter<(Response,)> {
+    warp::path!("token" / "mainnet" / String / "metadata")
+        .and(warp::get())
+        .and(warp::header::optional::<String>("x-api-key"))
+        .map(move |mint: String, api_key: Option<String>| {
+            let mut state = state.lock().unwrap();
+            state.requests.push(mint.clone());
+            let (body, status) = match (api_key, state.tokens.get(&mint)) {
+                (None, _) => (json!({ "message": "Missing API key" }), StatusCode::UNAUTHORIZED),
+                (Some(_), Some(metadata)) => (metadata.clone(), StatusCode::OK),
+                (Some(_), None) => (json!({ "message": "Token not found" }), StatusCode::NOT_FOUND),
+            };
+            warp::reply::with_status(warp::reply::json(&body), status).into_response()
+        })
+        .boxed()
+}
@@ -0,0 +1,486 @@
+//! A Solana JSON-RPC node over programmable state: accounts, confirmed
+//! transactions, health and the outcome of the next simulation. Methods it
+//! doesn't know answer `-32601` unless a test scripts them with
+//! [`MockRpc::respond`].
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use warp::filters::BoxedFilter;
+use warp::reply::Response;
+use warp::{Filter, Reply};
+
+pub const SYSTEM_PROGRAM: &str = "11111111111111111111111111111111";
+pub const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+pub const TOKEN_2022_PROGRAM: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+
+/// Base length of a token account and of a mint.
+const TOKEN_ACCOUNT_LEN: usize = 165;
+const MINT_LEN: usize = 82;
+/// Rent-exempt minimums for those lengths.
+const TOKEN_ACCOUNT_RENT: u64 = 2_039_280;
+const MINT_RENT: u64 = 1_461_600;
+
+type Handler = Arc<dyn Fn(&Value) -> Result<Value, Value> + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockAccount {
+    pub lamports: u64,
+    /// Owning program, base58.
+    pub owner: String,
+    pub data: Vec<u8>,
+    /// What `jsonParsed` returns as the account's data. Accounts without it
+    /// fall back to base64, as on a real node.
+    pub parsed: Option<Value>,
+}
+
+impl MockAccount {
+    pub fn system(lamports: u64) -> Self {
+        MockAccount { lamports, owner: SYSTEM_PROGRAM.to_string(), data: Vec::new(), parsed: None }
+    }
+
+    /// An initialized account of `program` holding `amount` of `mint` for
+    /// `owner`.
+    pub fn token(program: &str, mint: &str, owner: &str, amount: u64) -> Self {
+        let mut data = vec![0u8; TOKEN_ACCOUNT_LEN];
+        data[..32].copy_from_slice(&public_key(mint));
+        data[32..64].copy_from_slice(&public_key(owner));
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        // `AccountState::Initialized`.
+        data[108] = 1;
+        let info = json!({
+            "mint": mint,
+            "owner": owner,
+            "state": "initialized",
+            "tokenAmount": { "amount": amount.to_string() },
+        });
+        MockAccount {
+            lamports: TOKEN_ACCOUNT_RENT,
+            owner: program.to_string(),
+            data,
+            parsed: Some(parsed_data(program, "account", info, TOKEN_ACCOUNT_LEN)),
+        }
+    }
+
+    /// An initialized mint of `program` with no authorities or supply.
+    pub fn mint(program: &str, decimals: u8) -> Self {
+        let mut data = vec![0u8; MINT_LEN];
+        data[44] = decimals;
+        data[45] = 1;
+        let info = json!({
+            "decimals": decimals,
+            "isInitialized": true,
+            "supply": "0",
+            "mintAuthority": null,
+            "freezeAuthority": null,
+        });
+        MockAccount {
+            lamports: MINT_RENT,
+            owner: program.to_string(),
+            data,
+            parsed: Some(parsed_data(program, "mint", info, MINT_LEN)),
+        }
+    }
+}
+
+fn parsed_data(program: &str, kind: &str, info: Value, space: usize) -> Value {
+    let name = if program == TOKEN_2022_PROGRAM { "spl-token-2022" } else { "spl-token" };
+    json!({ "program": name, "parsed": { "type": kind, "info": info }, "space": space })
+}
+
+fn public_key(key: &str) -> [u8; 32] {
+    key_bytes(key).unwrap_or_else(|| panic!("{} is not a base58 public key", key))
+}
+
+fn key_bytes(key: &str) -> Option<[u8; 32]> {
+    bs58::decode(key).into_vec().ok()?.try_into().ok()
+}
+
+#[derive(Default)]
+struct Simulation {
+    err: Value,
+    logs: Vec<String>,
+    units_consumed: u64,
+    /// State after the transaction where it differs from the current one;
+    /// `None` for accounts the transaction closes.
+    accounts: HashMap<String, Option<MockAccount>>,
+}
+
+#[derive(Default)]
+struct State {
+    slot: u64,
+    unhealthy: Option<String>,
+    accounts: BTreeMap<String, MockAccount>,
+    transactions: HashMap<String, Value>,
+    /// `getSignaturesForAddress` entries per address, newest first.
+    signatures: HashMap<String, Vec<Value>>,
+    simulation: Simulation,
+    overrides: HashMap<String, Handler>,
+    calls: Vec<(String, Value)>,
+}
+
+/// Handle to a running mock node; clones share its state.
+#[derive(Clone)]
+pub struct MockRpc {
+    url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockRpc {
+    pub fn start() -> Self {
+        let state = Arc::new(Mutex::new(State { slot: 1, ..State::default() }));
+        MockRpc { url: crate::serve(routes(state.clone())), state }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Context slot of every answer.
+    pub fn set_slot(&self, slot: u64) {
+        self.state.lock().unwrap().slot = slot;
+    }
+
+    pub fn set_account(&self, address: &str, account: MockAccount) {
+        self.state.lock().unwrap().accounts.insert(address.to_string(), account);
+    }
+
+    pub fn remove_account(&self, address: &str) {
+        self.state.lock().unwrap().accounts.remove(address);
+    }
+
+    /// Stores a confirmed transaction, as `getTransaction` returns it, and
+    /// lists it for every account it loads the way the node's signature
+    /// index does. Returns its signature.
+    pub fn add_transaction(&self, transaction: Value) -> String {
+        let signature = transaction["transaction"]["signatures"][0]
+            .as_str()
+            .expect("transaction without a signature")
+            .to_string();
+        let slot = transaction["slot"].as_u64().unwrap_or_default();
+        let entry = json!({
+            "signature": signature,
+            "slot": slot,
+            "blockTime": transaction["blockTime"],
+            "err": transaction["meta"]["err"],
+            "memo": null,
+            "confirmationStatus": "finalized",
+        });
+
+        let mut state = self.state.lock().unwrap();
+        for address in account_keys(&transaction) {
+            let entries = state.signatures.entry(address).or_default();
+            if entries.iter().any(|existing| existing["signature"] == entry["signature"]) {
+                continue;
+            }
+            let at = entries
+                .iter()
+                .position(|existing| existing["slot"].as_u64().unwrap_or_default() < slot)
+                .unwrap_or(entries.len());
+            entries.insert(at, entry.clone());
+        }
+        state.transactions.insert(signature.clone(), transaction);
+        signature
+    }
+
+    /// Outcome of every following `simulateTransaction`; `err` is the RPC's
+    /// transaction error, `null` for success.
+    pub fn set_simulation(&self, err: Value, logs: &[&str], units_consumed: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.simulation.err = err;
+        state.simulation.logs = logs.iter().map(|log| log.to_string()).collect();
+        state.simulation.units_consumed = units_consumed;
+    }
+
+    /// State of `address` after simulated transactions; `None` closes it.
+    /// Accounts not set here come back unchanged.
+    pub fn set_simulated_account(&self, address: &str, account: Option<MockAccount>) {
+        self.state.lock().unwrap().simulation.accounts.insert(address.to_string(), account);
+    }
+
+    /// Makes `getHealth` fail with `message`, or pass again with `None`.
+    pub fn set_unhealthy(&self, message: Option<&str>) {
+        self.state.lock().unwrap().unhealthy = message.map(String::from);
+    }
+
+    /// Answers `method` with `handler` instead, given the call's params;
+    /// `Err` values become the JSON-RPC error object.
+    pub fn respond<F>(&self, method: &str, handler: F)
+    where
+        F: Fn(&Value) -> Result<Value, Value> + Send + Sync + 'static,
+    {
+        self.state.lock().unwrap().overrides.insert(method.to_string(), Arc::new(handler));
+    }
+
+    /// Params of every `method` call so far, oldest first.
+    pub fn calls(&self, method: &str) -> Vec<Value> {
+        let state = self.state.lock().unwrap();
+        state.calls.iter().filter(|(called, _)| called == method).map(|(_, params)| params.clone()).collect()
+    }
+}
+
+fn routes(state: Arc<Mutex<State>>) -> BoxedFilter<(Response,)> {
+    warp::post()
+        .and(warp::body::json())
+        .map(move |body: Value| {
+            let reply = match body {
+                Value::Array(batch) => Value::Array(batch.iter().map(|request| handle(&state, request)).collect()),
+                request => handle(&state, &request),
+            };
+            warp::reply::json(&reply).into_response()
+        })
+        .boxed()
+}
+
+fn handle(state: &Mutex<State>, request: &Value) -> Value {
+    let method = request["method"].as_str().unwrap_or_default();
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    // Scripted handlers run unlocked so they may program the mock.
+    let handler = {
+        let mut state = state.lock().unwrap();
+        state.calls.push((method.to_string(), params.clone()));
+        state.overrides.get(method).cloned()
+    };
+    let outcome = match handler {
+        Some(handler) => handler(&params),
+        None => state.lock().unwrap().call(method, &params),
+    };
+    match outcome {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": request["id"], "error": error }),
+    }
+}
+
+fn invalid_params() -> Value {
+    json!({ "code": -32602, "message": "Invalid params" })
+}
+
+impl State {
+    fn call(&self, method: &str, params: &Value) -> Result<Value, Value> {
+        match method {
+            "getHealth" => match &self.unhealthy {
+                None => Ok(json!("ok")),
+                Some(message) => Err(json!({ "code": -32005, "message": message })),
+            },
+            "getSlot" => Ok(json!(self.slot)),
+            "getAccountInfo" => {
+                let address = params[0].as_str().ok_or_else(invalid_params)?;
+                let value = self.accounts.get(address).map_or(Value::Null, |account| encode(account, &params[1]));
+                Ok(self.with_context(value))
+            }
+            "getMultipleAccounts" => {
+                let addresses = params[0].as_array().ok_or_else(invalid_params)?;
+                let values = addresses
+                    .iter()
+                    .map(|address| {
+                        let account = address.as_str().and_then(|address| self.accounts.get(address));
+                        account.map_or(Value::Null, |account| encode(account, &params[1]))
+                    })
+                    .collect();
+                Ok(self.with_context(Value::Array(values)))
+            }
+            "getTokenAccountsByOwner" => self.token_accounts_by_owner(params),
+            "getSignaturesForAddress" => self.signatures_for_address(params),
+            "getTransaction" => {
+                let signature = params[0].as_str().ok_or_else(invalid_params)?;
+                Ok(self.transactions.get(signature).cloned().unwrap_or(Value::Null))
+            }
+            "simulateTransaction" => self.simulate(params),
+            _ => Err(json!({ "code": -32601, "message": "Method not found" })),
+        }
+    }
+
+    fn with_context(&self, value: Value) -> Value {
+        json!({ "context": { "slot": self.slot }, "value": value })
+    }
+
+    fn token_accounts_by_owner(&self, params: &Value) -> Result<Value, Value> {
+        let owner = params[0].as_str().and_then(key_bytes).ok_or_else(invalid_params)?;
+        let program = params[1]["programId"].as_str();
+        let mint = match params[1]["mint"].as_str() {
+            Some(mint) => Some(key_bytes(mint).ok_or_else(invalid_params)?),
+            None => None,
+        };
+        if program.is_none() && mint.is_none() {
+            return Err(invalid_params());
+        }
+
+        let accounts = self
+            .accounts
+            .iter()
+            .filter(|(_, account)| {
+                (account.owner == TOKEN_PROGRAM || account.owner == TOKEN_2022_PROGRAM)
+                    && account.data.len() >= TOKEN_ACCOUNT_LEN
+                    && account.data[32..64] == owner
+                    && program.is_none_or(|program| account.owner == program)
+                    && mint.is_none_or(|mint| account.data[..32] == mint)
+            })
+            .map(|(address, account)| json!({ "pubkey": address, "account": encode(account, &params[2]) }))
+            .collect();
+        Ok(self.with_context(Value::Array(accounts)))
+    }
+
+    fn signatures_for_address(&self, params: &Value) -> Result<Value, Value> {
+        let address = params[0].as_str().ok_or_else(invalid_params)?;
+        let config = &params[1];
+        let limit = config["limit"].as_u64().unwrap_or(1_000) as usize;
+        let entries = self.signatures.get(address).map(Vec::as_slice).unwrap_or_default();
+        let start = match config["before"].as_str() {
+            Some(before) => entries.iter().position(|entry| entry["signature"] == before).map_or(entries.len(), |i| i + 1),
+            None => 0,
+        };
+        let until = config["until"].as_str();
+        Ok(entries[start..]
+            .iter()
+            .take_while(|entry| until.is_none_or(|until| entry["signature"] != until))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    fn simulate(&self, params: &Value) -> Result<Value, Value> {
+        let encoded = params[0].as_str().ok_or_else(invalid_params)?;
+        BASE64.decode(encoded).map_err(|_| invalid_params())?;
+
+        let simulation = &self.simulation;
+        let config = &params[1];
+        // Like a real node, failed simulations return no account state.
+        let accounts = match config["accounts"]["addresses"].as_array() {
+            Some(addresses) if simulation.err.is_null() => addresses
+                .iter()
+                .map(|address| {
+                    let address = address.as_str().unwrap_or_default();
+                    let account = match simulation.accounts.get(address) {
+                        Some(simulated) => simulated.as_ref(),
+                        None => self.accounts.get(address),
+                    };
+                    account.map_or(Value::Null, |account| encode(account, &config["accounts"]))
+                })
+                .collect(),
+            _ => Value::Null,
+        };
+        Ok(self.with_context(json!({
+            "err": simulation.err,
+            "logs": simulation.logs,
+            "accounts": accounts,
+            "unitsConsumed": simulation.units_consumed,
+            "returnData": null,
+        })))
+    }
+}
+
+/// An account as the node encodes it under `config`'s `encoding` and
+/// `dataSlice`.
+fn encode(account: &MockAccount, config: &Value) -> Value {
+    let data = match (config["encoding"].as_str(), &account.parsed) {
+        (Some("jsonParsed"), Some(parsed)) => parsed.clone(),
+        _ => {
+            let slice = &config["dataSlice"];
+            let offset = (slice["offset"].as_u64().unwrap_or_default() as usize).min(account.data.len());
+            let end = match slice["length"].as_u64() {
+                Some(length) => (offset + length as usize).min(account.data.len()),
+                None => account.data.len(),
+            };
+            json!([BASE64.encode(&account.data[offset..end]), "base64"])
+        }
+    };
+    json!({
+        "lamports": account.lamports,
+        "owner": account.owner,
+        "data": data,
+        "executable": false,
+        "rentEpoch": u64::MAX,
+        "space": account.data.len(),
+    })
+}
+
+/// Static keys of a `json` or `jsonParsed` transaction, then the ones it
+/// loaded from lookup tables.
+fn account_keys(transaction: &Value) -> Vec<String> {
+    let static_keys = transaction["transaction"]["message"]["accountKeys"].as_array().cloned().unwrap_or_default();
+    let loaded = &transaction["meta"]["loadedAddresses"];
+    let loaded_keys = [&loaded["writable"], &loaded["readonly"]]
+        .into_iter()
+        .filter_map(Value::as_array)
+        .flatten()
+        .cloned();
+    static_keys
+        .into_iter()
+        .chain(loaded_keys)
+        .filter_map(|key| key.as_str().or_else(|| key["pubkey"].as_str()).map(String::from))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OWNER: &str = "GfsJWjmGXMfct8JMR9Lm9ySUnniZbnGUTQDbT8ipWf9U";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    async fn call(rpc: &MockRpc, method: &str, params: Value) -> Value {
+        let response = warp::test::request()
+            .method("POST")
+            .json(&json!({ "jsonrpc": "2.0", "id": 7, "method": method, "params": params }))
+            .reply(&routes(rpc.state.clone()))
+            .await;
+        let body: Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["id"], 7);
+        body
+    }
+
+    #[tokio::test]
+    async fn serves_token_accounts_and_parsed_mints() {
+        let rpc = MockRpc::start();
+        rpc.set_slot(300);
+        rpc.set_account("TokenAccount1111111111111111111111111111111", MockAccount::token(TOKEN_PROGRAM, USDC, OWNER, 5));
+        rpc.set_account(USDC, MockAccount::mint(TOKEN_PROGRAM, 6));
+
+        let body = call(
+            &rpc,
+            "getTokenAccountsByOwner",
+            json!([OWNER, { "programId": TOKEN_PROGRAM }, { "encoding": "base64", "dataSlice": { "offset": 0, "length": 0 } }]),
+        )
+        .await;
+        assert_eq!(body["result"]["context"]["slot"], 300);
+        let accounts = body["result"]["value"].as_array().unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0]["pubkey"], "TokenAccount1111111111111111111111111111111");
+        assert_eq!(accounts[0]["account"]["data"], json!(["", "base64"]));
+
+        let body = call(&rpc, "getTokenAccountsByOwner", json!([OWNER, { "programId": TOKEN_2022_PROGRAM }])).await;
+        assert_eq!(body["result"]["value"], json!([]));
+
+        let body = call(&rpc, "getAccountInfo", json!([USDC, { "encoding": "jsonParsed" }])).await;
+        assert_eq!(body["result"]["value"]["data"]["parsed"]["info"]["decimals"], 6);
+        assert_eq!(call(&rpc, "getBlockHeight", json!([])).await["error"]["code"], -32601);
+        assert_eq!(rpc.calls("getTokenAccountsByOwner").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn pages_signatures_newest_first() {
+        let rpc = MockRpc::start();
+        for (signature, slot) in [("first", 10), ("third", 30), ("second", 20)] {
+            rpc.add_transaction(json!({
+                "slot": slot,
+                "blockTime": slot * 100,
+                "meta": { "err": null },
+                "transaction": { "signatures": [signature], "message": { "accountKeys": [{ "pubkey": OWNER }] } },
+            }));
+        }
+
+        let page = |config: Value| call(&rpc, "getSignaturesForAddress", json!([OWNER, config]));
+        let signatures = |body: Value| -> Vec<String> {
+            body["result"].as_array().unwrap().iter().map(|e| e["signature"].as_str().unwrap().to_string()).collect()
+        };
+        assert_eq!(signatures(page(json!({ "limit": 2 })).await), ["third", "second"]);
+        assert_eq!(signatures(page(json!({ "before": "second" })).await), ["first"]);
+        assert_eq!(call(&rpc, "getTransaction", json!(["second"])).await["result"]["slot"], 20);
+        assert!(call(&rpc, "getTransaction", json!(["missing"])).await["result"].is_null());
+
+        rpc.respond("getTransaction", |_| Err(json!({ "code": -32009, "message": "pruned" })));
+        assert_eq!(call(&rpc, "getTransaction", json!(["second"])).await["error"]["code"], -32009);
+    }
+}
@@ -0,0 +1,190 @@
+//! The Solflare wallet API (portfolio, capped token search) and activity
+//! API (signatures, parsed transactions) on one server. Activity responses
+//! use Solflare's streaming format: JSON chunks separated by `<|EOF|>`.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use warp::filters::BoxedFilter;
+use warp::path::FullPath;
+use warp::reply::Response;
+use warp::{Filter, Reply};
+
+const CHUNK_DELIMITER: &str = "<|EOF|>";
+/// Entries per streamed chunk.
+const CHUNK_SIZE: usize = 10;
+
+#[derive(Default)]
+struct State {
+    portfolios: HashMap<String, Value>,
+    /// Capped token answers by search query, `""` for none.
+    capped_tokens: HashMap<String, Value>,
+    /// Signature entries per address, newest first.
+    signatures: HashMap<String, Vec<Value>>,
+    /// Parsed transactions by hash.
+    transactions: HashMap<String, Value>,
+    requests: Vec<String>,
+}
+
+/// Handle to a running fake; clones share its state.
+#[derive(Clone)]
+pub struct FakeSolflare {
+    url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeSolflare {
+    pub fn start() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        FakeSolflare { url: crate::serve(routes(state.clone())), state }
+    }
+
+    /// Base URL of both the wallet and the activity API.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// `/v3/portfolio/tokens/{address}`; unknown wallets hold nothing.
+    pub fn set_portfolio(&self, address: &str, portfolio: Value) {
+        self.state.lock().unwrap().portfolios.insert(address.to_string(), portfolio);
+    }
+
+    /// `/v2/swap/capped-tokens` for `query`, `""` for the unfiltered list.
+    pub fn set_capped_tokens(&self, query: &str, tokens: Value) {
+        self.state.lock().unwrap().capped_tokens.insert(query.to_string(), tokens);
+    }
+
+    /// `/v1/signatures` entries for `address`, newest first.
+    pub fn set_signatures(&self, address: &str, entries: Vec<Value>) {
+        self.state.lock().unwrap().signatures.insert(address.to_string(), entries);
+    }
+
+    /// A `/v1/transactions` entry, served when its `hash` is requested.
+    pub fn add_transaction(&self, transaction: Value) {
+        let hash = transaction["hash"].as_str().expect("transaction without a hash").to_string();
+        self.state.lock().unwrap().transactions.insert(hash, transaction);
+    }
+
+    /// Path and query of every request so far, e.g.
+    /// `GET /v1/signatures?address=...`.
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+/// The entries of a recorded activity response body, so captured payloads
+/// can be replayed through the fake.
+pub fn chunk_entries(body: &str) -> Vec<Value> {
+    body.split(CHUNK_DELIMITER)
+        .filter_map(|chunk| serde_json::from_str::<Value>(chunk.trim()).ok())
+        .flat_map(|chunk| chunk["data"].as_array().cloned().unwrap_or_default())
+        .collect()
+}
+
+fn chunked(entries: &[Value]) -> String {
+    entries.chunks(CHUNK_SIZE).map(|chunk| format!("{}{}", json!({ "data": chunk }), CHUNK_DELIMITER)).collect()
+}
+
+fn routes(state: Arc<Mutex<State>>) -> BoxedFilter<(Response,)> {
+    let log = {
+        let state = state.clone();
+        warp::method().and(warp::path::full()).and(warp::query::raw().or(warp::any().map(String::new)).unify()).map(
+            move |method: warp::http::Method, path: FullPath, query: String| {
+                let request = if query.is_empty() {
+                    format!("{} {}", method, path.as_str())
+                } else {
+                    format!("{} {}?{}", method, path.as_str(), query)
+                };
+                state.lock().unwrap().requests.push(request);
+            },
+        )
+    };
+
+    let portfolio = {
+        let state = state.clone();
+        warp::path!("v3" / "portfolio" / "tokens" / String).and(warp::get()).map(move |address: String| {
+            let state = state.lock().unwrap();
+            let portfolio = state.portfolios.get(&address).cloned().unwrap_or_else(|| json!({ "tokens": [] }));
+            warp::reply::json(&portfolio).into_response()
+        })
+    };
+
+    let capped_tokens = {
+        let state = state.clone();
+        warp::path!("v2" / "swap" / "capped-tokens")
+            .and(warp::get())
+            .and(warp::query::<HashMap<String, String>>())
+            .map(move |query: HashMap<String, String>| {
+                let search = query.get("query").map(String::as_str).unwrap_or_default();
+                let state = state.lock().unwrap();
+                let tokens = state.capped_tokens.get(search).cloned().unwrap_or_else(|| json!({ "tokens": [] }));
+                warp::reply::json(&tokens).into_response()
+            })
+    };
+
+    let signatures = {
+        let state = state.clone();
+        warp::path!("v1" / "signatures")
+            .and(warp::get())
+            .and(warp::query::<HashMap<String, String>>())
+            .map(move |query: HashMap<String, String>| {
+                let limit = query.get("limit").and_then(|limit| limit.parse().ok()).unwrap_or(usize::MAX);
+                let state = state.lock().unwrap();
+                let entries = query.get("address").and_then(|address| state.signatures.get(address));
+                let entries = entries.map(|entries| &entries[..limit.min(entries.len())]).unwrap_or_default();
+                chunked(entries).into_response()
+            })
+    };
+
+    let transactions = warp::path!("v1" / "transactions")
+        .and(warp::post())
+        .and(warp::body::json())
+        .map(move |body: Value| {
+            let state = state.lock().unwrap();
+            let found: Vec<Value> = body["signatures"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|signature| state.transactions.get(signature.as_str()?).cloned())
+                .collect();
+            chunked(&found).into_response()
+        });
+
+    log.untuple_one()
+        .and(portfolio.or(capped_tokens).unify().or(signatures).unify().or(transactions).unify())
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn streams_signatures_and_requested_transactions() {
+        let solflare = FakeSolflare::start();
+        let entries: Vec<Value> = (0..12).map(|i| json!({ "hash": format!("sig-{}", i), "slot": 100 - i })).collect();
+        solflare.set_signatures("wallet", entries.clone());
+        solflare.add_transaction(json!({ "hash": "sig-1", "type": "transfer" }));
+        solflare.add_transaction(json!({ "hash": "sig-0", "type": "swap" }));
+        let routes = routes(solflare.state.clone());
+
+        let response = warp::test::request().path("/v1/signatures?address=wallet&limit=11").reply(&routes).await;
+        let body = std::str::from_utf8(response.body()).unwrap();
+        assert_eq!(body.matches(CHUNK_DELIMITER).count(), 2);
+        assert_eq!(chunk_entries(body), entries[..11]);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/v1/transactions?network=mainnet")
+            .json(&json!({ "address": "wallet", "signatures": ["sig-1", "missing", "sig-0"] }))
+            .reply(&routes)
+            .await;
+        let found = chunk_entries(std::str::from_utf8(response.body()).unwrap());
+        assert_eq!(found, [json!({ "hash": "sig-1", "type": "transfer" }), json!({ "hash": "sig-0", "type": "swap" })]);
+
+        assert_eq!(
+            solflare.requests(),
+            ["GET /v1/signatures?address=wallet&limit=11", "POST /v1/transactions?network=mainnet"]
+        );
+    }
+}